@@ -1,14 +1,16 @@
-use super::Color;
+use super::{Color, ColorTheme, GradientDirection, HarmonyScheme, HueFamily, PaletteFormat};
 use crate::{
-    configuration::Config,
+    configuration::{Config, Instance},
     wallpaper_generators::{
-        ApodDate, AstraImage, WallpaperGeneratorError, generate_bing_spotlight, generate_julia_set,
-        generate_nasa_apod, generate_solid_color, parse_yymmdd,
+        ApodDate, AstraImage, MAX_ANIMATION_FRAMES, WallpaperGeneratorError,
+        generate_bing_spotlight, generate_datetime, generate_flame, generate_ifs,
+        generate_julia_set, generate_mandala, generate_nasa_apod, generate_solid_color,
+        generate_text, generate_truchet, parse_yymmdd,
     },
 };
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
-use std::str::FromStr;
+use std::{path::PathBuf, str::FromStr};
 
 #[derive(Parser)]
 #[command(author, version, about)]
@@ -17,12 +19,66 @@ pub struct Cli {
     /// Subcommands
     pub(crate) command: Option<Commands>,
     #[arg(short, long)]
-    /// Verbose output
+    /// Verbose output (printed to stderr)
     pub(crate) verbose: bool,
+    #[arg(long)]
+    /// Seed for the random number generator (the same seed reproduces the same wallpaper)
+    pub(crate) seed: Option<u64>,
+    #[arg(long)]
+    /// Seed for the theme selection only, so the theme stays the same while `--seed` varies the
+    /// rest of the image (or the other way around)
+    pub(crate) theme_seed: Option<u64>,
+    #[arg(long)]
+    /// Generate a new wallpaper even if the configured frequency has not elapsed yet (macOS)
+    pub(crate) force: bool,
+    #[arg(long)]
+    /// Print the effective configuration (file, environment variables and flags) as JSON and exit
+    pub(crate) print_config: bool,
+    #[arg(long)]
+    /// Print why the wallpaper's generator, theme and appearance were chosen
+    pub(crate) explain: bool,
+    #[arg(long)]
+    /// Generate and apply a wallpaper without installing, updating or removing astra's OS
+    /// scheduled job (e.g. in containers)
+    pub(crate) no_scheduler: bool,
+    #[arg(long, value_name = "NAME", value_parser = |name: &str| Instance::new(name))]
+    /// Run as this instance, which has its own configuration file, scheduled job, state and
+    /// wallpapers (e.g. "work" and "personal"). Takes precedence over `instance` in the
+    /// configuration file
+    pub(crate) instance: Option<Instance>,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
+    /// Exports an animated GIF of a generator sweeping one of its parameters (the wallpaper is not
+    /// changed). Supported generators: julia, solid (hue rotation)
+    Animate {
+        /// The type of image to animate
+        #[command(subcommand)]
+        image: Generator,
+        #[arg(short, long, default_value_t = 30, value_parser = clap::value_parser!(u32).range(2..=MAX_ANIMATION_FRAMES as i64))]
+        /// Number of frames of the animation
+        frames: u32,
+        #[arg(short, long)]
+        /// File the GIF is written to
+        output: PathBuf,
+    },
+    /// Times each generator and prints them from the fastest to the slowest (nothing is saved and
+    /// the wallpaper is not changed). Generators that download their image are skipped
+    Benchmark {
+        #[arg(short, long, default_value_t = 3)]
+        /// Number of images generated by each generator
+        iterations: u32,
+        #[arg(long)]
+        /// Width of the generated images (default: the screen resolution)
+        width: Option<u32>,
+        #[arg(long)]
+        /// Height of the generated images (default: the screen resolution)
+        height: Option<u32>,
+        #[arg(long)]
+        /// Exit with an error if the mean time of any generator exceeds this many milliseconds
+        fail_above_ms: Option<f64>,
+    },
     /// Deletes images from "astra_wallpapers" folder (deletes all images by default)
     Clean {
         #[arg(short, long)]
@@ -32,11 +88,44 @@ pub enum Commands {
         /// Deletes all images and the "astra_wallpapers" directory
         directory: bool,
     },
+    /// Prints the dominant colors of an image (useful to create a theme from a picture)
+    Colors {
+        /// Image to analyze
+        path: PathBuf,
+        #[arg(short, long, default_value_t = 5)]
+        /// Number of colors to print
+        count: usize,
+    },
     /// Return path to configuration file (creates config first if it doesn't exist)
     Config {
         #[arg(short, long)]
         /// Open the configuration file in the default text editor
         open: bool,
+        #[arg(short, long, conflicts_with = "open")]
+        /// Also print the configuration, data and wallpapers folders
+        data: bool,
+    },
+    /// Prints how different two images are (mean absolute difference and SSIM), e.g. to compare
+    /// the output of two generator settings
+    Diff {
+        /// First image
+        a: PathBuf,
+        /// Second image, the larger of the two is resized to the size of the other one
+        b: PathBuf,
+        #[arg(long, value_name = "PATH")]
+        /// Also write an image of the differences (black where the images match)
+        heatmap: Option<PathBuf>,
+    },
+    /// Exports the colors of a built-in or configured theme to a palette file
+    ExportTheme {
+        /// Name of the theme (e.g. "Neon Dreams" or neon-dreams)
+        name: String,
+        #[arg(short, long, value_enum)]
+        /// Palette file format
+        format: PaletteFormat,
+        #[arg(short, long)]
+        /// File the palette is written to
+        output: PathBuf,
     },
     /// Generates a new wallpaper
     Generate {
@@ -44,24 +133,161 @@ pub enum Commands {
         #[command(subcommand)]
         image: Generator,
         #[arg(long)]
-        /// Skip saving the image to the "astra_wallpapers" folder.
+        /// Skip saving the image to the "astra_wallpapers" folder. When updating the wallpaper, a
+        /// temporary file is used instead (deleted afterwards, except on Linux where the last one
+        /// is kept until the next update)
         no_save: bool,
         #[arg(long)]
         /// Skip updating current desktop wallpaper to generated image
         no_update: bool,
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        /// Width of the generated image. If `--height` is omitted, it is derived from the aspect
+        /// ratio of the screen
+        width: Option<u32>,
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        /// Height of the generated image. If `--width` is omitted, it is derived from the aspect
+        /// ratio of the screen
+        height: Option<u32>,
+        #[arg(long, value_name = "NAME", conflicts_with = "theme_from_file")]
+        /// Use this built-in or configured theme (e.g. "Neon Dreams" or neon-dreams). Takes
+        /// precedence over the ASTRA_THEME environment variable
+        theme: Option<String>,
+        #[arg(long, value_name = "PATH", requires = "theme_name")]
+        /// Use a theme from this JSON file (an array of themes, same format as `themes` in the
+        /// configuration file) instead of the configured themes. Requires `--theme-name`
+        theme_from_file: Option<PathBuf>,
+        #[arg(long, value_name = "NAME", requires = "theme_from_file")]
+        /// Name of the theme to use from `--theme-from-file`
+        theme_name: Option<String>,
+        #[arg(
+            long,
+            value_name = "COLORS",
+            value_parser = ColorTheme::from_palette,
+            conflicts_with_all = ["theme", "theme_from_file"]
+        )]
+        /// Use these comma separated color names or hex values as the theme (e.g.
+        /// "#001f3f,#0074D9,#7FDBFF"), without defining a theme. Cannot be combined with `--theme`
+        /// and takes precedence over the ASTRA_THEME environment variable
+        palette: Option<ColorTheme>,
+        #[arg(long, value_name = "INDEX", conflicts_with = "no_update")]
+        /// Only set the wallpaper of the monitor at this index (the first monitor is 0), other
+        /// monitors keep their wallpaper (Windows and macOS)
+        apply_to: Option<usize>,
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        /// Number of images to generate. Each one is saved and applied like a single image (use
+        /// `filename_scheme: "timestamp_hash"` to keep images generated within the same second)
+        count: u32,
+        #[arg(long, value_name = "PATH")]
+        /// Also write a grid of thumbnails of every generated image to this file
+        contact_sheet: Option<PathBuf>,
+        #[arg(long)]
+        /// Print the URL and title of the downloaded image (spotlight)
+        show_source: bool,
+        #[arg(long)]
+        /// Never use the current Spotlight wallpaper again (its hash is added to
+        /// `spotlight_blocklist`), then set a new one (spotlight)
+        block_current: bool,
+        #[arg(
+            long,
+            conflicts_with_all = ["apply_to", "count", "contact_sheet", "show_source", "block_current"]
+        )]
+        /// Write the image as PNG to stdout instead of saving and applying it (implies `--no-save
+        /// --no-update`), e.g. to pipe it into another tool
+        stdout: bool,
     },
     /// Generate shell completion scripts
     GenerateCompletions {
         /// The shell to generate completion scripts for
-        #[arg(value_enum)]
-        shell: Shell,
+        #[arg(value_enum, required_unless_present = "list", conflicts_with = "list")]
+        shell: Option<Shell>,
+        #[arg(short, long)]
+        /// List the supported shells
+        list: bool,
+        #[arg(short, long, value_name = "PATH", conflicts_with = "list")]
+        /// Write the completion script to this file instead of stdout
+        file: Option<PathBuf>,
+        #[arg(short, long, requires = "file")]
+        /// Append to `--file` instead of overwriting it. Completions appended by a previous run
+        /// are replaced
+        append: bool,
+    },
+    /// Adds a theme from a palette URL (e.g. https://coolors.co/palette/1e1e1e-ff0000-ffd700) or a
+    /// dash-separated list of hex colors to the configuration file
+    ImportTheme {
+        /// Palette URL or hex colors (e.g. 1e1e1e-ff0000-ffd700)
+        url: String,
+        #[arg(short, long)]
+        /// Name of the new theme
+        name: String,
+    },
+    /// Prints how a wallpaper saved by astra was generated (generator, seed, theme and settings
+    /// stored in the PNG)
+    Inspect {
+        /// PNG image saved by astra
+        path: PathBuf,
+    },
+    /// Rename deprecated keys of the configuration file (the original is kept as
+    /// config.json.bak)
+    Migrate,
+    /// Lists the most recently applied wallpapers with a preview, or applies one of them again
+    Recent {
+        #[arg(short, long, default_value_t = 5)]
+        /// Number of wallpapers to list
+        count: usize,
+        #[arg(short, long)]
+        /// Apply the wallpaper listed at this index (the first one is 1) instead of listing them
+        apply: Option<usize>,
+        #[arg(long)]
+        /// List the wallpapers without previews
+        no_preview: bool,
+    },
+    /// Uninstall the scheduled job and delete wallpapers, state and configuration
+    Reset {
+        #[arg(long)]
+        /// Keep the configuration file
+        keep_config: bool,
+        #[arg(short, long)]
+        /// Skip the confirmation prompt
+        yes: bool,
+    },
+    /// Let the operating system rotate the wallpapers saved in astra's Wallpapers folder (macOS
+    /// and GNOME)
+    Slideshow {
+        #[arg(long, conflicts_with = "interval")]
+        /// Stop the slideshow and keep the most recent wallpaper
+        disable: bool,
+        #[arg(short, long, value_name = "FREQUENCY")]
+        /// Time each wallpaper is shown (e.g. 30m, 2h), defaults to the configured frequency or
+        /// 30m
+        interval: Option<String>,
+    },
+    /// Checks that the configuration file parses
+    Validate {
+        #[arg(long)]
+        /// Also warn about settings that have no effect (e.g. configuring a generator that is not
+        /// in `generators`)
+        lint: bool,
+        #[arg(long)]
+        /// Also warn about themes whose average color leaves desktop text hard to read (WCAG
+        /// contrast below 4.5:1, built-in themes are checked when none are configured)
+        theme_contrast: bool,
     },
 }
 
 #[derive(Clone, Debug, PartialEq, Subcommand)]
 pub enum Generator {
+    /// Sets wallpaper to the current date (and optionally time) over a themed gradient, see
+    /// `datetime_gen` in the configuration
+    #[command(name = "datetime")]
+    DateTime,
+    /// Sets wallpaper to a fractal flame: glowing, swirling shapes drawn by the chaos game
+    Flame,
+    /// Sets wallpaper to a fractal (fern, Sierpinski triangle, dragon curve) drawn by the chaos game
+    Ifs,
     /// Sets wallpaper to a randomly generated Julia Set
     Julia,
+    /// Sets wallpaper to a kaleidoscopic pattern repeated around the center of the screen
+    Mandala,
     /// Sets wallpaper to one of NASA's Astronomy Pictures of the Day (if no args passed, defaults
     /// to today's image)
     NasaAPOD {
@@ -76,6 +302,11 @@ pub enum Generator {
     },
     /// Sets wallpaper to one of Bing's daily Spotlight images
     Spotlight,
+    /// Sets wallpaper to a text or quote (see `text_gen` in the configuration) over a themed
+    /// gradient
+    Text,
+    /// Sets wallpaper to a maze-like pattern of randomly oriented Truchet tiles
+    Truchet,
 }
 
 impl FromStr for Generator {
@@ -83,9 +314,15 @@ impl FromStr for Generator {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
+            "datetime" => Ok(Generator::DateTime),
+            "flame" => Ok(Generator::Flame),
+            "ifs" => Ok(Generator::Ifs),
             "julia" => Ok(Generator::Julia),
+            "mandala" => Ok(Generator::Mandala),
             "nasa_apod" => Ok(Generator::NasaAPOD { date: None }),
             "spotlight" => Ok(Generator::Spotlight),
+            "text" => Ok(Generator::Text),
+            "truchet" => Ok(Generator::Truchet),
             "solid" => Ok(Generator::Solid {
                 mode: SolidMode::Random,
             }),
@@ -100,19 +337,36 @@ impl Generator {
         config: &Config,
     ) -> Result<AstraImage, WallpaperGeneratorError> {
         match self {
+            Generator::DateTime => generate_datetime(config),
+            Generator::Flame => generate_flame(config),
+            Generator::Ifs => generate_ifs(config),
             Generator::Julia => generate_julia_set(config),
+            Generator::Mandala => generate_mandala(config),
             Generator::NasaAPOD { date } => generate_nasa_apod(config, date),
             Generator::Solid { mode } => generate_solid_color(config, mode),
             Generator::Spotlight => generate_bing_spotlight(config),
+            Generator::Text => generate_text(config),
+            Generator::Truchet => generate_truchet(config),
         }
     }
 
+    /// True for generators downloading their image instead of rendering it
+    pub fn downloads(&self) -> bool {
+        matches!(self, Generator::NasaAPOD { .. } | Generator::Spotlight)
+    }
+
     pub fn prefix(&self) -> &str {
         match self {
+            Generator::DateTime => "datetime",
+            Generator::Flame => "flame",
+            Generator::Ifs => "ifs",
             Generator::Julia => "julia",
+            Generator::Mandala => "mandala",
             Generator::NasaAPOD { date: _ } => "nasa_apod",
             Generator::Solid { mode: _ } => "solid",
             Generator::Spotlight => "spotlight",
+            Generator::Text => "text",
+            Generator::Truchet => "truchet",
         }
     }
 }
@@ -125,6 +379,31 @@ pub enum SolidMode {
         #[arg(value_enum)]
         name: Color,
     },
+    /// Use a random color from a curated list of named colors
+    Curated {
+        #[arg(long, value_enum)]
+        /// Only pick colors from this hue family
+        hue: Option<HueFamily>,
+    },
+    /// Use a linear gradient between two colors
+    Gradient {
+        /// Starting color as a color name or hex value (e.g. navy-blue, #1e90ff)
+        from: String,
+        /// Ending color as a color name or hex value
+        to: String,
+        #[arg(long, value_enum, default_value_t = GradientDirection::Horizontal)]
+        /// Direction the gradient goes from `from` to `to`
+        direction: GradientDirection,
+    },
+    /// Use a color in harmony with a base color (hue rotated on the color wheel)
+    Harmony {
+        /// Color harmony used to derive the color from the base color
+        #[arg(value_enum)]
+        scheme: HarmonyScheme,
+        #[arg(long)]
+        /// Base color as a color name or hex value (e.g. navy-blue, #1e90ff). Random if omitted
+        base: Option<String>,
+    },
     /// Use a random color
     Random,
     /// Use a custom color by RGB value
@@ -137,3 +416,96 @@ pub enum SolidMode {
         b: u8,
     },
 }
+
+/// Names of the shells `astra generate-completions` can generate completion scripts for
+pub fn supported_shells() -> Vec<String> {
+    Shell::value_variants()
+        .iter()
+        .filter_map(|shell| shell.to_possible_value())
+        .map(|value| value.get_name().to_string())
+        .collect()
+}
+
+/// Lines wrapping the completions written by `astra generate-completions --append`
+const COMPLETIONS_BLOCK_START: &str = "# >>> astra completions >>>";
+const COMPLETIONS_BLOCK_END: &str = "# <<< astra completions <<<";
+
+/// Adds `completions` to the end of `contents` between marker comments. If `contents` already has
+/// a block of astra completions, that block is replaced instead.
+pub fn append_completions(contents: &str, completions: &str) -> String {
+    let block = format!(
+        "{COMPLETIONS_BLOCK_START}\n{}\n{COMPLETIONS_BLOCK_END}\n",
+        completions.trim_end()
+    );
+    if let Some(start) = contents.find(COMPLETIONS_BLOCK_START)
+        && let Some(end) = contents[start..].find(COMPLETIONS_BLOCK_END)
+    {
+        let after = &contents[start + end + COMPLETIONS_BLOCK_END.len()..];
+        return format!(
+            "{}{block}{}",
+            &contents[..start],
+            after.strip_prefix('\n').unwrap_or(after)
+        );
+    }
+    let separator = if contents.is_empty() || contents.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+    format!("{contents}{separator}{block}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supported_shells_contains_known_shells() {
+        let shells = supported_shells();
+        for shell in ["bash", "elvish", "fish", "powershell", "zsh"] {
+            assert!(shells.contains(&shell.to_string()), "missing {shell}");
+        }
+    }
+
+    #[test]
+    fn test_appending_completions_twice_replaces_the_previous_block() {
+        let rc = "alias ll='ls -l'\n";
+        let once = append_completions(rc, "complete -F _astra astra\n");
+        assert!(once.starts_with(rc));
+        let twice = append_completions(&once, "complete -F _astra_v2 astra\n");
+        assert_eq!(twice.matches(COMPLETIONS_BLOCK_START).count(), 1);
+        assert!(twice.contains("_astra_v2"));
+        assert!(!twice.contains("_astra astra"));
+        assert!(twice.starts_with(rc));
+
+        // content after the block is kept
+        let edited = format!("{once}export PATH=$PATH:~/bin\n");
+        let replaced = append_completions(&edited, "complete -F _astra_v2 astra");
+        assert!(replaced.ends_with("export PATH=$PATH:~/bin\n"));
+        assert_eq!(replaced.matches(COMPLETIONS_BLOCK_END).count(), 1);
+    }
+
+    #[test]
+    fn test_generate_completions_requires_exactly_one_of_shell_or_list() {
+        assert!(Cli::try_parse_from(["astra", "generate-completions", "--list"]).is_ok());
+        assert!(Cli::try_parse_from(["astra", "generate-completions", "zsh"]).is_ok());
+        assert!(Cli::try_parse_from(["astra", "generate-completions"]).is_err());
+        assert!(Cli::try_parse_from(["astra", "generate-completions", "zsh", "--list"]).is_err());
+    }
+
+    #[test]
+    fn test_palette_conflicts_with_theme() {
+        let palette = ["--palette", "#001f3f,#0074d9"];
+        let generate = |args: &[&str]| {
+            Cli::try_parse_from(
+                ["astra", "generate"]
+                    .iter()
+                    .chain(args)
+                    .chain(&["solid", "random"]),
+            )
+        };
+        assert!(generate(&palette).is_ok());
+        assert!(generate(&[&palette[..], &["--theme", "neon-dreams"]].concat()).is_err());
+        assert!(generate(&[&["--theme", "neon-dreams"][..], &palette].concat()).is_err());
+    }
+}
@@ -1,60 +1,814 @@
-use super::super::constants::{APPLICATION, ORGANIZATION, QUALIFIER};
+use super::super::cli::Generator;
+use super::super::constants::{
+    APPLICATION, FREQUENCY_ENV_VAR, ORGANIZATION, QUALIFIER, THEME_ENV_VAR,
+};
+use super::super::logger::{DEFAULT_LOG_MAX_SIZE_KB, FileLogger, LogLevel};
+use super::super::os_implementations::{get_screen_resolution, is_dark_mode_active};
+use super::super::themes::{ColorTheme, ThemeSelector};
+use super::super::wallpaper_generators::{
+    FilenameScheme, OriginalImage, UpscaleFilter, WallpaperFormat, parse_color,
+};
 use super::{
+    explain::{Decision, DecisionLog},
     frequency::Frequency,
-    generators::{Generators, JuliaConfig, NasaApodConfig, SolidConfig, SpotlightConfig},
-    theme::ThemeConfigs,
+    generators::{
+        DateTimeConfig, FlameConfig, Generators, IfsConfig, JuliaConfig, MandalaConfig,
+        NasaApodConfig, SolidConfig, SpotlightConfig, TextConfig, TruchetConfig,
+    },
+    hook::HookCommand,
+    instance::Instance,
+    lint::{LintWarning, lint, theme_contrast, theme_shadows_built_in},
+    log_file::LogFileConfig,
+    migrate::{MIGRATIONS, Migration, migrate},
+    post_process::PostProcessConfig,
+    state::State,
+    theme::{ThemeConfig, ThemeConfigs, ThemeSelection, max_contrast_theme, normalize_theme_name},
     user_config::UserConfig,
+    watermark::WatermarkConfig,
 };
+use chrono::{Datelike, Local, NaiveDate, Timelike};
 use directories::ProjectDirs;
+use rand::{Rng, RngExt, SeedableRng, rngs::StdRng};
 use std::{
+    collections::BTreeMap,
     error::Error,
     fmt::Display,
     fs,
     io::Write,
     path::{Path, PathBuf},
+    process::Stdio,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
 };
 
+/// Smallest `render_scale`, below it the upscaled wallpaper is too blurry
+const MIN_RENDER_SCALE: f64 = 0.25;
+/// Entries of the color maps fractal generators color their images with
+const DEFAULT_COLOR_STEPS: usize = 256;
+/// A color map needs both ends of the gradient
+const MIN_COLOR_STEPS: usize = 2;
+/// Desktop icon labels are drawn in white by default on every OS
+const DEFAULT_FOREGROUND: [u8; 3] = [255, 255, 255];
+
 pub struct Config {
     // true if call to 'astra', false if specific gen called: 'astra generate solid random'
     pub respect_user_config: bool,
     // From CLI options
     verbose: bool,
-    user_config: Option<UserConfig>,
+    // Bypasses the elapsed-time gate of scheduled runs (`astra --force`)
+    force: bool,
+    // Shared with the copies made by `fork` for worker threads
+    user_config: Option<Arc<UserConfig>>,
+    // From the ASTRA_FREQUENCY environment variable, only used when the config has no frequency
+    env_frequency: Option<Frequency>,
+    // Source of randomness for generators, seeded from the OS unless `--seed` is passed
+    rng: Mutex<StdRng>,
+    // Seed `rng` started from, random unless `--seed` is passed (recorded in the saved PNGs)
+    seed: u64,
+    // Picks the themes instead of `rng` when `--theme-seed` is passed, so the theme and the image
+    // parameters can be reproduced independently
+    theme_rng: Option<Mutex<StdRng>>,
+    theme_seed: Option<u64>,
+    // Used instead of querying the OS, allows generators to run headless (e.g. `astra benchmark`)
+    resolution_override: Option<(u32, u32)>,
+    dark_mode_override: Option<bool>,
+    // From `--instance`, takes precedence over `instance` in the configuration file
+    instance_override: Option<Instance>,
+    // Theme every generator uses with where it was forced from: `astra generate --theme <name>`,
+    // `--theme-from-file <path> --theme-name <name>` or the ASTRA_THEME environment variable
+    theme_override: Option<(ColorTheme, &'static str)>,
+    // Collects how the wallpaper was picked when `--explain` is passed, shared with forks
+    decision_log: Option<Arc<DecisionLog>>,
+    // First theme picked in the run, reused by every generator when `consistent_theme` is
+    // enabled. Shared with forks
+    run_theme: Arc<Mutex<Option<ColorTheme>>>,
+    // Name of the theme picked for the image being generated, cleared before each generator
+    // attempt. Shared with forks
+    image_theme: Arc<Mutex<Option<String>>>,
+    // Downloaded file of the latest image, saved as is with `spotlight_gen.keep_original`. Shared
+    // with forks
+    original_image: Arc<Mutex<Option<OriginalImage>>>,
+    // State changed by the generator of the image being generated (e.g. the next quote), written
+    // when the wallpaper is committed. Not shared with forks, see `generate_with_timeout`
+    staged_state: Arc<Mutex<Option<State>>>,
 }
 
 impl Config {
-    pub fn new(verbose: bool) -> Self {
+    /// Reads the configuration file of `instance` (`--instance`, `None` for the default
+    /// configuration file) and selects the instance astra runs as, see `Instance::select`
+    pub fn new(verbose: bool, instance: Option<Instance>) -> Self {
+        if let Some(instance) = &instance {
+            Instance::select(instance.clone());
+        }
+        let seed = rand::random();
+        let env_frequency =
+            Self::frequency_from_env(std::env::var(FREQUENCY_ENV_VAR).ok().as_deref())
+                .unwrap_or_else(|e| {
+                    if verbose {
+                        eprintln!("WARN - ignoring {FREQUENCY_ENV_VAR} due to error(s): {e}");
+                    }
+                    None
+                });
         match Config::read_config_file_if_exists(verbose) {
-            Ok(user_config) => Self {
-                respect_user_config: false,
-                verbose,
-                user_config: Some(UserConfig {
-                    auto_clean: user_config.auto_clean,
-                    frequency: user_config.frequency,
-                    generators: user_config.generators,
-                    julia_gen: user_config.julia_gen,
-                    nasa_apod_gen: user_config.nasa_apod_gen,
-                    solid_gen: user_config.solid_gen,
-                    spotlight_gen: user_config.spotlight_gen,
-                    themes: user_config.themes,
-                }),
-            },
+            Ok(user_config) => {
+                if let Some(instance) = &user_config.instance {
+                    Instance::select(instance.clone());
+                }
+                if verbose {
+                    for warning in theme_shadows_built_in(&user_config) {
+                        eprintln!("WARN - {warning}");
+                    }
+                }
+                Self {
+                    respect_user_config: false,
+                    verbose,
+                    force: false,
+                    user_config: Some(Arc::new(UserConfig {
+                        all_spaces: user_config.all_spaces,
+                        auto_clean: user_config.auto_clean,
+                        background: user_config.background,
+                        calendar: user_config.calendar,
+                        color_steps: user_config.color_steps,
+                        consistent_theme: user_config.consistent_theme,
+                        daily_seed: user_config.daily_seed,
+                        datetime_gen: user_config.datetime_gen,
+                        fallback_generator: user_config.fallback_generator,
+                        filename_scheme: user_config.filename_scheme,
+                        flame_gen: user_config.flame_gen,
+                        foreground: user_config.foreground,
+                        format: user_config.format,
+                        frequency: user_config.frequency,
+                        generation_timeout_secs: user_config.generation_timeout_secs,
+                        generator_weights: user_config.generator_weights,
+                        generators: user_config.generators,
+                        ifs_gen: user_config.ifs_gen,
+                        instance: user_config.instance,
+                        julia_gen: user_config.julia_gen,
+                        latest_symlink: user_config.latest_symlink,
+                        log_file: user_config.log_file,
+                        mandala_gen: user_config.mandala_gen,
+                        max_resolution: user_config.max_resolution,
+                        nasa_apod_gen: user_config.nasa_apod_gen,
+                        per_monitor: user_config.per_monitor,
+                        post_process: user_config.post_process,
+                        post_update_hook: user_config.post_update_hook,
+                        pre_generate_hook: user_config.pre_generate_hook,
+                        render_scale: user_config.render_scale,
+                        schedule_by_time: user_config.schedule_by_time,
+                        single_file_mode: user_config.single_file_mode,
+                        skip_if_unchanged: user_config.skip_if_unchanged,
+                        solid_gen: user_config.solid_gen,
+                        spotlight_blocklist: user_config.spotlight_blocklist,
+                        spotlight_gen: user_config.spotlight_gen,
+                        text_gen: user_config.text_gen,
+                        theme_selection: user_config.theme_selection,
+                        themes: user_config.themes,
+                        transition: user_config.transition,
+                        transition_frames: user_config.transition_frames,
+                        truchet_gen: user_config.truchet_gen,
+                        upscale_filter: user_config.upscale_filter,
+                        watermark: user_config.watermark,
+                    })),
+                    env_frequency,
+                    rng: Mutex::new(StdRng::seed_from_u64(seed)),
+                    seed,
+                    theme_rng: None,
+                    theme_seed: None,
+                    resolution_override: None,
+                    dark_mode_override: None,
+                    instance_override: instance,
+                    theme_override: None,
+                    decision_log: None,
+                    run_theme: Arc::default(),
+                    image_theme: Arc::default(),
+                    original_image: Arc::default(),
+                    staged_state: Arc::default(),
+                }
+            }
             Err(e) => {
                 if verbose {
-                    println!("WARN - ignoring configuration due to error(s): {e}");
+                    eprintln!("WARN - ignoring configuration due to error(s): {e}");
                 }
                 Self {
                     respect_user_config: false,
                     verbose,
+                    force: false,
                     user_config: None,
+                    env_frequency,
+                    rng: Mutex::new(StdRng::seed_from_u64(seed)),
+                    seed,
+                    theme_rng: None,
+                    theme_seed: None,
+                    resolution_override: None,
+                    dark_mode_override: None,
+                    instance_override: instance,
+                    theme_override: None,
+                    decision_log: None,
+                    run_theme: Arc::default(),
+                    image_theme: Arc::default(),
+                    original_image: Arc::default(),
+                    staged_state: Arc::default(),
                 }
             }
         }
     }
 
+    /// Builds a configuration from a JSON string (same format as the configuration file).
+    /// Useful for tests that depend on specific user configuration.
+    #[cfg(test)]
+    pub fn from_json(json: &str) -> Self {
+        let seed = rand::random();
+        Self {
+            respect_user_config: false,
+            verbose: false,
+            force: false,
+            user_config: Some(Arc::new(
+                serde_json::from_str(json).expect("test config should be valid"),
+            )),
+            env_frequency: None,
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+            seed,
+            theme_rng: None,
+            theme_seed: None,
+            resolution_override: None,
+            dark_mode_override: None,
+            instance_override: None,
+            theme_override: None,
+            decision_log: None,
+            run_theme: Arc::default(),
+            image_theme: Arc::default(),
+            original_image: Arc::default(),
+            staged_state: Arc::default(),
+        }
+    }
+
+    /// Copy of this configuration for another thread. The copy gets its own random number
+    /// generator (seeded from this one, so `--seed` stays reproducible) so that a worker that never
+    /// finishes cannot hold on to this configuration's generator. State staged in the copy is not
+    /// seen by this configuration (see `stage_state`).
+    pub fn fork(&self) -> Config {
+        Self {
+            respect_user_config: self.respect_user_config,
+            verbose: self.verbose,
+            force: self.force,
+            user_config: self.user_config.clone(),
+            env_frequency: self.env_frequency.clone(),
+            rng: Mutex::new(StdRng::seed_from_u64(self.rng().random())),
+            seed: self.seed,
+            theme_rng: self.theme_rng.as_ref().map(|rng| {
+                let seed = rng.lock().unwrap_or_else(|e| e.into_inner()).random();
+                Mutex::new(StdRng::seed_from_u64(seed))
+            }),
+            theme_seed: self.theme_seed,
+            resolution_override: self.resolution_override,
+            dark_mode_override: self.dark_mode_override,
+            instance_override: self.instance_override.clone(),
+            theme_override: self.theme_override.clone(),
+            decision_log: self.decision_log.clone(),
+            run_theme: self.run_theme.clone(),
+            image_theme: self.image_theme.clone(),
+            original_image: self.original_image.clone(),
+            staged_state: Arc::default(),
+        }
+    }
+
+    /// Re-seeds the random number generator so that generators produce reproducible output
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = Mutex::new(StdRng::seed_from_u64(seed));
+        self.seed = seed;
+    }
+
+    /// Seed of the random number generator, passing it to `--seed` reproduces the run
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Seed passed to `--theme-seed`, if any
+    pub fn theme_seed(&self) -> Option<u64> {
+        self.theme_seed
+    }
+
+    /// Seeds the themes picked by generators separately from the rest of the image (`--theme-seed`),
+    /// so that `--seed` no longer changes the theme
+    pub fn seed_theme_rng(&mut self, seed: u64) {
+        self.theme_rng = Some(Mutex::new(StdRng::seed_from_u64(seed)));
+        self.theme_seed = Some(seed);
+    }
+
+    /// Re-seeds the random number generator from `date` (`daily_seed`), so every run on the same
+    /// day picks the same wallpaper. Returns the seed, the date as `YYYYMMDD`.
+    pub fn seed_rng_from_date(&mut self, date: NaiveDate) -> u64 {
+        let seed = date.year() as u64 * 10_000 + date.month() as u64 * 100 + date.day() as u64;
+        self.seed_rng(seed);
+        seed
+    }
+
+    /// Instance whose scheduled job astra installs and removes, `None` for the default job
+    pub fn instance(&self) -> Option<&Instance> {
+        self.instance_override.as_ref().or_else(|| {
+            self.user_config
+                .as_ref()
+                .and_then(|user_config| user_config.instance.as_ref())
+        })
+    }
+
+    /// Makes manual runs ignore whether the configured frequency has elapsed
+    pub fn set_force(&mut self, force: bool) {
+        self.force = force;
+    }
+
+    pub fn force(&self) -> bool {
+        self.force
+    }
+
+    /// Configuration astra runs with: the configuration file with the `ASTRA_FREQUENCY` fallback
+    /// applied, and the global command line flags under `cli` (printed by `--print-config`)
+    pub fn effective_config(
+        &self,
+        seed: Option<u64>,
+        theme_seed: Option<u64>,
+    ) -> serde_json::Value {
+        let mut effective = match &self.user_config {
+            Some(user_config) => serde_json::to_value(user_config.as_ref()),
+            None => serde_json::to_value(UserConfig::default()),
+        }
+        .expect("configuration only contains serializable values");
+        effective["frequency"] = serde_json::json!(self.frequency());
+        effective["instance"] = serde_json::json!(self.instance());
+        effective["cli"] = serde_json::json!({
+            "force": self.force,
+            "seed": seed,
+            "theme_seed": theme_seed,
+            "verbose": self.verbose,
+        });
+        effective
+    }
+
+    /// Runs the `pre_generate_hook` and merges the JSON object it prints over the configuration for
+    /// this run (objects are merged key by key). Output that is not JSON is ignored. Fails if the
+    /// hook cannot run, exits with an error or prints an invalid configuration.
+    pub fn apply_pre_generate_hook(&mut self) -> Result<(), ConfigError> {
+        let Some(user_config) = &self.user_config else {
+            return Ok(());
+        };
+        let Some(hook) = &user_config.pre_generate_hook else {
+            return Ok(());
+        };
+        self.print_if_verbose(format!("Running pre_generate_hook: {hook}").as_str());
+        let output = hook
+            .command()
+            .ok_or_else(|| ConfigError::PreGenerateHook("the command is empty".to_string()))?
+            .stderr(Stdio::inherit())
+            .output()
+            .map_err(|e| ConfigError::PreGenerateHook(e.to_string()))?;
+        if !output.status.success() {
+            return Err(ConfigError::PreGenerateHook(format!(
+                "exited with {}",
+                output.status
+            )));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout.trim().is_empty() {
+            return Ok(());
+        }
+        let overrides = match serde_json::from_str::<serde_json::Value>(&stdout) {
+            Ok(serde_json::Value::Object(overrides)) => overrides,
+            Ok(_) => {
+                return Err(ConfigError::PreGenerateHook(
+                    "printed JSON that is not an object".to_string(),
+                ));
+            }
+            Err(_) => {
+                self.print_if_verbose(
+                    "pre_generate_hook did not print JSON, keeping configuration",
+                );
+                return Ok(());
+            }
+        };
+        let mut config = serde_json::to_value(user_config.as_ref())
+            .expect("configuration only contains serializable values");
+        merge_json(&mut config, serde_json::Value::Object(overrides));
+        let user_config: UserConfig = serde_json::from_value(config)
+            .map_err(|e| ConfigError::PreGenerateHook(format!("invalid configuration - {e}")))?;
+        if self.verbose {
+            eprintln!("configuration for this run:");
+            eprintln!("{user_config}");
+        }
+        self.user_config = Some(Arc::new(user_config));
+        Ok(())
+    }
+
+    /// Makes generators use the given resolution instead of detecting the screen resolution
+    pub fn set_resolution_override(&mut self, width: u32, height: u32) {
+        self.resolution_override = Some((width, height));
+    }
+
+    /// Makes generators render at `width` x `height` (`--width`/`--height`). When only one of them
+    /// is given, the other is derived from the aspect ratio of the screen. Does nothing when neither
+    /// is given.
+    pub fn set_resolution_from_dimensions(
+        &mut self,
+        width: Option<u32>,
+        height: Option<u32>,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some((width, height)) =
+            Self::resolution_from_dimensions(width, height, || Ok(get_screen_resolution()?))?
+        {
+            self.print_if_verbose(format!("Using resolution: {width}x{height}").as_str());
+            self.set_resolution_override(width, height);
+        }
+        Ok(())
+    }
+
+    fn resolution_from_dimensions(
+        width: Option<u32>,
+        height: Option<u32>,
+        screen_resolution: impl FnOnce() -> Result<(u32, u32), Box<dyn Error>>,
+    ) -> Result<Option<(u32, u32)>, Box<dyn Error>> {
+        let scale = |length: u32, from: u32, to: u32| {
+            ((length as f64 * to as f64 / from.max(1) as f64).round() as u32).max(1)
+        };
+        Ok(match (width, height) {
+            (Some(width), Some(height)) => Some((width, height)),
+            (Some(width), None) => {
+                let (screen_width, screen_height) = screen_resolution()?;
+                Some((width, scale(width, screen_width, screen_height)))
+            }
+            (None, Some(height)) => {
+                let (screen_width, screen_height) = screen_resolution()?;
+                Some((scale(height, screen_height, screen_width), height))
+            }
+            (None, None) => None,
+        })
+    }
+
+    /// Makes generators use the given appearance instead of detecting the OS dark mode
+    pub fn set_dark_mode_override(&mut self, dark_mode: bool) {
+        self.dark_mode_override = Some(dark_mode);
+    }
+
+    /// Makes every generator use the theme named `name` from the JSON file at `path` (an array of
+    /// themes, in the same format as `themes` in the configuration file)
+    pub fn set_theme_from_file(&mut self, path: &Path, name: &str) -> Result<(), ConfigError> {
+        let themes = ThemeConfigs::from_file(path)?;
+        let theme = themes.find(name).ok_or_else(|| {
+            ConfigError::ThemeFile(format!(
+                "no theme named \"{name}\" in {} (available: {})",
+                path.display(),
+                themes
+                    .themes()
+                    .iter()
+                    .map(|theme| theme.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        })?;
+        self.print_if_verbose(format!("Using theme {name} from {}", path.display()).as_str());
+        self.theme_override = Some((theme.to_color_theme(), "forced with --theme-from-file"));
+        Ok(())
+    }
+
+    /// Makes every generator use the theme named by `--theme` (`flag`), else by the ASTRA_THEME
+    /// environment variable (`env_theme`). Fails if no built-in or configured theme has that name.
+    pub fn set_theme_from_flag_or_env(
+        &mut self,
+        flag: Option<&str>,
+        env_theme: Option<&str>,
+    ) -> Result<(), ConfigError> {
+        let (name, source) = match (flag, env_theme.map(str::trim)) {
+            (Some(name), _) => (name, "forced with --theme"),
+            (None, Some(name)) if !name.is_empty() => (name, "forced with ASTRA_THEME"),
+            _ => return Ok(()),
+        };
+        let theme = self.resolve_theme_by_name(name).ok_or_else(|| {
+            ConfigError::UnknownTheme(format!(
+                "no built-in or configured theme named \"{name}\" ({source})"
+            ))
+        })?;
+        self.print_if_verbose(format!("Using theme {} ({source})", theme.name()).as_str());
+        self.theme_override = Some((theme, source));
+        Ok(())
+    }
+
+    /// Makes every generator use `palette` (`--palette`) as their theme
+    pub fn set_theme_from_palette(&mut self, palette: ColorTheme) {
+        self.print_if_verbose(
+            format!(
+                "Using an inline palette of {} colors",
+                palette.get_colors(false).len()
+            )
+            .as_str(),
+        );
+        self.theme_override = Some((palette, "forced with --palette"));
+    }
+
+    /// Same as `set_theme_from_flag_or_env` with the value of the ASTRA_THEME environment variable
+    pub fn set_theme_from_flag_or_env_var(
+        &mut self,
+        flag: Option<&str>,
+    ) -> Result<(), ConfigError> {
+        self.set_theme_from_flag_or_env(flag, std::env::var(THEME_ENV_VAR).ok().as_deref())
+    }
+
+    /// Theme a generator should use: the theme forced with `--theme`, `--theme-from-file` or
+    /// ASTRA_THEME if any, else one of
+    /// the user's themes when `respect_color_themes` is enabled for the generator, else a random
+    /// built-in theme. With `consistent_theme`, every generator of the run reuses the first pick.
+    pub fn select_theme<R: Rng + ?Sized>(
+        &self,
+        respect_color_themes: bool,
+        rng: &mut R,
+    ) -> ThemeSelector {
+        let selector = if self.theme_override.is_none() && self.consistent_theme() {
+            let mut run_theme = self.run_theme.lock().unwrap_or_else(|e| e.into_inner());
+            match run_theme.as_ref() {
+                Some(theme) => {
+                    self.explain(Decision::Theme {
+                        name: theme.name().to_string(),
+                        source: "same theme as the rest of the run (consistent_theme)",
+                    });
+                    ThemeSelector::from_color_theme(theme.clone())
+                }
+                None => {
+                    let selector = self.pick_theme(respect_color_themes, rng);
+                    *run_theme = Some(selector.selected().clone());
+                    selector
+                }
+            }
+        } else {
+            self.pick_theme(respect_color_themes, rng)
+        };
+        *self.image_theme.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some(selector.selected().name().to_string());
+        selector
+    }
+
+    /// Name of the theme picked for the image being generated, if any
+    pub fn image_theme_name(&self) -> Option<String> {
+        self.image_theme
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Forgets the theme of the previous image, called before each generator attempt so that an
+    /// image generated without a theme does not report the theme of a failed attempt
+    pub fn reset_image_theme(&self) {
+        *self.image_theme.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Keeps the downloaded file of the image a generator returns so that it is saved instead of
+    /// re-encoding the image (`None` forgets the previous one)
+    pub fn set_original_image(&self, original: Option<OriginalImage>) {
+        *self
+            .original_image
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = original;
+    }
+
+    /// Downloaded file of the latest image, if a generator kept one
+    pub fn original_image(&self) -> Option<OriginalImage> {
+        self.original_image
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Changes the state file with `change` once the image being generated is set as the
+    /// wallpaper: the staged state is taken by `handle_generate_options` and written when the
+    /// wallpaper is committed, so an image that is not applied leaves the state file as it was
+    pub fn stage_state(&self, change: impl FnOnce(&mut State)) {
+        let mut staged = self.staged_state.lock().unwrap_or_else(|e| e.into_inner());
+        change(staged.get_or_insert_with(State::load));
+    }
+
+    /// Replaces the staged state (`None` forgets the changes of the previous image)
+    pub fn set_staged_state(&self, state: Option<State>) {
+        *self.staged_state.lock().unwrap_or_else(|e| e.into_inner()) = state;
+    }
+
+    /// Takes the state staged by the generator of the latest image, if it changed it
+    pub fn take_staged_state(&self) -> Option<State> {
+        self.staged_state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+    }
+
+    /// Picks a theme with `rng`, or with the `--theme-seed` generator when one is set
+    fn pick_theme<R: Rng + ?Sized>(
+        &self,
+        respect_color_themes: bool,
+        rng: &mut R,
+    ) -> ThemeSelector {
+        match &self.theme_rng {
+            Some(theme_rng) => self.pick_theme_with(
+                respect_color_themes,
+                &mut *theme_rng.lock().unwrap_or_else(|e| e.into_inner()),
+            ),
+            None => self.pick_theme_with(respect_color_themes, rng),
+        }
+    }
+
+    fn pick_theme_with<R: Rng + ?Sized>(
+        &self,
+        respect_color_themes: bool,
+        rng: &mut R,
+    ) -> ThemeSelector {
+        let (selector, source) = match (&self.theme_override, respect_color_themes, self.themes()) {
+            (Some((theme, source)), _, _) => {
+                (ThemeSelector::from_color_theme(theme.clone()), *source)
+            }
+            (None, respect_color_themes, themes)
+                if self.theme_selection() == ThemeSelection::MaxContrast =>
+            {
+                let candidates = match (respect_color_themes, themes) {
+                    (true, Some(themes)) => themes
+                        .themes()
+                        .iter()
+                        .map(|theme| theme.to_color_theme())
+                        .collect(),
+                    _ => ThemeSelector::built_in_themes(),
+                };
+                let dark_mode = self.is_dark_mode_active().unwrap_or(false);
+                match max_contrast_theme(candidates, dark_mode, self.foreground()) {
+                    Some(theme) => (
+                        ThemeSelector::from_color_theme(theme),
+                        "highest contrast with the foreground color (theme_selection)",
+                    ),
+                    None => (
+                        ThemeSelector::random_with(rng),
+                        "random built-in theme, no theme has colors to compare (theme_selection)",
+                    ),
+                }
+            }
+            (None, true, Some(themes)) => (
+                themes.random_with(rng).to_theme_selector(),
+                "random theme from your themes (respect_color_themes)",
+            ),
+            (None, true, None) => (
+                ThemeSelector::random_with(rng),
+                "random built-in theme, respect_color_themes is on but no themes are configured",
+            ),
+            (None, false, _) => (ThemeSelector::random_with(rng), "random built-in theme"),
+        };
+        self.explain(Decision::Theme {
+            name: selector.selected().name().to_string(),
+            source,
+        });
+        selector
+    }
+
+    /// Looks up a theme by name for every feature selecting a theme by name. Case, spaces, dashes
+    /// and underscores are ignored (`neon-dreams` matches "Neon Dreams"). A user theme takes
+    /// precedence over a built-in theme with the same name.
+    pub fn resolve_theme_by_name(&self, name: &str) -> Option<ColorTheme> {
+        let name = normalize_theme_name(name);
+        self.themes()
+            .and_then(|themes| {
+                themes
+                    .themes()
+                    .iter()
+                    .find(|theme| normalize_theme_name(theme.name()) == name)
+                    .map(|theme| theme.to_color_theme())
+            })
+            .or_else(|| {
+                ThemeSelector::built_in_themes()
+                    .into_iter()
+                    .find(|theme| normalize_theme_name(theme.name()) == name)
+            })
+    }
+
+    pub fn has_theme_override(&self) -> bool {
+        self.theme_override.is_some()
+    }
+
+    /// Resolution generators should render at: the output resolution scaled by `render_scale`
+    pub fn screen_resolution(&self) -> Result<(u32, u32), Box<dyn Error>> {
+        let (width, height) = self.output_resolution()?;
+        let scale = self.render_scale();
+        if scale >= 1.0 {
+            return Ok((width, height));
+        }
+        let scaled = |length: u32| ((length as f64 * scale).round() as u32).max(1);
+        Ok((scaled(width), scaled(height)))
+    }
+
+    /// Resolution of the saved wallpaper: the override if set, else the detected screen resolution
+    /// scaled down to fit `max_resolution`
+    pub fn output_resolution(&self) -> Result<(u32, u32), Box<dyn Error>> {
+        match self.resolution_override {
+            Some(resolution) => Ok(resolution),
+            None => Ok(self.capped_resolution(get_screen_resolution()?)),
+        }
+    }
+
+    /// Scales `resolution` down, keeping its aspect ratio, so that it fits in `max_resolution`
+    pub fn capped_resolution(&self, (width, height): (u32, u32)) -> (u32, u32) {
+        let Some((max_width, max_height)) = self.max_resolution() else {
+            return (width, height);
+        };
+        let scale =
+            (max_width as f64 / width.max(1) as f64).min(max_height as f64 / height.max(1) as f64);
+        if scale >= 1.0 {
+            return (width, height);
+        }
+        let capped = (
+            ((width as f64 * scale).round() as u32).max(1),
+            ((height as f64 * scale).round() as u32).max(1),
+        );
+        self.print_if_verbose(
+            format!(
+                "Rendering at {}x{} instead of {width}x{height} (max_resolution)",
+                capped.0, capped.1
+            )
+            .as_str(),
+        );
+        capped
+    }
+
+    /// Whether generators should use dark mode colors: the override if set, else the OS setting
+    pub fn is_dark_mode_active(&self) -> Result<bool, Box<dyn Error>> {
+        let (dark_mode, source) = match self.dark_mode_override {
+            Some(dark_mode) => (dark_mode, "overridden"),
+            None => (is_dark_mode_active()?, "detected from the OS"),
+        };
+        self.explain(Decision::Appearance { dark_mode, source });
+        Ok(dark_mode)
+    }
+
+    /// Records the decisions made while picking the wallpaper, see `explain`
+    pub fn enable_explain(&mut self) {
+        self.decision_log = Some(Arc::new(DecisionLog::default()));
+    }
+
+    /// Appends a step to the reasoning printed by `astra --explain` (does nothing without it)
+    pub fn explain(&self, decision: Decision) {
+        if let Some(decision_log) = &self.decision_log {
+            decision_log.record(decision);
+        }
+    }
+
+    /// Decisions recorded since `enable_explain` was called
+    pub fn explanation(&self) -> Vec<Decision> {
+        self.decision_log
+            .as_ref()
+            .map(|decision_log| decision_log.decisions())
+            .unwrap_or_default()
+    }
+
+    /// Returns the random number generator that generators should use for any random choice.
+    ///
+    /// The guard must not be held while calling `rng` again.
+    pub fn rng(&self) -> MutexGuard<'_, StdRng> {
+        self.rng.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Random number generator for a generator whose configuration section may set a `seed`. A
+    /// configured seed makes the generator reproducible regardless of `--seed`, otherwise the
+    /// generator is seeded from `rng` (so `--seed` stays reproducible).
+    pub fn generator_rng(&self, seed: Option<u64>) -> StdRng {
+        match seed {
+            Some(seed) => {
+                self.print_if_verbose(format!("Using configured seed: {seed}").as_str());
+                StdRng::seed_from_u64(seed)
+            }
+            None => StdRng::seed_from_u64(self.rng().random()),
+        }
+    }
+
     pub fn print_if_verbose(&self, message: &str) {
         if self.verbose {
-            println!("{}", message);
+            eprintln!("{}", message);
+        }
+    }
+
+    /// Appends a line to the log file when `log_file` is configured.
+    ///
+    /// Only runs of `astra` (such as the ones triggered by the scheduler) are logged, unless
+    /// `log_file.interactive` is enabled. Failing to write the log never fails the run.
+    pub fn log(&self, level: LogLevel, message: &str) {
+        let Some(log_file) = self.log_file() else {
+            return;
+        };
+        if !self.respect_user_config && !log_file.interactive().unwrap_or(false) {
+            return;
+        }
+        let logger = FileLogger::new(
+            log_file.path().unwrap_or_else(Self::default_log_path),
+            log_file.max_size_kb().unwrap_or(DEFAULT_LOG_MAX_SIZE_KB),
+        );
+        if let Err(e) = logger.log(level, message) {
+            self.print_if_verbose(format!("WARN: unable to write to log file: {e}").as_str());
+        }
+    }
+
+    /// Relative chance of each generator (by prefix) to be picked by a run of `astra`
+    pub fn generator_weights(&self) -> Option<&BTreeMap<String, u32>> {
+        if let Some(user_config) = &self.user_config {
+            user_config.generator_weights.as_ref()
+        } else {
+            None
         }
     }
 
@@ -66,14 +820,115 @@ impl Config {
         }
     }
 
+    /// Generators that `schedule_by_time` assigns to the current local time, if any
+    pub fn scheduled_generators(&self) -> Option<&Generators> {
+        if let Some(user_config) = &self.user_config {
+            let now = Local::now();
+            user_config
+                .schedule_by_time
+                .as_ref()
+                .and_then(|schedule| schedule.generators_at(now.hour() * 60 + now.minute()))
+        } else {
+            None
+        }
+    }
+
+    /// Generators a run of `astra` picks from: the ones `schedule_by_time` assigns to the current
+    /// time, else `generators`, else every generator
+    pub fn candidate_generators(&self) -> Vec<Generator> {
+        let (candidates, reason) = match (self.scheduled_generators(), self.generators()) {
+            (Some(scheduled), _) => (
+                scheduled.to_vec(),
+                "not scheduled for the current time (schedule_by_time)",
+            ),
+            (None, Some(generators)) => (generators.to_vec(), "not in generators"),
+            (None, None) => (Generators::ALL_GENERATORS.to_vec(), ""),
+        };
+        for generator in Generators::ALL_GENERATORS {
+            if !candidates.iter().any(|c| c.prefix() == generator.prefix()) {
+                self.explain(Decision::Excluded {
+                    generator: generator.prefix().to_string(),
+                    reason,
+                });
+            }
+        }
+        self.explain(Decision::Candidates(
+            candidates.iter().map(|c| c.prefix().to_string()).collect(),
+        ));
+        candidates
+    }
+
+    /// Frequency from the config file, falling back to the `ASTRA_FREQUENCY` environment variable
     pub fn frequency(&self) -> Option<&Frequency> {
         if let Some(user_config) = &self.user_config {
-            user_config.frequency.as_ref()
+            user_config
+                .frequency
+                .as_ref()
+                .or(self.env_frequency.as_ref())
+        } else {
+            self.env_frequency.as_ref()
+        }
+    }
+
+    /// Parses the value of the `ASTRA_FREQUENCY` environment variable, if set
+    fn frequency_from_env(value: Option<&str>) -> Result<Option<Frequency>, ConfigError> {
+        value.map(Frequency::new).transpose()
+    }
+
+    /// How long a generator may run before `astra` gives up on it, `None` when unlimited
+    pub fn generation_timeout(&self) -> Option<Duration> {
+        if let Some(user_config) = &self.user_config {
+            user_config
+                .generation_timeout_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
         } else {
             None
         }
     }
 
+    /// Color (name or hex) that transparent pixels of loaded images are composited over
+    pub fn background(&self) -> Option<&str> {
+        if let Some(user_config) = &self.user_config {
+            user_config.background.as_deref()
+        } else {
+            None
+        }
+    }
+
+    /// Color of the desktop icon labels and text that `theme_selection = "max_contrast"` keeps
+    /// readable, white when unset or invalid
+    pub fn foreground(&self) -> [u8; 3] {
+        let foreground = if let Some(user_config) = &self.user_config {
+            user_config.foreground.as_deref()
+        } else {
+            None
+        };
+        let Some(foreground) = foreground else {
+            return DEFAULT_FOREGROUND;
+        };
+        parse_color(foreground).unwrap_or_else(|e| {
+            self.print_if_verbose(format!("WARN: foreground - {e}, using white").as_str());
+            DEFAULT_FOREGROUND
+        })
+    }
+
+    pub fn theme_selection(&self) -> ThemeSelection {
+        if let Some(user_config) = &self.user_config {
+            user_config.theme_selection.unwrap_or_default()
+        } else {
+            ThemeSelection::default()
+        }
+    }
+
+    pub fn all_spaces(&self) -> bool {
+        if let Some(user_config) = &self.user_config {
+            user_config.all_spaces.unwrap_or(false)
+        } else {
+            false
+        }
+    }
+
     pub fn auto_clean(&self) -> Option<&Frequency> {
         if let Some(user_config) = &self.user_config {
             user_config.auto_clean.as_ref()
@@ -82,6 +937,175 @@ impl Config {
         }
     }
 
+    /// Format `generator`'s wallpapers are saved in: its `format` key, then the global `format`
+    /// key, then PNG
+    pub fn wallpaper_format(&self, generator: &Generator) -> WallpaperFormat {
+        let generator_format = match generator {
+            // datetime_gen.format is the format of the date
+            Generator::DateTime => None,
+            Generator::Flame => self.flame_gen().and_then(|c| c.format()),
+            Generator::Ifs => self.ifs_gen().and_then(|c| c.format()),
+            Generator::Julia => self.julia_gen().and_then(|c| c.format()),
+            Generator::Mandala => self.mandala_gen().and_then(|c| c.format()),
+            Generator::NasaAPOD { .. } => self.nasa_apod_gen().and_then(|c| c.format()),
+            Generator::Solid { .. } => self.solid_gen().and_then(|c| c.format()),
+            Generator::Spotlight => self.spotlight_gen().and_then(|c| c.format()),
+            Generator::Text => self.text_gen().and_then(|c| c.format()),
+            Generator::Truchet => self.truchet_gen().and_then(|c| c.format()),
+        };
+        generator_format
+            .or_else(|| self.user_config.as_ref().and_then(|c| c.format))
+            .unwrap_or_default()
+    }
+
+    pub fn log_file(&self) -> Option<&LogFileConfig> {
+        if let Some(user_config) = &self.user_config {
+            user_config.log_file.as_ref()
+        } else {
+            None
+        }
+    }
+
+    pub fn per_monitor(&self) -> bool {
+        if let Some(user_config) = &self.user_config {
+            user_config.per_monitor.unwrap_or(false)
+        } else {
+            false
+        }
+    }
+
+    pub fn post_process(&self) -> Option<&PostProcessConfig> {
+        if let Some(user_config) = &self.user_config {
+            user_config.post_process.as_ref()
+        } else {
+            None
+        }
+    }
+
+    pub fn filename_scheme(&self) -> FilenameScheme {
+        if let Some(user_config) = &self.user_config {
+            user_config.filename_scheme.unwrap_or_default()
+        } else {
+            FilenameScheme::default()
+        }
+    }
+
+    pub fn single_file_mode(&self) -> bool {
+        if let Some(user_config) = &self.user_config {
+            user_config.single_file_mode.unwrap_or(false)
+        } else {
+            false
+        }
+    }
+
+    /// Generator used when the selected one fails, instead of picking another candidate
+    pub fn fallback_generator(&self) -> Option<&Generator> {
+        if let Some(user_config) = &self.user_config {
+            user_config.fallback_generator.as_ref()
+        } else {
+            None
+        }
+    }
+
+    /// Raw systemd `OnCalendar` expression used instead of `frequency` (Linux only)
+    pub fn calendar(&self) -> Option<&str> {
+        if let Some(user_config) = &self.user_config {
+            user_config.calendar.as_deref()
+        } else {
+            None
+        }
+    }
+
+    /// Fixed path kept pointing at the newest saved wallpaper
+    pub fn latest_symlink(&self) -> Option<&Path> {
+        if let Some(user_config) = &self.user_config {
+            user_config.latest_symlink.as_deref()
+        } else {
+            None
+        }
+    }
+
+    /// Fraction of the output resolution generators render at (`0.25..=1.0`), the image is then
+    /// upscaled with `upscale_filter`
+    pub fn render_scale(&self) -> f64 {
+        if let Some(user_config) = &self.user_config {
+            user_config
+                .render_scale
+                .map_or(1.0, |scale| scale.clamp(MIN_RENDER_SCALE, 1.0))
+        } else {
+            1.0
+        }
+    }
+
+    pub fn upscale_filter(&self) -> UpscaleFilter {
+        if let Some(user_config) = &self.user_config {
+            user_config.upscale_filter.unwrap_or_default()
+        } else {
+            UpscaleFilter::default()
+        }
+    }
+
+    /// Command run after the wallpaper is updated
+    pub fn post_update_hook(&self) -> Option<&HookCommand> {
+        if let Some(user_config) = &self.user_config {
+            user_config.post_update_hook.as_ref()
+        } else {
+            None
+        }
+    }
+
+    pub fn watermark(&self) -> Option<&WatermarkConfig> {
+        if let Some(user_config) = &self.user_config {
+            user_config.watermark.as_ref()
+        } else {
+            None
+        }
+    }
+
+    /// Largest resolution generators render at, the OS scales the wallpaper up to the screen
+    pub fn max_resolution(&self) -> Option<(u32, u32)> {
+        if let Some(user_config) = &self.user_config {
+            user_config.max_resolution
+        } else {
+            None
+        }
+    }
+
+    pub fn daily_seed(&self) -> bool {
+        if let Some(user_config) = &self.user_config {
+            user_config.daily_seed.unwrap_or(false)
+        } else {
+            false
+        }
+    }
+
+    /// Number of colors in the color maps of the fractal generators (at least 2)
+    pub fn color_steps(&self) -> usize {
+        if let Some(user_config) = &self.user_config {
+            user_config
+                .color_steps
+                .map_or(DEFAULT_COLOR_STEPS, |steps| steps.max(MIN_COLOR_STEPS))
+        } else {
+            DEFAULT_COLOR_STEPS
+        }
+    }
+
+    pub fn consistent_theme(&self) -> bool {
+        if let Some(user_config) = &self.user_config {
+            user_config.consistent_theme.unwrap_or(false)
+        } else {
+            false
+        }
+    }
+
+    pub fn skip_if_unchanged(&self) -> bool {
+        if let Some(user_config) = &self.user_config {
+            user_config.skip_if_unchanged.unwrap_or(false)
+        } else {
+            false
+        }
+    }
+
     pub fn solid_gen(&self) -> Option<&SolidConfig> {
         if let Some(user_config) = &self.user_config {
             user_config.solid_gen.as_ref()
@@ -90,6 +1114,49 @@ impl Config {
         }
     }
 
+    pub fn flame_gen(&self) -> Option<&FlameConfig> {
+        if let Some(user_config) = &self.user_config {
+            user_config.flame_gen.as_ref()
+        } else {
+            None
+        }
+    }
+
+    pub fn ifs_gen(&self) -> Option<&IfsConfig> {
+        if let Some(user_config) = &self.user_config {
+            user_config.ifs_gen.as_ref()
+        } else {
+            None
+        }
+    }
+
+    /// Settings of `generator` (e.g. `julia_gen`) as JSON, without the unset ones, when generators
+    /// follow the user configuration and the section is set
+    pub fn generator_section(&self, generator: &Generator) -> Option<serde_json::Value> {
+        let user_config = self
+            .user_config
+            .as_ref()
+            .filter(|_| self.respect_user_config)?;
+        let mut config = serde_json::to_value(user_config.as_ref()).ok()?;
+        let mut section = config
+            .get_mut(format!("{}_gen", generator.prefix()))?
+            .take();
+        match &mut section {
+            serde_json::Value::Null => return None,
+            serde_json::Value::Object(settings) => settings.retain(|_, value| !value.is_null()),
+            _ => {}
+        }
+        Some(section)
+    }
+
+    pub fn datetime_gen(&self) -> Option<&DateTimeConfig> {
+        if let Some(user_config) = &self.user_config {
+            user_config.datetime_gen.as_ref()
+        } else {
+            None
+        }
+    }
+
     pub fn julia_gen(&self) -> Option<&JuliaConfig> {
         if let Some(user_config) = &self.user_config {
             user_config.julia_gen.as_ref()
@@ -98,6 +1165,14 @@ impl Config {
         }
     }
 
+    pub fn mandala_gen(&self) -> Option<&MandalaConfig> {
+        if let Some(user_config) = &self.user_config {
+            user_config.mandala_gen.as_ref()
+        } else {
+            None
+        }
+    }
+
     pub fn nasa_apod_gen(&self) -> Option<&NasaApodConfig> {
         if let Some(user_config) = &self.user_config {
             user_config.nasa_apod_gen.as_ref()
@@ -106,6 +1181,15 @@ impl Config {
         }
     }
 
+    /// Hashes of the downloaded Spotlight images that are never used as the wallpaper
+    pub fn spotlight_blocklist(&self) -> Vec<String> {
+        if let Some(user_config) = &self.user_config {
+            user_config.spotlight_blocklist.clone().unwrap_or_default()
+        } else {
+            vec![]
+        }
+    }
+
     pub fn spotlight_gen(&self) -> Option<&SpotlightConfig> {
         if let Some(user_config) = &self.user_config {
             user_config.spotlight_gen.as_ref()
@@ -114,6 +1198,14 @@ impl Config {
         }
     }
 
+    pub fn text_gen(&self) -> Option<&TextConfig> {
+        if let Some(user_config) = &self.user_config {
+            user_config.text_gen.as_ref()
+        } else {
+            None
+        }
+    }
+
     pub fn themes(&self) -> Option<&ThemeConfigs> {
         if let Some(user_config) = &self.user_config {
             user_config.themes.as_ref()
@@ -122,16 +1214,54 @@ impl Config {
         }
     }
 
-    fn config_dir() -> PathBuf {
-        ProjectDirs::from(QUALIFIER, ORGANIZATION, APPLICATION)
+    pub fn transition(&self) -> bool {
+        if let Some(user_config) = &self.user_config {
+            user_config.transition.unwrap_or(false)
+        } else {
+            false
+        }
+    }
+
+    pub fn transition_frames(&self) -> u8 {
+        if let Some(user_config) = &self.user_config {
+            user_config.transition_frames.unwrap_or(5)
+        } else {
+            5
+        }
+    }
+
+    pub fn truchet_gen(&self) -> Option<&TruchetConfig> {
+        if let Some(user_config) = &self.user_config {
+            user_config.truchet_gen.as_ref()
+        } else {
+            None
+        }
+    }
+
+    /// Folder of the configuration file of the instance astra runs as
+    pub fn config_dir() -> PathBuf {
+        let base = ProjectDirs::from(QUALIFIER, ORGANIZATION, APPLICATION)
             .map(|dirs| dirs.config_dir().to_path_buf())
-            .expect("config folders are defined for each OS")
+            .expect("config folders are defined for each OS");
+        Instance::config_dir(&base, Instance::selected())
     }
 
     pub fn config_path() -> PathBuf {
         Self::config_dir().join("config.json")
     }
 
+    /// Folder where the instance astra runs as keeps its data (wallpapers, state, logs)
+    pub fn data_dir() -> PathBuf {
+        let base = ProjectDirs::from(QUALIFIER, ORGANIZATION, APPLICATION)
+            .map(|dirs| dirs.data_dir().to_path_buf())
+            .expect("data folders are defined for each OS");
+        Instance::data_dir(&base, Instance::selected())
+    }
+
+    fn default_log_path() -> PathBuf {
+        Self::data_dir().join("astra.log")
+    }
+
     pub fn create_config_file_if_not_exists(config: &Config) -> Result<(), ConfigError> {
         if !Self::config_path().exists() {
             config.print_if_verbose(
@@ -162,22 +1292,145 @@ impl Config {
         let config_path = Config::config_path();
         if config_path.exists() {
             if verbose {
-                println!("reading configuration file at {}", &config_path.display());
+                eprintln!("reading configuration file at {}", &config_path.display());
             }
             let config = Self::read_config_file(&config_path, verbose)?;
             if verbose {
-                println!("configuration loaded:");
-                println!("{config}");
+                eprintln!("configuration loaded:");
+                eprintln!("{config}");
             }
             Ok(config)
         } else {
             if verbose {
-                println!("no configuration file found, using defaults")
+                eprintln!("no configuration file found, using defaults")
             }
             Ok(UserConfig::default())
         }
     }
 
+    /// Parses the configuration file, returning the first error found. With `lint`, also checks
+    /// the parsed configuration for settings that have no effect (`astra validate --lint`), and
+    /// with `check_theme_contrast` for themes that make desktop text hard to read.
+    pub fn validate_config_file(
+        lint_config: bool,
+        check_theme_contrast: bool,
+    ) -> Result<Vec<LintWarning>, ConfigError> {
+        let config_path = Config::config_path();
+        let user_config = if config_path.exists() {
+            Self::read_config_file(&config_path, false)?
+        } else {
+            UserConfig::default()
+        };
+        let mut warnings = vec![];
+        if lint_config {
+            warnings.extend(lint(&user_config));
+        }
+        if check_theme_contrast {
+            warnings.extend(theme_contrast(&user_config));
+        }
+        Ok(warnings)
+    }
+
+    /// Renames the deprecated keys of the configuration file (`astra migrate`). The file is only
+    /// rewritten when a key was renamed, after copying it to `config.json.bak`.
+    pub fn migrate_config_file() -> Result<Vec<Migration>, ConfigError> {
+        Self::migrate_config_file_at(&Config::config_path(), MIGRATIONS)
+    }
+
+    fn migrate_config_file_at(
+        path: &Path,
+        renames: &[(&str, &str)],
+    ) -> Result<Vec<Migration>, ConfigError> {
+        let data = fs::read_to_string(path)
+            .map_err(|e| ConfigError::Migrate(format!("{}: {e}", path.display())))?;
+        let mut config: serde_json::Value =
+            serde_json::from_str(&data).map_err(|e| ConfigError::Parse(e.to_string()))?;
+        let migrations =
+            migrate(&mut config, renames).map_err(|e| ConfigError::Parse(e.to_string()))?;
+        if migrations.iter().any(Migration::is_rename) {
+            let backup_path = path.with_extension("json.bak");
+            fs::copy(path, &backup_path)
+                .map_err(|e| ConfigError::Migrate(format!("{}: {e}", backup_path.display())))?;
+            let contents = serde_json::to_string_pretty(&config)
+                .expect("configuration only contains serializable values");
+            fs::write(path, contents + "\n")
+                .map_err(|e| ConfigError::Migrate(format!("{}: {e}", path.display())))?;
+        }
+        Ok(migrations)
+    }
+
+    /// Adds a theme built from a palette URL (see `ThemeConfig::from_palette_url`) to the themes
+    /// of the configuration file
+    pub fn import_theme(name: &str, url: &str) -> Result<ThemeConfig, ConfigError> {
+        let theme = ThemeConfig::from_palette_url(name, url)?;
+        Self::add_theme_to_file(&Config::config_path(), &theme)?;
+        Ok(theme)
+    }
+
+    fn add_theme_to_file(path: &Path, theme: &ThemeConfig) -> Result<(), ConfigError> {
+        let data = fs::read_to_string(path)
+            .map_err(|e| ConfigError::ImportTheme(format!("{}: {e}", path.display())))?;
+        let user_config: UserConfig =
+            serde_json::from_str(&data).map_err(|e| ConfigError::Parse(e.to_string()))?;
+        if let Some(themes) = &user_config.themes
+            && themes.find(theme.name()).is_some()
+        {
+            return Err(ConfigError::ImportTheme(format!(
+                "a theme named \"{}\" already exists",
+                theme.name()
+            )));
+        }
+        // edit the JSON itself so that keys astra does not know about are kept
+        let mut config: serde_json::Value =
+            serde_json::from_str(&data).map_err(|e| ConfigError::Parse(e.to_string()))?;
+        let theme = serde_json::to_value(theme).expect("themes are serializable");
+        match config.get_mut("themes") {
+            Some(serde_json::Value::Array(themes)) => themes.push(theme),
+            _ => config["themes"] = serde_json::Value::Array(vec![theme]),
+        }
+        let contents = serde_json::to_string_pretty(&config)
+            .expect("configuration only contains serializable values");
+        fs::write(path, contents + "\n")
+            .map_err(|e| ConfigError::ImportTheme(format!("{}: {e}", path.display())))
+    }
+
+    /// Adds `hash` to `spotlight_blocklist` in the configuration file and in this configuration,
+    /// so the image is already skipped by the current run (`astra generate --block-current`).
+    /// Returns false if the image was already blocked.
+    pub fn block_spotlight_image(&mut self, hash: &str) -> Result<bool, ConfigError> {
+        let added = Self::add_to_spotlight_blocklist(&Config::config_path(), hash)?;
+        let mut config = serde_json::to_value(self.user_config.as_deref())
+            .expect("configuration only contains serializable values");
+        let mut blocklist = self.spotlight_blocklist();
+        if !blocklist.iter().any(|blocked| blocked == hash) {
+            blocklist.push(hash.to_string());
+        }
+        config["spotlight_blocklist"] = serde_json::json!(blocklist);
+        let user_config: UserConfig =
+            serde_json::from_value(config).map_err(|e| ConfigError::Parse(e.to_string()))?;
+        self.user_config = Some(Arc::new(user_config));
+        Ok(added)
+    }
+
+    fn add_to_spotlight_blocklist(path: &Path, hash: &str) -> Result<bool, ConfigError> {
+        let data = fs::read_to_string(path)
+            .map_err(|e| ConfigError::Blocklist(format!("{}: {e}", path.display())))?;
+        // edit the JSON itself so that keys astra does not know about are kept
+        let mut config: serde_json::Value =
+            serde_json::from_str(&data).map_err(|e| ConfigError::Parse(e.to_string()))?;
+        let hash = serde_json::Value::String(hash.to_string());
+        match config.get_mut("spotlight_blocklist") {
+            Some(serde_json::Value::Array(hashes)) if hashes.contains(&hash) => return Ok(false),
+            Some(serde_json::Value::Array(hashes)) => hashes.push(hash),
+            _ => config["spotlight_blocklist"] = serde_json::Value::Array(vec![hash]),
+        }
+        let contents = serde_json::to_string_pretty(&config)
+            .expect("configuration only contains serializable values");
+        fs::write(path, contents + "\n")
+            .map_err(|e| ConfigError::Blocklist(format!("{}: {e}", path.display())))?;
+        Ok(true)
+    }
+
     fn read_config_file(path: &Path, verbose: bool) -> Result<UserConfig, ConfigError> {
         // TODO: find way to partially process config and report err instead of current impl
         // (ignores all of user config)
@@ -187,8 +1440,8 @@ impl Config {
             }
             Err(e) => {
                 if verbose {
-                    println!("error(s) in config file: {e:#?}");
-                    println!("ignoring config...")
+                    eprintln!("error(s) in config file: {e:#?}");
+                    eprintln!("ignoring config...")
                 }
                 Ok(UserConfig::default())
             }
@@ -198,36 +1451,293 @@ impl Config {
 
 #[derive(Debug, PartialEq)]
 pub enum ConfigError {
+    Blocklist(String),
     CreateDir(String),
     CreateFile(String),
+    ImportTheme(String),
+    Instance(String),
+    Migrate(String),
     Parse(String),
+    PreGenerateHook(String),
+    ThemeFile(String),
+    UnknownTheme(String),
 }
 
 impl Display for ConfigError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            ConfigError::Blocklist(err_msg) => {
+                write!(f, "Unable to update spotlight_blocklist: {err_msg}")
+            }
             ConfigError::CreateDir(err_msg) => {
                 write!(f, "Unable to create configuration directory: {err_msg}")
             }
             ConfigError::CreateFile(err_msg) => {
                 write!(f, "Unable to create configuration file: {err_msg}")
             }
+            ConfigError::ImportTheme(err_msg) => {
+                write!(f, "Unable to import theme: {err_msg}")
+            }
+            ConfigError::Instance(err_msg) => write!(f, "Invalid instance name: {err_msg}"),
+            ConfigError::Migrate(err_msg) => {
+                write!(f, "Unable to migrate configuration file: {err_msg}")
+            }
             ConfigError::Parse(err_msg) => {
                 write!(f, "Unable to parse configuration file: {err_msg}")
             }
+            ConfigError::PreGenerateHook(err_msg) => {
+                write!(f, "pre_generate_hook failed: {err_msg}")
+            }
+            ConfigError::ThemeFile(err_msg) => {
+                write!(f, "Unable to load theme file: {err_msg}")
+            }
+            ConfigError::UnknownTheme(err_msg) => write!(f, "Unknown theme: {err_msg}"),
         }
     }
 }
 
 impl Error for ConfigError {}
 
+/// Sets the keys of `overrides` in `base`, merging the objects both of them have
+fn merge_json(base: &mut serde_json::Value, overrides: serde_json::Value) {
+    match (base, overrides) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::super::cli::Generator;
+    use super::super::migrate::TEST_MIGRATIONS;
     use super::*;
     use crate::cli::SolidMode;
     use std::path::PathBuf;
 
+    #[test]
+    fn test_user_theme_overrides_built_in_theme_with_same_name() {
+        let config = Config::from_json(
+            r#"{ "themes": [{ "name": "Ocean Breeze", "colors": [[1, 2, 3]] }] }"#,
+        );
+        for name in ["Ocean Breeze", "ocean-breeze", "OCEAN BREEZE"] {
+            let theme = config.resolve_theme_by_name(name).unwrap();
+            assert_eq!(theme.get_colors(false), &vec![[1, 2, 3]]);
+        }
+        let built_in = Config::from_json("{}")
+            .resolve_theme_by_name("ocean breeze")
+            .unwrap();
+        assert_ne!(built_in.get_colors(false), &vec![[1, 2, 3]]);
+        assert_eq!(
+            config.resolve_theme_by_name("NEON_DREAMS").unwrap().name(),
+            "Neon Dreams"
+        );
+        assert!(config.resolve_theme_by_name("unknown").is_none());
+    }
+
+    #[test]
+    fn test_theme_env_var_forces_theme_unless_flag_is_passed() {
+        let mut config = Config::from_json("{}");
+        config
+            .set_theme_from_flag_or_env(None, Some("NeonDreams"))
+            .unwrap();
+        for seed in 0..8 {
+            let theme = config.select_theme(false, &mut StdRng::seed_from_u64(seed));
+            assert_eq!(theme.selected().name(), "Neon Dreams");
+        }
+
+        config
+            .set_theme_from_flag_or_env(Some("ocean-breeze"), Some("NeonDreams"))
+            .unwrap();
+        let theme = config.select_theme(true, &mut StdRng::seed_from_u64(0));
+        assert_eq!(theme.selected().name(), "Ocean Breeze");
+
+        let mut config = Config::from_json("{}");
+        let err = config
+            .set_theme_from_flag_or_env(None, Some("Unknown"))
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("\"Unknown\" (forced with ASTRA_THEME)")
+        );
+        config.set_theme_from_flag_or_env(None, Some(" ")).unwrap();
+        assert!(!config.has_theme_override());
+    }
+
+    #[test]
+    fn test_max_contrast_picks_darker_theme_for_white_foreground() {
+        let mut config = Config::from_json(
+            r#"{
+                "theme_selection": "max_contrast",
+                "foreground": "white",
+                "themes": [
+                    { "name": "Light", "colors": [[240, 240, 230], [255, 250, 240]] },
+                    { "name": "Dark", "colors": [[20, 20, 40], [40, 30, 60]] }
+                ]
+            }"#,
+        );
+        config.set_dark_mode_override(false);
+        for seed in 0..8 {
+            let theme = config.select_theme(true, &mut StdRng::seed_from_u64(seed));
+            assert_eq!(theme.selected().name(), "Dark");
+        }
+
+        // a dark foreground turns the pick around
+        let mut config = Config::from_json(
+            r#"{
+                "theme_selection": "max_contrast",
+                "foreground": "black",
+                "themes": [
+                    { "name": "Light", "colors": [[240, 240, 230], [255, 250, 240]] },
+                    { "name": "Dark", "colors": [[20, 20, 40], [40, 30, 60]] }
+                ]
+            }"#,
+        );
+        config.set_dark_mode_override(false);
+        let theme = config.select_theme(true, &mut StdRng::seed_from_u64(0));
+        assert_eq!(theme.selected().name(), "Light");
+    }
+
+    #[test]
+    fn test_color_steps_defaults_to_256_and_is_at_least_2() {
+        assert_eq!(Config::from_json("{}").color_steps(), 256);
+        assert_eq!(
+            Config::from_json(r#"{ "color_steps": 1024 }"#).color_steps(),
+            1024
+        );
+        assert_eq!(
+            Config::from_json(r#"{ "color_steps": 1 }"#).color_steps(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_generator_rng_uses_the_configured_seed_over_the_run_seed() {
+        let sample = |config: &Config, seed: Option<u64>| -> [u64; 4] {
+            let mut rng = config.generator_rng(seed);
+            std::array::from_fn(|_| rng.random())
+        };
+        let seeded = |seed: u64| {
+            let mut config = Config::from_json("{}");
+            config.seed_rng(seed);
+            config
+        };
+        assert_eq!(sample(&seeded(1), Some(7)), sample(&seeded(2), Some(7)));
+        assert_ne!(sample(&seeded(1), None), sample(&seeded(2), None));
+        assert_eq!(sample(&seeded(1), None), sample(&seeded(1), None));
+    }
+
+    #[test]
+    fn test_consistent_theme_is_shared_by_every_image_of_the_run() {
+        let config = Config::from_json(r#"{ "consistent_theme": true }"#);
+        let first = config.select_theme(false, &mut StdRng::seed_from_u64(1));
+        // generators running on worker threads use a fork of the configuration
+        let forked = config.fork();
+        for seed in 2..12 {
+            let other = forked.select_theme(true, &mut StdRng::seed_from_u64(seed));
+            assert_eq!(other.selected().name(), first.selected().name());
+        }
+
+        let config = Config::from_json("{}");
+        let names: std::collections::HashSet<String> = (0..12)
+            .map(|seed| {
+                let theme = config.select_theme(false, &mut StdRng::seed_from_u64(seed));
+                theme.selected().name().to_string()
+            })
+            .collect();
+        assert!(names.len() > 1);
+    }
+
+    #[test]
+    fn test_migrate_config_file_keeps_a_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        let original = r#"{ "autoclean": "1w" }"#;
+        fs::write(&path, original).unwrap();
+
+        Config::migrate_config_file_at(&path, TEST_MIGRATIONS).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("config.json.bak")).unwrap(),
+            original
+        );
+        let migrated: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(migrated, serde_json::json!({ "auto_clean": "1w" }));
+
+        // nothing left to rename, the file is not rewritten
+        fs::remove_file(dir.path().join("config.json.bak")).unwrap();
+        assert!(
+            Config::migrate_config_file_at(&path, TEST_MIGRATIONS)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(!dir.path().join("config.json.bak").exists());
+    }
+
+    #[test]
+    fn test_imported_theme_is_appended_to_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        fs::write(&path, r#"{ "frequency": "1d", "my_note": "kept" }"#).unwrap();
+        let theme = ThemeConfig::from_palette_url("Sunset", "1e1e1e-ff0000").unwrap();
+
+        Config::add_theme_to_file(&path, &theme).unwrap();
+        let config: UserConfig = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(config.themes.unwrap().find("Sunset"), Some(&theme));
+        assert!(fs::read_to_string(&path).unwrap().contains("my_note"));
+        assert!(matches!(
+            Config::add_theme_to_file(&path, &theme),
+            Err(ConfigError::ImportTheme(_))
+        ));
+    }
+
+    #[test]
+    fn test_max_resolution_caps_detected_resolution() {
+        let config = Config::from_json(r#"{ "max_resolution": [3840, 2160] }"#);
+        assert_eq!(config.capped_resolution((7680, 4320)), (3840, 2160));
+        // ultrawide screens are capped by their width, keeping the aspect ratio
+        assert_eq!(config.capped_resolution((7680, 2160)), (3840, 1080));
+        assert_eq!(config.capped_resolution((2560, 1440)), (2560, 1440));
+        assert_eq!(
+            Config::from_json("{}").capped_resolution((7680, 4320)),
+            (7680, 4320)
+        );
+    }
+
+    #[test]
+    fn test_single_dimension_uses_screen_aspect_ratio() {
+        let screen = || Ok((1920, 1080));
+        assert_eq!(
+            Config::resolution_from_dimensions(Some(1280), None, screen).unwrap(),
+            Some((1280, 720))
+        );
+        assert_eq!(
+            Config::resolution_from_dimensions(None, Some(1440), screen).unwrap(),
+            Some((2560, 1440))
+        );
+    }
+
+    #[test]
+    fn test_both_dimensions_are_used_verbatim() {
+        let screen = || -> Result<(u32, u32), Box<dyn Error>> { panic!("screen was queried") };
+        assert_eq!(
+            Config::resolution_from_dimensions(Some(1000), Some(1000), screen).unwrap(),
+            Some((1000, 1000))
+        );
+        assert_eq!(
+            Config::resolution_from_dimensions(None, None, screen).unwrap(),
+            None
+        );
+    }
+
     #[test]
     fn test_read_config_file_returns_default_for_missing_file() {
         let path = PathBuf::from("nonexistent_config.json");
@@ -246,6 +1756,45 @@ mod tests {
         assert_eq!(config.generators, None);
     }
 
+    #[test]
+    fn test_frequency_from_env_is_used_when_config_has_none() {
+        let mut config = Config::from_json("{}");
+        config.env_frequency = Config::frequency_from_env(Some("2h")).unwrap();
+        assert_eq!(config.frequency(), Some(&Frequency::new("2h").unwrap()));
+    }
+
+    #[test]
+    fn test_frequency_from_config_takes_precedence_over_env() {
+        let mut config = Config::from_json(r#"{ "frequency": "1w" }"#);
+        config.env_frequency = Config::frequency_from_env(Some("2h")).unwrap();
+        assert_eq!(config.frequency(), Some(&Frequency::new("1w").unwrap()));
+    }
+
+    #[test]
+    fn test_effective_config_includes_env_and_cli_overrides() {
+        let mut config = Config::from_json(r#"{ "generators": ["julia"] }"#);
+        config.env_frequency = Config::frequency_from_env(Some("2h")).unwrap();
+        config.set_force(true);
+
+        let effective = config.effective_config(Some(7), None);
+        assert_eq!(effective["generators"], serde_json::json!(["julia"]));
+        assert_eq!(effective["frequency"], "2h");
+        assert_eq!(effective["cli"]["force"], true);
+        assert_eq!(effective["cli"]["seed"], 7);
+        // the printed configuration can be pasted back into the configuration file
+        let printed = serde_json::to_string_pretty(&effective).unwrap();
+        assert!(serde_json::from_str::<UserConfig>(&printed).is_ok());
+    }
+
+    #[test]
+    fn test_frequency_from_env_reports_parse_errors() {
+        assert_eq!(Config::frequency_from_env(None), Ok(None));
+        assert!(matches!(
+            Config::frequency_from_env(Some("1x")),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+
     #[test]
     fn test_read_config_file_parses_correct_partial_config_when_generators_defined() {
         let dir = tempfile::tempdir().unwrap();
@@ -271,6 +1820,57 @@ mod tests {
         );
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_pre_generate_hook_output_overrides_config_for_the_run() {
+        let mut config = Config::from_json(
+            r#"{
+                "generators": ["julia"],
+                "julia_gen": { "segments": 4, "format": "jpeg" },
+                "pre_generate_hook": "echo '{\"generators\": [\"solid\"], \"julia_gen\": { \"segments\": 6 }}'"
+            }"#,
+        );
+        config.apply_pre_generate_hook().unwrap();
+        assert_eq!(
+            config.candidate_generators(),
+            vec![Generator::Solid {
+                mode: SolidMode::Random
+            }]
+        );
+        // nested objects are merged
+        assert_eq!(
+            config.wallpaper_format(&Generator::Julia),
+            WallpaperFormat::Jpeg
+        );
+
+        let mut config = Config::from_json(r#"{ "pre_generate_hook": "echo checking weather" }"#);
+        assert!(config.apply_pre_generate_hook().is_ok());
+
+        let mut config = Config::from_json(r#"{ "pre_generate_hook": ["false"] }"#);
+        assert!(matches!(
+            config.apply_pre_generate_hook(),
+            Err(ConfigError::PreGenerateHook(_))
+        ));
+    }
+
+    #[test]
+    fn test_daily_seed_repeats_wallpaper_within_a_day() {
+        let wallpaper_on = |date: NaiveDate| {
+            let mut config = Config::from_json(r#"{ "daily_seed": true }"#);
+            config.set_resolution_override(8, 8);
+            assert!(config.daily_seed());
+            let seed = config.seed_rng_from_date(date);
+            let mode = SolidMode::Random;
+            let image = crate::wallpaper_generators::generate_solid_color(&config, &mode).unwrap();
+            (seed, image)
+        };
+        let today = NaiveDate::from_ymd_opt(2026, 3, 14).unwrap();
+        let (seed, first_run) = wallpaper_on(today);
+        assert_eq!(seed, 20260314);
+        assert_eq!(wallpaper_on(today).1, first_run);
+        assert_ne!(wallpaper_on(today.succ_opt().unwrap()).1, first_run);
+    }
+
     #[test]
     fn test_read_config_file_parses_correct_empty_config() {
         let dir = tempfile::tempdir().unwrap();
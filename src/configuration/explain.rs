@@ -0,0 +1,93 @@
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+
+/// One step of how a run of `astra` picked its wallpaper, printed by `astra --explain`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Decision {
+    /// A generator was removed from the candidates by a configuration filter
+    Excluded {
+        generator: String,
+        reason: &'static str,
+    },
+    /// Generators left to pick from after every filter
+    Candidates(Vec<String>),
+    /// Random pick among the remaining candidates, each one is equally likely unless
+    /// `generator_weights` is set
+    Roll {
+        candidates: usize,
+        weighted: bool,
+    },
+    Chosen(String),
+    /// The chosen generator failed, the next decision tells what was tried instead
+    Failed {
+        generator: String,
+        error: String,
+    },
+    /// The configured `fallback_generator` replaces the generator that failed
+    Fallback(String),
+    Theme {
+        name: String,
+        source: &'static str,
+    },
+    Appearance {
+        dark_mode: bool,
+        source: &'static str,
+    },
+    /// No wallpaper was generated
+    Skipped(&'static str),
+}
+
+impl Display for Decision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Decision::Excluded { generator, reason } => write!(f, "excluded {generator}: {reason}"),
+            Decision::Candidates(generators) => write!(f, "candidates: {}", generators.join(", ")),
+            Decision::Roll {
+                candidates,
+                weighted,
+            } => write!(
+                f,
+                "random pick among {candidates} candidate(s) ({})",
+                if *weighted {
+                    "weighted by generator_weights"
+                } else {
+                    "every candidate has the same weight"
+                }
+            ),
+            Decision::Chosen(generator) => write!(f, "chosen generator: {generator}"),
+            Decision::Failed { generator, error } => write!(f, "{generator} failed ({error})"),
+            Decision::Fallback(generator) => {
+                write!(
+                    f,
+                    "using fallback generator: {generator} (fallback_generator)"
+                )
+            }
+            Decision::Theme { name, source } => write!(f, "theme: {name} ({source})"),
+            Decision::Appearance { dark_mode, source } => write!(
+                f,
+                "appearance: {} ({source})",
+                if *dark_mode { "dark" } else { "light" }
+            ),
+            Decision::Skipped(reason) => write!(f, "skipped: {reason}"),
+        }
+    }
+}
+
+/// Decisions recorded by the selection code while `--explain` is enabled
+#[derive(Debug, Default)]
+pub struct DecisionLog(Mutex<Vec<Decision>>);
+
+impl DecisionLog {
+    /// Appends `decision`, unless it repeats the previous one (e.g. the appearance is queried by
+    /// several steps of a generator)
+    pub fn record(&self, decision: Decision) {
+        let mut decisions = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if decisions.last() != Some(&decision) {
+            decisions.push(decision);
+        }
+    }
+
+    pub fn decisions(&self) -> Vec<Decision> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
@@ -1,5 +1,5 @@
 use super::config::ConfigError;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 
 /// Enum for all schedule type options supported by schtasks in Windows systems
@@ -25,7 +25,7 @@ impl Display for ScheduleType {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Frequency(String);
 
 impl Frequency {
@@ -263,6 +263,15 @@ impl<'de> Deserialize<'de> for Frequency {
     }
 }
 
+impl Serialize for Frequency {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
 mod tests {
     #[allow(unused_imports)]
     use super::*;
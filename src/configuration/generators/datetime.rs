@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Write};
+
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct DateTimeConfig {
+    // Height of a line in pixels
+    font_size: Option<u32>,
+    // chrono format string of the date (the image format is the global `format` key)
+    format: Option<String>,
+    // Adds a line with the time under the date
+    include_time: Option<bool>,
+    respect_color_themes: Option<bool>,
+}
+
+impl DateTimeConfig {
+    pub fn font_size(&self) -> Option<u32> {
+        self.font_size
+    }
+
+    pub fn format(&self) -> Option<String> {
+        self.format.clone()
+    }
+
+    pub fn include_time(&self) -> Option<bool> {
+        self.include_time
+    }
+
+    pub fn respect_color_themes(&self) -> Option<bool> {
+        self.respect_color_themes
+    }
+}
+
+impl Display for DateTimeConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // only write if defined, else return empty string
+        let mut s = String::new();
+        if let Some(val) = &self.font_size {
+            writeln!(&mut s, "    font_size: {}", val)?;
+        }
+        if let Some(val) = &self.format {
+            writeln!(&mut s, "    format: {:?}", val)?;
+        }
+        if let Some(val) = &self.include_time {
+            writeln!(&mut s, "    include_time: {}", val)?;
+        }
+        if let Some(val) = &self.respect_color_themes {
+            writeln!(&mut s, "    respect_color_themes: {}", val)?;
+        }
+        if !s.is_empty() {
+            writeln!(f)?;
+            s.pop(); // remove last newline character
+        }
+        write!(f, "{s}")
+    }
+}
@@ -0,0 +1,64 @@
+use crate::wallpaper_generators::{FlameVariation, WallpaperFormat};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Write};
+
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct FlameConfig {
+    format: Option<WallpaperFormat>,
+    // Number of points plotted by the chaos game
+    iterations: Option<u64>,
+    respect_color_themes: Option<bool>,
+    // Same seed (and resolution) always produces the same flame
+    seed: Option<u64>,
+    // Variations the transforms blend, all of them if unset
+    variations: Option<Vec<FlameVariation>>,
+}
+
+impl FlameConfig {
+    pub fn format(&self) -> Option<WallpaperFormat> {
+        self.format
+    }
+
+    pub fn iterations(&self) -> Option<u64> {
+        self.iterations
+    }
+
+    pub fn respect_color_themes(&self) -> Option<bool> {
+        self.respect_color_themes
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn variations(&self) -> Option<Vec<FlameVariation>> {
+        self.variations.clone()
+    }
+}
+
+impl Display for FlameConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // only write if defined, else return empty string
+        let mut s = String::new();
+        if let Some(val) = &self.format {
+            writeln!(&mut s, "    format: {}", val)?;
+        }
+        if let Some(val) = &self.iterations {
+            writeln!(&mut s, "    iterations: {}", val)?;
+        }
+        if let Some(val) = &self.respect_color_themes {
+            writeln!(&mut s, "    respect_color_themes: {}", val)?;
+        }
+        if let Some(val) = &self.seed {
+            writeln!(&mut s, "    seed: {}", val)?;
+        }
+        if let Some(val) = &self.variations {
+            writeln!(&mut s, "    variations: {:?}", val)?;
+        }
+        if !s.is_empty() {
+            writeln!(f)?;
+            s.pop(); // remove last newline character
+        }
+        write!(f, "{s}")
+    }
+}
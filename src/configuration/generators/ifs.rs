@@ -0,0 +1,72 @@
+use crate::wallpaper_generators::{AffineTransform, IfsPreset, WallpaperFormat};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Write};
+
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct IfsConfig {
+    format: Option<WallpaperFormat>,
+    // Number of points plotted by the chaos game
+    iterations: Option<u64>,
+    preset: Option<IfsPreset>,
+    respect_color_themes: Option<bool>,
+    // Same seed (and resolution) always produces the same fractal
+    seed: Option<u64>,
+    // If set, used instead of a preset
+    transforms: Option<Vec<AffineTransform>>,
+}
+
+impl IfsConfig {
+    pub fn format(&self) -> Option<WallpaperFormat> {
+        self.format
+    }
+
+    pub fn iterations(&self) -> Option<u64> {
+        self.iterations
+    }
+
+    pub fn preset(&self) -> Option<IfsPreset> {
+        self.preset
+    }
+
+    pub fn respect_color_themes(&self) -> Option<bool> {
+        self.respect_color_themes
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn transforms(&self) -> Option<Vec<AffineTransform>> {
+        self.transforms.clone()
+    }
+}
+
+impl Display for IfsConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // only write if defined, else return empty string
+        let mut s = String::new();
+        if let Some(val) = &self.format {
+            writeln!(&mut s, "    format: {}", val)?;
+        }
+        if let Some(val) = &self.iterations {
+            writeln!(&mut s, "    iterations: {}", val)?;
+        }
+        if let Some(val) = &self.preset {
+            writeln!(&mut s, "    preset: {:?}", val)?;
+        }
+        if let Some(val) = &self.respect_color_themes {
+            writeln!(&mut s, "    respect_color_themes: {}", val)?;
+        }
+        if let Some(val) = &self.seed {
+            writeln!(&mut s, "    seed: {}", val)?;
+        }
+        if let Some(val) = &self.transforms {
+            writeln!(&mut s, "    transforms: {} custom transform(s)", val.len())?;
+        }
+        if !s.is_empty() {
+            writeln!(f)?;
+            s.pop(); // remove last newline character
+        }
+        write!(f, "{s}")
+    }
+}
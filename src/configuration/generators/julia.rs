@@ -1,17 +1,73 @@
-use serde::Deserialize;
+use crate::wallpaper_generators::WallpaperFormat;
+use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter, Write};
 
-#[derive(Debug, Default, Deserialize, PartialEq)]
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct JuliaConfig {
     appearance: Option<Appearance>,
-    complex_numbers: Option<Vec<(f64, f64)>>,
+    // Constants interpolated from the left edge to the right edge of the image
+    blend_constants: Option<[(f64, f64); 2]>,
+    complex_numbers: Option<Vec<JuliaConstant>>,
+    format: Option<WallpaperFormat>,
+    // Region of the complex plane shown instead of a random zoom on a hotspot
+    plane_center: Option<(f64, f64)>,
+    plane_height: Option<f64>,
+    plane_width: Option<f64>,
+    // Number of segments the screen height is split into when sampling the set
+    segments: Option<u32>,
     // Iterations required to become a hotspot (higher = more detailed)
     starting_sample_threshold: Option<u8>,
     respect_color_themes: Option<bool>,
 }
 
+/// Constant `c` of a julia set, optionally named so it can be recognized in verbose output and logs.
+///
+/// Deserializes from either a bare `[re, im]` pair or `{ "name": "...", "re": ..., "im": ... }`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(from = "JuliaConstantRepr")]
+pub struct JuliaConstant {
+    pub name: Option<String>,
+    pub re: f64,
+    pub im: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JuliaConstantRepr {
+    Pair(f64, f64),
+    Named {
+        name: Option<String>,
+        re: f64,
+        im: f64,
+    },
+}
+
+impl From<JuliaConstantRepr> for JuliaConstant {
+    fn from(repr: JuliaConstantRepr) -> Self {
+        match repr {
+            JuliaConstantRepr::Pair(re, im) => (re, im).into(),
+            JuliaConstantRepr::Named { name, re, im } => JuliaConstant { name, re, im },
+        }
+    }
+}
+
+impl From<(f64, f64)> for JuliaConstant {
+    fn from((re, im): (f64, f64)) -> Self {
+        JuliaConstant { name: None, re, im }
+    }
+}
+
+impl Display for JuliaConstant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{name} ({}, {})", self.re, self.im),
+            None => write!(f, "({}, {})", self.re, self.im),
+        }
+    }
+}
+
 // TODO: relocate to color_themes
-#[derive(Copy, Clone, Debug, Deserialize, PartialEq)]
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub(crate) enum Appearance {
     Auto,
     Light,
@@ -23,10 +79,34 @@ impl JuliaConfig {
         self.appearance
     }
 
-    pub fn complex_numbers(&self) -> Option<Vec<(f64, f64)>> {
+    pub fn blend_constants(&self) -> Option<[(f64, f64); 2]> {
+        self.blend_constants
+    }
+
+    pub fn complex_numbers(&self) -> Option<Vec<JuliaConstant>> {
         self.complex_numbers.clone()
     }
 
+    pub fn format(&self) -> Option<WallpaperFormat> {
+        self.format
+    }
+
+    pub fn plane_center(&self) -> Option<(f64, f64)> {
+        self.plane_center
+    }
+
+    pub fn plane_height(&self) -> Option<f64> {
+        self.plane_height
+    }
+
+    pub fn plane_width(&self) -> Option<f64> {
+        self.plane_width
+    }
+
+    pub fn segments(&self) -> Option<u32> {
+        self.segments
+    }
+
     pub fn starting_sample_threshold(&self) -> Option<u8> {
         self.starting_sample_threshold
     }
@@ -43,8 +123,27 @@ impl Display for JuliaConfig {
         if let Some(val) = &self.appearance {
             writeln!(&mut s, "    appearance: {:?}", val)?;
         }
+        if let Some(val) = &self.blend_constants {
+            writeln!(&mut s, "    blend_constants: {:?}", val)?;
+        }
         if let Some(val) = &self.complex_numbers {
-            writeln!(&mut s, "    complex_numbers: {:?}", val)?;
+            let constants: Vec<String> = val.iter().map(|c| c.to_string()).collect();
+            writeln!(&mut s, "    complex_numbers: [{}]", constants.join(", "))?;
+        }
+        if let Some(val) = &self.format {
+            writeln!(&mut s, "    format: {}", val)?;
+        }
+        if let Some(val) = &self.plane_center {
+            writeln!(&mut s, "    plane_center: {:?}", val)?;
+        }
+        if let Some(val) = &self.plane_height {
+            writeln!(&mut s, "    plane_height: {}", val)?;
+        }
+        if let Some(val) = &self.plane_width {
+            writeln!(&mut s, "    plane_width: {}", val)?;
+        }
+        if let Some(val) = &self.segments {
+            writeln!(&mut s, "    segments: {}", val)?;
         }
         if let Some(val) = &self.starting_sample_threshold {
             writeln!(&mut s, "    starting_sample_threshold: {:?}", val)?;
@@ -59,3 +158,38 @@ impl Display for JuliaConfig {
         write!(f, "{s}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complex_numbers_accept_pairs_and_named_constants() {
+        let config: JuliaConfig = serde_json::from_str(
+            r#"{ "complex_numbers": [[-0.4, 0.6], { "name": "Dendrite", "re": -0.0, "im": 1.0 }] }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.complex_numbers(),
+            Some(vec![
+                JuliaConstant {
+                    name: None,
+                    re: -0.4,
+                    im: 0.6
+                },
+                JuliaConstant {
+                    name: Some("Dendrite".to_string()),
+                    re: 0.0,
+                    im: 1.0
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_named_constant_without_name_matches_pair() {
+        let pair: JuliaConstant = serde_json::from_str("[0.28, 0.008]").unwrap();
+        let named: JuliaConstant = serde_json::from_str(r#"{ "re": 0.28, "im": 0.008 }"#).unwrap();
+        assert_eq!(pair, named);
+    }
+}
@@ -0,0 +1,64 @@
+use crate::wallpaper_generators::WallpaperFormat;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Write};
+
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct MandalaConfig {
+    format: Option<WallpaperFormat>,
+    // Reflect every other wedge so neighboring wedges join seamlessly
+    mirror: Option<bool>,
+    respect_color_themes: Option<bool>,
+    // Same seed (and resolution) always produces the same mandala
+    seed: Option<u64>,
+    // Number of wedges the base pattern is repeated in
+    symmetry: Option<u8>,
+}
+
+impl MandalaConfig {
+    pub fn format(&self) -> Option<WallpaperFormat> {
+        self.format
+    }
+
+    pub fn mirror(&self) -> Option<bool> {
+        self.mirror
+    }
+
+    pub fn respect_color_themes(&self) -> Option<bool> {
+        self.respect_color_themes
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn symmetry(&self) -> Option<u8> {
+        self.symmetry
+    }
+}
+
+impl Display for MandalaConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // only write if defined, else return empty string
+        let mut s = String::new();
+        if let Some(val) = &self.format {
+            writeln!(&mut s, "    format: {}", val)?;
+        }
+        if let Some(val) = &self.mirror {
+            writeln!(&mut s, "    mirror: {}", val)?;
+        }
+        if let Some(val) = &self.respect_color_themes {
+            writeln!(&mut s, "    respect_color_themes: {}", val)?;
+        }
+        if let Some(val) = &self.seed {
+            writeln!(&mut s, "    seed: {}", val)?;
+        }
+        if let Some(val) = &self.symmetry {
+            writeln!(&mut s, "    symmetry: {}", val)?;
+        }
+        if !s.is_empty() {
+            writeln!(f)?;
+            s.pop(); // remove last newline character
+        }
+        write!(f, "{s}")
+    }
+}
@@ -1,17 +1,29 @@
 use super::super::cli::{Generator, SolidMode};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 
+mod datetime;
+mod flame;
+mod ifs;
 pub(crate) mod julia;
+mod mandala;
 mod nasa_apod;
 mod solid;
 mod spotlight;
+mod text;
+mod truchet;
 
 // Any generator config should be added to ALL_GENERATORS with default values (see Generators below)
+pub(super) use datetime::DateTimeConfig;
+pub(super) use flame::FlameConfig;
+pub(super) use ifs::IfsConfig;
 pub(super) use julia::JuliaConfig;
+pub(super) use mandala::MandalaConfig;
 pub(super) use nasa_apod::NasaApodConfig;
 pub(super) use solid::SolidConfig;
 pub(super) use spotlight::SpotlightConfig;
+pub(super) use text::TextConfig;
+pub(super) use truchet::TruchetConfig;
 
 #[macro_export]
 macro_rules! respect_user_config_or_default {
@@ -36,13 +48,19 @@ macro_rules! respect_user_config_or_default {
 pub struct Generators(pub(super) Vec<Generator>);
 
 impl Generators {
-    pub const ALL_GENERATORS: [Generator; 4] = [
+    pub const ALL_GENERATORS: [Generator; 10] = [
+        Generator::DateTime,
+        Generator::Flame,
+        Generator::Ifs,
         Generator::Julia,
+        Generator::Mandala,
         Generator::NasaAPOD { date: None },
         Generator::Solid {
             mode: SolidMode::Random,
         },
         Generator::Spotlight,
+        Generator::Text,
+        Generator::Truchet,
     ];
 }
 
@@ -86,3 +104,38 @@ impl<'de> Deserialize<'de> for Generators {
         }
     }
 }
+
+/// (De)serializes an optional generator as its prefix (e.g. `fallback_generator`), so that an
+/// unknown prefix is rejected when the configuration is loaded
+pub(super) mod generator_prefix {
+    use super::Generator;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(generator: &Option<Generator>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match generator {
+            Some(generator) => serializer.serialize_some(generator.prefix()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Generator>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|prefix| prefix.parse().map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+impl Serialize for Generators {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.0.iter().map(|g| g.prefix()))
+    }
+}
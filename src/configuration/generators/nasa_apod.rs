@@ -1,11 +1,12 @@
-use super::super::super::wallpaper_generators::ApodDate;
-use serde::Deserialize;
+use super::super::super::wallpaper_generators::{ApodDate, WallpaperFormat};
+use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter, Write};
 
-#[derive(Debug, Default, Deserialize, PartialEq)]
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct NasaApodConfig {
     date_from: Option<ApodDate>,
     date_to: Option<ApodDate>,
+    format: Option<WallpaperFormat>,
 }
 
 impl NasaApodConfig {
@@ -23,6 +24,10 @@ impl NasaApodConfig {
             None
         }
     }
+
+    pub fn format(&self) -> Option<WallpaperFormat> {
+        self.format
+    }
 }
 
 impl Display for NasaApodConfig {
@@ -34,6 +39,9 @@ impl Display for NasaApodConfig {
         if let Some(val) = &self.date_to {
             writeln!(&mut s, "    date_to: {}", val)?;
         }
+        if let Some(val) = &self.format {
+            writeln!(&mut s, "    format: {}", val)?;
+        }
         if !s.is_empty() {
             writeln!(f)?;
             s.pop(); // remove last newline character
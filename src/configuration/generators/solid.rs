@@ -1,16 +1,29 @@
-use crate::wallpaper_generators::Color;
-use serde::Deserialize;
+use crate::wallpaper_generators::{Color, HarmonyScheme, SolidThemeMode, WallpaperFormat};
+use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter, Write};
 
-#[derive(Debug, Default, Deserialize, PartialEq)]
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct SolidConfig {
+    format: Option<WallpaperFormat>,
+    // If set, the color derived from the theme is rotated on the color wheel using this harmony
+    harmony: Option<HarmonyScheme>,
     preferred_default_colors: Option<Vec<Color>>,
     preferred_rgb_colors: Option<Vec<(u8, u8, u8)>>,
     // If true, ignore above fields
     respect_color_themes: Option<bool>,
+    // How the color is taken from the theme when respect_color_themes is true
+    theme_mode: Option<SolidThemeMode>,
 }
 
 impl SolidConfig {
+    pub fn format(&self) -> Option<WallpaperFormat> {
+        self.format
+    }
+
+    pub fn harmony(&self) -> Option<HarmonyScheme> {
+        self.harmony
+    }
+
     pub fn preferred_default_colors(&self) -> Option<Vec<Color>> {
         self.preferred_default_colors.clone()
     }
@@ -22,12 +35,22 @@ impl SolidConfig {
     pub fn respect_color_themes(&self) -> Option<bool> {
         self.respect_color_themes
     }
+
+    pub fn theme_mode(&self) -> Option<SolidThemeMode> {
+        self.theme_mode
+    }
 }
 
 impl Display for SolidConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         // only write if defined, else return empty string
         let mut s = String::new();
+        if let Some(val) = &self.format {
+            writeln!(&mut s, "    format: {}", val)?;
+        }
+        if let Some(val) = &self.harmony {
+            writeln!(&mut s, "    harmony: {:?}", val)?;
+        }
         if let Some(val) = &self.preferred_default_colors {
             writeln!(&mut s, "    preferred_default_colors: {:?}", val)?;
         }
@@ -37,6 +60,9 @@ impl Display for SolidConfig {
         if let Some(val) = &self.respect_color_themes {
             writeln!(&mut s, "    respect_color_themes: {}", val)?;
         }
+        if let Some(val) = &self.theme_mode {
+            writeln!(&mut s, "    theme_mode: {:?}", val)?;
+        }
         if !s.is_empty() {
             writeln!(f)?;
             s.pop(); // remove last newline character
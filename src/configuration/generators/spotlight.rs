@@ -1,10 +1,16 @@
-use serde::Deserialize;
+use crate::wallpaper_generators::{SpotlightFit, WallpaperFormat};
+use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter, Write};
 
 // looks to be [ISO_3166-1_alpha-2](https://en.wikipedia.org/wiki/ISO_3166-1_alpha-2#US), test to confirm
-#[derive(Debug, Default, Deserialize, PartialEq)]
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct SpotlightConfig {
     country: Option<String>,
+    // How the image is adapted to the screen's aspect ratio
+    fit: Option<SpotlightFit>,
+    format: Option<WallpaperFormat>,
+    // Save the downloaded file as is instead of re-encoding it in `format`
+    keep_original: Option<bool>,
     locale: Option<String>,
     respect_color_themes: Option<bool>,
 }
@@ -14,6 +20,18 @@ impl SpotlightConfig {
         self.country.clone()
     }
 
+    pub fn fit(&self) -> Option<SpotlightFit> {
+        self.fit
+    }
+
+    pub fn format(&self) -> Option<WallpaperFormat> {
+        self.format
+    }
+
+    pub fn keep_original(&self) -> Option<bool> {
+        self.keep_original
+    }
+
     pub fn locale(&self) -> Option<String> {
         self.locale.clone()
     }
@@ -29,6 +47,15 @@ impl Display for SpotlightConfig {
         if let Some(val) = &self.country {
             writeln!(&mut s, "    country: {}", val)?;
         }
+        if let Some(val) = &self.fit {
+            writeln!(&mut s, "    fit: {:?}", val)?;
+        }
+        if let Some(val) = &self.format {
+            writeln!(&mut s, "    format: {}", val)?;
+        }
+        if let Some(val) = &self.keep_original {
+            writeln!(&mut s, "    keep_original: {}", val)?;
+        }
         if let Some(val) = &self.locale {
             writeln!(&mut s, "    locale: {}", val)?;
         }
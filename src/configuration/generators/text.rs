@@ -0,0 +1,97 @@
+use crate::wallpaper_generators::{TextAlignment, TextOrder, WallpaperFormat};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter, Write},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct TextConfig {
+    // Alignment of the lines within the centered block of text
+    alignment: Option<TextAlignment>,
+    // A string, or a list of strings to pick one from at random
+    content: Option<TextContent>,
+    // Height of a line in pixels
+    font_size: Option<u32>,
+    format: Option<WallpaperFormat>,
+    // How the text is picked from the content list or the quotes of source_file
+    order: Option<TextOrder>,
+    respect_color_themes: Option<bool>,
+    // File of quotes to pick from instead of content
+    source_file: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum TextContent {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl TextConfig {
+    pub fn alignment(&self) -> Option<TextAlignment> {
+        self.alignment
+    }
+
+    /// Texts to pick from, `None` if `content` is not set or is an empty list
+    pub fn content(&self) -> Option<Vec<String>> {
+        match self.content.clone()? {
+            TextContent::One(text) => Some(vec![text]),
+            TextContent::Many(texts) if texts.is_empty() => None,
+            TextContent::Many(texts) => Some(texts),
+        }
+    }
+
+    pub fn font_size(&self) -> Option<u32> {
+        self.font_size
+    }
+
+    pub fn format(&self) -> Option<WallpaperFormat> {
+        self.format
+    }
+
+    pub fn order(&self) -> Option<TextOrder> {
+        self.order
+    }
+
+    pub fn respect_color_themes(&self) -> Option<bool> {
+        self.respect_color_themes
+    }
+
+    pub fn source_file(&self) -> Option<&Path> {
+        self.source_file.as_deref()
+    }
+}
+
+impl Display for TextConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // only write if defined, else return empty string
+        let mut s = String::new();
+        if let Some(val) = &self.alignment {
+            writeln!(&mut s, "    alignment: {:?}", val)?;
+        }
+        if let Some(val) = self.content() {
+            writeln!(&mut s, "    content: {:?}", val)?;
+        }
+        if let Some(val) = &self.font_size {
+            writeln!(&mut s, "    font_size: {}", val)?;
+        }
+        if let Some(val) = &self.format {
+            writeln!(&mut s, "    format: {}", val)?;
+        }
+        if let Some(val) = &self.order {
+            writeln!(&mut s, "    order: {:?}", val)?;
+        }
+        if let Some(val) = &self.respect_color_themes {
+            writeln!(&mut s, "    respect_color_themes: {}", val)?;
+        }
+        if let Some(val) = &self.source_file {
+            writeln!(&mut s, "    source_file: {}", val.display())?;
+        }
+        if !s.is_empty() {
+            writeln!(f)?;
+            s.pop(); // remove last newline character
+        }
+        write!(f, "{s}")
+    }
+}
@@ -0,0 +1,63 @@
+use crate::wallpaper_generators::{TruchetStyle, WallpaperFormat};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Write};
+
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct TruchetConfig {
+    format: Option<WallpaperFormat>,
+    respect_color_themes: Option<bool>,
+    // Same seed (and resolution) always produces the same pattern
+    seed: Option<u64>,
+    style: Option<TruchetStyle>,
+    // Width and height of a tile in pixels
+    tile_size: Option<u32>,
+}
+
+impl TruchetConfig {
+    pub fn format(&self) -> Option<WallpaperFormat> {
+        self.format
+    }
+
+    pub fn respect_color_themes(&self) -> Option<bool> {
+        self.respect_color_themes
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn style(&self) -> Option<TruchetStyle> {
+        self.style
+    }
+
+    pub fn tile_size(&self) -> Option<u32> {
+        self.tile_size
+    }
+}
+
+impl Display for TruchetConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // only write if defined, else return empty string
+        let mut s = String::new();
+        if let Some(val) = &self.format {
+            writeln!(&mut s, "    format: {}", val)?;
+        }
+        if let Some(val) = &self.respect_color_themes {
+            writeln!(&mut s, "    respect_color_themes: {}", val)?;
+        }
+        if let Some(val) = &self.seed {
+            writeln!(&mut s, "    seed: {}", val)?;
+        }
+        if let Some(val) = &self.style {
+            writeln!(&mut s, "    style: {:?}", val)?;
+        }
+        if let Some(val) = &self.tile_size {
+            writeln!(&mut s, "    tile_size: {}", val)?;
+        }
+        if !s.is_empty() {
+            writeln!(f)?;
+            s.pop(); // remove last newline character
+        }
+        write!(f, "{s}")
+    }
+}
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    process::Command,
+};
+
+/// User command run by astra (e.g. `post_update_hook`), either a shell command line or a program
+/// followed by its arguments
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum HookCommand {
+    Shell(String),
+    Args(Vec<String>),
+}
+
+impl HookCommand {
+    /// Command running the hook, arguments added to it are passed to the hook (`$1`, `$2`... of a
+    /// shell command line). `None` for an empty command.
+    pub fn command(&self) -> Option<Command> {
+        match self {
+            HookCommand::Shell(line) if line.trim().is_empty() => None,
+            HookCommand::Shell(line) => {
+                #[cfg(target_os = "windows")]
+                let command = {
+                    let mut command = Command::new("cmd");
+                    command.args(["/C", line]);
+                    command
+                };
+                #[cfg(not(target_os = "windows"))]
+                let command = {
+                    let mut command = Command::new("sh");
+                    // the word after the command line is $0, so the added arguments start at $1
+                    command.args(["-c", line, "astra"]);
+                    command
+                };
+                Some(command)
+            }
+            HookCommand::Args(args) => {
+                let (program, args) = args.split_first()?;
+                let mut command = Command::new(program);
+                command.args(args);
+                Some(command)
+            }
+        }
+    }
+}
+
+impl Display for HookCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HookCommand::Shell(line) => write!(f, "{line}"),
+            HookCommand::Args(args) => write!(f, "{args:?}"),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use super::config::ConfigError;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+/// Folder of astra's configuration and data folders holding the instances' own folders
+const INSTANCES_DIR: &str = "instances";
+
+/// Instance astra runs as, whose files every path of astra points to (see `Instance::select`)
+static SELECTED: OnceLock<Instance> = OnceLock::new();
+
+/// Name of an astra instance (`--instance` or `instance` in the configuration file). Each instance
+/// has its own scheduled job and keeps its state, wallpapers and log in its own data folder, so
+/// several configurations can update the wallpaper on independent schedules. Without an instance,
+/// the job and files keep their original names.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instance {
+    name: String,
+    // Named by `instance` in the default configuration file rather than by `--instance`, the
+    // instance then keeps using the default configuration file
+    from_config_file: bool,
+}
+
+impl Instance {
+    /// Parses an instance name, only letters, digits, `-` and `_` are allowed since it is part of
+    /// file names and job labels
+    pub fn new(name: &str) -> Result<Self, ConfigError> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(ConfigError::Instance(format!(
+                "\"{name}\", use letters, digits, '-' or '_'"
+            )));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            from_config_file: false,
+        })
+    }
+
+    /// Runs astra as `instance` for the rest of the process, called once at startup before the
+    /// configuration file is read. Later calls are ignored, `--instance` is selected first so it
+    /// takes precedence over the configuration file.
+    pub fn select(instance: Instance) {
+        let _ = SELECTED.set(instance);
+    }
+
+    /// Instance astra runs as, `None` for the default instance
+    pub fn selected() -> Option<&'static Instance> {
+        SELECTED.get()
+    }
+
+    /// Configuration folder of `instance` in astra's configuration folder `base`:
+    /// `<base>/instances/<name>` for an instance from `--instance`, `base` otherwise
+    pub fn config_dir(base: &Path, instance: Option<&Instance>) -> PathBuf {
+        match instance {
+            Some(instance) if !instance.from_config_file => {
+                base.join(INSTANCES_DIR).join(&instance.name)
+            }
+            _ => base.to_path_buf(),
+        }
+    }
+
+    /// Data folder of `instance` (state, wallpapers, log) in astra's data folder `base`:
+    /// `<base>/instances/<name>`, `base` for the default instance
+    pub fn data_dir(base: &Path, instance: Option<&Instance>) -> PathBuf {
+        match instance {
+            Some(instance) => base.join(INSTANCES_DIR).join(&instance.name),
+            None => base.to_path_buf(),
+        }
+    }
+
+    /// Name of the scheduled job of `instance`: `base` for the default instance, otherwise `base`
+    /// followed by `separator` and the instance name
+    pub fn job_name(instance: Option<&Instance>, base: &str, separator: char) -> String {
+        match instance {
+            Some(instance) => format!("{base}{separator}{instance}"),
+            None => base.to_string(),
+        }
+    }
+
+    /// Arguments the scheduled job passes to astra so it runs as `instance`. An instance named in
+    /// the default configuration file needs none, the job reads that file.
+    pub fn job_args(instance: Option<&Instance>) -> Vec<String> {
+        match instance {
+            Some(instance) if !instance.from_config_file => {
+                vec!["--instance".to_string(), instance.to_string()]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+impl Display for Instance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl<'de> Deserialize<'de> for Instance {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        let instance = Instance::new(s.as_str()).map_err(serde::de::Error::custom)?;
+        Ok(Self {
+            from_config_file: true,
+            ..instance
+        })
+    }
+}
+
+impl Serialize for Instance {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance_names_are_validated() {
+        assert!(Instance::new("work").is_ok());
+        assert!(Instance::new("home_2-b").is_ok());
+        for name in ["", "a b", "../x", "work.plist"] {
+            assert!(Instance::new(name).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_instances_have_their_own_folders() {
+        let (config, data) = (Path::new("/config/astra"), Path::new("/data/astra"));
+        assert_eq!(Instance::config_dir(config, None), config);
+        assert_eq!(Instance::data_dir(data, None), data);
+
+        let work = Instance::new("work").unwrap();
+        assert_eq!(
+            Instance::config_dir(config, Some(&work)),
+            Path::new("/config/astra/instances/work")
+        );
+        assert_eq!(
+            Instance::data_dir(data, Some(&work)),
+            Path::new("/data/astra/instances/work")
+        );
+        assert_eq!(Instance::job_args(Some(&work)), ["--instance", "work"]);
+
+        // an instance named in the default configuration file keeps using that file
+        let personal: Instance = serde_json::from_str(r#""personal""#).unwrap();
+        assert_eq!(Instance::config_dir(config, Some(&personal)), config);
+        assert_eq!(
+            Instance::data_dir(data, Some(&personal)),
+            Path::new("/data/astra/instances/personal")
+        );
+        assert!(Instance::job_args(Some(&personal)).is_empty());
+    }
+}
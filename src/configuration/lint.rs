@@ -0,0 +1,391 @@
+use super::super::themes::{ColorTheme, MIN_TEXT_CONTRAST, ThemeSelector};
+use super::theme::normalize_theme_name;
+use super::user_config::UserConfig;
+use std::fmt::{Display, Formatter};
+
+/// Frequencies below this many seconds regenerate (or download) a wallpaper so often that the
+/// result is barely seen
+const MIN_FREQUENCY_SECS: u64 = 5 * 60;
+
+/// Setting that parses fine but has no (or an unintended) effect, found by `astra validate --lint`
+#[derive(Debug, PartialEq)]
+pub struct LintWarning {
+    pub key: String,
+    pub message: String,
+    pub suggestion: String,
+}
+
+impl LintWarning {
+    fn new(
+        key: impl Into<String>,
+        message: impl Into<String>,
+        suggestion: impl Into<String>,
+    ) -> Self {
+        Self {
+            key: key.into(),
+            message: message.into(),
+            suggestion: suggestion.into(),
+        }
+    }
+}
+
+impl Display for LintWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} (suggestion: {})",
+            self.key, self.message, self.suggestion
+        )
+    }
+}
+
+type LintRule = fn(&UserConfig) -> Vec<LintWarning>;
+
+// Add new checks here
+const RULES: [LintRule; 6] = [
+    theme_shadows_built_in,
+    respect_color_themes_without_themes,
+    solid_colors_ignored_for_themes,
+    generator_config_for_unused_generator,
+    frequency_below_floor,
+    auto_clean_before_frequency,
+];
+
+/// Runs every lint rule over the configuration
+pub(super) fn lint(config: &UserConfig) -> Vec<LintWarning> {
+    RULES.iter().flat_map(|rule| rule(config)).collect()
+}
+
+/// Warns about themes whose average color makes the desktop's text hard to read: black text
+/// over the light colors and white text over the dark mode colors (`astra validate
+/// --theme-contrast`). Checks the built-in themes when none are configured.
+pub(super) fn theme_contrast(config: &UserConfig) -> Vec<LintWarning> {
+    let themes: Vec<ColorTheme> = match &config.themes {
+        Some(themes) if !themes.themes().is_empty() => themes
+            .themes()
+            .iter()
+            .map(|theme| theme.to_color_theme())
+            .collect(),
+        _ => ThemeSelector::built_in_themes(),
+    };
+    let mut warnings = vec![];
+    for theme in &themes {
+        let modes = [(false, "colors", "black", [0, 0, 0])].into_iter().chain(
+            theme.supports_dark_mode().then_some((
+                true,
+                "dark_mode_colors",
+                "white",
+                [255, 255, 255],
+            )),
+        );
+        for (dark_mode, key, text, foreground) in modes {
+            let Ok(ratio) = theme.contrast_ratio(dark_mode, foreground) else {
+                continue;
+            };
+            if ratio < MIN_TEXT_CONTRAST {
+                warnings.push(LintWarning::new(
+                    format!("themes.{}.{key}", theme.name()),
+                    format!("average color has a contrast of {ratio:.2}:1 with {text} text"),
+                    format!(
+                        "use {} colors to reach at least {MIN_TEXT_CONTRAST}:1",
+                        if dark_mode { "darker" } else { "lighter" }
+                    ),
+                ));
+            }
+        }
+    }
+    warnings
+}
+
+/// Warns about user themes named like a built-in theme (also reported when the configuration is
+/// loaded with `--verbose`). Looking the name up finds the user theme.
+pub(super) fn theme_shadows_built_in(config: &UserConfig) -> Vec<LintWarning> {
+    let Some(themes) = &config.themes else {
+        return vec![];
+    };
+    let built_in: Vec<String> = ThemeSelector::built_in_themes()
+        .iter()
+        .map(|theme| normalize_theme_name(theme.name()))
+        .collect();
+    themes
+        .themes()
+        .iter()
+        .filter(|theme| built_in.contains(&normalize_theme_name(theme.name())))
+        .map(|theme| {
+            LintWarning::new(
+                format!("themes.{}.name", theme.name()),
+                "is also the name of a built-in theme, which this theme replaces when a theme is looked up by name",
+                "rename the theme to keep using the built-in one by name",
+            )
+        })
+        .collect()
+}
+
+/// `respect_color_themes` of every generator that has one, keyed by its generator config
+fn respect_color_themes(config: &UserConfig) -> Vec<(&'static str, Option<bool>)> {
+    macro_rules! respect {
+        ($field:ident) => {
+            (
+                stringify!($field),
+                config
+                    .$field
+                    .as_ref()
+                    .and_then(|c| c.respect_color_themes()),
+            )
+        };
+    }
+
+    vec![
+        respect!(datetime_gen),
+        respect!(flame_gen),
+        respect!(ifs_gen),
+        respect!(julia_gen),
+        respect!(mandala_gen),
+        respect!(solid_gen),
+        respect!(spotlight_gen),
+        respect!(text_gen),
+        respect!(truchet_gen),
+    ]
+}
+
+fn respect_color_themes_without_themes(config: &UserConfig) -> Vec<LintWarning> {
+    let has_themes = config
+        .themes
+        .as_ref()
+        .is_some_and(|themes| !themes.themes().is_empty());
+    if has_themes {
+        return vec![];
+    }
+    respect_color_themes(config)
+        .into_iter()
+        .filter(|(_, respect)| *respect == Some(true))
+        .map(|(key, _)| {
+            LintWarning::new(
+                format!("{key}.respect_color_themes"),
+                "is true but no themes are configured, so a built-in theme is used",
+                "add a theme to `themes` or remove respect_color_themes",
+            )
+        })
+        .collect()
+}
+
+fn solid_colors_ignored_for_themes(config: &UserConfig) -> Vec<LintWarning> {
+    let Some(solid) = &config.solid_gen else {
+        return vec![];
+    };
+    if solid.respect_color_themes() != Some(true) {
+        return vec![];
+    }
+    [
+        (
+            "preferred_default_colors",
+            solid.preferred_default_colors().is_some(),
+        ),
+        (
+            "preferred_rgb_colors",
+            solid.preferred_rgb_colors().is_some(),
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, is_set)| *is_set)
+    .map(|(key, _)| {
+        LintWarning::new(
+            format!("solid_gen.{key}"),
+            "is ignored because solid_gen.respect_color_themes is true",
+            "set solid_gen.respect_color_themes to false or remove the preferred colors",
+        )
+    })
+    .collect()
+}
+
+fn generator_config_for_unused_generator(config: &UserConfig) -> Vec<LintWarning> {
+    // Without a generators list every generator can be picked
+    let Some(generators) = &config.generators else {
+        return vec![];
+    };
+    let is_used = |prefix: &str| {
+        generators.iter().any(|g| g.prefix() == prefix)
+            || config.schedule_by_time.as_ref().is_some_and(|schedule| {
+                schedule
+                    .generator_sets()
+                    .any(|set| set.iter().any(|g| g.prefix() == prefix))
+            })
+    };
+    [
+        ("datetime_gen", "datetime", config.datetime_gen.is_some()),
+        ("flame_gen", "flame", config.flame_gen.is_some()),
+        ("ifs_gen", "ifs", config.ifs_gen.is_some()),
+        ("julia_gen", "julia", config.julia_gen.is_some()),
+        ("mandala_gen", "mandala", config.mandala_gen.is_some()),
+        ("nasa_apod_gen", "nasa_apod", config.nasa_apod_gen.is_some()),
+        ("solid_gen", "solid", config.solid_gen.is_some()),
+        ("spotlight_gen", "spotlight", config.spotlight_gen.is_some()),
+        ("text_gen", "text", config.text_gen.is_some()),
+        ("truchet_gen", "truchet", config.truchet_gen.is_some()),
+    ]
+    .into_iter()
+    .filter(|(_, prefix, is_set)| *is_set && !is_used(prefix))
+    .map(|(key, prefix, _)| {
+        LintWarning::new(
+            key,
+            format!("is set but \"{prefix}\" is not in generators"),
+            format!("add \"{prefix}\" to generators or remove {key}"),
+        )
+    })
+    .collect()
+}
+
+fn frequency_below_floor(config: &UserConfig) -> Vec<LintWarning> {
+    match &config.frequency {
+        Some(frequency) if frequency.to_seconds() < MIN_FREQUENCY_SECS => {
+            vec![LintWarning::new(
+                "frequency",
+                format!("{frequency} changes the wallpaper before it can be noticed"),
+                format!("use a frequency of at least {}m", MIN_FREQUENCY_SECS / 60),
+            )]
+        }
+        _ => vec![],
+    }
+}
+
+fn auto_clean_before_frequency(config: &UserConfig) -> Vec<LintWarning> {
+    match (&config.auto_clean, &config.frequency) {
+        (Some(auto_clean), Some(frequency)) if auto_clean.to_seconds() < frequency.to_seconds() => {
+            vec![LintWarning::new(
+                "auto_clean",
+                format!(
+                    "{auto_clean} is shorter than frequency ({frequency}), so the current wallpaper's image is deleted before the next one is generated"
+                ),
+                "use an auto_clean period longer than frequency",
+            )]
+        }
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warnings(json: &str) -> Vec<String> {
+        lint(&serde_json::from_str(json).unwrap())
+            .into_iter()
+            .map(|warning| warning.key)
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_config_has_no_warnings() {
+        assert!(warnings("{}").is_empty());
+        assert!(
+            warnings(
+                r#"{
+                    "frequency": "1h",
+                    "auto_clean": "1w",
+                    "generators": ["julia", "solid"],
+                    "julia_gen": { "respect_color_themes": true },
+                    "solid_gen": { "preferred_rgb_colors": [[1, 2, 3]] },
+                    "themes": [{ "name": "a", "colors": [[0, 0, 0]] }]
+                }"#
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_respect_color_themes_without_themes() {
+        let expected = vec!["julia_gen.respect_color_themes".to_string()];
+        assert_eq!(
+            warnings(r#"{ "julia_gen": { "respect_color_themes": true } }"#),
+            expected
+        );
+        assert_eq!(
+            warnings(r#"{ "julia_gen": { "respect_color_themes": true }, "themes": [] }"#),
+            expected
+        );
+        assert!(warnings(r#"{ "julia_gen": { "respect_color_themes": false } }"#).is_empty());
+    }
+
+    #[test]
+    fn test_solid_colors_ignored_for_themes() {
+        let themes = r#""themes": [{ "name": "a", "colors": [[0, 0, 0]] }]"#;
+        assert_eq!(
+            warnings(&format!(
+                r#"{{ "solid_gen": {{ "respect_color_themes": true, "preferred_rgb_colors": [[1, 2, 3]] }}, {themes} }}"#
+            )),
+            vec!["solid_gen.preferred_rgb_colors".to_string()]
+        );
+        assert!(
+            warnings(&format!(
+                r#"{{ "solid_gen": {{ "respect_color_themes": true }}, {themes} }}"#
+            ))
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_generator_config_for_unused_generator() {
+        assert_eq!(
+            warnings(r#"{ "generators": ["julia"], "mandala_gen": { "symmetry": 6 } }"#),
+            vec!["mandala_gen".to_string()]
+        );
+        assert!(
+            warnings(
+                r#"{
+                    "generators": ["julia"],
+                    "schedule_by_time": { "18:00-06:00": ["mandala"] },
+                    "mandala_gen": { "symmetry": 6 }
+                }"#
+            )
+            .is_empty()
+        );
+        assert!(warnings(r#"{ "mandala_gen": { "symmetry": 6 } }"#).is_empty());
+    }
+
+    #[test]
+    fn test_theme_contrast_flags_low_contrast_palettes() {
+        let config: UserConfig = serde_json::from_str(
+            r#"{ "themes": [
+                { "name": "Pastel", "colors": [[250, 240, 230], [220, 230, 250]] },
+                { "name": "Navy", "colors": [[20, 30, 90], [10, 10, 60]] },
+                { "name": "Dusk", "colors": [[250, 250, 250]], "dark_mode_colors": [[200, 200, 210]] }
+            ] }"#,
+        )
+        .unwrap();
+        let keys: Vec<String> = theme_contrast(&config)
+            .into_iter()
+            .map(|warning| warning.key)
+            .collect();
+        assert_eq!(
+            keys,
+            vec!["themes.Navy.colors", "themes.Dusk.dark_mode_colors"]
+        );
+    }
+
+    #[test]
+    fn test_theme_shadows_built_in() {
+        assert_eq!(
+            warnings(r#"{ "themes": [{ "name": "ocean_breeze", "colors": [[0, 0, 0]] }] }"#),
+            vec!["themes.ocean_breeze.name".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_frequency_below_floor() {
+        assert_eq!(
+            warnings(r#"{ "frequency": "30s" }"#),
+            vec!["frequency".to_string()]
+        );
+        assert!(warnings(r#"{ "frequency": "5m" }"#).is_empty());
+    }
+
+    #[test]
+    fn test_auto_clean_before_frequency() {
+        assert_eq!(
+            warnings(r#"{ "frequency": "1d", "auto_clean": "1h" }"#),
+            vec!["auto_clean".to_string()]
+        );
+        assert!(warnings(r#"{ "frequency": "1h", "auto_clean": "1d" }"#).is_empty());
+        assert!(warnings(r#"{ "auto_clean": "1h" }"#).is_empty());
+    }
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    path::PathBuf,
+};
+
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct LogFileConfig {
+    path: Option<PathBuf>,
+    max_size_kb: Option<u64>,
+    // If true, `astra generate ...` runs are logged too
+    interactive: Option<bool>,
+}
+
+impl LogFileConfig {
+    pub fn path(&self) -> Option<PathBuf> {
+        self.path.clone()
+    }
+
+    pub fn max_size_kb(&self) -> Option<u64> {
+        self.max_size_kb
+    }
+
+    pub fn interactive(&self) -> Option<bool> {
+        self.interactive
+    }
+}
+
+impl Display for LogFileConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut fields = vec![];
+        if let Some(val) = &self.path {
+            fields.push(format!("path: {}", val.display()));
+        }
+        if let Some(val) = &self.max_size_kb {
+            fields.push(format!("max_size_kb: {}", val));
+        }
+        if let Some(val) = &self.interactive {
+            fields.push(format!("interactive: {}", val));
+        }
+        write!(f, "{{ {} }}", fields.join(", "))
+    }
+}
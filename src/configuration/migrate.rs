@@ -0,0 +1,161 @@
+use super::user_config::UserConfig;
+use serde_json::Value;
+use std::fmt::{Display, Formatter};
+
+/// Keys astra no longer reads, with the key that replaced them (nested keys are separated by
+/// dots). Add an entry here whenever a configuration key is renamed.
+pub(super) const MIGRATIONS: &[(&str, &str)] = &[];
+
+/// Renames used to test migrations while `MIGRATIONS` is empty
+#[cfg(test)]
+pub(super) const TEST_MIGRATIONS: &[(&str, &str)] =
+    &[("autoclean", "auto_clean"), ("generator", "generators")];
+
+/// Objects whose keys are values rather than configuration keys (e.g. time ranges)
+const FREE_FORM_KEYS: [&str; 1] = ["schedule_by_time"];
+
+/// Change reported by `astra migrate`
+#[derive(Debug, PartialEq)]
+pub enum Migration {
+    Renamed {
+        from: String,
+        to: String,
+    },
+    /// Key astra does not know about, kept as is
+    UnknownKey(String),
+}
+
+impl Display for Migration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Migration::Renamed { from, to } => write!(f, "renamed {from} to {to}"),
+            Migration::UnknownKey(key) => write!(f, "unknown key {key} (kept as is)"),
+        }
+    }
+}
+
+impl Migration {
+    pub fn is_rename(&self) -> bool {
+        matches!(self, Migration::Renamed { .. })
+    }
+}
+
+/// Renames the keys of `renames` (usually `MIGRATIONS`) in the configuration file's JSON
+/// `config` in place, then reports the keys that are still unknown. Fails if the migrated
+/// configuration does not parse.
+pub(super) fn migrate(
+    config: &mut Value,
+    renames: &[(&str, &str)],
+) -> Result<Vec<Migration>, serde_json::Error> {
+    let mut migrations: Vec<Migration> = renames
+        .iter()
+        .filter(|(from, to)| rename_key(config, from, to))
+        .map(|(from, to)| Migration::Renamed {
+            from: from.to_string(),
+            to: to.to_string(),
+        })
+        .collect();
+
+    // Serializing the parsed configuration gives back every key astra knows about (unset ones as
+    // null), anything else in the file is unknown
+    let known = serde_json::to_value(serde_json::from_value::<UserConfig>(config.clone())?)?;
+    let mut unknown = vec![];
+    unknown_keys(config, &known, "", &mut unknown);
+    migrations.extend(unknown.into_iter().map(Migration::UnknownKey));
+    Ok(migrations)
+}
+
+/// Moves the value at the dotted path `from` to `to` (same parent object), unless `to` is
+/// already set. Returns true if the key was moved.
+fn rename_key(config: &mut Value, from: &str, to: &str) -> bool {
+    let (parent_path, from_key) = from.rsplit_once('.').unwrap_or(("", from));
+    let to_key = to.rsplit_once('.').map_or(to, |(_, key)| key);
+    let parent = parent_path
+        .split('.')
+        .filter(|key| !key.is_empty())
+        .try_fold(&mut *config, |value, key| value.get_mut(key));
+    let Some(Value::Object(parent)) = parent else {
+        return false;
+    };
+    if parent.contains_key(to_key) {
+        return false;
+    }
+    match parent.remove(from_key) {
+        Some(value) => {
+            parent.insert(to_key.to_string(), value);
+            true
+        }
+        None => false,
+    }
+}
+
+fn unknown_keys(value: &Value, known: &Value, path: &str, unknown: &mut Vec<String>) {
+    match (value, known) {
+        (Value::Object(object), Value::Object(known)) => {
+            for (key, value) in object {
+                let key_path = if path.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{path}.{key}")
+                };
+                match known.get(key) {
+                    None => unknown.push(key_path),
+                    Some(_) if FREE_FORM_KEYS.contains(&key_path.as_str()) => {}
+                    Some(known) => unknown_keys(value, known, &key_path, unknown),
+                }
+            }
+        }
+        (Value::Array(values), Value::Array(known)) => {
+            for (index, (value, known)) in values.iter().zip(known).enumerate() {
+                unknown_keys(value, known, &format!("{path}[{index}]"), unknown);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_renames_old_key_and_keeps_unknown_ones() {
+        let mut config: Value = serde_json::from_str(
+            r#"{
+                "generator": ["julia"],
+                "julia_gen": { "segments": 4, "sparkles": true },
+                "wallpaper_dir": "~/Pictures"
+            }"#,
+        )
+        .unwrap();
+        let migrations = migrate(&mut config, TEST_MIGRATIONS).unwrap();
+
+        assert_eq!(
+            migrations,
+            vec![
+                Migration::Renamed {
+                    from: "generator".to_string(),
+                    to: "generators".to_string(),
+                },
+                Migration::UnknownKey("julia_gen.sparkles".to_string()),
+                Migration::UnknownKey("wallpaper_dir".to_string()),
+            ]
+        );
+        assert_eq!(config["generators"], serde_json::json!(["julia"]));
+        assert!(config.get("generator").is_none());
+        assert_eq!(config["wallpaper_dir"], "~/Pictures");
+        assert!(serde_json::from_value::<UserConfig>(config).is_ok());
+    }
+
+    #[test]
+    fn test_migrate_does_not_overwrite_current_key() {
+        let mut config: Value =
+            serde_json::from_str(r#"{ "generator": ["julia"], "generators": ["solid"] }"#).unwrap();
+        let migrations = migrate(&mut config, TEST_MIGRATIONS).unwrap();
+        assert_eq!(
+            migrations,
+            vec![Migration::UnknownKey("generator".to_string())]
+        );
+        assert_eq!(config["generators"], serde_json::json!(["solid"]));
+    }
+}
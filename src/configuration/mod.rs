@@ -1,9 +1,23 @@
 mod config;
+mod explain;
 mod frequency;
 pub(crate) mod generators;
+mod hook;
+mod instance;
+mod lint;
+mod log_file;
+mod migrate;
+mod post_process;
+mod schedule;
+mod state;
 mod theme;
 mod user_config;
+mod watermark;
 
 pub use config::Config;
+pub use explain::Decision;
 pub use frequency::Frequency;
 pub use generators::Generators;
+pub use hook::HookCommand;
+pub use instance::Instance;
+pub use state::{AppliedWallpaper, ImageSource, State};
@@ -0,0 +1,84 @@
+use crate::wallpaper_generators::RetroPalette;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Write};
+
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PostProcessConfig {
+    // -100 (cool) to 100 (warm), ignored when temperature_schedule is true
+    color_temperature: Option<i8>,
+    quantize: Option<QuantizeConfig>,
+    temperature_schedule: Option<bool>,
+}
+
+impl PostProcessConfig {
+    pub fn color_temperature(&self) -> Option<i8> {
+        self.color_temperature
+    }
+
+    pub fn temperature_schedule(&self) -> Option<bool> {
+        self.temperature_schedule
+    }
+
+    pub fn quantize(&self) -> Option<&QuantizeConfig> {
+        self.quantize.as_ref()
+    }
+}
+
+impl Display for PostProcessConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // only write if defined, else return empty string
+        let mut s = String::new();
+        if let Some(val) = &self.color_temperature {
+            writeln!(&mut s, "    color_temperature: {}", val)?;
+        }
+        if let Some(val) = &self.quantize {
+            writeln!(&mut s, "    quantize: {}", val)?;
+        }
+        if let Some(val) = &self.temperature_schedule {
+            writeln!(&mut s, "    temperature_schedule: {}", val)?;
+        }
+        if !s.is_empty() {
+            writeln!(f)?;
+            s.pop(); // remove last newline character
+        }
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct QuantizeConfig {
+    // If set, colors is ignored
+    palette: Option<RetroPalette>,
+    colors: Option<u8>,
+    dither: Option<bool>,
+}
+
+impl QuantizeConfig {
+    pub fn palette(&self) -> Option<RetroPalette> {
+        self.palette
+    }
+
+    pub fn colors(&self) -> Option<u8> {
+        self.colors
+    }
+
+    pub fn dither(&self) -> Option<bool> {
+        self.dither
+    }
+}
+
+impl Display for QuantizeConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut fields = vec![];
+        if let Some(val) = &self.palette {
+            fields.push(format!("palette: {:?}", val));
+        }
+        if let Some(val) = &self.colors {
+            fields.push(format!("colors: {}", val));
+        }
+        if let Some(val) = &self.dither {
+            fields.push(format!("dither: {}", val));
+        }
+        write!(f, "{{ {} }}", fields.join(", "))
+    }
+}
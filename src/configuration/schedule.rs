@@ -0,0 +1,184 @@
+use super::config::ConfigError;
+use super::generators::Generators;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// Part of the day written as `HH:MM-HH:MM` (start included, end excluded). Ranges ending before
+/// they start wrap past midnight, e.g. `18:00-06:00`, and `00:00-00:00` covers the whole day.
+#[derive(Debug, PartialEq)]
+pub struct TimeRange {
+    start: u32,
+    end: u32,
+}
+
+impl TimeRange {
+    /// True if `minute` (minutes since midnight) falls in the range
+    pub fn contains(&self, minute: u32) -> bool {
+        if self.start < self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+
+    fn overlaps(&self, other: &TimeRange) -> bool {
+        self.contains(other.start) || other.contains(self.start)
+    }
+}
+
+/// Minutes since midnight of a `HH:MM` time
+fn parse_time_of_day(s: &str) -> Option<u32> {
+    let (hours, minutes) = s.trim().split_once(':')?;
+    if minutes.len() != 2 {
+        return None;
+    }
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+impl FromStr for TimeRange {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            ConfigError::Parse(format!(
+                "invalid time range \"{s}\" - expected HH:MM-HH:MM (e.g. 06:00-18:00)"
+            ))
+        };
+        let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+        let start = parse_time_of_day(start).ok_or_else(invalid)?;
+        let end = parse_time_of_day(end).ok_or_else(invalid)?;
+        Ok(TimeRange { start, end })
+    }
+}
+
+impl Display for TimeRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start / 60,
+            self.start % 60,
+            self.end / 60,
+            self.end % 60
+        )
+    }
+}
+
+/// Generators to choose from depending on the local time of day (`schedule_by_time` key)
+#[derive(Debug, PartialEq)]
+pub struct TimeSchedule(Vec<(TimeRange, Generators)>);
+
+impl TimeSchedule {
+    /// Generators of the range containing `minute` (minutes since midnight), if any
+    pub fn generators_at(&self, minute: u32) -> Option<&Generators> {
+        self.0
+            .iter()
+            .find(|(range, _)| range.contains(minute % MINUTES_PER_DAY))
+            .map(|(_, generators)| generators)
+    }
+
+    /// Generators of every range
+    pub fn generator_sets(&self) -> impl Iterator<Item = &Generators> {
+        self.0.iter().map(|(_, generators)| generators)
+    }
+}
+
+impl Display for TimeSchedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (range, generators) in &self.0 {
+            write!(f, "\n    {range}: {generators}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeSchedule {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let raw: BTreeMap<String, Generators> = BTreeMap::deserialize(deserializer)?;
+        let mut schedule: Vec<(TimeRange, Generators)> = vec![];
+        for (range, generators) in raw {
+            let range: TimeRange = range.parse().map_err(serde::de::Error::custom)?;
+            if let Some((other, _)) = schedule.iter().find(|(other, _)| other.overlaps(&range)) {
+                return Err(serde::de::Error::custom(format!(
+                    "time ranges {other} and {range} overlap"
+                )));
+            }
+            schedule.push((range, generators));
+        }
+        Ok(TimeSchedule(schedule))
+    }
+}
+
+impl Serialize for TimeSchedule {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_map(
+            self.0
+                .iter()
+                .map(|(range, generators)| (range.to_string(), generators)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Generator;
+
+    fn schedule() -> TimeSchedule {
+        serde_json::from_str(
+            r#"{ "06:00-18:00": ["julia", "spotlight"], "18:00-06:00": ["solid", "truchet"] }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_generators_at_evening_uses_range_wrapping_past_midnight() {
+        let schedule = schedule();
+        let evening = schedule.generators_at(20 * 60).unwrap();
+        assert!(evening.contains(&Generator::Truchet));
+        assert!(!evening.contains(&Generator::Julia));
+        assert_eq!(
+            schedule.generators_at(2 * 60),
+            schedule.generators_at(20 * 60)
+        );
+        assert!(
+            schedule
+                .generators_at(12 * 60)
+                .unwrap()
+                .contains(&Generator::Julia)
+        );
+        // 18:00 starts the evening range
+        assert!(
+            schedule
+                .generators_at(18 * 60)
+                .unwrap()
+                .contains(&Generator::Truchet)
+        );
+    }
+
+    #[test]
+    fn test_invalid_time_ranges_are_rejected() {
+        for json in [
+            r#"{ "6pm-8pm": ["julia"] }"#,
+            r#"{ "24:00-06:00": ["julia"] }"#,
+            r#"{ "06:00-18:00": ["julia"], "12:00-20:00": ["solid"] }"#,
+        ] {
+            assert!(
+                serde_json::from_str::<TimeSchedule>(json).is_err(),
+                "{json}"
+            );
+        }
+    }
+}
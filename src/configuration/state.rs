@@ -0,0 +1,229 @@
+use super::config::Config;
+use crate::wallpaper_generators::{WallpaperGeneratorError, write_atomically};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Number of applied wallpapers kept in the history listed by `astra recent`
+const MAX_HISTORY: usize = 20;
+
+/// Runtime state that astra persists between runs (as opposed to the user's configuration).
+/// Stored as `state.json` in astra's data directory.
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct State {
+    last_image_hash: Option<String>,
+    theme_averages: Option<ThemeAverages>,
+    last_source: Option<ImageSource>,
+    /// Index of the next quote of `text_gen.source_file` shown in sequential order
+    quote_index: Option<usize>,
+    /// Wallpapers applied from the wallpaper folder, the most recent first
+    history: Option<Vec<AppliedWallpaper>>,
+}
+
+/// Saved wallpaper that was set on the desktop, listed by `astra recent`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct AppliedWallpaper {
+    pub path: PathBuf,
+    /// Prefix of the generator that made the image (e.g. "julia")
+    pub generator: String,
+    /// Local time the wallpaper was applied (YYYY-MM-DD HH:MM)
+    pub applied_at: String,
+}
+
+impl AppliedWallpaper {
+    /// `path` made by `generator`, applied now
+    pub fn now(path: PathBuf, generator: &str) -> Self {
+        Self {
+            path,
+            generator: generator.to_string(),
+            applied_at: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+        }
+    }
+}
+
+/// Where a downloaded wallpaper came from, so it can be found again later
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ImageSource {
+    /// Prefix of the generator that downloaded the image (e.g. "spotlight")
+    pub generator: String,
+    pub url: String,
+    pub title: Option<String>,
+    /// Day the image was published or fetched (YYYY-MM-DD)
+    pub date: String,
+    /// Hash of the downloaded file, added to `spotlight_blocklist` by `--block-current`
+    pub hash: Option<String>,
+}
+
+/// Average colors of the user's themes, along with a hash of the themes they were computed from
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct ThemeAverages {
+    themes_hash: String,
+    averages: Vec<[u8; 3]>,
+}
+
+impl State {
+    pub fn path() -> PathBuf {
+        Config::data_dir().join("state.json")
+    }
+
+    /// Loads the state file. A missing or unreadable state file results in the default state, as
+    /// the state only holds information that can be rebuilt on later runs.
+    pub fn load() -> Self {
+        Self::load_from(&Self::path())
+    }
+
+    pub(crate) fn load_from(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), StateError> {
+        self.save_to(&Self::path())
+    }
+
+    pub(crate) fn save_to(&self, path: &Path) -> Result<(), StateError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| StateError::Write(e.to_string()))?;
+        }
+        let data =
+            serde_json::to_string_pretty(self).map_err(|e| StateError::Write(e.to_string()))?;
+        write_atomically(path, |partial_path| {
+            fs::write(partial_path, data).map_err(|e| WallpaperGeneratorError::OS(e.to_string()))
+        })
+        .map_err(|e| StateError::Write(e.to_string()))
+    }
+
+    /// Hash of the last image that was applied as the wallpaper
+    pub fn last_image_hash(&self) -> Option<&str> {
+        self.last_image_hash.as_deref()
+    }
+
+    pub fn set_last_image_hash(&mut self, hash: String) {
+        self.last_image_hash = Some(hash);
+    }
+
+    /// Cached average colors of the user's themes, if they were computed from themes with the
+    /// given hash
+    pub fn theme_averages(&self, themes_hash: &str) -> Option<&[[u8; 3]]> {
+        self.theme_averages
+            .as_ref()
+            .filter(|cached| cached.themes_hash == themes_hash)
+            .map(|cached| cached.averages.as_slice())
+    }
+
+    /// Source of the last downloaded image
+    pub fn last_source(&self) -> Option<&ImageSource> {
+        self.last_source.as_ref()
+    }
+
+    pub fn set_last_source(&mut self, source: ImageSource) {
+        self.last_source = Some(source);
+    }
+
+    pub fn quote_index(&self) -> Option<usize> {
+        self.quote_index
+    }
+
+    pub fn set_quote_index(&mut self, index: usize) {
+        self.quote_index = Some(index);
+    }
+
+    /// Wallpapers applied from the wallpaper folder, the most recent first
+    pub fn history(&self) -> &[AppliedWallpaper] {
+        self.history.as_deref().unwrap_or_default()
+    }
+
+    /// Adds `wallpaper` at the top of the history (moving it there if the file was already
+    /// listed), keeping the `MAX_HISTORY` most recent ones
+    pub fn record_applied(&mut self, wallpaper: AppliedWallpaper) {
+        let history = self.history.get_or_insert_default();
+        history.retain(|applied| applied.path != wallpaper.path);
+        history.insert(0, wallpaper);
+        history.truncate(MAX_HISTORY);
+    }
+
+    pub fn set_theme_averages(&mut self, themes_hash: String, averages: Vec<[u8; 3]>) {
+        self.theme_averages = Some(ThemeAverages {
+            themes_hash,
+            averages,
+        });
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StateError {
+    Write(String),
+}
+
+impl Display for StateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StateError::Write(err_msg) => write!(f, "Unable to write state file: {err_msg}"),
+        }
+    }
+}
+
+impl Error for StateError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("state.json");
+        let mut state = State::default();
+        state.set_last_image_hash("abc".to_string());
+        state.save_to(&path).unwrap();
+        assert_eq!(State::load_from(&path), state);
+    }
+
+    #[test]
+    fn test_history_keeps_most_recent_first_without_duplicates() {
+        let mut state = State::default();
+        assert!(state.history().is_empty());
+        for i in 0..MAX_HISTORY + 5 {
+            state.record_applied(AppliedWallpaper::now(
+                PathBuf::from(format!("julia_{i}.png")),
+                "julia",
+            ));
+        }
+        assert_eq!(state.history().len(), MAX_HISTORY);
+        assert_eq!(
+            state.history()[0].path,
+            PathBuf::from(format!("julia_{}.png", MAX_HISTORY + 4))
+        );
+
+        state.record_applied(AppliedWallpaper::now(
+            PathBuf::from("julia_10.png"),
+            "julia",
+        ));
+        assert_eq!(state.history().len(), MAX_HISTORY);
+        assert_eq!(state.history()[0].path, PathBuf::from("julia_10.png"));
+        assert_eq!(
+            state
+                .history()
+                .iter()
+                .filter(|applied| applied.path == Path::new("julia_10.png"))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_missing_or_invalid_state_is_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        assert_eq!(State::load_from(&path), State::default());
+        fs::write(&path, "not json").unwrap();
+        assert_eq!(State::load_from(&path), State::default());
+    }
+}
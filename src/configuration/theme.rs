@@ -1,9 +1,61 @@
 use super::super::themes::{ColorTheme, ThemeSelector};
-use rand::seq::IndexedRandom;
-use serde::Deserialize;
+use super::config::ConfigError;
+use rand::{Rng, seq::IndexedRandom};
+use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter, Write};
+use std::path::Path;
 
-#[derive(Debug, Deserialize, PartialEq)]
+/// Key theme names are compared by: case, spaces, dashes and underscores are ignored (`neon-dreams`
+/// matches "Neon Dreams")
+pub(super) fn normalize_theme_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// How generators pick a theme when none is forced (`theme_selection` key)
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeSelection {
+    #[default]
+    Random,
+    /// The theme whose average color contrasts the most with the `foreground` color
+    MaxContrast,
+}
+
+impl Display for ThemeSelection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThemeSelection::Random => write!(f, "random"),
+            ThemeSelection::MaxContrast => write!(f, "max_contrast"),
+        }
+    }
+}
+
+/// Theme of `themes` with the highest WCAG contrast ratio between its average color and
+/// `foreground`, the first one on ties. Themes without colors are skipped.
+pub(super) fn max_contrast_theme(
+    themes: Vec<ColorTheme>,
+    dark_mode: bool,
+    foreground: [u8; 3],
+) -> Option<ColorTheme> {
+    let mut best: Option<(f64, ColorTheme)> = None;
+    for theme in themes {
+        let Ok(ratio) = theme.contrast_ratio(dark_mode, foreground) else {
+            continue;
+        };
+        if best
+            .as_ref()
+            .is_none_or(|(best_ratio, _)| ratio > *best_ratio)
+        {
+            best = Some((ratio, theme));
+        }
+    }
+    best.map(|(_, theme)| theme)
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct ThemeConfig {
     name: String,
     colors: Vec<(u8, u8, u8)>,
@@ -11,6 +63,10 @@ pub struct ThemeConfig {
 }
 
 impl ThemeConfig {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     pub fn dark_mode_colors(&self) -> &Option<Vec<(u8, u8, u8)>> {
         &self.dark_mode_colors
     }
@@ -26,6 +82,35 @@ impl ThemeConfig {
         ))
     }
 
+    /// Builds a theme from a palette URL whose last path segment is a dash-separated list of hex
+    /// colors (e.g. `https://coolors.co/palette/1e1e1e-ff0000-ffd700`), or from such a list
+    pub fn from_palette_url(name: &str, url: &str) -> Result<Self, ConfigError> {
+        let path = url.split('?').next().unwrap_or(url).trim_end_matches('/');
+        let segment = path.rsplit('/').next().unwrap_or(path);
+        let colors = segment
+            .split('-')
+            .map(|hex| {
+                let digits = hex.strip_prefix('#').unwrap_or(hex);
+                if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(ConfigError::ImportTheme(format!(
+                        "\"{hex}\" in {url} is not a hex color like 1e90ff"
+                    )));
+                }
+                let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).unwrap();
+                Ok((channel(0), channel(2), channel(4)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ThemeConfig {
+            name: name.to_string(),
+            colors,
+            dark_mode_colors: None,
+        })
+    }
+
+    pub fn colors(&self) -> &[(u8, u8, u8)] {
+        &self.colors
+    }
+
     pub fn to_color_theme(&self) -> ColorTheme {
         ColorTheme::new(
             self.name.clone(),
@@ -58,17 +143,35 @@ impl Display for ThemeConfig {
     }
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
 pub struct ThemeConfigs(Vec<ThemeConfig>);
 
 impl ThemeConfigs {
-    pub fn random(&self) -> &ThemeConfig {
-        self.0.choose(&mut rand::rng()).expect("Failed to choose random theme because ThemeConfigs was empty - this should never happen")
+    pub fn random_with<R: Rng + ?Sized>(&self, rng: &mut R) -> &ThemeConfig {
+        self.0.choose(rng).expect("Failed to choose random theme because ThemeConfigs was empty - this should never happen")
     }
 
     pub fn themes(&self) -> &Vec<ThemeConfig> {
         &self.0
     }
+
+    /// Hex encoded hash of the themes, changes whenever a theme is added, removed or edited
+    pub fn content_hash(&self) -> String {
+        let json = serde_json::to_vec(self).expect("themes are serializable");
+        blake3::hash(&json).to_hex().to_string()
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ThemeConfig> {
+        self.0.iter().find(|theme| theme.name == name)
+    }
+
+    /// Reads a JSON file containing an array of themes
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError::ThemeFile(format!("{}: {e}", path.display())))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| ConfigError::ThemeFile(format!("{}: {e}", path.display())))
+    }
 }
 
 impl Display for ThemeConfigs {
@@ -86,3 +189,76 @@ impl Display for ThemeConfigs {
         write!(f, "[{s}]")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn test_theme_from_palette_url() {
+        let theme = ThemeConfig::from_palette_url(
+            "Sunset",
+            "https://coolors.co/palette/1e1e1e-ff0000-FFD700?ref=share",
+        )
+        .unwrap();
+        assert_eq!(theme.name(), "Sunset");
+        assert_eq!(
+            theme.colors(),
+            &[(0x1e, 0x1e, 0x1e), (0xff, 0, 0), (0xff, 0xd7, 0)]
+        );
+        let plain = ThemeConfig::from_palette_url("Sunset", "#1e1e1e-ff0000-ffd700").unwrap();
+        assert_eq!(plain, theme);
+        assert!(ThemeConfig::from_palette_url("x", "https://coolors.co/1e1e1e-ff00").is_err());
+        assert!(ThemeConfig::from_palette_url("x", "https://coolors.co/").is_err());
+    }
+
+    fn themes() -> ThemeConfigs {
+        serde_json::from_str(
+            r#"[
+                { "name": "a", "colors": [[0, 0, 0]] },
+                { "name": "b", "colors": [[1, 1, 1]] },
+                { "name": "c", "colors": [[2, 2, 2]] }
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_seeded_random_theme_is_reproducible() {
+        let themes = themes();
+        let mut rng_a = StdRng::seed_from_u64(1);
+        let mut rng_b = StdRng::seed_from_u64(1);
+        for _ in 0..20 {
+            assert_eq!(
+                themes.random_with(&mut rng_a),
+                themes.random_with(&mut rng_b)
+            );
+        }
+    }
+
+    #[test]
+    fn test_theme_file_is_validated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("themes.json");
+        std::fs::write(&path, r#"[{ "name": "ocean", "colors": [[0, 0, 255]] }]"#).unwrap();
+        let themes = ThemeConfigs::from_file(&path).unwrap();
+        assert_eq!(themes.find("ocean").unwrap().name(), "ocean");
+        assert!(themes.find("forest").is_none());
+
+        std::fs::write(&path, r#"{ "name": "ocean" }"#).unwrap();
+        assert!(ThemeConfigs::from_file(&path).is_err());
+        assert!(ThemeConfigs::from_file(&dir.path().join("missing.json")).is_err());
+    }
+
+    #[test]
+    fn test_random_theme_covers_all_themes() {
+        let themes = themes();
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..200 {
+            seen.insert(themes.random_with(&mut rng).name.clone());
+        }
+        assert_eq!(seen.len(), 3);
+    }
+}
@@ -1,22 +1,72 @@
 use super::{
     frequency::Frequency,
-    generators::{Generators, JuliaConfig, NasaApodConfig, SolidConfig, SpotlightConfig},
-    theme::ThemeConfigs,
+    generators::{
+        DateTimeConfig, FlameConfig, Generators, IfsConfig, JuliaConfig, MandalaConfig,
+        NasaApodConfig, SolidConfig, SpotlightConfig, TextConfig, TruchetConfig, generator_prefix,
+    },
+    hook::HookCommand,
+    instance::Instance,
+    log_file::LogFileConfig,
+    post_process::PostProcessConfig,
+    schedule::TimeSchedule,
+    theme::{ThemeConfigs, ThemeSelection},
+    watermark::WatermarkConfig,
 };
-use serde::Deserialize;
+use crate::cli::Generator;
+use crate::wallpaper_generators::{FilenameScheme, UpscaleFilter, WallpaperFormat};
+use serde::{Deserialize, Serialize};
 use std::cmp::PartialEq;
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
 
-#[derive(Debug, Default, Deserialize, PartialEq)]
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
 pub(super) struct UserConfig {
+    pub(super) all_spaces: Option<bool>,
     pub(super) auto_clean: Option<Frequency>,
+    pub(super) background: Option<String>,
+    pub(super) calendar: Option<String>,
+    pub(super) color_steps: Option<usize>,
+    pub(super) consistent_theme: Option<bool>,
+    pub(super) daily_seed: Option<bool>,
+    pub(super) datetime_gen: Option<DateTimeConfig>,
+    #[serde(default, with = "generator_prefix")]
+    pub(super) fallback_generator: Option<Generator>,
+    pub(super) filename_scheme: Option<FilenameScheme>,
+    pub(super) flame_gen: Option<FlameConfig>,
+    pub(super) foreground: Option<String>,
+    pub(super) format: Option<WallpaperFormat>,
     pub(super) frequency: Option<Frequency>,
+    pub(super) generation_timeout_secs: Option<u64>,
+    pub(super) generator_weights: Option<BTreeMap<String, u32>>,
     pub(super) generators: Option<Generators>,
+    pub(super) ifs_gen: Option<IfsConfig>,
+    pub(super) instance: Option<Instance>,
     pub(super) julia_gen: Option<JuliaConfig>,
+    pub(super) latest_symlink: Option<PathBuf>,
+    pub(super) log_file: Option<LogFileConfig>,
+    pub(super) mandala_gen: Option<MandalaConfig>,
+    pub(super) max_resolution: Option<(u32, u32)>,
     pub(super) nasa_apod_gen: Option<NasaApodConfig>,
+    pub(super) per_monitor: Option<bool>,
+    pub(super) post_process: Option<PostProcessConfig>,
+    pub(super) post_update_hook: Option<HookCommand>,
+    pub(super) pre_generate_hook: Option<HookCommand>,
+    pub(super) render_scale: Option<f64>,
+    pub(super) schedule_by_time: Option<TimeSchedule>,
+    pub(super) single_file_mode: Option<bool>,
+    pub(super) skip_if_unchanged: Option<bool>,
     pub(super) solid_gen: Option<SolidConfig>,
+    pub(super) spotlight_blocklist: Option<Vec<String>>,
     pub(super) spotlight_gen: Option<SpotlightConfig>,
+    pub(super) text_gen: Option<TextConfig>,
+    pub(super) theme_selection: Option<ThemeSelection>,
     pub(super) themes: Option<ThemeConfigs>,
+    pub(super) transition: Option<bool>,
+    pub(super) transition_frames: Option<u8>,
+    pub(super) truchet_gen: Option<TruchetConfig>,
+    pub(super) upscale_filter: Option<UpscaleFilter>,
+    pub(super) watermark: Option<WatermarkConfig>,
     // IF New user config fields, ensure you push_field! in Display impl below & update readme
 }
 
@@ -32,14 +82,60 @@ impl Display for UserConfig {
             };
         }
 
+        push_field!(all_spaces);
         push_field!(auto_clean);
+        push_field!(background);
+        push_field!(calendar);
+        push_field!(color_steps);
+        push_field!(consistent_theme);
+        push_field!(daily_seed);
+        push_field!(datetime_gen);
+        if let Some(generator) = &self.fallback_generator {
+            fields.push(format!("fallback_generator: {}", generator.prefix()));
+        }
+        push_field!(filename_scheme);
+        push_field!(flame_gen);
+        push_field!(foreground);
+        push_field!(format);
         push_field!(frequency);
+        push_field!(generation_timeout_secs);
+        if let Some(weights) = &self.generator_weights {
+            fields.push(format!("generator_weights: {weights:?}"));
+        }
         push_field!(generators);
+        push_field!(ifs_gen);
+        push_field!(instance);
         push_field!(julia_gen);
+        if let Some(path) = &self.latest_symlink {
+            fields.push(format!("latest_symlink: {}", path.display()));
+        }
+        push_field!(log_file);
+        push_field!(mandala_gen);
+        if let Some((width, height)) = &self.max_resolution {
+            fields.push(format!("max_resolution: {width}x{height}"));
+        }
         push_field!(nasa_apod_gen);
+        push_field!(per_monitor);
+        push_field!(post_process);
+        push_field!(post_update_hook);
+        push_field!(pre_generate_hook);
+        push_field!(render_scale);
+        push_field!(schedule_by_time);
+        push_field!(single_file_mode);
+        push_field!(skip_if_unchanged);
         push_field!(solid_gen);
+        if let Some(hashes) = &self.spotlight_blocklist {
+            fields.push(format!("spotlight_blocklist: {} images", hashes.len()));
+        }
         push_field!(spotlight_gen);
+        push_field!(text_gen);
+        push_field!(theme_selection);
         push_field!(themes);
+        push_field!(transition);
+        push_field!(transition_frames);
+        push_field!(truchet_gen);
+        push_field!(upscale_filter);
+        push_field!(watermark);
 
         for (index, field) in fields.iter().enumerate() {
             if index == fields.len() - 1 {
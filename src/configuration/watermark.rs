@@ -0,0 +1,51 @@
+use crate::wallpaper_generators::WatermarkPosition;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct WatermarkConfig {
+    // Image stamped on every wallpaper (a PNG with transparency works best)
+    path: PathBuf,
+    // 0 (invisible) to 1, multiplied with the transparency of the image
+    opacity: Option<f64>,
+    position: Option<WatermarkPosition>,
+    // Size of the stamped image relative to the size of the file
+    scale: Option<f64>,
+}
+
+impl WatermarkConfig {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn opacity(&self) -> Option<f64> {
+        self.opacity
+    }
+
+    pub fn position(&self) -> Option<WatermarkPosition> {
+        self.position
+    }
+
+    pub fn scale(&self) -> Option<f64> {
+        self.scale
+    }
+}
+
+impl Display for WatermarkConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut fields = vec![format!("path: {}", self.path.display())];
+        if let Some(val) = &self.opacity {
+            fields.push(format!("opacity: {}", val));
+        }
+        if let Some(val) = &self.position {
+            fields.push(format!("position: {:?}", val));
+        }
+        if let Some(val) = &self.scale {
+            fields.push(format!("scale: {}", val));
+        }
+        write!(f, "{{ {} }}", fields.join(", "))
+    }
+}
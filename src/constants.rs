@@ -2,5 +2,19 @@ pub const QUALIFIER: &str = "dev";
 pub const ORGANIZATION: &str = "CharlieKarafotias";
 pub const APPLICATION: &str = "Astra";
 
+/// Environment variable used as the update frequency when the config does not define one
+pub const FREQUENCY_ENV_VAR: &str = "ASTRA_FREQUENCY";
+
+/// Environment variable holding the path of the new wallpaper for `post_update_hook`
+pub const WALLPAPER_ENV_VAR: &str = "ASTRA_WALLPAPER";
+
+/// Environment variable naming a theme every generator uses, unless a theme is passed on the
+/// command line
+pub const THEME_ENV_VAR: &str = "ASTRA_THEME";
+
+/// Time each wallpaper is shown by `astra slideshow` when neither `--interval` nor a frequency is
+/// set
+pub const DEFAULT_SLIDESHOW_INTERVAL: &str = "30m";
+
 #[cfg(target_os = "macos")]
 pub const MAC_OS_LAUNCHCTL_INTERVAL: u64 = 600; // 10 minutes
@@ -0,0 +1,23 @@
+use std::sync::Mutex;
+
+/// Exit code of a process stopped by SIGINT (128 + signal number), used for SIGTERM too
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Held while the wallpaper is being committed (saved, applied and recorded in the state files)
+static COMMIT: Mutex<()> = Mutex::new(());
+
+/// Exits on SIGINT/SIGTERM (Ctrl-C, a scheduler timeout or system shutdown). A render is simply
+/// dropped, but an interruption during `uninterruptible` waits for it to finish, so a wallpaper is
+/// never half saved or applied without its state being updated.
+pub fn install_interrupt_handler() -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(|| {
+        let _commit = COMMIT.lock().unwrap_or_else(|e| e.into_inner());
+        std::process::exit(INTERRUPTED_EXIT_CODE);
+    })
+}
+
+/// Runs `commit` without being interrupted by SIGINT/SIGTERM, see `install_interrupt_handler`
+pub fn uninterruptible<T>(commit: impl FnOnce() -> T) -> T {
+    let _commit = COMMIT.lock().unwrap_or_else(|e| e.into_inner());
+    commit()
+}
@@ -0,0 +1,146 @@
+use chrono::{DateTime, Local};
+use std::{
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Default size (in kilobytes) a log file can reach before it is rotated
+pub const DEFAULT_LOG_MAX_SIZE_KB: u64 = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogLevel::Info => write!(f, "INFO"),
+            LogLevel::Warn => write!(f, "WARN"),
+            LogLevel::Error => write!(f, "ERROR"),
+        }
+    }
+}
+
+/// Appends timestamped, leveled lines to a log file.
+///
+/// Once the log file reaches `max_size` bytes it is renamed to `<file name>.1` (replacing any
+/// previous rotated file) and a new log file is started, so at most two log files exist.
+pub struct FileLogger {
+    path: PathBuf,
+    max_size: u64,
+}
+
+impl FileLogger {
+    pub fn new(path: PathBuf, max_size_kb: u64) -> Self {
+        Self {
+            path,
+            max_size: max_size_kb.saturating_mul(1024),
+        }
+    }
+
+    pub fn log(&self, level: LogLevel, message: &str) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        self.rotate_if_needed()?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", format_line(Local::now(), level, message))
+    }
+
+    fn rotate_if_needed(&self) -> io::Result<()> {
+        match fs::metadata(&self.path) {
+            Ok(metadata) if metadata.len() >= self.max_size => {
+                fs::rename(&self.path, rotated_path(&self.path))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Path that the log file is moved to when it is rotated (e.g. `astra.log` -> `astra.log.1`)
+fn rotated_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".1");
+    path.with_file_name(file_name)
+}
+
+fn format_line(timestamp: DateTime<Local>, level: LogLevel, message: &str) -> String {
+    format!(
+        "{} [{}] {}",
+        timestamp.format("%Y-%m-%dT%H:%M:%S%:z"),
+        level,
+        message
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_log_line(line: &str, level: &str, message: &str) -> bool {
+        // 2026-01-01T00:00:00+00:00 [INFO] message
+        let Some((timestamp, rest)) = line.split_once(' ') else {
+            return false;
+        };
+        DateTime::parse_from_rfc3339(timestamp).is_ok() && rest == format!("[{level}] {message}")
+    }
+
+    #[test]
+    fn test_log_lines_are_appended_with_expected_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logs").join("astra.log");
+        let logger = FileLogger::new(path.clone(), DEFAULT_LOG_MAX_SIZE_KB);
+        logger.log(LogLevel::Info, "generator: julia").unwrap();
+        logger.log(LogLevel::Error, "something failed").unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(is_log_line(lines[0], "INFO", "generator: julia"));
+        assert!(is_log_line(lines[1], "ERROR", "something failed"));
+    }
+
+    #[test]
+    fn test_log_file_rotates_at_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("astra.log");
+        let logger = FileLogger::new(path.clone(), 1);
+        let message = "x".repeat(600);
+
+        logger.log(LogLevel::Info, &message).unwrap();
+        assert!(!rotated_path(&path).exists());
+        // file is now ~630 bytes, still under the 1kb cap
+        logger.log(LogLevel::Info, &message).unwrap();
+        assert!(!rotated_path(&path).exists());
+        // file is over the cap so it is rotated before writing
+        logger.log(LogLevel::Warn, "after rotation").unwrap();
+
+        assert!(rotated_path(&path).exists());
+        assert_eq!(
+            fs::read_to_string(rotated_path(&path))
+                .unwrap()
+                .lines()
+                .count(),
+            2
+        );
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 1);
+        assert!(is_log_line(contents.trim_end(), "WARN", "after rotation"));
+    }
+
+    #[test]
+    fn test_rotated_path() {
+        assert_eq!(
+            rotated_path(Path::new("/tmp/astra.log")),
+            PathBuf::from("/tmp/astra.log.1")
+        );
+    }
+}
@@ -1,6 +1,8 @@
 mod cli;
 mod configuration;
 mod constants;
+mod interrupt;
+mod logger;
 mod os_implementations;
 mod themes;
 mod wallpaper_generators;
@@ -8,12 +10,22 @@ mod wallpaper_generators;
 use clap::{CommandFactory, Parser};
 use clap_complete::generate;
 use cli::{Cli, Commands, Generator};
-use configuration::{Config, Frequency, Generators};
-use os_implementations::{handle_frequency, open_editor};
-use rand::random_range;
+use configuration::{AppliedWallpaper, Config, Decision, Frequency, Generators, State};
+use constants::DEFAULT_SLIDESHOW_INTERVAL;
+use interrupt::{install_interrupt_handler, uninterruptible};
+use logger::LogLevel;
+use os_implementations::{
+    disable_slideshow, enable_slideshow, handle_frequency, open_editor, uninstall_scheduler,
+};
+use std::io::Write;
+use themes::{ColorTheme, PaletteFormat, export_palette};
 use wallpaper_generators::{
-    Color, delete_wallpapers, generate_bing_spotlight, generate_julia_set, generate_nasa_apod,
-    generate_solid_color, handle_generate_options,
+    Color, GradientDirection, HarmonyScheme, HueFamily, PREVIEW_COLUMNS, Transaction,
+    animation_resolution, apply_post_processing, apply_recent, apply_wallpaper_file, average_color,
+    benchmark_generators, check_apply_to, compare_images, create_contact_sheet, delete_wallpapers,
+    dominant_colors, encode_gif, generate_animation, generate_or_fallback, generate_with_fallback,
+    generate_with_timeout, handle_generate_options, read_png_metadata, recent_wallpapers,
+    reset_data, terminal_preview, wallpaper_folder_path, write_png,
 };
 
 #[cfg(target_os = "macos")]
@@ -21,10 +33,93 @@ use os_implementations::save_last_execution_time;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
-    let mut config = Config::new(cli.verbose);
+    let mut config = Config::new(cli.verbose, cli.instance);
+    if let Some(seed) = cli.seed {
+        config.print_if_verbose(format!("Using seed: {seed}").as_str());
+        config.seed_rng(seed);
+    } else if config.daily_seed() {
+        let seed = config.seed_rng_from_date(chrono::Local::now().date_naive());
+        config.print_if_verbose(format!("Using daily seed: {seed}").as_str());
+    }
+    if let Some(seed) = cli.theme_seed {
+        config.print_if_verbose(format!("Using theme seed: {seed}").as_str());
+        config.seed_theme_rng(seed);
+    }
+    config.set_force(cli.force);
+    if let Some(instance) = config.instance() {
+        config.print_if_verbose(format!("Running as instance: {instance}").as_str());
+    }
+    if let Err(e) = install_interrupt_handler() {
+        config.print_if_verbose(format!("WARN: interruptions are not handled - {e}").as_str());
+    }
+    if cli.explain {
+        config.enable_explain();
+    }
+    if cli.print_config {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&config.effective_config(cli.seed, cli.theme_seed))?
+        );
+        return Ok(());
+    }
 
     // TODO: Errors coming out in strange format. Fix this so its standardized (Error: ParseError("invalid...")) looks weird
     match cli.command {
+        Some(Commands::Animate {
+            image,
+            frames,
+            output,
+        }) => {
+            let (width, height) = config.screen_resolution()?;
+            let (width, height) = animation_resolution(width, height);
+            config.set_resolution_override(width, height);
+            config.print_if_verbose(
+                format!("Rendering {frames} frames at {width}x{height}...").as_str(),
+            );
+            let frames = generate_animation(&config, &image, frames)?;
+            encode_gif(
+                &frames,
+                std::io::BufWriter::new(std::fs::File::create(&output)?),
+            )?;
+            println!("Saved {} frames to {}", frames.len(), output.display());
+        }
+        Some(Commands::Benchmark {
+            iterations,
+            width,
+            height,
+            fail_above_ms,
+        }) => {
+            // query the OS once, so that only the generators are timed
+            let (width, height) = match (width, height) {
+                (None, None) => config.screen_resolution()?,
+                _ => {
+                    config.set_resolution_from_dimensions(width, height)?;
+                    config.screen_resolution()?
+                }
+            };
+            config.set_resolution_override(width, height);
+            config.set_dark_mode_override(false);
+            if cli.seed.is_none() {
+                // same work on every run so timings are comparable
+                config.seed_rng(0);
+            }
+            println!("Benchmarking generators at {width}x{height} ({iterations} iterations)...");
+            let results = benchmark_generators(&config, &Generators::ALL_GENERATORS, iterations)?;
+            for result in &results {
+                println!("{result}");
+            }
+            // sorted from the fastest, so the last one is the slowest
+            if let (Some(limit), Some(slowest)) = (fail_above_ms, results.last()) {
+                let mean_ms = slowest.mean().as_secs_f64() * 1000.0;
+                if mean_ms > limit {
+                    return Err(format!(
+                        "{} took {mean_ms:.3}ms on average (limit: {limit}ms)",
+                        slowest.name()
+                    )
+                    .into());
+                }
+            }
+        }
         Some(Commands::Clean {
             older_than,
             directory,
@@ -37,64 +132,555 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 delete_wallpapers(&config, true, directory, None)?;
             }
         }
-        Some(Commands::Config { open }) => {
+        Some(Commands::Config { open, data }) => {
             config.print_if_verbose("Opening configuration file...");
             Config::create_config_file_if_not_exists(&config)?;
             if open {
                 open_editor(&config, Config::config_path())?;
+            } else if data {
+                println!("Config file:       {}", Config::config_path().display());
+                println!("Config directory:  {}", Config::config_dir().display());
+                println!("Data directory:    {}", Config::data_dir().display());
+                println!("Wallpapers folder: {}", wallpaper_folder_path()?.display());
             } else {
                 println!("{}", Config::config_path().display());
             }
         }
+        Some(Commands::Colors { path, count }) => {
+            let image = image::open(&path)?.to_rgb8();
+            let to_hex = |[r, g, b]: [u8; 3]| format!("#{r:02x}{g:02x}{b:02x}");
+            for (color, share) in dominant_colors(&image, count) {
+                println!("{}  {:>5.1}%", to_hex(color), share * 100.0);
+            }
+            println!("Average color: {}", to_hex(average_color(&image).0));
+        }
+        Some(Commands::Diff { a, b, heatmap }) => {
+            let comparison = compare_images(image::open(&a)?.to_rgb8(), image::open(&b)?.to_rgb8());
+            println!("{comparison}");
+            if let Some(path) = heatmap {
+                comparison.heatmap().save(&path)?;
+                println!("Heatmap written to {}", path.display());
+            }
+        }
+        Some(Commands::ExportTheme {
+            name,
+            format,
+            output,
+        }) => {
+            let theme = config
+                .resolve_theme_by_name(&name)
+                .ok_or(format!("No built-in or configured theme named \"{name}\""))?;
+            std::fs::write(&output, export_palette(&theme, format))?;
+            println!("Exported {} to {}", theme.name(), output.display());
+        }
         Some(Commands::Generate {
             image,
             no_save,
             no_update,
+            width,
+            height,
+            theme,
+            theme_from_file,
+            theme_name,
+            palette,
+            apply_to,
+            count,
+            contact_sheet,
+            show_source,
+            block_current,
+            stdout,
+        }) => {
+            if let Some(index) = apply_to {
+                check_apply_to(index)?;
+            }
+            if block_current {
+                block_current_spotlight_image(&mut config, &image)?;
+            }
+            config.set_resolution_from_dimensions(width, height)?;
+            if let Some(palette) = palette {
+                config.set_theme_from_palette(palette);
+            } else if let (Some(path), Some(name)) = (theme_from_file, theme_name) {
+                config.set_theme_from_file(&path, &name)?;
+            } else {
+                config.set_theme_from_flag_or_env_var(theme.as_deref())?;
+            }
+            let mut images = vec![];
+            for _ in 0..count {
+                config.print_if_verbose(
+                    format!("Generating image of type: {:?}...", &image).as_str(),
+                );
+                let (generator, image_buf) = generate_or_fallback(&config, &image, |generator| {
+                    generator.with_default_mode(&config)
+                })?;
+                let image_buf = apply_post_processing(&config, image_buf);
+                if stdout {
+                    let mut stdout = std::io::stdout().lock();
+                    write_png(&config, &generator, &image_buf, &mut stdout)?;
+                    stdout.flush()?;
+                    continue;
+                }
+                let outcome = commit_wallpaper(
+                    &config,
+                    Transaction::begin(),
+                    |transaction| {
+                        handle_generate_options(
+                            &config,
+                            &image_buf,
+                            &generator,
+                            no_save,
+                            no_update,
+                            apply_to,
+                            transaction,
+                        )
+                    },
+                    || Ok(()),
+                )?;
+                outcome.finish(&config);
+                config.log(LogLevel::Info, format!("Generated {outcome}").as_str());
+                if contact_sheet.is_some() {
+                    images.push(image_buf);
+                }
+                if show_source {
+                    print_source(&generator);
+                }
+            }
+            if let Some(path) = contact_sheet {
+                create_contact_sheet(&images).save(&path)?;
+                println!(
+                    "Saved contact sheet of {} images to {}",
+                    images.len(),
+                    path.display()
+                );
+            }
+        }
+        Some(Commands::GenerateCompletions {
+            shell,
+            list,
+            file,
+            append,
+        }) => {
+            if list {
+                cli::supported_shells()
+                    .iter()
+                    .for_each(|shell| println!("{shell}"));
+            } else if let Some(shell) = shell {
+                let mut completions = vec![];
+                generate(shell, &mut Cli::command(), "astra", &mut completions);
+                match file {
+                    Some(path) if append => {
+                        let existing = match std::fs::read_to_string(&path) {
+                            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+                            contents => contents?,
+                        };
+                        let completions = String::from_utf8_lossy(&completions);
+                        std::fs::write(&path, cli::append_completions(&existing, &completions))?;
+                    }
+                    Some(path) => std::fs::write(&path, completions)?,
+                    None => std::io::stdout().write_all(&completions)?,
+                }
+            }
+        }
+        Some(Commands::ImportTheme { url, name }) => {
+            Config::create_config_file_if_not_exists(&config)?;
+            let theme = Config::import_theme(&name, &url)?;
+            println!(
+                "Added theme {} ({} colors) to {}",
+                theme.name(),
+                theme.colors().len(),
+                Config::config_path().display()
+            );
+        }
+        Some(Commands::Inspect { path }) => {
+            let metadata = read_png_metadata(&path)?;
+            if metadata.is_empty() {
+                println!("No generation parameters stored in {}", path.display());
+            }
+            for (key, value) in metadata {
+                println!("{key}: {value}");
+            }
+        }
+        Some(Commands::Migrate) => {
+            let migrations = Config::migrate_config_file()?;
+            for migration in &migrations {
+                println!("{migration}");
+            }
+            if migrations.iter().any(|migration| migration.is_rename()) {
+                println!(
+                    "Migrated {} (backup saved as config.json.bak)",
+                    Config::config_path().display()
+                );
+            } else {
+                println!("{} is up to date", Config::config_path().display());
+            }
+        }
+        Some(Commands::Recent {
+            count,
+            apply,
+            no_preview,
+        }) => {
+            let mut state = State::load();
+            if let Some(index) = apply {
+                let wallpaper =
+                    apply_recent(&config, state.history(), index, apply_wallpaper_file)?;
+                println!("Applied {}", wallpaper.path.display());
+                state.record_applied(AppliedWallpaper::now(wallpaper.path, &wallpaper.generator));
+                if let Err(e) = state.save() {
+                    config.print_if_verbose(format!("WARN: {e}").as_str());
+                }
+                return Ok(());
+            }
+            let recent = recent_wallpapers(&state, count);
+            if recent.is_empty() {
+                println!("No wallpaper was applied from the wallpaper folder yet");
+            }
+            for (index, wallpaper) in recent.iter().enumerate() {
+                println!(
+                    "{}. {} {} {}",
+                    index + 1,
+                    wallpaper.applied_at,
+                    wallpaper.generator,
+                    wallpaper.path.display()
+                );
+                if no_preview {
+                    continue;
+                }
+                match image::open(&wallpaper.path) {
+                    Ok(image) => print!("{}", terminal_preview(&image.to_rgb8(), PREVIEW_COLUMNS)),
+                    Err(e) => println!("   (no preview: {e})"),
+                }
+            }
+        }
+        Some(Commands::Reset { keep_config, yes }) => {
+            let prompt = if keep_config {
+                "Delete all wallpapers and astra's scheduled job?"
+            } else {
+                "Delete all wallpapers, astra's scheduled job and the configuration file?"
+            };
+            if !yes && !confirm(prompt)? {
+                println!("Reset cancelled");
+                return Ok(());
+            }
+            config.print_if_verbose("Uninstalling scheduled job...");
+            uninstall_scheduler(&config)?;
+            reset_data(
+                &config,
+                &Config::data_dir(),
+                &Config::config_path(),
+                keep_config,
+            )?;
+            println!("Astra was reset");
+        }
+        Some(Commands::Slideshow { disable, interval }) => {
+            let folder = wallpaper_folder_path()?;
+            if disable {
+                disable_slideshow(&config, &folder)?;
+                println!("Slideshow disabled");
+            } else {
+                let interval = match interval {
+                    Some(interval) => Frequency::new(interval.as_str())?,
+                    None => match config.frequency() {
+                        Some(frequency) => frequency.clone(),
+                        None => Frequency::new(DEFAULT_SLIDESHOW_INTERVAL)?,
+                    },
+                };
+                enable_slideshow(&config, &folder, interval.to_seconds())?;
+                println!(
+                    "Slideshow of {} enabled, changing every {interval}",
+                    folder.display()
+                );
+            }
+        }
+        Some(Commands::Validate {
+            lint,
+            theme_contrast,
         }) => {
-            config.print_if_verbose(format!("Generating image of type: {:?}...", &image).as_str());
-            let image_buf = match &image {
-                Generator::Julia => generate_julia_set(&config),
-                Generator::NasaAPOD { date } => generate_nasa_apod(&config, date),
-                Generator::Solid { mode } => generate_solid_color(&config, mode),
-                Generator::Spotlight => generate_bing_spotlight(&config),
-            }?;
-            handle_generate_options(&config, &image_buf, &image, no_save, no_update)?;
-        }
-        Some(Commands::GenerateCompletions { shell }) => {
-            generate(shell, &mut Cli::command(), "astra", &mut std::io::stdout());
+            let warnings = Config::validate_config_file(lint, theme_contrast)?;
+            println!("{} is valid", Config::config_path().display());
+            for warning in &warnings {
+                println!("WARN - {warning}");
+            }
+            if (lint || theme_contrast) && warnings.is_empty() {
+                println!("No issues found");
+            }
         }
         None => {
             // Since 'astra' was called, respect user config
             config.respect_user_config = true;
-
-            if let Some(auto_clean_frequency) = config.auto_clean() {
-                config.print_if_verbose(
-                    format!(
-                        "Auto clean enabled - cleaning images older than {}",
-                        auto_clean_frequency
-                    )
-                    .as_str(),
-                );
-                delete_wallpapers(&config, false, false, config.auto_clean())?;
+            let result = match config
+                .apply_pre_generate_hook()
+                .and_then(|()| config.set_theme_from_flag_or_env_var(None))
+            {
+                Ok(()) => run(&config, cli.no_scheduler),
+                Err(e) => Err(e.into()),
+            };
+            if cli.explain {
+                print_explanation(&config);
+            }
+            if let Err(e) = result {
+                config.log(LogLevel::Error, e.to_string().as_str());
+                return Err(e);
             }
+        }
+    };
+    Ok(())
+}
 
-            let generators = config
-                .generators()
-                .as_ref()
-                .map(|generators| generators.to_vec())
-                .unwrap_or(Generators::ALL_GENERATORS.to_vec());
+/// Runs `astra` with the user's configuration (invoked directly or by the OS scheduler)
+fn run(config: &Config, no_scheduler: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(auto_clean_frequency) = config.auto_clean() {
+        config.print_if_verbose(
+            format!(
+                "Auto clean enabled - cleaning images older than {}",
+                auto_clean_frequency
+            )
+            .as_str(),
+        );
+        delete_wallpapers(config, false, false, config.auto_clean())?;
+    }
 
-            // If true, then run update - else ignore
-            if handle_frequency(&config)? {
-                let index = random_range(0..generators.len());
-                let image_type = &generators[index];
-                let image_buf = image_type.with_default_mode(&config)?;
-                handle_generate_options(&config, &image_buf, image_type, false, false)?;
+    let generators = config.candidate_generators();
 
-                #[cfg(target_os = "macos")]
-                save_last_execution_time()?;
+    if config.force() {
+        config.print_if_verbose("Forcing a new wallpaper regardless of frequency");
+    }
+    // If true, then run update - else ignore
+    if !should_generate(config, no_scheduler, handle_frequency)? {
+        config.explain(Decision::Skipped(
+            "the configured frequency has not elapsed since the last wallpaper (use --force)",
+        ));
+    } else {
+        let timeout = config.generation_timeout();
+        let (image_type, image_buf) = generate_with_fallback(config, &generators, |generator| {
+            let generator = generator.clone();
+            generate_with_timeout(config, timeout, move |config| {
+                generator.with_default_mode(config)
+            })
+        })?;
+        let image_buf = apply_post_processing(config, image_buf);
+        let outcome = commit_wallpaper(
+            config,
+            Transaction::begin(),
+            |transaction| {
+                handle_generate_options(
+                    config,
+                    &image_buf,
+                    &image_type,
+                    false,
+                    false,
+                    None,
+                    transaction,
+                )
+            },
+            record_run,
+        )?;
+        outcome.finish(config);
+        config.log(
+            LogLevel::Info,
+            format!("Wallpaper generated successfully: {outcome}").as_str(),
+        );
+    }
+    Ok(())
+}
+
+/// Applies the wallpaper with `apply`, staging its side effects in `transaction`, then commits
+/// them and records the run with `record_run`, without being interrupted. When `apply` fails the
+/// transaction is rolled back and the run is not recorded, so the next scheduled run tries again
+/// from the same state.
+fn commit_wallpaper<T>(
+    config: &Config,
+    mut transaction: Transaction,
+    apply: impl FnOnce(&mut Transaction) -> Result<T, Box<dyn std::error::Error>>,
+    record_run: impl FnOnce() -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<T, Box<dyn std::error::Error>> {
+    uninterruptible(|| match apply(&mut transaction) {
+        Ok(outcome) => {
+            transaction.commit(config, record_run)?;
+            Ok(outcome)
+        }
+        Err(e) => {
+            transaction.rollback(config);
+            Err(e)
+        }
+    })
+}
+
+/// Records when the instance last generated a wallpaper, read by the elapsed-time gate on macOS
+fn record_run() -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(target_os = "macos")]
+    save_last_execution_time()?;
+    Ok(())
+}
+
+/// Whether a wallpaper should be generated by this run. `scheduler` reconciles astra's OS job with
+/// the configured frequency (and on macOS checks that it elapsed), unless `no_scheduler` is set.
+fn should_generate<E>(
+    config: &Config,
+    no_scheduler: bool,
+    scheduler: impl FnOnce(&Config) -> Result<bool, E>,
+) -> Result<bool, E> {
+    if no_scheduler {
+        config.print_if_verbose("Skipping the OS scheduler (--no-scheduler)");
+        Ok(true)
+    } else {
+        scheduler(config)
+    }
+}
+
+/// Prints the decisions recorded during the run (`--explain`)
+fn print_explanation(config: &Config) {
+    println!("Explanation:");
+    for decision in config.explanation() {
+        println!("  - {decision}");
+    }
+}
+
+/// Prints where the image just downloaded by `generator` came from (`--show-source`)
+fn print_source(generator: &Generator) {
+    match State::load().last_source() {
+        Some(source) if source.generator == generator.prefix() => {
+            println!("Source: {}", source.url);
+            if let Some(title) = &source.title {
+                println!("Title:  {title}");
             }
+            println!("Date:   {}", source.date);
         }
+        _ => println!("No source recorded for {} images", generator.prefix()),
+    }
+}
+
+/// Adds the hash of the last Spotlight image to `spotlight_blocklist` (`--block-current`)
+fn block_current_spotlight_image(
+    config: &mut Config,
+    generator: &Generator,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if *generator != Generator::Spotlight {
+        return Err("--block-current only applies to the spotlight generator".into());
+    }
+    let state = State::load();
+    let Some(hash) = state
+        .last_source()
+        .filter(|source| source.generator == generator.prefix())
+        .and_then(|source| source.hash.as_deref())
+    else {
+        return Err("No spotlight image recorded to block, generate one first".into());
     };
+    Config::create_config_file_if_not_exists(config)?;
+    if config.block_spotlight_image(hash)? {
+        println!("Blocked spotlight image {hash}");
+    } else {
+        println!("Spotlight image {hash} is already blocked");
+    }
     Ok(())
 }
+
+/// Asks a yes/no question on stdin (defaults to no)
+fn confirm(prompt: &str) -> std::io::Result<bool> {
+    print!("{prompt} [y/N] ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_apply_does_not_record_run() {
+        let config = Config::from_json("{}");
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = dir.path().join("state.json");
+        let mut recorded = false;
+        let result: Result<(), _> = commit_wallpaper(
+            &config,
+            Transaction::with_state_path(state_path.clone()),
+            |_| Err("wallpaper was saved but could not be applied".into()),
+            || {
+                recorded = true;
+                Ok(())
+            },
+        );
+        assert!(result.is_err());
+        assert!(!recorded);
+
+        let result = commit_wallpaper(
+            &config,
+            Transaction::with_state_path(state_path),
+            |_| Ok("applied"),
+            || {
+                recorded = true;
+                Ok(())
+            },
+        );
+        assert_eq!(result.unwrap(), "applied");
+        assert!(recorded);
+    }
+
+    #[test]
+    fn test_failed_apply_rolls_back_staged_files_and_state() {
+        let config = Config::from_json("{}");
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = dir.path().join("state.json");
+        let last_exec_path = dir.path().join("last_exec.txt");
+        std::fs::write(&state_path, "{}").unwrap();
+        std::fs::write(&last_exec_path, "last_run = 1").unwrap();
+        let wallpaper = dir.path().join("julia_1.png");
+
+        let result: Result<(), _> = commit_wallpaper(
+            &config,
+            Transaction::with_state_path(state_path.clone()),
+            |transaction| {
+                std::fs::write(&wallpaper, b"png").unwrap();
+                transaction.stage_file(wallpaper.clone());
+                let mut state = State::default();
+                state.set_last_image_hash("new".to_string());
+                transaction.stage_state(state);
+                Err("no desktop to apply the wallpaper to".into())
+            },
+            || Ok(std::fs::write(&last_exec_path, "last_run = 2")?),
+        );
+        assert!(result.is_err());
+        assert!(!wallpaper.exists());
+        assert_eq!(std::fs::read_to_string(&state_path).unwrap(), "{}");
+        assert_eq!(
+            std::fs::read_to_string(&last_exec_path).unwrap(),
+            "last_run = 1"
+        );
+
+        let result = commit_wallpaper(
+            &config,
+            Transaction::with_state_path(state_path.clone()),
+            |transaction| {
+                std::fs::write(&wallpaper, b"png").unwrap();
+                transaction.stage_file(wallpaper.clone());
+                let mut state = State::default();
+                state.set_last_image_hash("new".to_string());
+                transaction.stage_state(state);
+                Ok(())
+            },
+            || Ok(std::fs::write(&last_exec_path, "last_run = 2")?),
+        );
+        assert!(result.is_ok());
+        assert!(wallpaper.exists());
+        assert_eq!(State::load_from(&state_path).last_image_hash(), Some("new"));
+        assert_eq!(
+            std::fs::read_to_string(&last_exec_path).unwrap(),
+            "last_run = 2"
+        );
+    }
+
+    #[test]
+    fn test_no_scheduler_does_not_invoke_scheduler() {
+        let config = Config::from_json(r#"{ "frequency": "1h" }"#);
+        let calls = std::cell::Cell::new(0);
+        let scheduler = |_: &Config| -> Result<bool, String> {
+            calls.set(calls.get() + 1);
+            Ok(false)
+        };
+        assert_eq!(should_generate(&config, true, scheduler), Ok(true));
+        assert_eq!(calls.get(), 0);
+        assert_eq!(should_generate(&config, false, scheduler), Ok(false));
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -6,11 +6,13 @@ pub enum LinuxOSError {
     DarkModeError(String),
     ExecutablePath(String),
     GenerateTimer(String),
+    NoDisplayTool(String),
     OpenEditorError,
     Os(String),
     ParseError(String),
     PathNotFound(String),
     ResolutionNotFound(String),
+    Slideshow(String),
     Write(String),
 }
 
@@ -32,6 +34,9 @@ impl std::fmt::Display for LinuxOSError {
             LinuxOSError::GenerateTimer(err_msg) => {
                 write!(f, "Unable to generate time file: {err_msg}")
             }
+            LinuxOSError::NoDisplayTool(err_msg) => {
+                write!(f, "No display server tool found: {err_msg}")
+            }
             LinuxOSError::OpenEditorError => {
                 write!(f, "Unable to open editor")
             }
@@ -50,6 +55,9 @@ impl std::fmt::Display for LinuxOSError {
                     "Unable to determine resolution of main display: {err_msg}"
                 )
             }
+            LinuxOSError::Slideshow(err_msg) => {
+                write!(f, "Unable to set up the slideshow: {err_msg}")
+            }
             LinuxOSError::Write(err_msg) => {
                 write!(f, "Unable to write file: {err_msg}")
             }
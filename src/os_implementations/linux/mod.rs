@@ -1,7 +1,9 @@
 mod errors;
+mod slideshow;
 mod systemd;
 mod utils;
 
 pub use errors::*;
+use slideshow::*;
 pub(self) use systemd::*;
 pub use utils::*;
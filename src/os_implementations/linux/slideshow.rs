@@ -0,0 +1,125 @@
+use super::LinuxOSError;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// File name of the GNOME slideshow written to astra's data directory
+pub(in crate::os_implementations::linux) const SLIDESHOW_FILE: &str = "slideshow.xml";
+/// Seconds GNOME takes to fade from one wallpaper of the slideshow to the next
+const SLIDESHOW_TRANSITION_SECS: u64 = 2;
+/// Extensions of the files in the wallpaper folder that are part of the slideshow
+const SLIDESHOW_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// Wallpapers of `folder` shown by the slideshow, sorted by name (hidden files, such as the
+/// partial files of a wallpaper being saved, are skipped)
+pub(in crate::os_implementations::linux) fn slideshow_images(
+    folder: &Path,
+) -> Result<Vec<PathBuf>, LinuxOSError> {
+    let entries = fs::read_dir(folder)
+        .map_err(|e| LinuxOSError::Slideshow(format!("{} - {e}", folder.display())))?;
+    let mut images: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| !name.to_string_lossy().starts_with('.'))
+                && path.extension().is_some_and(|extension| {
+                    SLIDESHOW_EXTENSIONS
+                        .iter()
+                        .any(|known| extension.eq_ignore_ascii_case(known))
+                })
+        })
+        .collect();
+    images.sort();
+    Ok(images)
+}
+
+fn escape_xml(path: &Path) -> String {
+    path.to_string_lossy()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// GNOME background slideshow showing each image for `interval_secs` seconds then fading to the
+/// next one, looping back to the first image after the last one
+pub(in crate::os_implementations::linux) fn gen_slideshow_xml(
+    images: &[PathBuf],
+    interval_secs: u64,
+) -> String {
+    let mut xml = String::from(
+        "<background>\n  <starttime>\n    <year>2000</year>\n    <month>1</month>\n    <day>1</day>\n    <hour>0</hour>\n    <minute>0</minute>\n    <second>0</second>\n  </starttime>\n",
+    );
+    for (index, image) in images.iter().enumerate() {
+        let next = &images[(index + 1) % images.len()];
+        xml.push_str(&format!(
+            "  <static>\n    <duration>{interval_secs}.0</duration>\n    <file>{}</file>\n  </static>\n",
+            escape_xml(image)
+        ));
+        if images.len() > 1 {
+            xml.push_str(&format!(
+                "  <transition>\n    <duration>{SLIDESHOW_TRANSITION_SECS}.0</duration>\n    <from>{}</from>\n    <to>{}</to>\n  </transition>\n",
+                escape_xml(image),
+                escape_xml(next)
+            ));
+        }
+    }
+    xml.push_str("</background>\n");
+    xml
+}
+
+/// Points both the light and dark GNOME wallpaper at `path` (an image or a slideshow)
+pub(in crate::os_implementations::linux) fn set_gnome_background(
+    path: &Path,
+) -> Result<(), LinuxOSError> {
+    let uri = format!("file://{}", path.display());
+    for key in ["picture-uri", "picture-uri-dark"] {
+        let output = Command::new("gsettings")
+            .args(["set", "org.gnome.desktop.background", key, &uri])
+            .output()
+            .map_err(|e| LinuxOSError::CommandError(e.to_string()))?;
+        if !output.status.success() {
+            return Err(LinuxOSError::Slideshow(format!(
+                "gsettings could not set {key} - {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slideshow_xml_lists_folder_images_with_interval() {
+        let folder = Path::new("/home/me/.local/share/astra/Wallpapers");
+        let images = vec![folder.join("julia_1.png"), folder.join("a&b.jpg")];
+        let xml = gen_slideshow_xml(&images, 1800);
+
+        assert!(xml.starts_with("<background>\n"));
+        assert!(xml.ends_with("</background>\n"));
+        assert_eq!(xml.matches("<duration>1800.0</duration>").count(), 2);
+        assert!(xml.contains("<file>/home/me/.local/share/astra/Wallpapers/julia_1.png</file>"));
+        assert!(xml.contains("<file>/home/me/.local/share/astra/Wallpapers/a&amp;b.jpg</file>"));
+        // the last image fades back into the first one
+        assert!(xml.contains(
+            "<from>/home/me/.local/share/astra/Wallpapers/a&amp;b.jpg</from>\n    <to>/home/me/.local/share/astra/Wallpapers/julia_1.png</to>"
+        ));
+    }
+
+    #[test]
+    fn test_slideshow_images_skips_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b.png", "a.JPG", ".c.png.partial", "notes.txt"] {
+            fs::write(dir.path().join(name), b"").unwrap();
+        }
+        assert_eq!(
+            slideshow_images(dir.path()).unwrap(),
+            vec![dir.path().join("a.JPG"), dir.path().join("b.png")]
+        );
+    }
+}
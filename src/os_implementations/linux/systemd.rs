@@ -1,35 +1,67 @@
-use super::super::super::{Config, Frequency};
 use super::super::LinuxOSError;
+use crate::configuration::Instance;
 use directories::BaseDirs;
 use std::{
-    env::current_exe,
+    env::{current_exe, var_os},
     fs::{self, create_dir_all},
     path::{Path, PathBuf},
     process::Command,
 };
 
+/// XDG variables locating astra's configuration and data. systemd does not pass the variables of
+/// the user's shell to its services, so the values set when installing are written to the service.
+const XDG_VARIABLES: [&str; 2] = ["XDG_CONFIG_HOME", "XDG_DATA_HOME"];
+
 /// Generates a service file which is used alongside the timer file from gen_timer_file().
 /// The service file is consumed by systemd in Linux to trigger automatic executions of the Astra
 /// binary. For details on service files, see [Arch Linux page](https://wiki.archlinux.org/title/Systemd/Timers#Service_units)
-fn gen_service_file() -> Result<String, LinuxOSError> {
+fn gen_service_file(instance: Option<&Instance>) -> Result<String, LinuxOSError> {
     let curr_exe_path = current_exe().map_err(|e| LinuxOSError::ExecutablePath(e.to_string()))?;
-    let file_contents = format!(
+    let environment: Vec<(&str, PathBuf)> = XDG_VARIABLES
+        .into_iter()
+        .filter_map(|name| var_os(name).map(|value| (name, PathBuf::from(value))))
+        .collect();
+    Ok(gen_service_file_for(
+        &curr_exe_path,
+        &Instance::job_args(instance),
+        &environment,
+    ))
+}
+
+fn gen_service_file_for(
+    exe_path: &Path,
+    args: &[String],
+    environment: &[(&str, PathBuf)],
+) -> String {
+    let environment: String = environment
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .to_string_lossy()
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('%', "%%");
+            format!("Environment=\"{name}={value}\"\n")
+        })
+        .collect();
+    format!(
         "[Unit]
 Description=Astra Wallpaper Updater
 
 [Service]
 Type=oneshot
-ExecStart={}
+{environment}ExecStart={}{}
 ",
-        curr_exe_path.to_string_lossy().to_string()
-    );
-    Ok(file_contents)
+        exe_path.to_string_lossy(),
+        args.iter().map(|arg| format!(" {arg}")).collect::<String>()
+    )
 }
 
 /// Generates a timer file to be used alongside the service file from gen_service_file().
-/// The timer file is based off the Frequency set by a user in their configuration file
+/// The timer file runs on the `OnCalendar` expression derived from the user's frequency (or their
+/// `calendar` expression).
 /// For details on timer files, see [Arch Linux page](https://wiki.archlinux.org/title/Systemd/Timers#Timer_units)
-fn gen_timer_file(f: &Frequency) -> Result<String, LinuxOSError> {
+fn gen_timer_file(on_calendar: &str) -> Result<String, LinuxOSError> {
     let file_content = format!(
         "[Unit]
 Description=Run Astra Wallpaper on schedule
@@ -41,11 +73,95 @@ Persistent=true
 [Install]
 WantedBy=timers.target
 ",
-        f.as_on_calendar_format()
+        on_calendar
     );
     Ok(file_content)
 }
 
+/// Shorthands accepted by systemd in place of a full `OnCalendar` expression
+const CALENDAR_SHORTHANDS: [&str; 9] = [
+    "minutely",
+    "hourly",
+    "daily",
+    "weekly",
+    "monthly",
+    "yearly",
+    "annually",
+    "quarterly",
+    "semiannually",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// Checks that `calendar` is a valid systemd `OnCalendar` expression (e.g. `Mon,Fri 09:00`) with
+/// `systemd-analyze calendar`, or with a basic syntax check when `systemd-analyze` is not installed
+pub(in crate::os_implementations::linux) fn validate_on_calendar(
+    calendar: &str,
+) -> Result<(), LinuxOSError> {
+    let valid = match Command::new("systemd-analyze")
+        .args(["calendar", calendar])
+        .output()
+    {
+        Ok(output) => output.status.success(),
+        Err(_) => is_plausible_on_calendar(calendar),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(LinuxOSError::GenerateTimer(format!(
+            "invalid calendar expression \"{calendar}\""
+        )))
+    }
+}
+
+/// Basic syntax check of an `OnCalendar` expression: a shorthand (e.g. `daily`), or an optional
+/// weekday list followed by date and/or time fields made of numbers and `*-:/.,~` separators
+fn is_plausible_on_calendar(calendar: &str) -> bool {
+    let calendar = calendar.trim().to_lowercase();
+    if CALENDAR_SHORTHANDS.contains(&calendar.as_str()) {
+        return true;
+    }
+    let mut tokens: Vec<&str> = calendar.split_whitespace().collect();
+    if tokens.last() == Some(&"utc") {
+        tokens.pop();
+    }
+    let is_weekday =
+        |day: &str| day.len() >= 3 && WEEKDAYS.iter().any(|weekday| weekday.starts_with(day));
+    let is_weekday_list = |token: &str| {
+        token
+            .split(',')
+            .flat_map(|range| range.split(".."))
+            .all(is_weekday)
+    };
+    let is_date_or_time = |token: &str| {
+        token.chars().any(|c| c.is_ascii_digit() || c == '*')
+            && token
+                .chars()
+                .all(|c| c.is_ascii_digit() || "*-:/.,~".contains(c))
+    };
+    match tokens.as_slice() {
+        [] => false,
+        [first, rest @ ..] if first.starts_with(char::is_alphabetic) => {
+            is_weekday_list(first) && rest.len() <= 2 && rest.iter().all(|t| is_date_or_time(t))
+        }
+        fields => fields.len() <= 2 && fields.iter().all(|t| is_date_or_time(t)),
+    }
+}
+
+/// File name of the systemd unit of `instance` with the given extension (`service` or `timer`),
+/// e.g. `astra.timer`, or `astra-work.timer` for the instance "work"
+fn unit_name(instance: Option<&Instance>, extension: &str) -> String {
+    format!("{}.{extension}", Instance::job_name(instance, "astra", '-'))
+}
+
 /// Installs Astra service and timer units to systemd
 /// Steps:
 ///  1. Generate both unit and timer files
@@ -54,16 +170,19 @@ WantedBy=timers.target
 ///  4. Run systemctl command to reload daemon
 ///  5. Run systemctl command to enable astra timer
 pub(in crate::os_implementations::linux) fn install_astra_service_and_timer(
-    frequency: &Frequency,
+    on_calendar: &str,
+    instance: Option<&Instance>,
 ) -> Result<(), LinuxOSError> {
     let systemd_dir = get_user_systemd_dir().ok_or_else(|| {
         LinuxOSError::PathNotFound("~/.config/systemd/user/ not found".to_string())
     })?;
+    let service = unit_name(instance, "service");
+    let timer = unit_name(instance, "timer");
     create_dir_all(&systemd_dir).map_err(|e| LinuxOSError::Os(e.to_string()))?;
-    fs::write(systemd_dir.join("astra.service"), gen_service_file()?)
-        .map_err(|e| LinuxOSError::Write(format!("astra.service file: {}", e.to_string())))?;
-    fs::write(systemd_dir.join("astra.timer"), gen_timer_file(frequency)?)
-        .map_err(|e| LinuxOSError::Write(format!("astra.timer file: {}", e.to_string())))?;
+    fs::write(systemd_dir.join(&service), gen_service_file(instance)?)
+        .map_err(|e| LinuxOSError::Write(format!("{service} file: {}", e.to_string())))?;
+    fs::write(systemd_dir.join(&timer), gen_timer_file(on_calendar)?)
+        .map_err(|e| LinuxOSError::Write(format!("{timer} file: {}", e.to_string())))?;
     Command::new("systemctl")
         .args(["--user", "daemon-reload"])
         .output()
@@ -71,10 +190,10 @@ pub(in crate::os_implementations::linux) fn install_astra_service_and_timer(
             LinuxOSError::CommandError(format!("systemctl daemon-reload errored - {}", e))
         })?;
     Command::new("systemctl")
-        .args(["--user", "enable", "--now", "astra.timer"])
+        .args(["--user", "enable", "--now", &timer])
         .output()
         .map_err(|e| {
-            LinuxOSError::CommandError(format!("systemctl astra.timer enable errored - {}", e))
+            LinuxOSError::CommandError(format!("systemctl {timer} enable errored - {}", e))
         })?;
     Ok(())
 }
@@ -84,24 +203,27 @@ pub(in crate::os_implementations::linux) fn install_astra_service_and_timer(
 ///  1. Run systemctl command to disable astra timer
 ///  2. Remove the service and timer files from systemd/user
 ///  3. Run systemctl command to reload daemon
-pub(in crate::os_implementations::linux) fn uninstall_astra_serivice_and_timer()
--> Result<(), LinuxOSError> {
+pub(in crate::os_implementations::linux) fn uninstall_astra_serivice_and_timer(
+    instance: Option<&Instance>,
+) -> Result<(), LinuxOSError> {
     let systemd_dir = get_user_systemd_dir().ok_or_else(|| {
         LinuxOSError::PathNotFound("~/.config/systemd/user/ not found".to_string())
     })?;
+    let service = unit_name(instance, "service");
+    let timer = unit_name(instance, "timer");
     Command::new("systemctl")
-        .args(["--user", "disable", "--now", "astra.timer"])
+        .args(["--user", "disable", "--now", &timer])
         .output()
         .map_err(|e| {
-            LinuxOSError::CommandError(format!("systemctl astra.timer disable errored - {}", e))
+            LinuxOSError::CommandError(format!("systemctl {timer} disable errored - {}", e))
         })?;
-    if Path::new(systemd_dir.join("astra.timer").as_path()).exists() {
-        fs::remove_file(systemd_dir.join("astra.timer"))
-            .map_err(|e| LinuxOSError::Os(format!("failed to delete astra.timer - {}", e)))?;
+    if Path::new(systemd_dir.join(&timer).as_path()).exists() {
+        fs::remove_file(systemd_dir.join(&timer))
+            .map_err(|e| LinuxOSError::Os(format!("failed to delete {timer} - {}", e)))?;
     }
-    if Path::new(systemd_dir.join("astra.service").as_path()).exists() {
-        fs::remove_file(systemd_dir.join("astra.service"))
-            .map_err(|e| LinuxOSError::Os(format!("failed to delete astra.service - {}", e)))?;
+    if Path::new(systemd_dir.join(&service).as_path()).exists() {
+        fs::remove_file(systemd_dir.join(&service))
+            .map_err(|e| LinuxOSError::Os(format!("failed to delete {service} - {}", e)))?;
     }
     // Command::new("systemctl").args(["--user", "daemon-reload"]).status()?;
     Command::new("systemctl")
@@ -113,6 +235,214 @@ pub(in crate::os_implementations::linux) fn uninstall_astra_serivice_and_timer()
     Ok(())
 }
 
+/// Checks that the installed units of `instance` run the current astra on `on_calendar` and that
+/// the timer is enabled (`systemctl is-enabled`). Returns why the units need to be reinstalled,
+/// `None` when they are up to date.
+pub(in crate::os_implementations::linux) fn check_astra_service_and_timer(
+    on_calendar: &str,
+    instance: Option<&Instance>,
+) -> Result<Option<String>, LinuxOSError> {
+    let systemd_dir = get_user_systemd_dir().ok_or_else(|| {
+        LinuxOSError::PathNotFound("~/.config/systemd/user/ not found".to_string())
+    })?;
+    let timer = unit_name(instance, "timer");
+    let installed_timer = fs::read_to_string(systemd_dir.join(&timer)).ok();
+    let installed_service =
+        fs::read_to_string(systemd_dir.join(unit_name(instance, "service"))).ok();
+    // a timer systemctl does not know about is reported as not enabled
+    let is_enabled = Command::new("systemctl")
+        .args(["--user", "is-enabled", &timer])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        .unwrap_or_default();
+    Ok(units_discrepancy(
+        &timer,
+        installed_timer.as_deref(),
+        installed_service.as_deref(),
+        &gen_service_file(instance)?,
+        &is_enabled,
+        on_calendar,
+    ))
+}
+
+/// Why the installed units differ from the ones astra would install, see
+/// `check_astra_service_and_timer`
+fn units_discrepancy(
+    timer: &str,
+    installed_timer: Option<&str>,
+    installed_service: Option<&str>,
+    expected_service: &str,
+    is_enabled: &str,
+    on_calendar: &str,
+) -> Option<String> {
+    let Some(installed_timer) = installed_timer else {
+        return Some(format!("{timer} is not installed"));
+    };
+    let installed_on_calendar = installed_timer
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("OnCalendar="));
+    if installed_on_calendar != Some(on_calendar) {
+        return Some(format!(
+            "{timer} runs on \"{}\" instead of \"{on_calendar}\"",
+            installed_on_calendar.unwrap_or_default()
+        ));
+    }
+    if installed_service != Some(expected_service) {
+        return Some(format!(
+            "the service of {timer} does not run this astra executable"
+        ));
+    }
+    match is_enabled.trim() {
+        "enabled" => None,
+        "" => Some(format!("{timer} is not enabled")),
+        state => Some(format!("{timer} is {state}")),
+    }
+}
+
 fn get_user_systemd_dir() -> Option<PathBuf> {
-    BaseDirs::new().map(|base| base.config_dir().join("systemd").join("user"))
+    BaseDirs::new().map(|base| user_systemd_dir(base.config_dir()))
+}
+
+/// Folder of the user's systemd units under the XDG configuration folder `config_home`
+fn user_systemd_dir(config_home: &Path) -> PathBuf {
+    config_home.join("systemd").join("user")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_passes_xdg_directories() {
+        let service = gen_service_file_for(
+            Path::new("/usr/bin/astra"),
+            &[],
+            &[
+                ("XDG_CONFIG_HOME", PathBuf::from("/home/me/.cfg")),
+                ("XDG_DATA_HOME", PathBuf::from("/home/me/100%")),
+            ],
+        );
+        assert!(service.contains(
+            "Type=oneshot\nEnvironment=\"XDG_CONFIG_HOME=/home/me/.cfg\"\nEnvironment=\"XDG_DATA_HOME=/home/me/100%%\"\nExecStart=/usr/bin/astra\n"
+        ));
+        assert!(
+            gen_service_file_for(Path::new("/usr/bin/astra"), &[], &[])
+                .contains("Type=oneshot\nExecStart=/usr/bin/astra\n")
+        );
+    }
+
+    #[test]
+    fn test_instances_have_distinct_units() {
+        let work = Instance::new("work").unwrap();
+        let personal = Instance::new("personal").unwrap();
+        assert_eq!(unit_name(None, "timer"), "astra.timer");
+        assert_eq!(unit_name(Some(&work), "timer"), "astra-work.timer");
+        assert_eq!(
+            unit_name(Some(&personal), "service"),
+            "astra-personal.service"
+        );
+        assert_ne!(
+            unit_name(Some(&work), "service"),
+            unit_name(Some(&personal), "service")
+        );
+
+        // the scheduled run uses the configuration of its instance
+        let service = gen_service_file_for(
+            Path::new("/usr/bin/astra"),
+            &Instance::job_args(Some(&work)),
+            &[],
+        );
+        assert!(service.contains("ExecStart=/usr/bin/astra --instance work\n"));
+    }
+
+    #[test]
+    fn test_paths_follow_xdg_directories() {
+        let config_home = Path::new("/home/me/.cfg");
+        let data_home = Path::new("/home/me/data");
+        // astra's folders as `ProjectDirs` derives them from XDG_CONFIG_HOME and XDG_DATA_HOME
+        let (config_dir, data_dir) = (config_home.join("astra"), data_home.join("astra"));
+        assert_eq!(
+            user_systemd_dir(config_home),
+            config_home.join("systemd").join("user")
+        );
+
+        // instances stay under the same folders
+        let work = Instance::new("work").unwrap();
+        assert!(Instance::config_dir(&config_dir, Some(&work)).starts_with(config_home));
+        assert!(Instance::data_dir(&data_dir, Some(&work)).starts_with(data_home));
+    }
+
+    #[test]
+    fn test_timer_with_other_calendar_is_reinstalled() {
+        let service = gen_service_file_for(Path::new("/usr/bin/astra"), &[], &[]);
+        let timer = gen_timer_file("*-*-* 0/6:00:00").unwrap();
+        let discrepancy = |installed_timer, is_enabled, on_calendar| {
+            units_discrepancy(
+                "astra.timer",
+                installed_timer,
+                Some(&service),
+                &service,
+                is_enabled,
+                on_calendar,
+            )
+        };
+
+        assert_eq!(
+            discrepancy(Some(&timer), "enabled\n", "*-*-* 0/6:00:00"),
+            None
+        );
+        assert_eq!(
+            discrepancy(Some(&timer), "enabled\n", "daily"),
+            Some("astra.timer runs on \"*-*-* 0/6:00:00\" instead of \"daily\"".to_string())
+        );
+        assert_eq!(
+            discrepancy(Some(&timer), "disabled\n", "*-*-* 0/6:00:00"),
+            Some("astra.timer is disabled".to_string())
+        );
+        assert_eq!(
+            discrepancy(None, "", "daily"),
+            Some("astra.timer is not installed".to_string())
+        );
+        // the executable moved (e.g. reinstalled elsewhere)
+        assert!(
+            units_discrepancy(
+                "astra.timer",
+                Some(&timer),
+                Some(&service),
+                &gen_service_file_for(Path::new("/opt/astra"), &[], &[]),
+                "enabled",
+                "*-*-* 0/6:00:00",
+            )
+            .is_some()
+        );
+    }
+
+    const VALID_CALENDARS: [&str; 4] = [
+        "Mon,Fri 09:00",
+        "daily",
+        "*-*-* 0/6:00:00",
+        "Sat..Sun 2026-*-* 10:30",
+    ];
+    const INVALID_CALENDARS: [&str; 3] = ["gibberish", "every monday please!", ""];
+
+    #[test]
+    fn test_is_plausible_on_calendar() {
+        for calendar in VALID_CALENDARS {
+            assert!(is_plausible_on_calendar(calendar), "{calendar}");
+        }
+        for calendar in INVALID_CALENDARS {
+            assert!(!is_plausible_on_calendar(calendar), "{calendar}");
+        }
+    }
+
+    #[test]
+    #[ignore = "depends on systemd-analyze being installed"]
+    fn test_validate_on_calendar_with_systemd_analyze() {
+        for calendar in VALID_CALENDARS {
+            assert!(validate_on_calendar(calendar).is_ok(), "{calendar}");
+        }
+        for calendar in INVALID_CALENDARS {
+            assert!(validate_on_calendar(calendar).is_err(), "{calendar}");
+        }
+    }
 }
@@ -1,8 +1,14 @@
 use super::super::super::Config;
-use super::{LinuxOSError, install_astra_service_and_timer, uninstall_astra_serivice_and_timer};
+use super::{
+    LinuxOSError, SLIDESHOW_FILE, check_astra_service_and_timer, gen_slideshow_xml,
+    install_astra_service_and_timer, set_gnome_background, slideshow_images,
+    uninstall_astra_serivice_and_timer, validate_on_calendar,
+};
+use crate::logger::LogLevel;
 use std::{
     env::var,
-    path::PathBuf,
+    ffi::OsString,
+    path::{Path, PathBuf},
     process::{Command, Stdio},
 };
 
@@ -25,15 +31,24 @@ pub fn is_dark_mode_active() -> Result<bool, LinuxOSError> {
     Ok(output_str.contains("prefer-dark"))
 }
 
-/// Gets the resolution of the primary display. This relies on the `xrandr` command to
-/// determine the resolution.
+/// Gets the resolution of the primary display. This relies on the `xrandr` command on X11, and on
+/// `wlr-randr` or GNOME's `Mutter.DisplayConfig` D-Bus interface on Wayland (where `xrandr` has
+/// no X server to query).
 ///
 /// # Errors
 ///
 /// Returns a `LinuxOSError` with the `ResolutionNotFound` variant if the command to determine
-/// screen resolution cannot be executed. It can also return an error if the output
-/// cannot be parsed.
+/// screen resolution cannot be executed, or the `NoDisplayTool` variant if none of the Wayland
+/// tools are installed. It can also return an error if the output cannot be parsed.
 pub fn get_screen_resolution() -> Result<(u32, u32), LinuxOSError> {
+    if var("XDG_SESSION_TYPE").is_ok_and(|session| session.eq_ignore_ascii_case("wayland")) {
+        get_wayland_screen_resolution()
+    } else {
+        get_x11_screen_resolution()
+    }
+}
+
+fn get_x11_screen_resolution() -> Result<(u32, u32), LinuxOSError> {
     // First, get the primary display name
     let output = Command::new("xrandr")
         .arg("--current")
@@ -68,8 +83,133 @@ pub fn get_screen_resolution() -> Result<(u32, u32), LinuxOSError> {
     ))
 }
 
+/// Tries `wlr-randr` (wlroots compositors), then GNOME's display configuration over D-Bus
+fn get_wayland_screen_resolution() -> Result<(u32, u32), LinuxOSError> {
+    let mut missing_tools = vec![];
+    match Command::new("wlr-randr").output() {
+        Ok(output) if output.status.success() => {
+            if let Some(resolution) =
+                parse_wlr_randr_output(&String::from_utf8_lossy(&output.stdout))
+            {
+                return Ok(resolution);
+            }
+        }
+        Ok(_) => {}
+        Err(_) => missing_tools.push("wlr-randr"),
+    }
+    match Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.gnome.Mutter.DisplayConfig",
+            "--object-path",
+            "/org/gnome/Mutter/DisplayConfig",
+            "--method",
+            "org.gnome.Mutter.DisplayConfig.GetCurrentState",
+        ])
+        .output()
+    {
+        Ok(output) if output.status.success() => {
+            if let Some(resolution) =
+                parse_mutter_current_state(&String::from_utf8_lossy(&output.stdout))
+            {
+                return Ok(resolution);
+            }
+        }
+        Ok(_) => {}
+        Err(_) => missing_tools.push("gdbus"),
+    }
+    if missing_tools.len() == 2 {
+        return Err(LinuxOSError::NoDisplayTool(
+            "install wlr-randr or gdbus to detect the resolution on Wayland".to_string(),
+        ));
+    }
+    Err(LinuxOSError::ResolutionNotFound(
+        "Could not determine screen resolution on Wayland".to_string(),
+    ))
+}
+
+/// Parses the current mode of the first output listed by `wlr-randr`, e.g.
+/// `    1920x1080 px, 60.000000 Hz (preferred, current)`
+fn parse_wlr_randr_output(output: &str) -> Option<(u32, u32)> {
+    output
+        .lines()
+        .filter(|line| line.contains("current"))
+        .find_map(|line| parse_dimensions(line.split_whitespace().next()?))
+}
+
+/// Parses the current mode from the output of Mutter's `GetCurrentState`, where each mode is
+/// listed as `('1920x1080@60.000', 1920, 1080, 60.0, ..., {'is-current': <true>, ...})`
+fn parse_mutter_current_state(output: &str) -> Option<(u32, u32)> {
+    let current = output.find("'is-current': <true>")?;
+    let mode_start = output[..current].rfind("('")? + 2;
+    let mode_id = output[mode_start..].split('@').next()?;
+    parse_dimensions(mode_id)
+}
+
+/// Parses `<width>x<height>`
+fn parse_dimensions(dimensions: &str) -> Option<(u32, u32)> {
+    let (width, height) = dimensions.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+/// Tool the wallpaper is set with, depending on the desktop
+#[derive(Debug, PartialEq)]
+enum WallpaperSetter {
+    /// GNOME settings, also used for the desktops without a dedicated setter yet
+    Gsettings,
+    /// `feh`, for window managers running on X11 without a desktop environment (e.g. i3, bspwm)
+    Feh,
+}
+
+/// Window managers that do not draw a wallpaper themselves, as found in `XDG_CURRENT_DESKTOP`
+const BARE_WINDOW_MANAGERS: [&str; 8] = [
+    "i3",
+    "bspwm",
+    "awesome",
+    "dwm",
+    "openbox",
+    "herbstluftwm",
+    "xmonad",
+    "qtile",
+];
+
+/// Picks the wallpaper setter from `XDG_CURRENT_DESKTOP`, `XDG_SESSION_TYPE` and `DISPLAY`: an
+/// X11 session running one of the `BARE_WINDOW_MANAGERS`, or with no desktop set at all while an
+/// X display is available, uses `feh`. Any other desktop uses `gsettings`.
+fn wallpaper_setter(
+    current_desktop: Option<&str>,
+    session_type: Option<&str>,
+    display: Option<&str>,
+) -> WallpaperSetter {
+    if session_type.is_some_and(|session| session.eq_ignore_ascii_case("wayland")) {
+        return WallpaperSetter::Gsettings;
+    }
+    let bare_window_manager = match current_desktop.map(str::trim) {
+        Some(desktop) if !desktop.is_empty() => desktop.split(':').all(|desktop| {
+            BARE_WINDOW_MANAGERS
+                .iter()
+                .any(|wm| desktop.eq_ignore_ascii_case(wm))
+        }),
+        // window managers started from .xinitrc usually do not set XDG_CURRENT_DESKTOP
+        _ => display.is_some_and(|display| !display.trim().is_empty()),
+    };
+    if bare_window_manager {
+        WallpaperSetter::Feh
+    } else {
+        WallpaperSetter::Gsettings
+    }
+}
+
+/// Arguments of the `feh` command scaling `path` to fill every screen
+fn feh_args(path: &Path) -> Vec<OsString> {
+    vec![OsString::from("--bg-fill"), path.as_os_str().to_os_string()]
+}
+
 /// Sets the wallpaper to the given path. This relies on the `gsettings` command to
-/// set the wallpaper.
+/// set the wallpaper, or on `feh` when no desktop environment is running on X11 (falling back to
+/// `gsettings` if `feh` is not installed or fails).
 ///
 /// This function has been tested on:
 ///   - Ubuntu 25.04 with Gnome Desktop
@@ -79,6 +219,20 @@ pub fn get_screen_resolution() -> Result<(u32, u32), LinuxOSError> {
 /// Returns a `LinuxOSError` with the `CommandError` variant if the `gsettings` command
 /// cannot be executed.
 pub fn update_wallpaper(path: PathBuf) -> Result<(), LinuxOSError> {
+    let setter = wallpaper_setter(
+        var("XDG_CURRENT_DESKTOP").ok().as_deref(),
+        var("XDG_SESSION_TYPE").ok().as_deref(),
+        var("DISPLAY").ok().as_deref(),
+    );
+    // gsettings is tried when feh is not installed or fails (e.g. the desktop was misdetected)
+    if setter == WallpaperSetter::Feh
+        && Command::new("feh")
+            .args(feh_args(&path))
+            .output()
+            .is_ok_and(|output| output.status.success())
+    {
+        return Ok(());
+    }
     // TODO: add support for other linux distros (non gnome based)
     let picture_uri_arg = if is_dark_mode_active()? {
         "picture-uri-dark"
@@ -95,6 +249,96 @@ pub fn update_wallpaper(path: PathBuf) -> Result<(), LinuxOSError> {
     Ok(())
 }
 
+/// Makes GNOME rotate the wallpapers of `folder`, showing each one for `interval_secs` seconds.
+/// The slideshow is written to astra's data directory, run this again to include wallpapers saved
+/// since.
+///
+/// # Errors
+///
+/// Returns a `LinuxOSError` with the `Slideshow` variant if `folder` has no wallpapers or the
+/// slideshow cannot be written, or the `CommandError` variant if `gsettings` cannot be executed.
+pub fn enable_slideshow(
+    config: &Config,
+    folder: &Path,
+    interval_secs: u64,
+) -> Result<(), LinuxOSError> {
+    let images = slideshow_images(folder)?;
+    if images.is_empty() {
+        return Err(LinuxOSError::Slideshow(format!(
+            "no wallpapers in {}, generate some first",
+            folder.display()
+        )));
+    }
+    let data_dir = Config::data_dir();
+    std::fs::create_dir_all(&data_dir).map_err(|e| LinuxOSError::Write(e.to_string()))?;
+    let slideshow = data_dir.join(SLIDESHOW_FILE);
+    std::fs::write(&slideshow, gen_slideshow_xml(&images, interval_secs))
+        .map_err(|e| LinuxOSError::Write(format!("{} - {e}", slideshow.display())))?;
+    config.print_if_verbose(
+        format!(
+            "Wrote a slideshow of {} wallpapers to {}",
+            images.len(),
+            slideshow.display()
+        )
+        .as_str(),
+    );
+    set_gnome_background(&slideshow)
+}
+
+/// Stops the slideshow set by `enable_slideshow`: the most recently saved wallpaper of `folder`
+/// becomes the static wallpaper and the slideshow file is removed
+pub fn disable_slideshow(config: &Config, folder: &Path) -> Result<(), LinuxOSError> {
+    let latest = slideshow_images(folder)?.into_iter().max_by_key(|path| {
+        path.metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    });
+    if let Some(latest) = &latest {
+        set_gnome_background(latest)?;
+        config.print_if_verbose(format!("Set {} as the wallpaper", latest.display()).as_str());
+    }
+    let slideshow = Config::data_dir().join(SLIDESHOW_FILE);
+    if slideshow.is_file() {
+        std::fs::remove_file(&slideshow)
+            .map_err(|e| LinuxOSError::Write(format!("{} - {e}", slideshow.display())))?;
+    }
+    Ok(())
+}
+
+/// Returns the path of the wallpaper currently set in GNOME, or `None` if no picture is set.
+///
+/// # Errors
+///
+/// Returns a `LinuxOSError` with the `CommandError` variant if the `gsettings` command
+/// cannot be executed.
+pub fn get_current_wallpaper() -> Result<Option<PathBuf>, LinuxOSError> {
+    // TODO: add support for other linux distros (non gnome based)
+    let picture_uri_arg = if is_dark_mode_active()? {
+        "picture-uri-dark"
+    } else {
+        "picture-uri"
+    };
+    let output = Command::new("gsettings")
+        .arg("get")
+        .arg("org.gnome.desktop.background")
+        .arg(picture_uri_arg)
+        .output()
+        .map_err(|e| LinuxOSError::CommandError(e.to_string()))?;
+    Ok(parse_picture_uri(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses the output of `gsettings get org.gnome.desktop.background picture-uri`, which is a
+/// quoted path or `file://` URI (e.g. `'file:///home/user/image.png'`)
+fn parse_picture_uri(output: &str) -> Option<PathBuf> {
+    let uri = output.trim().trim_matches('\'');
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    if path.is_empty() {
+        None
+    } else {
+        Some(PathBuf::from(path))
+    }
+}
+
 /// Opens the given file in the user's default editor.
 /// This function will first check the `EDITOR` environment variable, and if it is not set,
 /// it will default to using `vim`.
@@ -124,13 +368,125 @@ pub fn open_editor(config: &Config, path: PathBuf) -> Result<(), LinuxOSError> {
 /// This function takes in the configuration struct and checks if user config contains a frequency
 /// key/value.
 ///
+/// - If `calendar` is defined, validate it and ensure astra service/timer runs on it
 /// - If key/value is defined, take the frequency and ensure astra service/timer is created/updated
 /// - If key/value is not defined, ensure the astra service/timer file is deleted (if it exists)
+///
+/// The service/timer is only reinstalled when the installed units differ from the configuration
+/// or the timer is not enabled (e.g. after `systemctl --user disable`), the reason is logged.
 pub fn handle_frequency(config: &Config) -> Result<bool, LinuxOSError> {
-    if let Some(frequency) = config.frequency() {
-        install_astra_service_and_timer(frequency)?;
+    let on_calendar = if let Some(calendar) = config.calendar() {
+        validate_on_calendar(calendar)?;
+        calendar.to_string()
+    } else if let Some(frequency) = config.frequency() {
+        frequency.as_on_calendar_format()
     } else {
-        uninstall_astra_serivice_and_timer()?;
+        uninstall_astra_serivice_and_timer(config.instance())?;
+        return Ok(true);
+    };
+    match check_astra_service_and_timer(&on_calendar, config.instance())? {
+        Some(discrepancy) => {
+            let message = format!("Repairing the scheduled job: {discrepancy}");
+            config.print_if_verbose(message.as_str());
+            config.log(LogLevel::Warn, message.as_str());
+            install_astra_service_and_timer(&on_calendar, config.instance())?;
+        }
+        None => config.print_if_verbose("Scheduled job is up to date"),
     }
     Ok(true)
 }
+
+/// Removes the astra service/timer of the instance from systemd (used by `astra reset`)
+pub fn uninstall_scheduler(config: &Config) -> Result<(), LinuxOSError> {
+    uninstall_astra_serivice_and_timer(config.instance())
+}
+
+#[cfg(test)]
+mod linux_tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_wlr_randr_output() {
+        let output = r#"eDP-1 "Sharp Corporation 0x1453 (eDP-1)"
+  Make: Sharp Corporation
+  Enabled: yes
+  Modes:
+    3840x2160 px, 60.000000 Hz (preferred)
+    2560x1440 px, 59.951000 Hz (current)
+    1920x1080 px, 60.000000 Hz
+  Position: 0,0
+"#;
+        assert_eq!(parse_wlr_randr_output(output), Some((2560, 1440)));
+        assert_eq!(parse_wlr_randr_output("eDP-1\n  Enabled: no\n"), None);
+    }
+
+    #[test]
+    fn it_parses_mutter_current_state() {
+        let output = "(uint32 4, [(('eDP-1', 'SHP', '0x1453', '0x00000000'), \
+            [('3840x2160@60.000', 3840, 2160, 60.0, 2.0, [1.0, 2.0], {'is-preferred': <true>}), \
+            ('1920x1080@60.000', 1920, 1080, 60.0, 1.0, [1.0], {'is-current': <true>})], {})], [], {})";
+        assert_eq!(parse_mutter_current_state(output), Some((1920, 1080)));
+        assert_eq!(parse_mutter_current_state("(uint32 4, [], [], {})"), None);
+    }
+
+    #[test]
+    fn it_picks_feh_on_bare_x11_window_managers() {
+        for desktop in [None, Some(""), Some("i3"), Some("bspwm"), Some("i3:qtile")] {
+            assert_eq!(
+                wallpaper_setter(desktop, Some("x11"), Some(":0")),
+                WallpaperSetter::Feh,
+                "{desktop:?}"
+            );
+        }
+        for desktop in [
+            Some("GNOME"),
+            Some("ubuntu:GNOME"),
+            Some("KDE"),
+            Some("XFCE"),
+            Some("Hyprland"),
+        ] {
+            assert_eq!(
+                wallpaper_setter(desktop, Some("x11"), Some(":0")),
+                WallpaperSetter::Gsettings,
+                "{desktop:?}"
+            );
+        }
+        assert_eq!(
+            wallpaper_setter(None, Some("wayland"), Some(":0")),
+            WallpaperSetter::Gsettings
+        );
+        // no desktop and no X display (e.g. a systemd service without the session's variables)
+        assert_eq!(
+            wallpaper_setter(None, None, None),
+            WallpaperSetter::Gsettings
+        );
+        assert_eq!(
+            wallpaper_setter(Some("i3"), None, None),
+            WallpaperSetter::Feh
+        );
+    }
+
+    #[test]
+    fn it_builds_feh_args() {
+        assert_eq!(
+            feh_args(Path::new("/home/user/astra wallpapers/julia_1.png")),
+            vec![
+                OsString::from("--bg-fill"),
+                OsString::from("/home/user/astra wallpapers/julia_1.png")
+            ]
+        );
+    }
+
+    #[test]
+    fn it_parses_picture_uri() {
+        assert_eq!(
+            parse_picture_uri("'file:///home/user/astra.png'\n"),
+            Some(PathBuf::from("/home/user/astra.png"))
+        );
+        assert_eq!(
+            parse_picture_uri("'/home/user/astra.png'"),
+            Some(PathBuf::from("/home/user/astra.png"))
+        );
+        assert_eq!(parse_picture_uri("''\n"), None);
+    }
+}
@@ -10,8 +10,12 @@ pub enum MacOSError {
     OS(String),
     ParseError(String),
     ResolutionNotFound,
+    Slideshow(String),
+    /// Setting the wallpaper of a Mission Control space (numbered from 1) failed
+    SpaceWallpaper(usize, String),
     StringConversion,
     SystemProfilerError,
+    WallpaperRefresh(String),
 }
 
 impl std::fmt::Display for MacOSError {
@@ -29,10 +33,19 @@ impl std::fmt::Display for MacOSError {
             MacOSError::ResolutionNotFound => {
                 write!(f, "Unable to determine resolution of main display")
             }
+            MacOSError::Slideshow(err_msg) => {
+                write!(f, "Unable to set up the slideshow: {err_msg}")
+            }
+            MacOSError::SpaceWallpaper(space, err_msg) => {
+                write!(f, "Unable to set the wallpaper of space {space}: {err_msg}")
+            }
             MacOSError::StringConversion => write!(f, "Unable to convert to String"),
             MacOSError::SystemProfilerError => {
                 write!(f, "Encountered error running system_profiler")
             }
+            MacOSError::WallpaperRefresh(err_msg) => {
+                write!(f, "Unable to refresh wallpaper cache: {err_msg}")
+            }
         }
     }
 }
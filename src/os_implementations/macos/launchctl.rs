@@ -1,21 +1,33 @@
 use super::super::MacOSError;
+use crate::configuration::Instance;
 use crate::constants::{APPLICATION, MAC_OS_LAUNCHCTL_INTERVAL, ORGANIZATION, QUALIFIER};
 use directories::UserDirs;
 use std::{fs, path::PathBuf, process::Command};
 
+/// Label of the launchd job of `instance`: dev.CharlieKarafotias.Astra, or
+/// dev.CharlieKarafotias.Astra.work for the instance "work"
+fn job_label(instance: Option<&Instance>) -> String {
+    Instance::job_name(
+        instance,
+        &format!("{QUALIFIER}.{ORGANIZATION}.{APPLICATION}"),
+        '.',
+    )
+}
+
 /// A helper function that generates the plist file path
-/// The path will be ~/Library/LaunchAgents/dev.CharlieKarafotias.astra.plist
+/// The path will be ~/Library/LaunchAgents/dev.CharlieKarafotias.astra.plist (named after the job
+/// label of the instance)
 ///
 /// # Errors
 /// - Will error is UserDirs is None. This should NEVER happen!
-fn gen_plist_path() -> Result<PathBuf, MacOSError> {
+fn gen_plist_path(instance: Option<&Instance>) -> Result<PathBuf, MacOSError> {
     let mut path_to_astra_plist = UserDirs::new()
         .ok_or(MacOSError::OS("home directory not defined".to_string()))?
         .home_dir()
         .to_path_buf();
     path_to_astra_plist.push("Library");
     path_to_astra_plist.push("LaunchAgents");
-    path_to_astra_plist.push(format!("{QUALIFIER}.{ORGANIZATION}.{APPLICATION}.plist"));
+    path_to_astra_plist.push(format!("{}.plist", job_label(instance)));
     Ok(path_to_astra_plist)
 }
 
@@ -28,40 +40,55 @@ fn gen_plist_path() -> Result<PathBuf, MacOSError> {
 /// if the right amount of time has elapsed and if wallpaper should be updated by astra.
 ///
 /// Resource: https://launchd.info/
-fn gen_plist_for_astra() -> Result<String, MacOSError> {
+fn gen_plist_for_astra(instance: Option<&Instance>) -> Result<String, MacOSError> {
     let curr_exe_path: String = std::env::current_exe()
         .map_err(|_| MacOSError::OS("failed to derive current executable path".to_string()))?
         .into_os_string()
         .into_string()
         .map_err(|_| MacOSError::StringConversion)?;
-    let file_contents = format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+    Ok(gen_plist_for(&curr_exe_path, instance))
+}
+
+fn gen_plist_for(exe_path: &str, instance: Option<&Instance>) -> String {
+    let program_arguments: String = std::iter::once(exe_path.to_string())
+        .chain(Instance::job_args(instance))
+        .map(|arg| format!("\n            <string>{arg}</string>"))
+        .collect();
+    format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>
 <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">
 <plist version=\"1.0\">
     <dict>
         <key>Label</key>
-        <string>{}.{}.{}</string>
-        <key>Program</key>
         <string>{}</string>
+        <key>ProgramArguments</key>
+        <array>{}
+        </array>
         <key>StartInterval</key>
         <integer>{}</integer>
         <key>RunAtLoad</key>
         <true/>
     </dict>
 </plist>",
-        QUALIFIER,
-        ORGANIZATION,
-        APPLICATION,
-        curr_exe_path,
+        job_label(instance),
+        program_arguments,
         MAC_OS_LAUNCHCTL_INTERVAL,
-    );
-    Ok(file_contents)
+    )
 }
 
 /// Run the bootstrap command using provided plist file.
 /// This command is useful to ensure new job runs prior to system
 /// restart.
-fn launchctl_bootstrap_astra(plist_path: &PathBuf) -> Result<(), MacOSError> {
+fn launchctl_bootstrap_astra(
+    plist_path: &PathBuf,
+    instance: Option<&Instance>,
+) -> Result<(), MacOSError> {
     let user_id = get_user_id()?;
+    // a disabled job is not run even once bootstrapped
+    Command::new("launchctl")
+        .arg("enable")
+        .arg(format!("gui/{user_id}/{}", job_label(instance)))
+        .output()
+        .map_err(|e| MacOSError::Launchctl(e.to_string()))?;
     Command::new("launchctl")
         .arg("bootstrap")
         .arg(format!("gui/{user_id}"))
@@ -88,14 +115,13 @@ fn launchctl_bootout_astra(plist_path: &PathBuf) -> Result<(), MacOSError> {
 /// Runs the print command to search for existance of Astra job.
 /// IF found, will return the run interval
 /// IF NOT found, will return None
-pub(in crate::os_implementations::macos) fn launchctl_check_existence_of_astra_job()
--> Result<Option<u64>, MacOSError> {
+pub(in crate::os_implementations::macos) fn launchctl_check_existence_of_astra_job(
+    instance: Option<&Instance>,
+) -> Result<Option<u64>, MacOSError> {
     let user_id = get_user_id()?;
     let output = Command::new("launchctl")
         .arg("print")
-        .arg(format!(
-            "gui/{user_id}/{QUALIFIER}.{ORGANIZATION}.{APPLICATION}"
-        ))
+        .arg(format!("gui/{user_id}/{}", job_label(instance)))
         .output()
         .map_err(|e| MacOSError::Launchctl(e.to_string()))?;
     // NOTE: any error status is interpreted as job doesn't exist and should be re-added by
@@ -106,6 +132,33 @@ pub(in crate::os_implementations::macos) fn launchctl_check_existence_of_astra_j
     Ok(extract_interval(&output.stdout))
 }
 
+/// Runs the print-disabled command to check whether the Astra job was disabled (e.g. with
+/// `launchctl disable`), in which case launchd does not run it even though it is loaded
+pub(in crate::os_implementations::macos) fn launchctl_is_astra_job_disabled(
+    instance: Option<&Instance>,
+) -> Result<bool, MacOSError> {
+    let user_id = get_user_id()?;
+    let output = Command::new("launchctl")
+        .arg("print-disabled")
+        .arg(format!("gui/{user_id}"))
+        .output()
+        .map_err(|e| MacOSError::Launchctl(e.to_string()))?;
+    Ok(is_disabled(&output.stdout, &job_label(instance)))
+}
+
+// A helper function that takes the output of launchctl print-disabled command and returns
+// whether the job with `label` is listed as disabled
+// example line: "dev.CharlieKarafotias.Astra" => disabled (or => true on older macOS)
+fn is_disabled(output: &[u8], label: &str) -> bool {
+    let quoted_label = format!("\"{label}\"");
+    String::from_utf8_lossy(output).lines().any(|l| {
+        l.trim()
+            .strip_prefix(&quoted_label)
+            .and_then(|l| l.trim().strip_prefix("=>"))
+            .is_some_and(|state| matches!(state.trim(), "disabled" | "true"))
+    })
+}
+
 // A helper function that takes the output of launchctl print command and returns the
 // value of run interval if it exists in the output
 fn extract_interval(output: &[u8]) -> Option<u64> {
@@ -140,21 +193,25 @@ fn get_user_id() -> Result<String, MacOSError> {
 ///   1. Check for the existance of the Astra job in launchctl.
 ///   2. IF job exists, check that interval is 10minutes. If so then exits.
 ///   3. IF update required, generates the plist file and writes it
-///   4. Then calls bootstrap to execute astra
-pub(in crate::os_implementations::macos) fn launchctl_install_astra_freq() -> Result<(), MacOSError>
-{
-    let path_to_astra_plist = gen_plist_path()?;
-    let file_contents = gen_plist_for_astra()?;
+///   4. Then calls bootstrap to execute astra (after booting out the loaded job, if any, so the
+///      new plist is used)
+pub(in crate::os_implementations::macos) fn launchctl_install_astra_freq(
+    instance: Option<&Instance>,
+) -> Result<(), MacOSError> {
+    let path_to_astra_plist = gen_plist_path(instance)?;
+    let file_contents = gen_plist_for_astra(instance)?;
+    launchctl_bootout_astra(&path_to_astra_plist)?;
     fs::write(&path_to_astra_plist, file_contents).map_err(|err_msg| {
         MacOSError::OS(format!("failed to create/update plist file: {err_msg}"))
     })?;
-    launchctl_bootstrap_astra(&path_to_astra_plist)?;
+    launchctl_bootstrap_astra(&path_to_astra_plist, instance)?;
     Ok(())
 }
 
-pub(in crate::os_implementations::macos) fn launchctl_uninstall_astra_freq()
--> Result<(), MacOSError> {
-    let path_to_astra_plist = gen_plist_path()?;
+pub(in crate::os_implementations::macos) fn launchctl_uninstall_astra_freq(
+    instance: Option<&Instance>,
+) -> Result<(), MacOSError> {
+    let path_to_astra_plist = gen_plist_path(instance)?;
     launchctl_bootout_astra(&path_to_astra_plist)?;
     if path_to_astra_plist.exists() {
         fs::remove_file(&path_to_astra_plist)
@@ -203,4 +260,46 @@ gui/501/dev.CharlieKarafotias.Astra = {
 
         assert_eq!(secs, None)
     }
+
+    #[test]
+    fn test_disabled_job_is_detected_in_print_disabled_output() {
+        let sample = r#"
+disabled services = {
+	"com.apple.ScreenReaderUIServer" => disabled
+	"dev.CharlieKarafotias.Astra" => disabled
+	"dev.CharlieKarafotias.Astra.work" => enabled
+}
+"#;
+        assert!(is_disabled(
+            sample.as_bytes(),
+            "dev.CharlieKarafotias.Astra"
+        ));
+        assert!(!is_disabled(
+            sample.as_bytes(),
+            "dev.CharlieKarafotias.Astra.work"
+        ));
+        assert!(!is_disabled(
+            sample.as_bytes(),
+            "dev.CharlieKarafotias.Astra.home"
+        ));
+        assert!(is_disabled(
+            b"\"dev.CharlieKarafotias.Astra\" => true",
+            "dev.CharlieKarafotias.Astra"
+        ));
+    }
+
+    #[test]
+    fn test_instances_have_distinct_labels() {
+        let work = Instance::new("work").unwrap();
+        let personal = Instance::new("personal").unwrap();
+        assert_eq!(job_label(None), "dev.CharlieKarafotias.Astra");
+        assert_eq!(job_label(Some(&work)), "dev.CharlieKarafotias.Astra.work");
+        assert_ne!(job_label(Some(&work)), job_label(Some(&personal)));
+
+        let plist = gen_plist_for("/Applications/astra", Some(&work));
+        assert!(plist.contains("<string>dev.CharlieKarafotias.Astra.work</string>"));
+        assert!(plist.contains(
+            "<string>/Applications/astra</string>\n            <string>--instance</string>\n            <string>work</string>"
+        ));
+    }
 }
@@ -1,7 +1,11 @@
 mod errors;
 mod launchctl;
+mod slideshow;
+mod spaces;
 mod utils;
 
 pub use errors::*;
 use launchctl::*;
+use slideshow::*;
+use spaces::*;
 pub use utils::*;
@@ -0,0 +1,48 @@
+use super::{MacOSError, applescript_string};
+use std::{path::Path, process::Command};
+
+/// AppleScript making every desktop rotate the pictures of `folder`, changing picture every
+/// `interval_secs` seconds
+pub(in crate::os_implementations::macos) fn gen_slideshow_script(
+    folder: &Path,
+    interval_secs: u64,
+) -> String {
+    format!(
+        "tell application \"System Events\" to tell every desktop\n  set pictures folder to {}\n  set change interval to {interval_secs}\n  set random order to false\n  set picture rotation to 1\nend tell",
+        applescript_string(folder)
+    )
+}
+
+/// AppleScript stopping the rotation of every desktop on its current picture
+pub(in crate::os_implementations::macos) fn gen_disable_slideshow_script() -> String {
+    "tell application \"System Events\" to tell every desktop to set picture rotation to 0"
+        .to_string()
+}
+
+pub(in crate::os_implementations::macos) fn run_slideshow_script(
+    script: &str,
+) -> Result<(), MacOSError> {
+    let output = Command::new("osascript")
+        .args(["-e", script])
+        .output()
+        .map_err(|e| MacOSError::Slideshow(format!("failed to run osascript - {e}")))?;
+    if !output.status.success() {
+        return Err(MacOSError::Slideshow(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slideshow_script_sets_folder_and_interval() {
+        let script = gen_slideshow_script(Path::new("/Users/me/Astra/Wallpapers"), 1800);
+        assert!(script.contains("set pictures folder to \"/Users/me/Astra/Wallpapers\"\n"));
+        assert!(script.contains("set change interval to 1800\n"));
+        assert!(script.contains("set picture rotation to 1\n"));
+    }
+}
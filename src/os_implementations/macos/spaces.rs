@@ -0,0 +1,261 @@
+use super::MacOSError;
+use std::{
+    env::var,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Id of Mission Control's "Switch to Desktop 1" shortcut in com.apple.symbolichotkeys.plist, the
+/// shortcuts of the next spaces follow it (only the first 9 spaces have one)
+const SWITCH_TO_DESKTOP_1_HOTKEY: usize = 118;
+const SPACES_WITH_SHORTCUT: usize = 9;
+/// Modifier flags of a shortcut in com.apple.symbolichotkeys.plist, with their AppleScript name
+const SHORTCUT_MODIFIERS: [(u64, &str); 4] = [
+    (1 << 17, "shift down"),
+    (1 << 18, "control down"),
+    (1 << 19, "option down"),
+    (1 << 20, "command down"),
+];
+/// Time given to the space switch animation before the wallpaper is set
+const SPACE_SWITCH_DELAY_SECS: f32 = 1.0;
+/// Key path of the main display's spaces in com.apple.spaces.plist
+const MAIN_DISPLAY_KEY_PATH: &str = "SpacesDisplayConfiguration.Management Data.Monitors.0";
+
+fn preferences_plist_path(domain: &str) -> Result<PathBuf, MacOSError> {
+    let home = var("HOME").map_err(|e| MacOSError::OS(format!("HOME is not set - {e}")))?;
+    Ok(PathBuf::from(home).join(format!("Library/Preferences/{domain}.plist")))
+}
+
+/// Value at `key_path` in the plist file (the number of elements for an array)
+fn plutil_extract(plist: &Path, key_path: &str) -> Result<String, MacOSError> {
+    let output = Command::new("plutil")
+        .args(["-extract", key_path, "raw", "-o", "-"])
+        .arg(plist)
+        .output()
+        .map_err(|e| MacOSError::OS(format!("failed to run plutil - {e}")))?;
+    if !output.status.success() {
+        return Err(MacOSError::ParseError(format!(
+            "{key_path} not found in {}",
+            plist.display()
+        )));
+    }
+    String::from_utf8(output.stdout)
+        .map(|value| value.trim().to_string())
+        .map_err(|_| MacOSError::StringConversion)
+}
+
+/// Number of Mission Control spaces of the main display, with the index (from 0) of the active
+/// one if it can be determined
+pub(in crate::os_implementations::macos) fn main_display_spaces()
+-> Result<(usize, Option<usize>), MacOSError> {
+    let plist = preferences_plist_path("com.apple.spaces")?;
+    let count = plutil_extract(&plist, &format!("{MAIN_DISPLAY_KEY_PATH}.Spaces"))?
+        .parse::<usize>()
+        .map_err(|e| MacOSError::ParseError(format!("number of spaces - {e}")))?;
+    let current = plutil_extract(
+        &plist,
+        &format!("{MAIN_DISPLAY_KEY_PATH}.Current Space.ManagedSpaceID"),
+    )
+    .ok();
+    let current_index = current.and_then(|current| {
+        (0..count).find(|index| {
+            plutil_extract(
+                &plist,
+                &format!("{MAIN_DISPLAY_KEY_PATH}.Spaces.{index}.ManagedSpaceID"),
+            )
+            .is_ok_and(|id| id == current)
+        })
+    });
+    Ok((count, current_index))
+}
+
+/// `path` as an AppleScript string literal
+pub(in crate::os_implementations::macos) fn applescript_string(path: &Path) -> String {
+    let escaped = path
+        .to_string_lossy()
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+    format!("\"{escaped}\"")
+}
+
+/// Key code and modifier flags of a "Switch to Desktop N" shortcut
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(in crate::os_implementations::macos) struct SpaceShortcut {
+    key_code: u16,
+    modifiers: u64,
+}
+
+/// "Switch to Desktop N" shortcuts of the first `space_count` spaces (up to 9), as configured in
+/// System Settings > Keyboard > Keyboard Shortcuts > Mission Control. Read before any key is
+/// sent, so astra never presses keys that would not switch spaces.
+///
+/// # Errors
+///
+/// Returns `MacOSError::SpaceWallpaper` if the shortcut of one of the spaces is disabled or was
+/// never enabled (it is then missing from com.apple.symbolichotkeys.plist).
+pub(in crate::os_implementations::macos) fn space_shortcuts(
+    space_count: usize,
+) -> Result<Vec<SpaceShortcut>, MacOSError> {
+    let plist = preferences_plist_path("com.apple.symbolichotkeys")?;
+    (0..space_count.min(SPACES_WITH_SHORTCUT))
+        .map(|index| {
+            let key_path = format!(
+                "AppleSymbolicHotKeys.{}",
+                SWITCH_TO_DESKTOP_1_HOTKEY + index
+            );
+            let value = |field: &str| plutil_extract(&plist, &format!("{key_path}.{field}")).ok();
+            parse_space_shortcut(
+                value("enabled").as_deref(),
+                value("value.parameters.1").as_deref(),
+                value("value.parameters.2").as_deref(),
+            )
+            .ok_or_else(|| {
+                MacOSError::SpaceWallpaper(
+                    index + 1,
+                    format!(
+                        "the \"Switch to Desktop {}\" shortcut is not enabled in System Settings > Keyboard > Keyboard Shortcuts > Mission Control",
+                        index + 1
+                    ),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Shortcut from the `enabled` flag, key code and modifier flags extracted from
+/// com.apple.symbolichotkeys.plist, `None` unless it is enabled and bound to a key
+fn parse_space_shortcut(
+    enabled: Option<&str>,
+    key_code: Option<&str>,
+    modifiers: Option<&str>,
+) -> Option<SpaceShortcut> {
+    if !matches!(enabled, Some("true" | "1")) {
+        return None;
+    }
+    // 65535 is the key code of a shortcut without a key
+    let key_code = key_code?
+        .parse()
+        .ok()
+        .filter(|&key_code| key_code != u16::MAX)?;
+    let modifiers = modifiers?.parse().ok()?;
+    Some(SpaceShortcut {
+        key_code,
+        modifiers,
+    })
+}
+
+/// AppleScript switching to a space with its `shortcut`
+pub(in crate::os_implementations::macos) fn gen_switch_space_script(
+    shortcut: &SpaceShortcut,
+) -> String {
+    let modifiers: Vec<&str> = SHORTCUT_MODIFIERS
+        .iter()
+        .filter(|(flag, _)| shortcut.modifiers & flag != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    format!(
+        "tell application \"System Events\" to key code {} using {{{}}}",
+        shortcut.key_code,
+        modifiers.join(", ")
+    )
+}
+
+/// One AppleScript per space of `shortcuts` that switches to the space and sets the wallpaper of
+/// every display to `path`
+pub(in crate::os_implementations::macos) fn gen_space_wallpaper_scripts(
+    path: &Path,
+    shortcuts: &[SpaceShortcut],
+) -> Vec<String> {
+    let path = applescript_string(path);
+    shortcuts
+        .iter()
+        .map(gen_switch_space_script)
+        .map(|switch| {
+            format!(
+                "{switch}\ndelay {SPACE_SWITCH_DELAY_SECS}\ntell application \"System Events\" to tell every desktop to set picture to {path}"
+            )
+        })
+        .collect()
+}
+
+/// Runs an AppleScript acting on the space `space` (numbered from 1)
+pub(in crate::os_implementations::macos) fn run_space_script(
+    space: usize,
+    script: &str,
+) -> Result<(), MacOSError> {
+    let output = Command::new("osascript")
+        .args(["-e", script])
+        .output()
+        .map_err(|e| MacOSError::SpaceWallpaper(space, format!("failed to run osascript - {e}")))?;
+    if !output.status.success() {
+        return Err(MacOSError::SpaceWallpaper(
+            space,
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Default bindings of the "Switch to Desktop N" shortcuts: control and the number key
+    fn control_digits(count: usize) -> Vec<SpaceShortcut> {
+        [18, 19, 20, 21, 23, 22, 26, 28, 25][..count]
+            .iter()
+            .map(|&key_code| SpaceShortcut {
+                key_code,
+                modifiers: 1 << 18,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_space_scripts_switch_to_each_space_then_set_picture() {
+        let scripts = gen_space_wallpaper_scripts(
+            Path::new("/Users/me/Astra \"1\"/astra_1.png"),
+            &control_digits(3),
+        );
+        assert_eq!(scripts.len(), 3);
+        for (script, key_code) in scripts.iter().zip([18, 19, 20]) {
+            assert!(script.starts_with(&format!(
+                "tell application \"System Events\" to key code {key_code} using {{control down}}\n"
+            )));
+            assert!(script.ends_with(
+                "tell every desktop to set picture to \"/Users/me/Astra \\\"1\\\"/astra_1.png\""
+            ));
+        }
+        assert!(gen_space_wallpaper_scripts(Path::new("/tmp/a.png"), &[]).is_empty());
+    }
+
+    #[test]
+    fn test_switch_script_uses_the_configured_modifiers() {
+        let shortcut = SpaceShortcut {
+            key_code: 19,
+            modifiers: (1 << 19) | (1 << 20),
+        };
+        assert_eq!(
+            gen_switch_space_script(&shortcut),
+            "tell application \"System Events\" to key code 19 using {option down, command down}"
+        );
+    }
+
+    #[test]
+    fn test_only_enabled_bound_shortcuts_are_used() {
+        assert_eq!(
+            parse_space_shortcut(Some("true"), Some("18"), Some("262144")),
+            Some(control_digits(1)[0])
+        );
+        // disabled, never enabled (missing from the plist) or without a key
+        assert_eq!(
+            parse_space_shortcut(Some("false"), Some("18"), Some("262144")),
+            None
+        );
+        assert_eq!(parse_space_shortcut(None, None, None), None);
+        assert_eq!(
+            parse_space_shortcut(Some("true"), Some("65535"), Some("0")),
+            None
+        );
+    }
+}
@@ -1,17 +1,24 @@
-use super::super::super::Config;
+use super::super::super::{Config, Frequency};
 use super::{
-    MacOSError, launchctl_check_existence_of_astra_job, launchctl_install_astra_freq,
-    launchctl_uninstall_astra_freq,
+    MacOSError, gen_disable_slideshow_script, gen_slideshow_script, gen_space_wallpaper_scripts,
+    gen_switch_space_script, launchctl_check_existence_of_astra_job, launchctl_install_astra_freq,
+    launchctl_is_astra_job_disabled, launchctl_uninstall_astra_freq, main_display_spaces,
+    run_slideshow_script, run_space_script, space_shortcuts,
 };
-use crate::constants::{APPLICATION, MAC_OS_LAUNCHCTL_INTERVAL, ORGANIZATION, QUALIFIER};
-use directories::ProjectDirs;
+use crate::constants::MAC_OS_LAUNCHCTL_INTERVAL;
+use crate::logger::LogLevel;
+use crate::wallpaper_generators::wallpaper_folder_path;
 use objc2::MainThreadMarker;
-use objc2::rc::autoreleasepool;
+use objc2::rc::{Retained, autoreleasepool};
 use objc2_app_kit::{NSScreen, NSWorkspace};
-use objc2_foundation::{NSArray, NSDictionary, NSString, NSURL};
+use objc2_foundation::{NSDictionary, NSString, NSURL};
 use std::fs;
 use std::time::{SystemTime, UNIX_EPOCH};
-use std::{env::var, path::PathBuf, process::Command};
+use std::{
+    env::var,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
 // --- OS specific code ---
 
@@ -65,17 +72,32 @@ pub fn get_screen_resolution() -> Result<(u32, u32), MacOSError> {
     Ok((width, height))
 }
 
+/// Names of the two files astra alternates between to set the wallpaper: `astra_1.png` and
+/// `astra_2.png` for every display, `astra_screen<index>_1.png` and `astra_screen<index>_2.png`
+/// for the display at `screen` only, so the other displays keep their file
+fn slot_names(screen: Option<usize>) -> [String; 2] {
+    let prefix = match screen {
+        Some(index) => format!("astra_screen{index}"),
+        None => "astra".to_string(),
+    };
+    [1, 2].map(|slot| format!("{prefix}_{slot}.png"))
+}
+
 /// A helper function to identify if the current wallpaper set on any of the screens ends with the
-/// suffix `astra_1.png`. If true, this indicates that astra has previously set a wallpaper and the
-/// `astra_2.png` path should be used for the update_wallpaper call.
-fn is_astra_1_wallpaper(workspace: &NSWorkspace, screens: &NSArray<NSScreen>) -> bool {
+/// suffix `first_slot` (e.g. `astra_1.png`). If true, this indicates that astra has previously set
+/// a wallpaper and the second slot should be used for the update_wallpaper call.
+fn is_astra_1_wallpaper(
+    workspace: &NSWorkspace,
+    screens: &[Retained<NSScreen>],
+    first_slot: &str,
+) -> bool {
     let mut res = false;
     for screen in screens.iter() {
-        let a = workspace.desktopImageURLForScreen(&screen);
+        let a = workspace.desktopImageURLForScreen(screen);
         if let Some(url) = a {
             let path = url.path();
             if let Some(path) = path {
-                if path.hasSuffix(&NSString::from_str("astra_1.png")) {
+                if path.hasSuffix(&NSString::from_str(first_slot)) {
                     res = true;
                 }
             }
@@ -84,6 +106,79 @@ fn is_astra_1_wallpaper(workspace: &NSWorkspace, screens: &NSArray<NSScreen>) ->
     res
 }
 
+/// A helper function that returns the paths of the wallpapers currently set on each of the screens.
+/// Screens whose wallpaper cannot be determined are skipped.
+fn current_wallpaper_paths(
+    workspace: &NSWorkspace,
+    screens: &[Retained<NSScreen>],
+) -> Vec<PathBuf> {
+    screens
+        .iter()
+        .filter_map(|screen| workspace.desktopImageURLForScreen(screen))
+        .filter_map(|url| url.path())
+        .map(|path| PathBuf::from(path.to_string()))
+        .collect()
+}
+
+/// Returns the path of the wallpaper currently set on the first screen, or `None` if it cannot
+/// be determined.
+///
+/// # Errors
+///
+/// Returns `MacOSError::OS` if the function is called from a non-main thread (required by AppKit).
+pub fn get_current_wallpaper() -> Result<Option<PathBuf>, MacOSError> {
+    autoreleasepool(|_| {
+        let mtm = MainThreadMarker::new().ok_or_else(|| {
+            MacOSError::OS("get_current_wallpaper must run on the main thread".into())
+        })?;
+        let workspace = NSWorkspace::sharedWorkspace();
+        let screens = NSScreen::screens(mtm).to_vec();
+        Ok(current_wallpaper_paths(&workspace, &screens)
+            .into_iter()
+            .next())
+    })
+}
+
+/// Returns true if `target` is already set as the wallpaper of at least one screen.
+///
+/// macOS caches wallpapers by path, so re-setting a path that is already displayed does not
+/// visibly update the wallpaper (e.g. when screens were left on different astra slots).
+fn is_same_wallpaper_path(current_paths: &[PathBuf], target: &Path) -> bool {
+    current_paths.iter().any(|p| p == target)
+}
+
+/// Forces macOS to reload the wallpaper at `path`.
+///
+/// The file's modification time is bumped and `WallpaperAgent` (macOS 14+) is restarted so that
+/// the cached image is discarded. On older versions of macOS, where wallpapers are drawn by the
+/// Dock, the Dock is restarted instead.
+///
+/// # Errors
+///
+/// Returns `MacOSError::WallpaperRefresh` if the modification time cannot be updated or if
+/// neither `WallpaperAgent` nor the Dock could be restarted.
+pub fn refresh_wallpaper_cache(path: &Path) -> Result<(), MacOSError> {
+    fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|f| f.set_modified(SystemTime::now()))
+        .map_err(|e| MacOSError::WallpaperRefresh(format!("failed to touch {path:?} - {e}")))?;
+
+    for process in ["WallpaperAgent", "Dock"] {
+        let restarted = Command::new("killall")
+            .arg(process)
+            .status()
+            .map_err(|e| MacOSError::WallpaperRefresh(format!("failed to run killall - {e}")))?
+            .success();
+        if restarted {
+            return Ok(());
+        }
+    }
+    Err(MacOSError::WallpaperRefresh(
+        "neither WallpaperAgent nor Dock could be restarted".to_string(),
+    ))
+}
+
 /// Updates the wallpaper on all connected displays to the image at the given path.
 ///
 /// NOTE: as of v1.1.4
@@ -101,6 +196,9 @@ fn is_astra_1_wallpaper(workspace: &NSWorkspace, screens: &NSArray<NSScreen>) ->
 /// - If wallpaper is generated by astra and is `astra_1.png` -> set path to `astra_2.png` (this forces macOS to recognize the change and update the wallpaper).
 /// - If wallpaper is generated by astra and is `astra_2.png` -> set path to `astra_1.png` (this forces macOS to recognize the change and update the wallpaper).
 /// - If wallpaper is not generated by astra -> set path to `astra_1.png` (this forces macOS to recognize the change and update the wallpaper).
+/// - If the chosen slot is already displayed on one of the screens (e.g. screens ended up on
+///   different slots), the wallpaper cache is refreshed with `refresh_wallpaper_cache`.
+/// - The slots are copies in the wallpaper folder, so `path` can be deleted once this returns.
 ///
 /// # Platform
 ///
@@ -109,43 +207,75 @@ fn is_astra_1_wallpaper(workspace: &NSWorkspace, screens: &NSArray<NSScreen>) ->
 /// # Errors
 ///
 /// - Returns MacOSError::Copy if failure to copy astra image to astra_1 or astra_2 slot
+/// - Returns MacOSError::WallpaperRefresh if the wallpaper cache needed a refresh and it failed
 /// - Returns `Err(MacOSError::OS(_))` if:
 ///   - The function is called from a non-main thread (required by AppKit).
 ///   - The path cannot be converted to a valid UTF-8 string.
 ///   - macOS fails to set the wallpaper for any display.
 pub fn update_wallpaper(path: PathBuf) -> Result<(), MacOSError> {
+    set_desktop_image(path, None).map(|_| ())
+}
+
+/// Number of connected displays, the displays `update_wallpaper_on_screen` can set a wallpaper on
+///
+/// # Errors
+///
+/// Returns `MacOSError::OS` if the function is called from a non-main thread (required by AppKit).
+pub fn screen_count() -> Result<usize, MacOSError> {
+    let mtm = MainThreadMarker::new()
+        .ok_or_else(|| MacOSError::OS("screen_count must run on the main thread".into()))?;
+    Ok(NSScreen::screens(mtm).count())
+}
+
+/// Updates the wallpaper of the display at `index` (in the order of `NSScreen::screens`, the main
+/// display first) like `update_wallpaper`, the other displays keep their wallpaper. The display
+/// alternates between its own slots (`astra_screen<index>_1.png` and `_2.png`) so the file another
+/// display shows is not overwritten.
+///
+/// # Errors
+///
+/// - Returns `MacOSError::OS` if there is no display at `index`
+/// - Returns the errors of `update_wallpaper`
+pub fn update_wallpaper_on_screen(path: PathBuf, index: usize) -> Result<(), MacOSError> {
+    set_desktop_image(path, Some(index)).map(|_| ())
+}
+
+/// Sets the wallpaper of every display, or of the display at `screen` only (see
+/// `update_wallpaper`), returning the path of the slot that is displayed
+fn set_desktop_image(path: PathBuf, screen: Option<usize>) -> Result<PathBuf, MacOSError> {
     autoreleasepool(|_| {
         let mtm = MainThreadMarker::new()
             .ok_or_else(|| MacOSError::OS("update_wallpaper must run on the main thread".into()))?;
 
         let workspace = NSWorkspace::sharedWorkspace();
-        let screens = NSScreen::screens(mtm);
-
-        let wallpaper_folder = path.parent();
-        // Check if current wallpaper is generated by astra and determine which image name to set
-        let astra_cp_path = if is_astra_1_wallpaper(&workspace, &screens) {
-            if let Some(p) = wallpaper_folder {
-                let astra_2_path = p.join("astra_2.png");
-                fs::copy(&path, &astra_2_path)
-                    .map_err(|e| MacOSError::Copy(format!("copy to astra_2.png failed - {e}")))?;
-                astra_2_path
-            } else {
-                return Err(MacOSError::OS(
-                    "failed to copy astra image to astra_2.png file".to_string(),
-                ));
+        let mut screens = NSScreen::screens(mtm).to_vec();
+        if let Some(index) = screen {
+            if index >= screens.len() {
+                return Err(MacOSError::OS(format!(
+                    "no display at index {index}, {} display(s) detected (the first one is 0)",
+                    screens.len()
+                )));
             }
+            screens = vec![screens.swap_remove(index)];
+        }
+        let [astra_1, astra_2] = slot_names(screen);
+
+        let current_paths = current_wallpaper_paths(&workspace, &screens);
+        // The slots live in the wallpaper folder, even when `path` is a temporary file, so the
+        // file macOS keeps reading is not deleted along with it
+        let wallpaper_folder = wallpaper_folder_path()
+            .map_err(|e| MacOSError::Copy(format!("no wallpaper folder - {e}")))?;
+        fs::create_dir_all(&wallpaper_folder)
+            .map_err(|e| MacOSError::Copy(format!("unable to create wallpaper folder - {e}")))?;
+        // Check if current wallpaper is generated by astra and determine which image name to set
+        let slot = if is_astra_1_wallpaper(&workspace, &screens, &astra_1) {
+            astra_2
         } else {
-            if let Some(p) = wallpaper_folder {
-                let astra_1_path = p.join("astra_1.png");
-                fs::copy(&path, &astra_1_path)
-                    .map_err(|e| MacOSError::Copy(format!("copy to astra_1.png failed - {e}")))?;
-                astra_1_path
-            } else {
-                return Err(MacOSError::OS(
-                    "expected image path to contain a parent but none returned".to_string(),
-                ));
-            }
+            astra_1
         };
+        let astra_cp_path = wallpaper_folder.join(&slot);
+        fs::copy(&path, &astra_cp_path)
+            .map_err(|e| MacOSError::Copy(format!("copy to {slot} failed - {e}")))?;
 
         let path_str = astra_cp_path
             .to_str()
@@ -165,10 +295,98 @@ pub fn update_wallpaper(path: PathBuf) -> Result<(), MacOSError> {
             }
         }
 
-        Ok(())
+        if is_same_wallpaper_path(&current_paths, &astra_cp_path) {
+            refresh_wallpaper_cache(&astra_cp_path)?;
+        }
+
+        Ok(astra_cp_path)
     })
 }
 
+/// Updates the wallpaper of every Mission Control space of the main display (`all_spaces`).
+///
+/// `setDesktopImageURL` only changes the wallpaper of the active space of each display, so
+/// unless "Show on all Spaces" is enabled the other spaces keep their wallpaper. This sets the
+/// wallpaper like `update_wallpaper`, then switches to each space with the "Switch to Desktop N"
+/// shortcuts of Mission Control to set it there too, and finally switches back to the space that
+/// was active. macOS has no API to set the wallpaper of another space, hence the shortcuts; they
+/// are read from the user's settings before anything changes, so no key is sent unless every
+/// space can be switched to.
+///
+/// # Errors
+///
+/// - Returns the errors of `update_wallpaper`
+/// - Returns `MacOSError::ParseError` if the spaces cannot be read from com.apple.spaces.plist
+/// - Returns `MacOSError::SpaceWallpaper` if the shortcut of a space is not enabled, or if
+///   switching to a space or setting its wallpaper fails (e.g. astra lacks the Accessibility
+///   permission)
+pub fn update_wallpaper_all_spaces(path: PathBuf) -> Result<(), MacOSError> {
+    let (space_count, current_space) = main_display_spaces()?;
+    let shortcuts = space_shortcuts(space_count)?;
+    let displayed_path = set_desktop_image(path, None)?;
+    for (index, script) in gen_space_wallpaper_scripts(&displayed_path, &shortcuts)
+        .iter()
+        .enumerate()
+    {
+        run_space_script(index + 1, script)?;
+    }
+    if let Some(index) = current_space
+        && let Some(shortcut) = shortcuts.get(index)
+    {
+        run_space_script(index + 1, &gen_switch_space_script(shortcut))?;
+    }
+    Ok(())
+}
+
+/// Makes every desktop rotate the wallpapers of `folder`, showing each one for `interval_secs`
+/// seconds. New wallpapers saved to `folder` are picked up by macOS.
+///
+/// # Errors
+///
+/// Returns `MacOSError::Slideshow` if `folder` does not exist or the desktops cannot be set
+/// through System Events (e.g. astra lacks the Automation permission)
+pub fn enable_slideshow(
+    config: &Config,
+    folder: &Path,
+    interval_secs: u64,
+) -> Result<(), MacOSError> {
+    if !folder.is_dir() {
+        return Err(MacOSError::Slideshow(format!(
+            "{} does not exist, generate some wallpapers first",
+            folder.display()
+        )));
+    }
+    run_slideshow_script(&gen_slideshow_script(folder, interval_secs))?;
+    config.print_if_verbose(format!("Rotating the wallpapers of {}", folder.display()).as_str());
+    Ok(())
+}
+
+/// Stops the slideshow set by `enable_slideshow`, the most recently saved wallpaper of `folder`
+/// becomes the static wallpaper
+pub fn disable_slideshow(config: &Config, folder: &Path) -> Result<(), MacOSError> {
+    run_slideshow_script(&gen_disable_slideshow_script())?;
+    let latest = fs::read_dir(folder)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .file_name()
+                    .is_some_and(|name| !name.to_string_lossy().starts_with('.'))
+        })
+        .max_by_key(|path| {
+            path.metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        });
+    if let Some(latest) = latest {
+        config.print_if_verbose(format!("Set {} as the wallpaper", latest.display()).as_str());
+        update_wallpaper(latest)?;
+    }
+    Ok(())
+}
+
 /// Opens the given file in the user's default editor.
 /// This function will first check the `EDITOR` environment variable, and if it is not set,
 /// it will default to using the `open` command.
@@ -216,41 +434,81 @@ pub fn open_editor(config: &Config, path: PathBuf) -> Result<(), MacOSError> {
 ///      IF not, then program execution will stop and no wallpaper update (returns false)
 /// - If key/value is not defined, remove the astra job from launchctl
 ///
-/// The job is defined in the User Agents location (~/Library/LaunchAgents/)
+/// The job is defined in the User Agents location (~/Library/LaunchAgents/). It is reinstalled
+/// when it is missing, runs on another interval or was disabled, the reason is logged.
 pub fn handle_frequency(config: &Config) -> Result<bool, MacOSError> {
     if let Some(frequency) = config.frequency() {
-        match launchctl_check_existence_of_astra_job()? {
-            Some(interval) => {
-                if interval != MAC_OS_LAUNCHCTL_INTERVAL {
-                    launchctl_install_astra_freq()?;
-                }
+        let discrepancy = job_discrepancy(
+            launchctl_check_existence_of_astra_job(config.instance())?,
+            launchctl_is_astra_job_disabled(config.instance())?,
+        );
+        match discrepancy {
+            Some(discrepancy) => {
+                let message = format!("Repairing the scheduled job: {discrepancy}");
+                config.print_if_verbose(message.as_str());
+                config.log(LogLevel::Warn, message.as_str());
+                launchctl_install_astra_freq(config.instance())?;
             }
-            None => launchctl_install_astra_freq()?,
+            None => config.print_if_verbose("Scheduled job is up to date"),
         }
 
         let current_timestamp_secs = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map_err(|_| MacOSError::OS("time should go forward".to_string()))?
             .as_secs();
-        if current_timestamp_secs - retrieve_last_execution_time()? < frequency.to_seconds() {
+        if !passes_elapsed_time_gate(
+            config.force(),
+            frequency,
+            retrieve_last_execution_time()?,
+            current_timestamp_secs,
+        ) {
             return Ok(false);
         }
     } else {
-        launchctl_uninstall_astra_freq()?;
+        launchctl_uninstall_astra_freq(config.instance())?;
     }
     Ok(true)
 }
 
+/// Why the launchd job needs to be reinstalled, given its run interval (`None` when the job is not
+/// loaded) and whether it was disabled. `None` when it runs every MAC_OS_LAUNCHCTL_INTERVAL
+/// seconds.
+fn job_discrepancy(installed_interval: Option<u64>, disabled: bool) -> Option<String> {
+    match installed_interval {
+        None => Some("the launchd job is not loaded".to_string()),
+        Some(interval) if interval != MAC_OS_LAUNCHCTL_INTERVAL => Some(format!(
+            "the launchd job runs every {interval} seconds instead of {MAC_OS_LAUNCHCTL_INTERVAL}"
+        )),
+        Some(_) if disabled => Some("the launchd job is disabled".to_string()),
+        Some(_) => None,
+    }
+}
+
+/// Removes the astra job of the instance from launchctl (used by `astra reset`)
+pub fn uninstall_scheduler(config: &Config) -> Result<(), MacOSError> {
+    launchctl_uninstall_astra_freq(config.instance())
+}
+
 // --- OS specific code ---
 
 // --- Helper functions ---
 
+/// Returns true if `frequency` elapsed since the last execution, or if `force` is set (manual
+/// `astra --force` runs always generate a wallpaper)
+fn passes_elapsed_time_gate(
+    force: bool,
+    frequency: &Frequency,
+    last_execution_secs: u64,
+    current_timestamp_secs: u64,
+) -> bool {
+    force || current_timestamp_secs.saturating_sub(last_execution_secs) >= frequency.to_seconds()
+}
+
 /// Helper function that retrieves the last execution time from the `last_exec.txt` file.
 /// This time stamp can be set using the save_last_execution_time function below
 fn retrieve_last_execution_time() -> Result<u64, MacOSError> {
-    let proj_dirs = ProjectDirs::from(QUALIFIER, ORGANIZATION, APPLICATION)
-        .ok_or_else(|| MacOSError::OS("could not derive data_dir".to_string()))?;
-    let path_to_time_stamp_file = proj_dirs.data_dir().join("last_exec.txt");
+    // kept in the data folder of the instance, so each instance has its own schedule
+    let path_to_time_stamp_file = Config::data_dir().join("last_exec.txt");
     let timestamp = if path_to_time_stamp_file.exists() {
         fs::read_to_string(&path_to_time_stamp_file)
             .map_err(|e| MacOSError::OS(format!("failed to read last_exec.txt: {e}")))?
@@ -276,9 +534,7 @@ fn retrieve_last_execution_time() -> Result<u64, MacOSError> {
 /// uses launchd job with 10 minute interval. This interval checks this time file
 /// to determine if duration from last exec is higher than the frequency specified by user.
 pub fn save_last_execution_time() -> Result<(), MacOSError> {
-    let proj_dirs = ProjectDirs::from(QUALIFIER, ORGANIZATION, APPLICATION)
-        .ok_or_else(|| MacOSError::OS("could not derive data_dir".to_string()))?;
-    let path_to_time_stamp_file = proj_dirs.data_dir().join("last_exec.txt");
+    let path_to_time_stamp_file = Config::data_dir().join("last_exec.txt");
     let seconds_timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map_err(|_| MacOSError::OS("time should go forward".to_string()))?
@@ -310,18 +566,17 @@ pub fn save_last_execution_time() -> Result<(), MacOSError> {
 /// the `system_profiler` command, this function will return an `Err` containing a
 /// `MacOSError` with the `ResolutionNotFound` variant.
 fn parse_output(output: &str) -> Result<(u32, u32), MacOSError> {
+    // collect once so lines can be indexed without re-scanning the output
+    let lines: Vec<&str> = output.lines().collect();
+
     // find line with Main Display: Yes
-    let main_display_idx = output
-        .lines()
+    let main_display_idx = lines
+        .iter()
         .position(|x| x.contains("Main Display: Yes"))
         .ok_or(MacOSError::MainDisplayNotFound)?;
 
     // count spaces preceding it until new line
-    let main_display_line = output
-        .lines()
-        .nth(main_display_idx)
-        .ok_or(MacOSError::MainDisplayNotFound)?;
-    let num_spaces = preceding_spaces(main_display_line);
+    let num_spaces = preceding_spaces(lines[main_display_idx]);
 
     // grab all lines with that many spaces preceding them
     let mut properties: Vec<&str> = vec![];
@@ -330,7 +585,7 @@ fn parse_output(output: &str) -> Result<(u32, u32), MacOSError> {
         .checked_sub(1)
         .ok_or(MacOSError::ResolutionNotFound)?;
     while i > 0 {
-        let line = output.lines().nth(i).expect("Unable to get line");
+        let line = lines[i];
         let added_property = get_key_value_pair_based_on_spaces(&mut properties, line, num_spaces);
         if !added_property {
             break;
@@ -339,8 +594,8 @@ fn parse_output(output: &str) -> Result<(u32, u32), MacOSError> {
     }
     // check down
     i = main_display_idx + 1;
-    while i < output.lines().count() {
-        let line = output.lines().nth(i).expect("Unable to get line");
+    while i < lines.len() {
+        let line = lines[i];
         let added_property = get_key_value_pair_based_on_spaces(&mut properties, line, num_spaces);
         if !added_property {
             break;
@@ -423,6 +678,52 @@ fn get_key_value_pair_based_on_spaces<'a>(
 mod macos_tests {
     use super::*;
 
+    #[test]
+    fn it_reinstalls_job_with_other_interval() {
+        assert_eq!(
+            job_discrepancy(Some(MAC_OS_LAUNCHCTL_INTERVAL), false),
+            None
+        );
+        assert_eq!(
+            job_discrepancy(Some(86_400), false),
+            Some(format!(
+                "the launchd job runs every 86400 seconds instead of {MAC_OS_LAUNCHCTL_INTERVAL}"
+            ))
+        );
+        assert_eq!(
+            job_discrepancy(Some(MAC_OS_LAUNCHCTL_INTERVAL), true),
+            Some("the launchd job is disabled".to_string())
+        );
+        assert_eq!(
+            job_discrepancy(None, false),
+            Some("the launchd job is not loaded".to_string())
+        );
+    }
+
+    #[test]
+    fn it_skips_elapsed_time_gate_when_forced() {
+        let frequency = Frequency::new("1d").unwrap();
+        let last_run = 1_000_000;
+        assert!(!passes_elapsed_time_gate(
+            false,
+            &frequency,
+            last_run,
+            last_run + 60
+        ));
+        assert!(passes_elapsed_time_gate(
+            true,
+            &frequency,
+            last_run,
+            last_run + 60
+        ));
+        assert!(passes_elapsed_time_gate(
+            false,
+            &frequency,
+            last_run,
+            last_run + 86_400
+        ));
+    }
+
     #[test]
     fn it_parses_valid_output() {
         let output = r#"
@@ -458,6 +759,27 @@ mod macos_tests {
         assert_eq!(height, 1600);
     }
 
+    #[test]
+    fn it_parses_large_output() {
+        let filler = "    Filler Key: Filler Value\n".repeat(20_000);
+        let output = format!(
+            "{filler}\
+            Displays:\n\
+            \x20     Color LCD:\n\
+            \x20       Display Type: Built-In Retina LCD\n\
+            \x20       Resolution: 3024 x 1964 Retina\n\
+            \x20       Main Display: Yes\n\
+            \x20       Mirror: Off\n\
+            \x20     LG HDR WFHD:\n\
+            \x20       Resolution: 2560 x 1080\n\
+            {filler}"
+        );
+
+        let (width, height) = super::parse_output(&output).unwrap();
+        assert_eq!(width, 3024);
+        assert_eq!(height, 1964);
+    }
+
     #[test]
     fn it_fails_to_parse_invalid_output() {
         let output = "";
@@ -506,6 +828,31 @@ mod macos_tests {
         assert_eq!(properties, vec!["    Key: Value"]);
     }
 
+    #[test]
+    fn it_detects_same_wallpaper_path() {
+        let current = vec![
+            PathBuf::from("/wallpapers/astra_1.png"),
+            PathBuf::from("/wallpapers/astra_2.png"),
+        ];
+        assert!(is_same_wallpaper_path(
+            &current,
+            Path::new("/wallpapers/astra_2.png")
+        ));
+    }
+
+    #[test]
+    fn it_does_not_detect_different_wallpaper_path() {
+        let current = vec![PathBuf::from("/wallpapers/astra_1.png")];
+        assert!(!is_same_wallpaper_path(
+            &current,
+            Path::new("/wallpapers/astra_2.png")
+        ));
+        assert!(!is_same_wallpaper_path(
+            &[],
+            Path::new("/wallpapers/astra_1.png")
+        ));
+    }
+
     #[test]
     fn it_does_not_add_line_with_incorrect_spaces() {
         let mut properties = vec![];
@@ -2,16 +2,22 @@ use std::error::Error;
 
 #[derive(Debug, PartialEq)]
 pub enum WindowsError {
+    ComError(String),
     CommandError(String),
     DarkModeError(String),
     ExecutablePath(String),
+    MonitorEnumeration(String),
     OpenEditorError(String),
+    Slideshow(String),
     UpdateDesktopError(String),
 }
 
 impl std::fmt::Display for WindowsError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            WindowsError::ComError(err) => {
+                write!(f, "COM error: {err}")
+            }
             WindowsError::CommandError(err) => {
                 write!(f, "Unable to execute command: {err}")
             }
@@ -21,9 +27,15 @@ impl std::fmt::Display for WindowsError {
             WindowsError::ExecutablePath(err) => {
                 write!(f, "Unable to determine path to current executable: {err}")
             }
+            WindowsError::MonitorEnumeration(err) => {
+                write!(f, "Unable to enumerate connected monitors: {err}")
+            }
             WindowsError::OpenEditorError(err) => {
                 write!(f, "Unable to open file in default editor: {err}")
             }
+            WindowsError::Slideshow(err) => {
+                write!(f, "Unable to set up the slideshow: {err}")
+            }
             WindowsError::UpdateDesktopError(err) => {
                 write!(f, "Unable to update desktop wallpaper: {err}")
             }
@@ -1,7 +1,29 @@
 use super::super::super::Frequency;
 use super::WindowsError;
+use crate::configuration::Instance;
 use crate::constants::{APPLICATION, ORGANIZATION, QUALIFIER};
-use std::{env::current_exe, process::Command};
+use std::{env::current_exe, path::Path, process::Command};
+
+/// Name of the scheduled task of `instance`: dev_CharlieKarafotias_Astra, or
+/// dev_CharlieKarafotias_Astra_work for the instance "work"
+fn task_name(instance: Option<&Instance>) -> String {
+    Instance::job_name(
+        instance,
+        &format!("{QUALIFIER}_{ORGANIZATION}_{APPLICATION}"),
+        '_',
+    )
+}
+
+/// Command run by the task (`/tr`). The executable is quoted when arguments follow it, so a path
+/// with spaces is not split
+fn task_command(exe_path: &Path, instance: Option<&Instance>) -> String {
+    let args = Instance::job_args(instance);
+    if args.is_empty() {
+        exe_path.to_string_lossy().to_string()
+    } else {
+        format!("\"{}\" {}", exe_path.to_string_lossy(), args.join(" "))
+    }
+}
 
 /// Installs astra task when user config includes a frequency
 /// Limitations:
@@ -11,6 +33,7 @@ use std::{env::current_exe, process::Command};
 /// [schtasks doc](https://learn.microsoft.com/en-us/windows-server/administration/windows-commands/schtasks-create)
 pub(in crate::os_implementations::windows) fn install_astra_task(
     frequency: &Frequency,
+    instance: Option<&Instance>,
 ) -> Result<(), WindowsError> {
     let curr_exe_path = current_exe().map_err(|e| WindowsError::ExecutablePath(e.to_string()))?;
     let (mo, sc) = frequency.as_task_scheduler_components();
@@ -20,9 +43,9 @@ pub(in crate::os_implementations::windows) fn install_astra_task(
             "/sc",
             &sc.to_string(),
             "/tn",
-            &format!("{QUALIFIER}_{ORGANIZATION}_{APPLICATION}"),
+            &task_name(instance),
             "/tr",
-            &curr_exe_path.to_string_lossy(),
+            &task_command(&curr_exe_path, instance),
             "/mo",
             &mo.to_string(),
             "/f",
@@ -38,10 +61,58 @@ pub(in crate::os_implementations::windows) fn install_astra_task(
     Ok(())
 }
 
+/// Checks that the installed task of `instance` is enabled and repeats on the schedule of
+/// `frequency` (from `schtasks /query /xml`). Returns why the task needs to be reinstalled,
+/// `None` when it is up to date.
+pub(in crate::os_implementations::windows) fn check_astra_task(
+    frequency: &Frequency,
+    instance: Option<&Instance>,
+) -> Result<Option<String>, WindowsError> {
+    let output = Command::new("schtasks")
+        .args(["/query", "/tn", &task_name(instance), "/xml"])
+        .output()
+        .map_err(|e| WindowsError::CommandError(format!("schtasks query returned error: {e}")))?;
+    // schtasks fails to query a task that does not exist
+    let installed = output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string());
+    let (mo, sc) = frequency.as_task_scheduler_components();
+    Ok(task_discrepancy(installed.as_deref(), mo, &sc.to_string()))
+}
+
+/// Why the task described by `installed` (the XML of `schtasks /query /xml`, `None` when the task
+/// does not exist) does not repeat every `modifier` `schedule` (`/mo` and `/sc` of schtasks create)
+fn task_discrepancy(installed: Option<&str>, modifier: u32, schedule: &str) -> Option<String> {
+    let Some(installed) = installed else {
+        return Some("the task is not installed".to_string());
+    };
+    if installed.contains("<Enabled>false</Enabled>") {
+        return Some("the task is disabled".to_string());
+    }
+    // monthly tasks list their months instead of an interval
+    let (interval, unit) = match schedule {
+        "MINUTE" => (format!("<Interval>PT{modifier}M</Interval>"), "minute(s)"),
+        "HOURLY" => (format!("<Interval>PT{modifier}H</Interval>"), "hour(s)"),
+        "DAILY" => (format!("<DaysInterval>{modifier}</DaysInterval>"), "day(s)"),
+        "WEEKLY" => (
+            format!("<WeeksInterval>{modifier}</WeeksInterval>"),
+            "week(s)",
+        ),
+        _ => return None,
+    };
+    if !installed.contains(&interval) {
+        return Some(format!("the task does not repeat every {modifier} {unit}"));
+    }
+    None
+}
+
 /// Uninstalls astra task when user config removes the frequency
 /// [schtasks doc](https://learn.microsoft.com/en-us/windows-server/administration/windows-commands/schtasks-delete)
-pub(in crate::os_implementations::windows) fn uninstall_astra_task() -> Result<(), WindowsError> {
-    let task_name = format!("{QUALIFIER}_{ORGANIZATION}_{APPLICATION}");
+pub(in crate::os_implementations::windows) fn uninstall_astra_task(
+    instance: Option<&Instance>,
+) -> Result<(), WindowsError> {
+    let task_name = task_name(instance);
 
     // Check if schtasks is available
     let is_available = Command::new("schtasks")
@@ -72,3 +143,62 @@ pub(in crate::os_implementations::windows) fn uninstall_astra_task() -> Result<(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instances_have_distinct_task_names() {
+        let work = Instance::new("work").unwrap();
+        let personal = Instance::new("personal").unwrap();
+        assert_eq!(task_name(None), "dev_CharlieKarafotias_Astra");
+        assert_eq!(task_name(Some(&work)), "dev_CharlieKarafotias_Astra_work");
+        assert_ne!(task_name(Some(&work)), task_name(Some(&personal)));
+
+        let exe = Path::new(r"C:\Program Files\astra\astra.exe");
+        assert_eq!(task_command(exe, None), r"C:\Program Files\astra\astra.exe");
+        assert_eq!(
+            task_command(exe, Some(&work)),
+            r#""C:\Program Files\astra\astra.exe" --instance work"#
+        );
+    }
+
+    #[test]
+    fn test_task_with_other_interval_is_reinstalled() {
+        // trimmed output of `schtasks /query /xml` for a task created with /sc MINUTE /mo 30
+        let installed = r#"<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
+  <Triggers>
+    <TimeTrigger>
+      <Repetition>
+        <Interval>PT30M</Interval>
+        <StopAtDurationEnd>false</StopAtDurationEnd>
+      </Repetition>
+      <StartBoundary>2026-10-16T09:00:00</StartBoundary>
+      <Enabled>true</Enabled>
+    </TimeTrigger>
+  </Triggers>
+  <Settings>
+    <Enabled>true</Enabled>
+  </Settings>
+</Task>"#;
+
+        assert_eq!(task_discrepancy(Some(installed), 30, "MINUTE"), None);
+        assert_eq!(
+            task_discrepancy(Some(installed), 2, "HOURLY"),
+            Some("the task does not repeat every 2 hour(s)".to_string())
+        );
+        let disabled = installed.replace(
+            "<Enabled>true</Enabled>\n  </Settings>",
+            "<Enabled>false</Enabled>\n  </Settings>",
+        );
+        assert_eq!(
+            task_discrepancy(Some(&disabled), 30, "MINUTE"),
+            Some("the task is disabled".to_string())
+        );
+        assert_eq!(
+            task_discrepancy(None, 30, "MINUTE"),
+            Some("the task is not installed".to_string())
+        );
+    }
+}
@@ -1,16 +1,32 @@
 use super::super::super::Config;
-use super::{WindowsError, install_astra_task, uninstall_astra_task};
+use super::{WindowsError, check_astra_task, install_astra_task, uninstall_astra_task};
+use crate::logger::LogLevel;
 use std::{
-    os::{raw::c_void, windows::ffi::OsStrExt},
-    path::PathBuf,
+    ffi::OsString,
+    os::{
+        raw::c_void,
+        windows::ffi::{OsStrExt, OsStringExt},
+    },
+    path::{Path, PathBuf},
     process::Command,
 };
 use windows::{
     Win32::{
-        System::Registry::{HKEY_CURRENT_USER, RRF_RT_REG_DWORD, RegGetValueW},
-        UI::WindowsAndMessaging::{
-            GetSystemMetrics, SM_CXSCREEN, SM_CYSCREEN, SPI_SETDESKWALLPAPER, SPIF_SENDCHANGE,
-            SPIF_UPDATEINIFILE, SystemParametersInfoW,
+        Foundation::MAX_PATH,
+        System::{
+            Com::{
+                CLSCTX_ALL, COINIT_APARTMENTTHREADED, CoCreateInstance, CoInitializeEx,
+                CoTaskMemFree, CoUninitialize,
+            },
+            Registry::{HKEY_CURRENT_USER, RRF_RT_REG_DWORD, RegGetValueW},
+        },
+        UI::{
+            Shell::{DesktopWallpaper, IDesktopWallpaper},
+            WindowsAndMessaging::{
+                GetSystemMetrics, SM_CXSCREEN, SM_CYSCREEN, SPI_GETDESKWALLPAPER,
+                SPI_SETDESKWALLPAPER, SPIF_SENDCHANGE, SPIF_UPDATEINIFILE,
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS, SystemParametersInfoW,
+            },
         },
     },
     core::PCWSTR,
@@ -79,6 +95,171 @@ pub(crate) fn update_wallpaper(path: PathBuf) -> Result<(), WindowsError> {
         .map_err(|e| WindowsError::UpdateDesktopError(format!("SystemParametersInfoW failed: {e}")))
 }
 
+/// Returns the path of the current desktop wallpaper, or `None` if no wallpaper image is set.
+///
+/// # Errors
+///
+/// Returns `WindowsError::UpdateDesktopError` if the wallpaper path cannot be read.
+pub(crate) fn get_current_wallpaper() -> Result<Option<PathBuf>, WindowsError> {
+    let mut buffer = [0u16; MAX_PATH as usize];
+    unsafe {
+        SystemParametersInfoW(
+            SPI_GETDESKWALLPAPER,
+            buffer.len() as u32,
+            Some(buffer.as_mut_ptr() as *mut c_void),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    }
+    .map_err(|e| WindowsError::UpdateDesktopError(format!("SystemParametersInfoW failed: {e}")))?;
+
+    let len = buffer.iter().position(|c| *c == 0).unwrap_or(buffer.len());
+    if len == 0 {
+        return Ok(None);
+    }
+    Ok(Some(PathBuf::from(OsString::from_wide(&buffer[..len]))))
+}
+
+/// A monitor `IDesktopWallpaper` can set a wallpaper on
+pub(crate) struct WallpaperMonitor {
+    /// Nul-terminated device path identifying the monitor
+    device_path: Vec<u16>,
+    /// Resolution of the monitor in pixels
+    pub(crate) resolution: (u32, u32),
+}
+
+/// Retrieves the monitors a wallpaper can be set on with their resolution, in the order of
+/// `IDesktopWallpaper::GetMonitorDevicePathAt`. Device paths without a rectangle (e.g. a monitor
+/// that is not attached) are skipped, so the index of a monitor is the same for every function
+/// taking a `WallpaperMonitor`.
+///
+/// # Errors
+///
+/// Returns `WindowsError::ComError` if COM cannot be initialized or the `DesktopWallpaper`
+/// instance cannot be created, and `WindowsError::MonitorEnumeration` if the device paths cannot
+/// be read.
+pub(crate) fn get_wallpaper_monitors() -> Result<Vec<WallpaperMonitor>, WindowsError> {
+    with_desktop_wallpaper(|desktop_wallpaper| {
+        let count = unsafe { desktop_wallpaper.GetMonitorDevicePathCount() }.map_err(|e| {
+            WindowsError::MonitorEnumeration(format!("GetMonitorDevicePathCount failed: {e}"))
+        })?;
+        let mut monitors = Vec::with_capacity(count as usize);
+        for index in 0..count {
+            let monitor_id =
+                unsafe { desktop_wallpaper.GetMonitorDevicePathAt(index) }.map_err(|e| {
+                    WindowsError::MonitorEnumeration(format!("GetMonitorDevicePathAt failed: {e}"))
+                })?;
+            let device_path: Vec<u16> = unsafe { monitor_id.as_wide() }
+                .iter()
+                .copied()
+                .chain(std::iter::once(0))
+                .collect();
+            // The monitor id string is allocated by COM and must be freed by the caller
+            unsafe { CoTaskMemFree(Some(monitor_id.0 as *const c_void)) };
+            if let Ok(rect) =
+                unsafe { desktop_wallpaper.GetMonitorRECT(PCWSTR(device_path.as_ptr())) }
+            {
+                monitors.push(WallpaperMonitor {
+                    device_path,
+                    resolution: (
+                        (rect.right - rect.left) as u32,
+                        (rect.bottom - rect.top) as u32,
+                    ),
+                });
+            }
+        }
+        Ok(monitors)
+    })
+}
+
+/// Updates the wallpaper of each monitor using the `IDesktopWallpaper` COM interface (Windows 8+):
+/// the wallpaper at `paths[i]` is applied to `monitors[i]`. When only one monitor is connected,
+/// this falls back to `update_wallpaper`.
+///
+/// # Errors
+///
+/// - Returns `WindowsError::UpdateDesktopError` if `paths` is empty or does not have a path for
+///   each monitor.
+/// - Returns `WindowsError::ComError` if COM cannot be initialized, the `DesktopWallpaper`
+///   instance cannot be created, or setting the wallpaper on any monitor fails.
+pub(crate) fn update_wallpaper_per_monitor(
+    monitors: &[WallpaperMonitor],
+    paths: &[PathBuf],
+) -> Result<(), WindowsError> {
+    let first_path = paths.first().ok_or_else(|| {
+        WindowsError::UpdateDesktopError("no wallpaper paths were provided".to_string())
+    })?;
+    if paths.len() != monitors.len() {
+        return Err(WindowsError::UpdateDesktopError(format!(
+            "{} wallpaper(s) for {} monitor(s)",
+            paths.len(),
+            monitors.len()
+        )));
+    }
+    if monitors.len() <= 1 {
+        return update_wallpaper(first_path.clone());
+    }
+    with_desktop_wallpaper(|desktop_wallpaper| {
+        for (index, (monitor, path)) in monitors.iter().zip(paths).enumerate() {
+            set_monitor_wallpaper(desktop_wallpaper, monitor, path)
+                .map_err(|e| WindowsError::ComError(format!("monitor {index}: {e}")))?;
+        }
+        Ok(())
+    })
+}
+
+/// Updates the wallpaper of `monitor` (see `get_wallpaper_monitors`) using the
+/// `IDesktopWallpaper` COM interface. The other monitors keep their wallpaper.
+///
+/// # Errors
+///
+/// Returns `WindowsError::ComError` if COM cannot be initialized, the `DesktopWallpaper`
+/// instance cannot be created, or setting the wallpaper fails.
+pub(crate) fn update_wallpaper_on_monitor(
+    path: PathBuf,
+    monitor: &WallpaperMonitor,
+) -> Result<(), WindowsError> {
+    with_desktop_wallpaper(|desktop_wallpaper| {
+        set_monitor_wallpaper(desktop_wallpaper, monitor, &path)
+    })
+}
+
+/// Runs `f` with a `DesktopWallpaper` instance, initializing COM on the current thread for the
+/// duration of the call
+fn with_desktop_wallpaper<T>(
+    f: impl FnOnce(&IDesktopWallpaper) -> Result<T, WindowsError>,
+) -> Result<T, WindowsError> {
+    unsafe { CoInitializeEx(None, COINIT_APARTMENTTHREADED) }
+        .ok()
+        .map_err(|e| WindowsError::ComError(format!("CoInitializeEx failed: {e}")))?;
+    let result = unsafe { CoCreateInstance(&DesktopWallpaper, None, CLSCTX_ALL) }
+        .map_err(|e| {
+            WindowsError::ComError(format!("failed to create DesktopWallpaper instance: {e}"))
+        })
+        .and_then(|desktop_wallpaper: IDesktopWallpaper| f(&desktop_wallpaper));
+    unsafe { CoUninitialize() };
+    result
+}
+
+/// Sets `path` as the wallpaper of `monitor`, assuming COM is already initialized
+fn set_monitor_wallpaper(
+    desktop_wallpaper: &IDesktopWallpaper,
+    monitor: &WallpaperMonitor,
+    path: &Path,
+) -> Result<(), WindowsError> {
+    let widestr: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    unsafe {
+        desktop_wallpaper.SetWallpaper(
+            PCWSTR(monitor.device_path.as_ptr()),
+            PCWSTR(widestr.as_ptr()),
+        )
+    }
+    .map_err(|e| WindowsError::ComError(format!("SetWallpaper failed: {e}")))
+}
+
 /// Opens the given file in the user's default editor. This function relies on the start
 /// command to open the file.
 ///
@@ -96,6 +277,26 @@ pub(crate) fn open_editor(config: &Config, path: PathBuf) -> Result<(), WindowsE
     Ok(())
 }
 
+/// Not supported on Windows yet, the slideshow of the Personalization settings can be pointed at
+/// astra's Wallpapers folder instead
+pub(crate) fn enable_slideshow(
+    _config: &Config,
+    _folder: &Path,
+    _interval_secs: u64,
+) -> Result<(), WindowsError> {
+    Err(WindowsError::Slideshow(
+        "not supported on Windows, select the Wallpapers folder in the Personalization settings"
+            .to_string(),
+    ))
+}
+
+/// Not supported on Windows yet, see `enable_slideshow`
+pub(crate) fn disable_slideshow(_config: &Config, _folder: &Path) -> Result<(), WindowsError> {
+    Err(WindowsError::Slideshow(
+        "not supported on Windows, pick a wallpaper in the Personalization settings".to_string(),
+    ))
+}
+
 /// CRUD operator function for interfacing with Windows task scheduler service
 ///
 /// This function will take in the configuration struct and check if the user
@@ -103,11 +304,27 @@ pub(crate) fn open_editor(config: &Config, path: PathBuf) -> Result<(), WindowsE
 ///
 /// - IF key/value is defined, take the frequency and ensure astra task is created/updated
 /// - IF key/value is not defined, ensure astra task is removed from scheduled tasks
+///
+/// The task is only recreated when it is missing, disabled or repeats on another schedule, the
+/// reason is logged.
 pub(crate) fn handle_frequency(config: &Config) -> Result<bool, WindowsError> {
     if let Some(frequency) = config.frequency() {
-        install_astra_task(frequency)?;
+        match check_astra_task(frequency, config.instance())? {
+            Some(discrepancy) => {
+                let message = format!("Repairing the scheduled job: {discrepancy}");
+                config.print_if_verbose(message.as_str());
+                config.log(LogLevel::Warn, message.as_str());
+                install_astra_task(frequency, config.instance())?;
+            }
+            None => config.print_if_verbose("Scheduled job is up to date"),
+        }
     } else {
-        uninstall_astra_task()?;
+        uninstall_astra_task(config.instance())?;
     }
     Ok(true)
 }
+
+/// Removes the astra task of the instance from the Windows task scheduler (used by `astra reset`)
+pub(crate) fn uninstall_scheduler(config: &Config) -> Result<(), WindowsError> {
+    uninstall_astra_task(config.instance())
+}
@@ -1,6 +1,8 @@
-use super::super::wallpaper_generators::{AstraImage, average_color as avg_color};
+use super::super::wallpaper_generators::{AstraImage, average_color as avg_color, parse_color};
+use super::contrast::contrast_ratio;
 use std::fmt::{self, Display};
 
+#[derive(Clone)]
 pub struct ColorTheme {
     name: String,
     supports_dark_mode: bool,
@@ -23,6 +25,24 @@ impl ColorTheme {
         }
     }
 
+    /// Ad-hoc theme from a comma separated list of color names or hex values
+    /// (`--palette "#001f3f,#0074D9,#7FDBFF"`), used in both light and dark mode
+    pub fn from_palette(palette: &str) -> Result<Self, String> {
+        let colors = palette
+            .split(',')
+            .map(|color| parse_color(color.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::new("Inline palette".to_string(), false, colors, None))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn supports_dark_mode(&self) -> bool {
+        self.supports_dark_mode
+    }
+
     pub fn get_colors(&self, dark_mode: bool) -> &Vec<[u8; 3]> {
         if dark_mode && self.supports_dark_mode {
             self.colors_dark_mode.as_ref().unwrap_or(&self.colors)
@@ -57,6 +77,16 @@ impl ColorTheme {
         ))?;
         Ok(avg_color(&astra_image).0)
     }
+
+    /// WCAG contrast ratio between the average color of the theme (the wallpaper's background)
+    /// and text drawn over it in the `foreground` color
+    pub fn contrast_ratio(
+        &self,
+        dark_mode: bool,
+        foreground: [u8; 3],
+    ) -> Result<f64, ColorThemeError> {
+        Ok(contrast_ratio(self.average_color(dark_mode)?, foreground))
+    }
 }
 
 impl Display for ColorTheme {
@@ -0,0 +1,40 @@
+/// Lowest contrast ratio WCAG 2 accepts for normal sized text (level AA)
+pub const MIN_TEXT_CONTRAST: f64 = 4.5;
+
+/// Relative luminance of an sRGB color as defined by WCAG 2, from 0 (black) to 1 (white)
+pub fn relative_luminance(color: [u8; 3]) -> f64 {
+    let linear = |channel: u8| {
+        let channel = channel as f64 / 255.0;
+        if channel <= 0.03928 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let [r, g, b] = color.map(linear);
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+/// WCAG 2 contrast ratio between two colors, from 1 (same luminance) to 21 (black and white)
+pub fn contrast_ratio(a: [u8; 3], b: [u8; 3]) -> f64 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contrast_ratio_of_known_pairs() {
+        assert!((contrast_ratio([0, 0, 0], [255, 255, 255]) - 21.0).abs() < 1e-9);
+        assert!((contrast_ratio([255, 255, 255], [0, 0, 0]) - 21.0).abs() < 1e-9);
+        assert!((contrast_ratio([120, 60, 200], [120, 60, 200]) - 1.0).abs() < 1e-9);
+        // #767676 is the lightest gray passing AA on white, #777777 just fails
+        assert!(contrast_ratio([0x76, 0x76, 0x76], [255, 255, 255]) >= MIN_TEXT_CONTRAST);
+        assert!(contrast_ratio([0x77, 0x77, 0x77], [255, 255, 255]) < MIN_TEXT_CONTRAST);
+        // pure blue on black is low contrast, yellow on black is high contrast
+        assert!(contrast_ratio([0, 0, 255], [0, 0, 0]) < 3.0);
+        assert!(contrast_ratio([255, 255, 0], [0, 0, 0]) > 19.0);
+    }
+}
@@ -1,6 +1,10 @@
 mod color_theme;
+mod contrast;
 mod default_themes;
+mod palette;
 mod theme_selector;
 
 pub(super) use color_theme::ColorTheme;
+pub(super) use contrast::{MIN_TEXT_CONTRAST, contrast_ratio};
+pub(super) use palette::{PaletteFormat, export_palette};
 pub(super) use theme_selector::ThemeSelector;
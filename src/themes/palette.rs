@@ -0,0 +1,177 @@
+use super::color_theme::ColorTheme;
+use clap::ValueEnum;
+use std::fmt::Write;
+
+/// File formats a theme's palette can be exported to
+#[derive(Copy, Clone, Debug, PartialEq, ValueEnum)]
+pub enum PaletteFormat {
+    /// GIMP palette (also read by Inkscape, Krita and Aseprite)
+    Gpl,
+    /// Adobe Swatch Exchange
+    Ase,
+    /// One hex color per line (e.g. Lospec's .hex)
+    Hex,
+    /// Same format as a theme in the configuration file
+    Json,
+}
+
+/// Serializes the theme's colors (and its dark mode colors if it has any) in the given format
+pub fn export_palette(theme: &ColorTheme, format: PaletteFormat) -> Vec<u8> {
+    let mut sets = vec![("Light", theme.get_colors(false))];
+    if theme.supports_dark_mode() {
+        sets.push(("Dark", theme.get_colors(true)));
+    }
+    match format {
+        PaletteFormat::Gpl => to_gpl(theme.name(), &sets).into_bytes(),
+        PaletteFormat::Ase => to_ase(theme.name(), &sets),
+        PaletteFormat::Hex => sets
+            .iter()
+            .flat_map(|(_, colors)| colors.iter())
+            .map(|[r, g, b]| format!("{r:02x}{g:02x}{b:02x}\n"))
+            .collect::<String>()
+            .into_bytes(),
+        PaletteFormat::Json => {
+            let mut json = serde_json::json!({
+                "name": theme.name(),
+                "colors": theme.get_colors(false),
+            });
+            if theme.supports_dark_mode() {
+                json["dark_mode_colors"] = serde_json::json!(theme.get_colors(true));
+            }
+            let mut bytes = serde_json::to_vec_pretty(&json).expect("palette is valid JSON");
+            bytes.push(b'\n');
+            bytes
+        }
+    }
+}
+
+fn to_gpl(name: &str, sets: &[(&str, &Vec<[u8; 3]>)]) -> String {
+    let mut gpl = format!("GIMP Palette\nName: {name}\nColumns: 0\n#\n");
+    for (label, colors) in sets {
+        for (i, [r, g, b]) in colors.iter().enumerate() {
+            // writing to a String never fails
+            let _ = writeln!(gpl, "{r:>3} {g:>3} {b:>3}\t{label} {}", i + 1);
+        }
+    }
+    gpl
+}
+
+/// Binary layout: "ASEF" signature, version 1.0 and the number of blocks, then one group per color
+/// set holding its colors. All numbers are big endian and names are null terminated UTF-16.
+fn to_ase(name: &str, sets: &[(&str, &Vec<[u8; 3]>)]) -> Vec<u8> {
+    const GROUP_START: u16 = 0xC001;
+    const GROUP_END: u16 = 0xC002;
+    const COLOR_ENTRY: u16 = 0x0001;
+    const NORMAL_COLOR: u16 = 2;
+
+    let ase_name = |name: &str| {
+        let units: Vec<u16> = name.encode_utf16().chain([0]).collect();
+        let mut bytes = (units.len() as u16).to_be_bytes().to_vec();
+        units
+            .iter()
+            .for_each(|unit| bytes.extend(unit.to_be_bytes()));
+        bytes
+    };
+    let block = |block_type: u16, content: Vec<u8>| {
+        let mut bytes = block_type.to_be_bytes().to_vec();
+        bytes.extend((content.len() as u32).to_be_bytes());
+        bytes.extend(content);
+        bytes
+    };
+
+    let mut blocks = vec![];
+    for (label, colors) in sets {
+        blocks.push(block(GROUP_START, ase_name(&format!("{name} ({label})"))));
+        for (i, [r, g, b]) in colors.iter().enumerate() {
+            let mut content = ase_name(&format!("{label} {}", i + 1));
+            content.extend(b"RGB ");
+            for channel in [r, g, b] {
+                content.extend((*channel as f32 / 255.0).to_be_bytes());
+            }
+            content.extend(NORMAL_COLOR.to_be_bytes());
+            blocks.push(block(COLOR_ENTRY, content));
+        }
+        blocks.push(block(GROUP_END, vec![]));
+    }
+
+    let mut ase = b"ASEF".to_vec();
+    ase.extend(1u16.to_be_bytes());
+    ase.extend(0u16.to_be_bytes());
+    ase.extend((blocks.len() as u32).to_be_bytes());
+    blocks.into_iter().for_each(|block| ase.extend(block));
+    ase
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn theme() -> ColorTheme {
+        ColorTheme::new(
+            "Test Theme".to_string(),
+            true,
+            vec![[245, 245, 245], [58, 12, 163], [0, 255, 183]],
+            Some(vec![[10, 10, 30], [102, 0, 255]]),
+        )
+    }
+
+    fn parse_gpl(gpl: &str) -> Vec<[u8; 3]> {
+        assert!(gpl.starts_with("GIMP Palette\n"));
+        gpl.lines()
+            .skip(1)
+            .filter(|line| !line.starts_with('#') && !line.contains(':'))
+            .map(|line| {
+                let channels: Vec<u8> = line
+                    .split_whitespace()
+                    .take(3)
+                    .map(|channel| channel.parse().unwrap())
+                    .collect();
+                [channels[0], channels[1], channels[2]]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_gpl_round_trip() {
+        let theme = theme();
+        let gpl = String::from_utf8(export_palette(&theme, PaletteFormat::Gpl)).unwrap();
+        assert!(gpl.contains("Name: Test Theme"));
+        let expected: Vec<[u8; 3]> = theme
+            .get_colors(false)
+            .iter()
+            .chain(theme.get_colors(true))
+            .copied()
+            .collect();
+        assert_eq!(parse_gpl(&gpl), expected);
+    }
+
+    #[test]
+    fn test_ase_layout() {
+        let light_only = ColorTheme::new("A".to_string(), false, vec![[255, 0, 0]], None);
+        let ase = export_palette(&light_only, PaletteFormat::Ase);
+        assert_eq!(&ase[..4], b"ASEF");
+        // group start, one color, group end
+        assert_eq!(u32::from_be_bytes(ase[8..12].try_into().unwrap()), 3);
+        let rgb = ase.windows(4).position(|w| w == b"RGB ").unwrap() + 4;
+        assert_eq!(
+            f32::from_be_bytes(ase[rgb..rgb + 4].try_into().unwrap()),
+            1.0
+        );
+        assert_eq!(&ase[ase.len() - 6..], &[0xC0, 0x02, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_hex_and_json() {
+        let theme = theme();
+        let hex = String::from_utf8(export_palette(&theme, PaletteFormat::Hex)).unwrap();
+        assert_eq!(hex.lines().next(), Some("f5f5f5"));
+        assert_eq!(hex.lines().count(), 5);
+        let json: serde_json::Value =
+            serde_json::from_slice(&export_palette(&theme, PaletteFormat::Json)).unwrap();
+        assert_eq!(json["name"], "Test Theme");
+        assert_eq!(
+            json["dark_mode_colors"][1],
+            serde_json::json!([102, 0, 255])
+        );
+    }
+}
@@ -6,6 +6,7 @@ use super::{
         theme_retro_pop, theme_sunlit_meadow,
     },
 };
+use rand::{Rng, RngExt};
 
 pub struct ThemeSelector {
     selected: ColorTheme,
@@ -17,7 +18,11 @@ impl ThemeSelector {
     }
 
     pub fn random() -> ThemeSelector {
-        ThemeSelector::new(rand::random())
+        ThemeSelector::random_with(&mut rand::rng())
+    }
+
+    pub fn random_with<R: Rng + ?Sized>(rng: &mut R) -> ThemeSelector {
+        ThemeSelector::new(rng.random())
     }
 
     pub fn new(theme: ColorThemes) -> ThemeSelector {
@@ -34,6 +39,22 @@ impl ThemeSelector {
             ColorThemes::SunlitMeadow => ThemeSelector::from_color_theme(theme_sunlit_meadow()),
         }
     }
+    /// Every built-in theme
+    pub fn built_in_themes() -> Vec<ColorTheme> {
+        vec![
+            theme_neon_dreams(),
+            theme_aurora_glow(),
+            theme_cyber_sunset(),
+            theme_mystic_forest(),
+            theme_retro_pop(),
+            theme_ocean_breeze(),
+            theme_galaxy_voyage(),
+            theme_fire_ice(),
+            theme_candy_crush(),
+            theme_sunlit_meadow(),
+        ]
+    }
+
     pub fn selected(&self) -> &ColorTheme {
         &self.selected
     }
@@ -44,3 +65,35 @@ impl Default for ThemeSelector {
         ThemeSelector::random()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_seeded_selection_is_reproducible() {
+        let mut rng_a = StdRng::seed_from_u64(42);
+        let mut rng_b = StdRng::seed_from_u64(42);
+        for _ in 0..20 {
+            assert_eq!(
+                ThemeSelector::random_with(&mut rng_a)
+                    .selected()
+                    .to_string(),
+                ThemeSelector::random_with(&mut rng_b)
+                    .selected()
+                    .to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn test_selection_covers_all_themes() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let names: HashSet<String> = (0..1000)
+            .map(|_| ThemeSelector::random_with(&mut rng).selected().to_string())
+            .collect();
+        assert_eq!(names.len(), 10);
+    }
+}
@@ -0,0 +1,153 @@
+use super::super::{cli::Generator, configuration::Config};
+use super::julia::animate_julia_set;
+use super::solid_color::animate_solid_color;
+use super::utils::{AstraImage, WallpaperGeneratorError};
+use image::{
+    Delay, DynamicImage, Frame,
+    codecs::gif::{GifEncoder, Repeat},
+};
+use std::{io::Write, time::Duration};
+
+/// Upper bound of `astra animate --frames`, GIFs grow linearly with the number of frames
+pub const MAX_ANIMATION_FRAMES: u32 = 120;
+/// Longest side of an animation in pixels, larger screens are scaled down
+const MAX_ANIMATION_SIDE: u32 = 960;
+/// Time each frame stays on screen
+const FRAME_DELAY: Duration = Duration::from_millis(80);
+
+/// Resolution to render an animation at: the screen resolution scaled down (keeping the aspect
+/// ratio) so that neither side exceeds `MAX_ANIMATION_SIDE`
+pub fn animation_resolution(width: u32, height: u32) -> (u32, u32) {
+    let scale = (MAX_ANIMATION_SIDE as f64 / width.max(height) as f64).min(1.0);
+    (
+        ((width as f64 * scale).round() as u32).max(1),
+        ((height as f64 * scale).round() as u32).max(1),
+    )
+}
+
+/// Renders `frames` images sweeping a parameter of the generator (e.g. the julia constant or the
+/// hue of a solid color)
+pub fn generate_animation(
+    config: &Config,
+    image: &Generator,
+    frames: u32,
+) -> Result<Vec<AstraImage>, WallpaperGeneratorError> {
+    match image {
+        Generator::Julia => animate_julia_set(config, frames),
+        Generator::Solid { mode } => animate_solid_color(config, mode, frames),
+        _ => Err(WallpaperGeneratorError::ImageGeneration(format!(
+            "the {} generator cannot be animated",
+            image.prefix()
+        ))),
+    }
+}
+
+/// Encodes `frames` as a GIF looping forever
+pub fn encode_gif<W: Write>(
+    frames: &[AstraImage],
+    writer: W,
+) -> Result<(), WallpaperGeneratorError> {
+    let mut encoder = GifEncoder::new(writer);
+    encoder
+        .set_repeat(Repeat::Infinite)
+        .map_err(|_| WallpaperGeneratorError::ImageSave)?;
+    encoder
+        .encode_frames(frames.iter().map(|frame| {
+            Frame::from_parts(
+                DynamicImage::ImageRgb8(frame.clone()).to_rgba8(),
+                0,
+                0,
+                Delay::from_saturating_duration(FRAME_DELAY),
+            )
+        }))
+        .map_err(|_| WallpaperGeneratorError::ImageSave)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{GradientDirection, color_harmony::rgb_to_hsl};
+    use super::*;
+    use crate::cli::SolidMode;
+    use image::{AnimationDecoder, codecs::gif::GifDecoder};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_julia_animation_encodes_every_frame() {
+        let mut config = Config::from_json("{}");
+        config.set_resolution_override(48, 27);
+        config.set_dark_mode_override(false);
+        config.seed_rng(1);
+
+        let frames = generate_animation(&config, &Generator::Julia, 3).unwrap();
+        let mut gif = vec![];
+        encode_gif(&frames, &mut gif).unwrap();
+
+        let decoded = GifDecoder::new(Cursor::new(gif))
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        assert_eq!(decoded.len(), 3);
+        assert_eq!(decoded[0].buffer().dimensions(), (48, 27));
+    }
+
+    #[test]
+    fn test_gradient_hue_animation_is_cyclic_and_keeps_structure() {
+        let mut config = Config::from_json("{}");
+        config.set_resolution_override(32, 8);
+        let gradient = Generator::Solid {
+            mode: SolidMode::Gradient {
+                from: "#c83c3c".to_string(),
+                to: "#3c3cc8".to_string(),
+                direction: GradientDirection::Horizontal,
+            },
+        };
+        let frames = generate_animation(&config, &gradient, MAX_ANIMATION_FRAMES).unwrap();
+        assert_eq!(frames.len(), 120);
+
+        let (first, last) = (&frames[0], &frames[119]);
+        let max_difference = first
+            .as_raw()
+            .iter()
+            .zip(last.as_raw())
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap();
+        assert!(
+            max_difference <= 10,
+            "last frame differs by {max_difference}"
+        );
+
+        let middle = &frames[60];
+        for frame in [first, middle] {
+            // every column is a single color, as in a horizontal gradient
+            for x in 0..32 {
+                assert!((0..8).all(|y| frame.get_pixel(x, y) == frame.get_pixel(x, 0)));
+            }
+        }
+        let (first_hue, first_saturation, first_lightness) = rgb_to_hsl(first.get_pixel(0, 0).0);
+        let (middle_hue, middle_saturation, middle_lightness) =
+            rgb_to_hsl(middle.get_pixel(0, 0).0);
+        assert!(((middle_hue - first_hue).rem_euclid(360.0) - 180.0).abs() < 2.0);
+        assert!((middle_saturation - first_saturation).abs() < 0.02);
+        assert!((middle_lightness - first_lightness).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_solid_hue_animation_rotates_the_color() {
+        let mut config = Config::from_json("{}");
+        config.set_resolution_override(4, 4);
+        let solid = Generator::Solid {
+            mode: SolidMode::Rgb { r: 255, g: 0, b: 0 },
+        };
+        let frames = generate_animation(&config, &solid, 3).unwrap();
+        let colors: Vec<[u8; 3]> = frames.iter().map(|frame| frame.get_pixel(3, 3).0).collect();
+        assert_eq!(colors, vec![[255, 0, 0], [0, 255, 0], [0, 0, 255]]);
+    }
+
+    #[test]
+    fn test_animation_resolution_is_capped() {
+        assert_eq!(animation_resolution(3840, 2160), (960, 540));
+        assert_eq!(animation_resolution(640, 360), (640, 360));
+    }
+}
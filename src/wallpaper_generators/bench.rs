@@ -0,0 +1,110 @@
+use super::super::{cli::Generator, configuration::Config};
+use super::utils::WallpaperGeneratorError;
+use std::{
+    fmt::{self, Display},
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+/// Timings collected for one benchmark
+pub struct BenchResult {
+    name: String,
+    timings: Vec<Duration>,
+}
+
+impl BenchResult {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn mean(&self) -> Duration {
+        self.timings.iter().sum::<Duration>() / self.timings.len().max(1) as u32
+    }
+
+    pub fn min(&self) -> Duration {
+        self.timings.iter().min().copied().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> Duration {
+        self.timings.iter().max().copied().unwrap_or_default()
+    }
+}
+
+impl Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<20} mean: {:>10.3?}  min: {:>10.3?}  max: {:>10.3?}",
+            self.name,
+            self.mean(),
+            self.min(),
+            self.max()
+        )
+    }
+}
+
+/// Times `iterations` images of each generator (generated only, as with `--no-save --no-update`)
+/// and returns the results from the fastest to the slowest mean time. Generators that download
+/// their image are skipped, their time depends on the network rather than on the machine.
+///
+/// The config should have a resolution and dark mode override set so that no OS calls are made
+/// and every iteration renders the same amount of pixels.
+///
+/// Expected order of magnitude (release build, 640x360): julia renders in tens to a couple hundred
+/// milliseconds (depending on the julia set picked). A mean julia render above a second at this
+/// size likely indicates a performance regression.
+pub fn benchmark_generators(
+    config: &Config,
+    generators: &[Generator],
+    iterations: u32,
+) -> Result<Vec<BenchResult>, WallpaperGeneratorError> {
+    let mut results = generators
+        .iter()
+        .filter(|generator| !generator.downloads())
+        .map(|generator| {
+            config.print_if_verbose(format!("Benchmarking {}...", generator.prefix()).as_str());
+            bench(generator.prefix(), iterations, || {
+                black_box(generator.with_default_mode(config)?);
+                Ok(())
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    results.sort_by_key(BenchResult::mean);
+    Ok(results)
+}
+
+fn bench(
+    name: &str,
+    iterations: u32,
+    mut f: impl FnMut() -> Result<(), WallpaperGeneratorError>,
+) -> Result<BenchResult, WallpaperGeneratorError> {
+    let mut timings = Vec::with_capacity(iterations as usize);
+    for _ in 0..iterations.max(1) {
+        let start = Instant::now();
+        f()?;
+        timings.push(start.elapsed());
+    }
+    Ok(BenchResult {
+        name: name.to_string(),
+        timings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generator_benchmark_times_solid_generator() {
+        let mut config = Config::from_json("{}");
+        config.set_resolution_override(64, 36);
+        config.set_dark_mode_override(false);
+        let solid = Generator::Solid {
+            mode: crate::cli::SolidMode::Random,
+        };
+        let results = benchmark_generators(&config, &[solid, Generator::Spotlight], 2).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name(), "solid");
+        assert!(results[0].min() > Duration::ZERO);
+    }
+}
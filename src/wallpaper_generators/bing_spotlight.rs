@@ -1,9 +1,28 @@
-use super::super::configuration::Config;
+use super::super::configuration::{Config, ImageSource, State};
 use super::{
     average_color,
-    utils::{AstraImage, WallpaperGeneratorError, download_image_to_memory},
+    utils::{
+        AstraImage, OriginalImage, WallpaperGeneratorError, download_image_to_memory, load_image,
+    },
 };
-use serde::Deserialize;
+use chrono::Local;
+use image::{ImageBuffer, Rgb, imageops};
+use serde::{Deserialize, Serialize};
+
+/// Times the images are fetched before giving up when all of them are in `spotlight_blocklist`
+const MAX_FETCHES: u32 = 3;
+
+/// How the downloaded image is adapted to the aspect ratio of the screen
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpotlightFit {
+    /// Crops the image (centered) so it fills the screen
+    Cover,
+    /// Pads the image with a color from a theme so all of it is visible
+    Contain,
+    /// Leaves the image as is, the OS stretches it to the screen
+    Stretch,
+}
 
 /// Generates a wallpaper from the Bing Spotlight API. The API provides a
 /// photo of the day, which is used as the wallpaper (same as Windows 11 Spotlight).
@@ -59,54 +78,189 @@ pub fn generate_bing_spotlight(config: &Config) -> Result<AstraImage, WallpaperG
             .themes()
             .is_empty();
 
-    let download_links = get_image_download_urls(
-        config,
-        APIParams {
-            // TODO: could be a user_config preference (compare w/ >2 images)
-            count: if respect_theme && has_user_defined_color_themes {
-                2
-            } else {
-                1
+    let user_theme_averages = if respect_theme && has_user_defined_color_themes {
+        Some(compute_user_theme_averages(config)?)
+    } else {
+        None
+    };
+    let blocklist = config.spotlight_blocklist();
+
+    let mut selected = None;
+    for attempt in 1..=MAX_FETCHES {
+        let sources = get_image_sources(
+            config,
+            APIParams {
+                // TODO: could be a user_config preference (compare w/ >2 images)
+                count: if user_theme_averages.is_some() { 2 } else { 1 },
+                country: &country,
+                locale: &locale,
             },
-            country: &country,
-            locale: &locale,
-        },
-    )?;
-
-    let selected_image: AstraImage = if respect_theme && has_user_defined_color_themes {
-        let user_theme_averages = compute_user_theme_averages(config)?;
-        let mut best_distance: u32 = u32::MAX;
-        let mut best_image: Option<AstraImage> = None;
-        for link in download_links {
-            let downloaded_img = download_image_to_memory(config, &link)?;
-            let loaded_img: AstraImage = image::load_from_memory(downloaded_img.as_slice())
-                .map_err(|e| WallpaperGeneratorError::ImageGeneration(e.to_string()))?
-                .to_rgb8();
-            let distance_from_closest_theme = compare_image_to_user_theme_averages(
-                config,
-                &user_theme_averages,
-                average_color(&loaded_img).0,
-            );
-            if distance_from_closest_theme < best_distance {
-                best_distance = distance_from_closest_theme;
-                best_image = Some(loaded_img);
-            }
-        }
-        if let Some(image) = best_image {
-            image
-        } else {
-            return Err(WallpaperGeneratorError::ImageGeneration(
-                "Failed to find best image match".to_string(),
-            ));
+        )?;
+        selected = select_image(
+            config,
+            sources,
+            user_theme_averages.as_deref(),
+            &blocklist,
+            |url| download_image_to_memory(config, url),
+        )?;
+        if selected.is_some() {
+            break;
         }
+        config.print_if_verbose(
+            format!("All fetched images are in spotlight_blocklist ({attempt}/{MAX_FETCHES})")
+                .as_str(),
+        );
+    }
+    let Some((selected_image, selected_bytes, selected_source)) = selected else {
+        return Err(WallpaperGeneratorError::ImageGeneration(format!(
+            "every image fetched in {MAX_FETCHES} attempts is in spotlight_blocklist"
+        )));
+    };
+    record_source(config, selected_source);
+
+    let keep_original =
+        crate::respect_user_config_or_default!(config, spotlight_gen, keep_original, {
+            Ok(false)
+        })?;
+    config.set_original_image(
+        keep_original
+            .then(|| OriginalImage::new(selected_bytes, &selected_image))
+            .flatten(),
+    );
+
+    let fit = crate::respect_user_config_or_default!(config, spotlight_gen, fit, {
+        Ok(SpotlightFit::Cover)
+    })?;
+    if fit == SpotlightFit::Stretch {
+        return Ok(selected_image);
+    }
+    // photos are fitted at full size, render_scale only speeds up rendered generators
+    let (width, height) = config
+        .output_resolution()
+        .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+    config.print_if_verbose(
+        format!("Fitting image to screen resolution {width}x{height} ({fit:?})").as_str(),
+    );
+    let background = if fit == SpotlightFit::Contain {
+        letterbox_color(config, respect_theme && has_user_defined_color_themes)?
     } else {
-        let downloaded_img = download_image_to_memory(config, &download_links[0])?;
-        image::load_from_memory(downloaded_img.as_slice())
-            .map_err(|e| WallpaperGeneratorError::ImageGeneration(e.to_string()))?
-            .to_rgb8()
+        [0, 0, 0]
     };
+    Ok(fit_to_aspect_ratio(
+        &selected_image,
+        (width, height),
+        fit,
+        background,
+    ))
+}
+
+/// Downloads the images of `sources` with `download` and returns the one closest to
+/// `theme_averages` (the first one without theme averages), along with its downloaded bytes and
+/// its source. Images whose hash is in `blocklist` are skipped, `None` when all of them are.
+fn select_image(
+    config: &Config,
+    sources: Vec<ImageSource>,
+    theme_averages: Option<&[[u8; 3]]>,
+    blocklist: &[String],
+    mut download: impl FnMut(&str) -> Result<Vec<u8>, WallpaperGeneratorError>,
+) -> Result<Option<(AstraImage, Vec<u8>, ImageSource)>, WallpaperGeneratorError> {
+    let mut best_distance: u32 = u32::MAX;
+    let mut best_image = None;
+    for mut source in sources {
+        let downloaded_img = download(&source.url)?;
+        let hash = downloaded_image_hash(&downloaded_img);
+        if blocklist.contains(&hash) {
+            config.print_if_verbose(
+                format!("Skipping {}, it is in spotlight_blocklist", source.url).as_str(),
+            );
+            continue;
+        }
+        source.hash = Some(hash);
+        let loaded_img = load_image(config, &downloaded_img)?;
+        let Some(theme_averages) = theme_averages else {
+            return Ok(Some((loaded_img, downloaded_img, source)));
+        };
+        let distance_from_closest_theme = compare_image_to_user_theme_averages(
+            config,
+            theme_averages,
+            average_color(&loaded_img).0,
+        );
+        if distance_from_closest_theme < best_distance {
+            best_distance = distance_from_closest_theme;
+            best_image = Some((loaded_img, downloaded_img, source));
+        }
+    }
+    Ok(best_image)
+}
 
-    Ok(selected_image)
+/// Hex encoded hash of a downloaded file, as stored in `spotlight_blocklist`
+fn downloaded_image_hash(bytes: &[u8]) -> String {
+    blake3::hash(bytes).to_hex().to_string()
+}
+
+/// Average color of the theme picked by `Config::select_theme`
+fn letterbox_color(
+    config: &Config,
+    respect_user_themes: bool,
+) -> Result<[u8; 3], WallpaperGeneratorError> {
+    let dark_mode = config
+        .is_dark_mode_active()
+        .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+    let theme = config.select_theme(respect_user_themes, &mut *config.rng());
+    config.print_if_verbose(format!("Letterbox theme: {}", theme.selected()).as_str());
+    theme
+        .selected()
+        .average_color(dark_mode)
+        .map_err(|e| WallpaperGeneratorError::ImageGeneration(e.to_string()))
+}
+
+/// Crops (`Cover`) or pads (`Contain`) the image around its center so that it has the aspect ratio
+/// of `screen`. The image keeps its resolution, the OS scales it to the screen without distorting
+/// it.
+fn fit_to_aspect_ratio(
+    image: &AstraImage,
+    screen: (u32, u32),
+    fit: SpotlightFit,
+    background: [u8; 3],
+) -> AstraImage {
+    let (width, height) = image.dimensions();
+    let (screen_width, screen_height) = (screen.0 as u64, screen.1 as u64);
+    // Width and height with the screen's aspect ratio, keeping one side of the image
+    let wider = width as u64 * screen_height > height as u64 * screen_width;
+    let target = match (fit, wider) {
+        (SpotlightFit::Stretch, _) => return image.clone(),
+        (SpotlightFit::Cover, true) | (SpotlightFit::Contain, false) => (
+            (height as u64 * screen_width / screen_height) as u32,
+            height,
+        ),
+        (SpotlightFit::Cover, false) | (SpotlightFit::Contain, true) => {
+            (width, (width as u64 * screen_height / screen_width) as u32)
+        }
+    };
+    let target = (target.0.max(1), target.1.max(1));
+    if target == (width, height) {
+        return image.clone();
+    }
+    match fit {
+        SpotlightFit::Cover => imageops::crop_imm(
+            image,
+            (width - target.0) / 2,
+            (height - target.1) / 2,
+            target.0,
+            target.1,
+        )
+        .to_image(),
+        _ => {
+            let mut padded = ImageBuffer::from_pixel(target.0, target.1, Rgb(background));
+            imageops::replace(
+                &mut padded,
+                image,
+                ((target.0 - width) / 2) as i64,
+                ((target.1 - height) / 2) as i64,
+            );
+            padded
+        }
+    }
 }
 
 fn compute_user_theme_averages(config: &Config) -> Result<Vec<[u8; 3]>, WallpaperGeneratorError> {
@@ -122,26 +276,55 @@ fn compute_user_theme_averages(config: &Config) -> Result<Vec<[u8; 3]>, Wallpape
         ));
     }
 
-    let mut theme_averages: Vec<[u8; 3]> = vec![];
-    for theme in user_themes.themes() {
-        config
-            .print_if_verbose(format!("Computing average color of user theme: {}", theme).as_str());
-        let curr_theme_avg = theme
-            .to_color_theme()
-            .average_color(false)
-            .map_err(|e| WallpaperGeneratorError::ImageGeneration(e.to_string()))?;
-        theme_averages.push(curr_theme_avg);
+    let themes_hash = user_themes.content_hash();
+    let (theme_averages, computed) =
+        cached_theme_averages(&mut State::load(), &themes_hash, || {
+            let mut theme_averages: Vec<[u8; 3]> = vec![];
+            for theme in user_themes.themes() {
+                config.print_if_verbose(
+                    format!("Computing average color of user theme: {}", theme).as_str(),
+                );
+                let curr_theme_avg = theme
+                    .to_color_theme()
+                    .average_color(false)
+                    .map_err(|e| WallpaperGeneratorError::ImageGeneration(e.to_string()))?;
+                theme_averages.push(curr_theme_avg);
+            }
+            Ok(theme_averages)
+        })?;
+    if computed {
+        config.print_if_verbose(
+            format!(
+                "Computed average colors for {} user themes",
+                theme_averages.len()
+            )
+            .as_str(),
+        );
+        // written with the wallpaper, an image that is not applied only costs recomputing them
+        config.stage_state(|state| state.set_theme_averages(themes_hash, theme_averages.clone()));
+    } else {
+        config.print_if_verbose("Using cached average colors of user themes");
     }
-    config.print_if_verbose(
-        format!(
-            "Computed average colors for {} user themes",
-            theme_averages.len()
-        )
-        .as_str(),
-    );
     Ok(theme_averages)
 }
 
+/// Average colors of the user themes from `state` when they were computed from themes with the
+/// same hash, otherwise computes them with `compute` and stores them in `state`.
+///
+/// Returns the averages and whether they were computed.
+fn cached_theme_averages(
+    state: &mut State,
+    themes_hash: &str,
+    compute: impl FnOnce() -> Result<Vec<[u8; 3]>, WallpaperGeneratorError>,
+) -> Result<(Vec<[u8; 3]>, bool), WallpaperGeneratorError> {
+    if let Some(averages) = state.theme_averages(themes_hash) {
+        return Ok((averages.to_vec(), false));
+    }
+    let averages = compute()?;
+    state.set_theme_averages(themes_hash.to_string(), averages.clone());
+    Ok((averages, true))
+}
+
 /// Compares the average color of an image to the average colors of each user theme.
 /// Returns an integer where the lower the number is the better. The best possible match is 0.
 fn compare_image_to_user_theme_averages(
@@ -171,16 +354,38 @@ fn compare_image_to_user_theme_averages(
     best_distance
 }
 
-fn get_image_download_urls(
+/// Stages where the wallpaper came from in the state file (printed by `--show-source`)
+fn record_source(config: &Config, source: ImageSource) {
+    config.print_if_verbose(
+        format!(
+            "Selected spotlight image: {} ({})",
+            source.title.as_deref().unwrap_or("untitled"),
+            source.url
+        )
+        .as_str(),
+    );
+    config.stage_state(|state| state.set_last_source(source));
+}
+
+fn get_image_sources(
     config: &Config,
     params: APIParams,
-) -> Result<Vec<String>, WallpaperGeneratorError> {
+) -> Result<Vec<ImageSource>, WallpaperGeneratorError> {
     let url = build_url(params);
     config.print_if_verbose("Fetching download URLs for spotlight wallpaper(s)...");
     let res = reqwest::blocking::get(url)
         .map_err(|e| WallpaperGeneratorError::Network(e.to_string()))?
         .json::<SpotlightResponse>()
         .map_err(|e| WallpaperGeneratorError::Parse(e.to_string()))?;
+    parse_image_sources(config, res, &Local::now().format("%Y-%m-%d").to_string())
+}
+
+/// Download URL and title of each image of the API's response, fetched on `date`
+fn parse_image_sources(
+    config: &Config,
+    res: SpotlightResponse,
+    date: &str,
+) -> Result<Vec<ImageSource>, WallpaperGeneratorError> {
     if res.batchrsp.items.is_empty() {
         return Err(WallpaperGeneratorError::ImageGeneration(
             "No download URLs found in response".to_string(),
@@ -194,13 +399,19 @@ fn get_image_download_urls(
         .as_str(),
     );
 
-    let mut urls: Vec<String> = Vec::new();
+    let mut sources: Vec<ImageSource> = Vec::new();
     for element in res.batchrsp.items {
         let image_info: ImageInfo = serde_json::from_str(&element.item)
             .map_err(|e| WallpaperGeneratorError::Parse(e.to_string()))?;
-        urls.push(image_info.ad.landscape_image.asset);
+        sources.push(ImageSource {
+            generator: "spotlight".to_string(),
+            url: image_info.ad.landscape_image.asset,
+            title: image_info.ad.title,
+            date: date.to_string(),
+            hash: None,
+        });
     }
-    Ok(urls)
+    Ok(sources)
 }
 
 fn build_url(params: APIParams) -> String {
@@ -243,9 +454,169 @@ struct ImageInfo {
 struct AdInfo {
     #[serde(rename = "landscapeImage")]
     landscape_image: LandscapeImage,
+    title: Option<String>,
 }
 
 #[derive(Deserialize)]
 struct LandscapeImage {
     asset: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selected_url_and_title_are_captured_from_response() {
+        // each item is a JSON document encoded as a string
+        let item = serde_json::json!({
+            "ad": {
+                "landscapeImage": { "asset": "https://img-s.msn.com/tenant/amp/entityid/AA1.img" },
+                "title": "Lake Bled, Slovenia",
+                "copyright": "© Photographer"
+            }
+        })
+        .to_string();
+        let response: SpotlightResponse = serde_json::from_value(serde_json::json!({
+            "batchrsp": { "items": [{ "item": item }] }
+        }))
+        .unwrap();
+
+        let sources =
+            parse_image_sources(&Config::from_json("{}"), response, "2026-10-16").unwrap();
+        assert_eq!(
+            sources,
+            vec![ImageSource {
+                generator: "spotlight".to_string(),
+                url: "https://img-s.msn.com/tenant/amp/entityid/AA1.img".to_string(),
+                title: Some("Lake Bled, Slovenia".to_string()),
+                date: "2026-10-16".to_string(),
+                hash: None,
+            }]
+        );
+    }
+
+    fn png_bytes(color: [u8; 3]) -> Vec<u8> {
+        let image: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb(color));
+        let mut bytes = std::io::Cursor::new(vec![]);
+        image.write_to(&mut bytes, image::ImageFormat::Png).unwrap();
+        bytes.into_inner()
+    }
+
+    fn source(url: &str) -> ImageSource {
+        ImageSource {
+            generator: "spotlight".to_string(),
+            url: url.to_string(),
+            title: None,
+            date: "2026-10-16".to_string(),
+            hash: None,
+        }
+    }
+
+    #[test]
+    fn test_blocked_image_is_skipped() {
+        let config = Config::from_json("{}");
+        let download = |url: &str| {
+            Ok(match url {
+                "blocked" => png_bytes([255, 0, 0]),
+                _ => png_bytes([0, 0, 255]),
+            })
+        };
+        let blocklist = vec![downloaded_image_hash(&png_bytes([255, 0, 0]))];
+
+        let (image, bytes, selected) = select_image(
+            &config,
+            vec![source("blocked"), source("allowed")],
+            None,
+            &blocklist,
+            download,
+        )
+        .unwrap()
+        .expect("the allowed image is selected");
+        assert_eq!(selected.url, "allowed");
+        assert_eq!(selected.hash, Some(downloaded_image_hash(&bytes)));
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 255]);
+
+        // the blocked image is skipped even when it matches the theme better
+        let (_, _, selected) = select_image(
+            &config,
+            vec![source("blocked"), source("allowed")],
+            Some(&[[255, 0, 0]]),
+            &blocklist,
+            download,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(selected.url, "allowed");
+
+        // nothing to select when every image is blocked, the images are fetched again
+        assert!(
+            select_image(&config, vec![source("blocked")], None, &blocklist, download)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_theme_averages_are_cached_until_themes_change() {
+        let config = Config::from_json(
+            r#"{ "themes": [{ "name": "a", "colors": [[200, 0, 0], [100, 0, 0]] }] }"#,
+        );
+        let themes = config.themes().unwrap();
+        let compute = || {
+            Ok(themes
+                .themes()
+                .iter()
+                .map(|theme| theme.to_color_theme().average_color(false).unwrap())
+                .collect())
+        };
+        let mut state = State::default();
+
+        let (first, computed) =
+            cached_theme_averages(&mut state, &themes.content_hash(), compute).unwrap();
+        assert!(computed);
+        let (second, computed) =
+            cached_theme_averages(&mut state, &themes.content_hash(), compute).unwrap();
+        assert!(!computed, "same themes should use the cache");
+        assert_eq!(first, second);
+
+        let changed = Config::from_json(
+            r#"{ "themes": [{ "name": "a", "colors": [[0, 0, 200], [0, 0, 100]] }] }"#,
+        );
+        let changed_themes = changed.themes().unwrap();
+        assert_ne!(changed_themes.content_hash(), themes.content_hash());
+        let (averages, computed) =
+            cached_theme_averages(&mut state, &changed_themes.content_hash(), || {
+                Ok(vec![[0, 0, 150]])
+            })
+            .unwrap();
+        assert!(computed, "changed themes should invalidate the cache");
+        assert_eq!(averages, vec![[0, 0, 150]]);
+    }
+
+    fn aspect_ratio((width, height): (u32, u32)) -> f64 {
+        width as f64 / height as f64
+    }
+
+    #[test]
+    fn test_cover_crops_to_screen_aspect_ratio() {
+        let source: AstraImage = ImageBuffer::from_pixel(1920, 1080, Rgb([10, 20, 30]));
+        let fitted = fit_to_aspect_ratio(&source, (3440, 1440), SpotlightFit::Cover, [0, 0, 0]);
+        assert_eq!(fitted.width(), 1920);
+        assert!((aspect_ratio(fitted.dimensions()) - aspect_ratio((3440, 1440))).abs() < 0.01);
+        assert!(fitted.pixels().all(|pixel| pixel.0 == [10, 20, 30]));
+    }
+
+    #[test]
+    fn test_contain_pads_to_screen_aspect_ratio() {
+        let source: AstraImage = ImageBuffer::from_pixel(1920, 1080, Rgb([10, 20, 30]));
+        let fitted = fit_to_aspect_ratio(&source, (3440, 1440), SpotlightFit::Contain, [1, 2, 3]);
+        assert_eq!(fitted.height(), 1080);
+        assert!((aspect_ratio(fitted.dimensions()) - aspect_ratio((3440, 1440))).abs() < 0.01);
+        assert_eq!(fitted.get_pixel(0, 540).0, [1, 2, 3]);
+        assert_eq!(fitted.get_pixel(fitted.width() / 2, 540).0, [10, 20, 30]);
+
+        let stretched = fit_to_aspect_ratio(&source, (3440, 1440), SpotlightFit::Stretch, [0; 3]);
+        assert_eq!(stretched.dimensions(), (1920, 1080));
+    }
+}
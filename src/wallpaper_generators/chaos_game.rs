@@ -0,0 +1,144 @@
+use super::utils::WallpaperGeneratorError;
+use rand::{SeedableRng, rngs::StdRng};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+/// Points used to find the attractor's bounds before plotting
+const WARM_UP_ITERATIONS: u64 = 20_000;
+/// First points of every chain, left out since they may not be on the attractor yet
+const SKIPPED_ITERATIONS: u64 = 20;
+/// Independent chains the chaos game is split in (fixed so the output does not depend on the
+/// number of cores)
+const CHAINS: u64 = 8;
+/// Share of the screen the attractor fills
+const FILL_RATIO: f64 = 0.9;
+
+/// State moved around by the chaos game: a point of the plane, possibly with extra data (e.g. a
+/// color)
+pub(super) trait ChaosGameState: Copy + Send + Sync {
+    fn point(&self) -> (f64, f64);
+}
+
+impl ChaosGameState for (f64, f64) {
+    fn point(&self) -> (f64, f64) {
+        *self
+    }
+}
+
+impl<T: Copy + Send + Sync> ChaosGameState for ((f64, f64), T) {
+    fn point(&self) -> (f64, f64) {
+        self.0
+    }
+}
+
+/// Plays the chaos game on a `width` x `height` image: starting from `initial`, `step` moves the
+/// state with a randomly picked transform `iterations` times, and `plot` is called with the index
+/// (row major) of the pixel every state lands on.
+///
+/// The attractor's bounds are found on a first run of `WARM_UP_ITERATIONS`, leaving out
+/// `outlier_ratio` of its points on each side. The attractor is then scaled to fill the image
+/// while keeping its aspect ratio, with the y axis pointing up.
+pub(super) fn play_chaos_game<S: ChaosGameState>(
+    (width, height): (u32, u32),
+    iterations: u64,
+    seed: u64,
+    outlier_ratio: f64,
+    initial: S,
+    step: impl Fn(&mut StdRng, S) -> S + Sync,
+    plot: impl Fn(usize, S) + Sync,
+) -> Result<(), WallpaperGeneratorError> {
+    // Find the attractor's bounds
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut state = initial;
+    let (mut xs, mut ys) = (vec![], vec![]);
+    for i in 0..WARM_UP_ITERATIONS {
+        state = step(&mut rng, state);
+        if i >= SKIPPED_ITERATIONS {
+            let (x, y) = state.point();
+            xs.push(x);
+            ys.push(y);
+        }
+    }
+    xs.sort_by(f64::total_cmp);
+    ys.sort_by(f64::total_cmp);
+    let outliers = (xs.len() as f64 * outlier_ratio) as usize;
+    let (min_x, max_x) = (xs[outliers], xs[xs.len() - 1 - outliers]);
+    let (min_y, max_y) = (ys[outliers], ys[ys.len() - 1 - outliers]);
+    if !(min_x.is_finite() && max_x.is_finite() && min_y.is_finite() && max_y.is_finite()) {
+        return Err(WallpaperGeneratorError::ImageGeneration(
+            "the transforms diverge, make sure they are contractive".to_string(),
+        ));
+    }
+    let scale = (width as f64 * FILL_RATIO / (max_x - min_x).max(f64::EPSILON))
+        .min(height as f64 * FILL_RATIO / (max_y - min_y).max(f64::EPSILON));
+    let offset_x = (width as f64 - (max_x - min_x) * scale) / 2.0;
+    let offset_y = (height as f64 - (max_y - min_y) * scale) / 2.0;
+
+    (0..CHAINS).into_par_iter().for_each(|chain| {
+        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(chain + 1));
+        let mut state = initial;
+        for i in 0..iterations / CHAINS {
+            state = step(&mut rng, state);
+            if i < SKIPPED_ITERATIONS {
+                continue;
+            }
+            let (x, y) = state.point();
+            let x = ((x - min_x) * scale + offset_x).floor();
+            let y = (height as f64 - ((y - min_y) * scale + offset_y)).floor();
+            if x >= 0.0 && y >= 0.0 && x < width as f64 && y < height as f64 {
+                plot(y as usize * width as usize + x as usize, state);
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_attractor_is_centered_and_fills_the_image() {
+        // Points converge to the segment from (0, 0) to (0, 1)
+        let (width, height) = (10, 20);
+        let hits: Vec<AtomicU32> = (0..width * height).map(|_| AtomicU32::new(0)).collect();
+        play_chaos_game(
+            (width as u32, height as u32),
+            80_000,
+            0,
+            0.0,
+            (0.0, 0.0),
+            |rng, (x, y): (f64, f64)| {
+                let target = if rand::RngExt::random::<bool>(rng) {
+                    1.0
+                } else {
+                    0.0
+                };
+                (x / 2.0, (y + target) / 2.0)
+            },
+            |index, _| {
+                hits[index].fetch_add(1, Ordering::Relaxed);
+            },
+        )
+        .unwrap();
+        let hit = |x: usize, y: usize| hits[y * width + x].load(Ordering::Relaxed) > 0;
+        // The segment is drawn in the middle column and spans FILL_RATIO of the height
+        let rows_hit = (0..height).filter(|&y| hit(width / 2, y)).count();
+        assert!(rows_hit >= height * 8 / 10, "{rows_hit}");
+        assert!((0..height).all(|y| (0..width).all(|x| x == width / 2 || !hit(x, y))));
+    }
+
+    #[test]
+    fn test_diverging_transforms_are_an_error() {
+        let result = play_chaos_game(
+            (8, 8),
+            100,
+            0,
+            0.0,
+            (1.0, 1.0),
+            |_, (x, y): (f64, f64)| (x * 10.0, y * 10.0),
+            |_, _| {},
+        );
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use super::solid_color::Color;
+use clap::ValueEnum;
+use rand::{Rng, RngExt};
+use serde::{Deserialize, Serialize};
+
+/// Color harmony used to derive a color from a base color by rotating its hue
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum HarmonyScheme {
+    /// Opposite hue on the color wheel (180°)
+    Complementary,
+    /// Neighboring hue on the color wheel (±30°)
+    Analogous,
+    /// One of the two hues evenly spaced on the color wheel (±120°)
+    Triadic,
+}
+
+impl HarmonyScheme {
+    /// Hue rotations (in degrees) that produce a color in harmony with the base color
+    fn rotations(&self) -> &'static [f64] {
+        match self {
+            HarmonyScheme::Complementary => &[180.0],
+            HarmonyScheme::Analogous => &[-30.0, 30.0],
+            HarmonyScheme::Triadic => &[-120.0, 120.0],
+        }
+    }
+}
+
+/// Returns a color in harmony with `base` according to `scheme`.
+///
+/// The hue of `base` is rotated in HSL space while saturation and lightness are kept. When a
+/// scheme has several possible rotations (e.g. analogous), one is picked at random.
+pub fn harmony_color<R: Rng + ?Sized>(
+    base: [u8; 3],
+    scheme: HarmonyScheme,
+    rng: &mut R,
+) -> [u8; 3] {
+    let rotations = scheme.rotations();
+    rotate_hue(base, rotations[rng.random_range(0..rotations.len())])
+}
+
+/// Rotates the hue of `color` by `degrees` in HSL space, keeping its saturation and lightness
+pub(super) fn rotate_hue(color: [u8; 3], degrees: f64) -> [u8; 3] {
+    let (h, s, l) = rgb_to_hsl(color);
+    hsl_to_rgb(((h + degrees) % 360.0 + 360.0) % 360.0, s, l)
+}
+
+/// Returns a random color with moderate saturation and lightness, avoiding the garish colors
+/// that fully random RGB values tend to produce
+pub fn random_pleasant_color<R: Rng + ?Sized>(rng: &mut R) -> [u8; 3] {
+    hsl_to_rgb(
+        rng.random_range(0.0..360.0),
+        rng.random_range(0.45..0.75),
+        rng.random_range(0.4..0.6),
+    )
+}
+
+/// Parses a color from either a named color (see `Color`, e.g. `navy-blue`) or a hex value
+/// (`#RRGGBB` or `RRGGBB`)
+pub fn parse_color(value: &str) -> Result<[u8; 3], String> {
+    if let Ok(color) = Color::from_str(value, true) {
+        let (r, g, b) = color.rgb();
+        return Ok([r, g, b]);
+    }
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "invalid color '{value}': expected a color name or a hex value like #1e90ff"
+        ));
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|e| e.to_string());
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Converts an RGB color to HSL (hue in degrees `0..360`, saturation and lightness in `0..=1`)
+pub(super) fn rgb_to_hsl([r, g, b]: [u8; 3]) -> (f64, f64, f64) {
+    let (r, g, b) = (r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return (0.0, 0.0, l);
+    }
+    let s = delta / (1.0 - (2.0 * l - 1.0).abs());
+    let h = if max == r {
+        60.0 * (((g - b) / delta) % 6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    ((h + 360.0) % 360.0, s, l)
+}
+
+/// Converts an HSL color (hue in degrees `0..360`, saturation and lightness in `0..=1`) to RGB
+pub(super) fn hsl_to_rgb(h: f64, s: f64, l: f64) -> [u8; 3] {
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+    let m = l - c / 2.0;
+    let (r, g, b) = match h {
+        h if h < 60.0 => (c, x, 0.0),
+        h if h < 120.0 => (x, c, 0.0),
+        h if h < 180.0 => (0.0, c, x),
+        h if h < 240.0 => (0.0, x, c),
+        h if h < 300.0 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let to_u8 = |v: f64| ((v + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    [to_u8(r), to_u8(g), to_u8(b)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    fn hue_distance(a: f64, b: f64) -> f64 {
+        let d = (a - b).abs() % 360.0;
+        d.min(360.0 - d)
+    }
+
+    #[test]
+    fn test_complementary_of_red_is_cyan() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let color = harmony_color([255, 0, 0], HarmonyScheme::Complementary, &mut rng);
+        let (h, _, _) = rgb_to_hsl(color);
+        assert!(hue_distance(h, 180.0) < 2.0, "hue was {h}");
+        assert_eq!(color, [0, 255, 255]);
+    }
+
+    #[test]
+    fn test_triadic_and_analogous_rotations() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..10 {
+            let (h, _, _) =
+                rgb_to_hsl(harmony_color([255, 0, 0], HarmonyScheme::Triadic, &mut rng));
+            assert!(hue_distance(h, 120.0) < 2.0 || hue_distance(h, 240.0) < 2.0);
+            let (h, _, _) = rgb_to_hsl(harmony_color(
+                [255, 0, 0],
+                HarmonyScheme::Analogous,
+                &mut rng,
+            ));
+            assert!(hue_distance(h, 30.0) < 2.0 || hue_distance(h, 330.0) < 2.0);
+        }
+    }
+
+    #[test]
+    fn test_hsl_round_trip() {
+        for color in [[255, 0, 0], [30, 144, 255], [128, 128, 128], [12, 200, 90]] {
+            let (h, s, l) = rgb_to_hsl(color);
+            assert_eq!(hsl_to_rgb(h, s, l), color);
+        }
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#1e90ff"), Ok([30, 144, 255]));
+        assert_eq!(parse_color("1E90FF"), Ok([30, 144, 255]));
+        assert_eq!(parse_color("navy-blue"), Ok([0, 0, 128]));
+        assert!(parse_color("#12345").is_err());
+        assert!(parse_color("not a color").is_err());
+    }
+}
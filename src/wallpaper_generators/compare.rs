@@ -0,0 +1,156 @@
+use super::utils::{AstraImage, Operator, create_color_map};
+use image::{GrayImage, ImageBuffer, Rgb, imageops};
+use std::fmt::{self, Display};
+
+/// Side of the square windows the SSIM is computed over
+const SSIM_WINDOW: u32 = 8;
+/// Stabilizing constants of the SSIM formula for 8-bit channels: (0.01 * 255)² and (0.03 * 255)²
+const SSIM_C1: f64 = 6.5025;
+const SSIM_C2: f64 = 58.5225;
+/// Colors of the difference heatmap, from identical pixels to opposite ones
+const HEATMAP_COLORS: [[u8; 3]; 4] = [[0, 0, 0], [128, 0, 0], [255, 160, 0], [255, 255, 255]];
+
+/// How different two images are, printed by `astra diff`
+pub struct ImageComparison {
+    /// Mean absolute difference of the channels, from 0 (identical) to 255 (black against white)
+    pub mean_absolute_difference: f64,
+    /// Mean structural similarity of the luminance, 1 for identical images
+    pub ssim: f64,
+    a: AstraImage,
+    b: AstraImage,
+}
+
+impl ImageComparison {
+    /// Image of the differences: black where the images match, up to white where a pixel is
+    /// black in one image and white in the other
+    pub fn heatmap(&self) -> AstraImage {
+        let color_map = create_color_map(Operator::Gradient, 256, &HEATMAP_COLORS)
+            .expect("HEATMAP_COLORS is not empty");
+        ImageBuffer::from_fn(self.a.width(), self.a.height(), |x, y| {
+            let (a, b) = (self.a.get_pixel(x, y).0, self.b.get_pixel(x, y).0);
+            let difference = (0..3).map(|c| a[c].abs_diff(b[c]) as usize).sum::<usize>() / 3;
+            Rgb(color_map[difference])
+        })
+    }
+}
+
+impl Display for ImageComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Compared at {}x{}", self.a.width(), self.a.height())?;
+        writeln!(
+            f,
+            "Mean absolute difference: {:.2} ({:.2}%)",
+            self.mean_absolute_difference,
+            self.mean_absolute_difference / 255.0 * 100.0
+        )?;
+        write!(f, "SSIM: {:.4}", self.ssim)
+    }
+}
+
+/// Compares two images. When their sizes differ, the larger one is resized to the size of the
+/// smaller one first.
+pub fn compare_images(a: AstraImage, b: AstraImage) -> ImageComparison {
+    let (a, b) = if a.dimensions() == b.dimensions() {
+        (a, b)
+    } else if a.width() as u64 * a.height() as u64 > b.width() as u64 * b.height() as u64 {
+        (resize_to(&a, &b), b)
+    } else {
+        let b = resize_to(&b, &a);
+        (a, b)
+    };
+    ImageComparison {
+        mean_absolute_difference: mean_absolute_difference(&a, &b),
+        ssim: ssim(&to_luma(&a), &to_luma(&b)),
+        a,
+        b,
+    }
+}
+
+fn resize_to(image: &AstraImage, target: &AstraImage) -> AstraImage {
+    imageops::resize(
+        image,
+        target.width(),
+        target.height(),
+        imageops::FilterType::Triangle,
+    )
+}
+
+fn mean_absolute_difference(a: &AstraImage, b: &AstraImage) -> f64 {
+    let total: u64 = a
+        .as_raw()
+        .iter()
+        .zip(b.as_raw())
+        .map(|(a, b)| a.abs_diff(*b) as u64)
+        .sum();
+    total as f64 / a.as_raw().len().max(1) as f64
+}
+
+fn to_luma(image: &AstraImage) -> GrayImage {
+    imageops::grayscale(image)
+}
+
+/// Mean SSIM over the non-overlapping `SSIM_WINDOW` sized windows of two images of the same size
+/// (an image smaller than a window is compared as a whole)
+fn ssim(a: &GrayImage, b: &GrayImage) -> f64 {
+    let (width, height) = a.dimensions();
+    if width == 0 || height == 0 {
+        return 1.0;
+    }
+    let window_width = SSIM_WINDOW.min(width);
+    let window_height = SSIM_WINDOW.min(height);
+    let mut total = 0.0;
+    let mut windows = 0;
+    for top in (0..=height - window_height).step_by(window_height as usize) {
+        for left in (0..=width - window_width).step_by(window_width as usize) {
+            let pixels: Vec<(f64, f64)> = (top..top + window_height)
+                .flat_map(|y| (left..left + window_width).map(move |x| (x, y)))
+                .map(|(x, y)| (a.get_pixel(x, y).0[0] as f64, b.get_pixel(x, y).0[0] as f64))
+                .collect();
+            total += window_ssim(&pixels);
+            windows += 1;
+        }
+    }
+    total / windows as f64
+}
+
+fn window_ssim(pixels: &[(f64, f64)]) -> f64 {
+    let n = pixels.len() as f64;
+    let mean_a = pixels.iter().map(|(a, _)| a).sum::<f64>() / n;
+    let mean_b = pixels.iter().map(|(_, b)| b).sum::<f64>() / n;
+    let (mut variance_a, mut variance_b, mut covariance) = (0.0, 0.0, 0.0);
+    for (a, b) in pixels {
+        variance_a += (a - mean_a).powi(2);
+        variance_b += (b - mean_b).powi(2);
+        covariance += (a - mean_a) * (b - mean_b);
+    }
+    let (variance_a, variance_b, covariance) = (variance_a / n, variance_b / n, covariance / n);
+    ((2.0 * mean_a * mean_b + SSIM_C1) * (2.0 * covariance + SSIM_C2))
+        / ((mean_a.powi(2) + mean_b.powi(2) + SSIM_C1) * (variance_a + variance_b + SSIM_C2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_images_have_no_difference() {
+        let image: AstraImage =
+            ImageBuffer::from_fn(20, 12, |x, y| Rgb([(x * 12) as u8, (y * 20) as u8, 90]));
+        let comparison = compare_images(image.clone(), image);
+        assert_eq!(comparison.mean_absolute_difference, 0.0);
+        assert!((comparison.ssim - 1.0).abs() < 1e-9);
+        assert!(comparison.heatmap().pixels().all(|p| p.0 == [0, 0, 0]));
+    }
+
+    #[test]
+    fn test_black_and_white_images_differ_the_most() {
+        let black: AstraImage = ImageBuffer::from_pixel(16, 16, Rgb([0, 0, 0]));
+        let white: AstraImage = ImageBuffer::from_pixel(32, 32, Rgb([255, 255, 255]));
+        let comparison = compare_images(black, white);
+        assert_eq!(comparison.mean_absolute_difference, 255.0);
+        assert!(comparison.ssim < 0.001);
+        let heatmap = comparison.heatmap();
+        assert_eq!(heatmap.dimensions(), (16, 16));
+        assert!(heatmap.pixels().all(|p| p.0 == [255, 255, 255]));
+    }
+}
@@ -0,0 +1,68 @@
+use super::utils::AstraImage;
+use image::{ImageBuffer, imageops};
+
+/// Width of each image on a contact sheet, the height follows the aspect ratio of the images
+const CONTACT_SHEET_THUMBNAIL_WIDTH: u32 = 320;
+
+/// Columns and rows of the most square grid holding `count` images
+fn contact_sheet_grid(count: u32) -> (u32, u32) {
+    let columns = (count as f64).sqrt().ceil().max(1.0) as u32;
+    (columns, count.div_ceil(columns).max(1))
+}
+
+/// Tiles thumbnails of `images` (left to right, top to bottom) into one overview image
+pub fn create_contact_sheet(images: &[AstraImage]) -> AstraImage {
+    let (columns, rows) = contact_sheet_grid(images.len() as u32);
+    let (thumbnail_width, thumbnail_height) = match images.first() {
+        Some(image) => (
+            CONTACT_SHEET_THUMBNAIL_WIDTH,
+            ((CONTACT_SHEET_THUMBNAIL_WIDTH as f64 * image.height() as f64
+                / image.width().max(1) as f64)
+                .round() as u32)
+                .max(1),
+        ),
+        None => (CONTACT_SHEET_THUMBNAIL_WIDTH, CONTACT_SHEET_THUMBNAIL_WIDTH),
+    };
+    let mut sheet = ImageBuffer::new(columns * thumbnail_width, rows * thumbnail_height);
+    for (index, image) in images.iter().enumerate() {
+        let thumbnail = imageops::resize(
+            image,
+            thumbnail_width,
+            thumbnail_height,
+            imageops::FilterType::Triangle,
+        );
+        let (column, row) = (index as u32 % columns, index as u32 / columns);
+        imageops::replace(
+            &mut sheet,
+            &thumbnail,
+            (column * thumbnail_width) as i64,
+            (row * thumbnail_height) as i64,
+        );
+    }
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    #[test]
+    fn test_contact_sheet_of_four_images_is_a_2x2_grid() {
+        let images: Vec<AstraImage> = (0..4)
+            .map(|i| ImageBuffer::from_pixel(64, 36, Rgb([i * 60, 0, 0])))
+            .collect();
+        let sheet = create_contact_sheet(&images);
+        assert_eq!(sheet.dimensions(), (2 * 320, 2 * 180));
+        // the last image is in the bottom right cell
+        assert_eq!(sheet.get_pixel(2 * 320 - 1, 2 * 180 - 1), &Rgb([180, 0, 0]));
+    }
+
+    #[test]
+    fn test_contact_sheet_grid_is_near_square() {
+        assert_eq!(contact_sheet_grid(1), (1, 1));
+        assert_eq!(contact_sheet_grid(5), (3, 2));
+        assert_eq!(contact_sheet_grid(9), (3, 3));
+        assert_eq!(contact_sheet_grid(10), (4, 3));
+    }
+}
@@ -0,0 +1,216 @@
+use super::color_harmony::rgb_to_hsl;
+use clap::ValueEnum;
+use rand::{Rng, seq::IndexedRandom};
+
+/// Curated list of named colors (based on the CSS named colors, without the harshest ones) that
+/// work well as solid wallpapers
+pub(super) const CURATED_COLORS: [(&str, [u8; 3]); 124] = [
+    ("alice_blue", [240, 248, 255]),
+    ("antique_white", [250, 235, 215]),
+    ("aquamarine", [127, 255, 212]),
+    ("azure", [240, 255, 255]),
+    ("beige", [245, 245, 220]),
+    ("bisque", [255, 228, 196]),
+    ("blanched_almond", [255, 235, 205]),
+    ("blue_violet", [138, 43, 226]),
+    ("brown", [165, 42, 42]),
+    ("burlywood", [222, 184, 135]),
+    ("cadet_blue", [95, 158, 160]),
+    ("chocolate", [210, 105, 30]),
+    ("coral", [255, 127, 80]),
+    ("cornflower_blue", [100, 149, 237]),
+    ("cornsilk", [255, 248, 220]),
+    ("crimson", [220, 20, 60]),
+    ("dark_blue", [0, 0, 139]),
+    ("dark_cyan", [0, 139, 139]),
+    ("dark_goldenrod", [184, 134, 11]),
+    ("dark_gray", [169, 169, 169]),
+    ("dark_green", [0, 100, 0]),
+    ("dark_khaki", [189, 183, 107]),
+    ("dark_magenta", [139, 0, 139]),
+    ("dark_olive_green", [85, 107, 47]),
+    ("dark_orange", [255, 140, 0]),
+    ("dark_orchid", [153, 50, 204]),
+    ("dark_red", [139, 0, 0]),
+    ("dark_salmon", [233, 150, 122]),
+    ("dark_sea_green", [143, 188, 143]),
+    ("dark_slate_blue", [72, 61, 139]),
+    ("dark_slate_gray", [47, 79, 79]),
+    ("dark_turquoise", [0, 206, 209]),
+    ("dark_violet", [148, 0, 211]),
+    ("deep_pink", [255, 20, 147]),
+    ("deep_sky_blue", [0, 191, 255]),
+    ("dim_gray", [105, 105, 105]),
+    ("dodger_blue", [30, 144, 255]),
+    ("firebrick", [178, 34, 34]),
+    ("floral_white", [255, 250, 240]),
+    ("forest_green", [34, 139, 34]),
+    ("gainsboro", [220, 220, 220]),
+    ("ghost_white", [248, 248, 255]),
+    ("gold", [255, 215, 0]),
+    ("goldenrod", [218, 165, 32]),
+    ("gray", [128, 128, 128]),
+    ("green", [0, 128, 0]),
+    ("honeydew", [240, 255, 240]),
+    ("hot_pink", [255, 105, 180]),
+    ("indian_red", [205, 92, 92]),
+    ("indigo", [75, 0, 130]),
+    ("ivory", [255, 255, 240]),
+    ("khaki", [240, 230, 140]),
+    ("lavender", [230, 230, 250]),
+    ("lavender_blush", [255, 240, 245]),
+    ("lemon_chiffon", [255, 250, 205]),
+    ("light_blue", [173, 216, 230]),
+    ("light_coral", [240, 128, 128]),
+    ("light_cyan", [224, 255, 255]),
+    ("light_goldenrod", [250, 250, 210]),
+    ("light_gray", [211, 211, 211]),
+    ("light_green", [144, 238, 144]),
+    ("light_pink", [255, 182, 193]),
+    ("light_salmon", [255, 160, 122]),
+    ("light_sea_green", [32, 178, 170]),
+    ("light_sky_blue", [135, 206, 250]),
+    ("light_slate_gray", [119, 136, 153]),
+    ("light_steel_blue", [176, 196, 222]),
+    ("light_yellow", [255, 255, 224]),
+    ("linen", [250, 240, 230]),
+    ("maroon", [128, 0, 0]),
+    ("medium_aquamarine", [102, 205, 170]),
+    ("medium_blue", [0, 0, 205]),
+    ("medium_orchid", [186, 85, 211]),
+    ("medium_purple", [147, 112, 219]),
+    ("medium_sea_green", [60, 179, 113]),
+    ("medium_slate_blue", [123, 104, 238]),
+    ("medium_spring_green", [0, 250, 154]),
+    ("medium_turquoise", [72, 209, 204]),
+    ("medium_violet_red", [199, 21, 133]),
+    ("midnight_blue", [25, 25, 112]),
+    ("mint_cream", [245, 255, 250]),
+    ("misty_rose", [255, 228, 225]),
+    ("moccasin", [255, 228, 181]),
+    ("navajo_white", [255, 222, 173]),
+    ("navy", [0, 0, 128]),
+    ("old_lace", [253, 245, 230]),
+    ("olive", [128, 128, 0]),
+    ("olive_drab", [107, 142, 35]),
+    ("orange", [255, 165, 0]),
+    ("orange_red", [255, 69, 0]),
+    ("orchid", [218, 112, 214]),
+    ("pale_goldenrod", [238, 232, 170]),
+    ("pale_green", [152, 251, 152]),
+    ("pale_turquoise", [175, 238, 238]),
+    ("pale_violet_red", [219, 112, 147]),
+    ("papaya_whip", [255, 239, 213]),
+    ("peach_puff", [255, 218, 185]),
+    ("peru", [205, 133, 63]),
+    ("pink", [255, 192, 203]),
+    ("plum", [221, 160, 221]),
+    ("powder_blue", [176, 224, 230]),
+    ("rebecca_purple", [102, 51, 153]),
+    ("rosy_brown", [188, 143, 143]),
+    ("royal_blue", [65, 105, 225]),
+    ("saddle_brown", [139, 69, 19]),
+    ("salmon", [250, 128, 114]),
+    ("sandy_brown", [244, 164, 96]),
+    ("sea_green", [46, 139, 87]),
+    ("seashell", [255, 245, 238]),
+    ("sienna", [160, 82, 45]),
+    ("sky_blue", [135, 206, 235]),
+    ("slate_blue", [106, 90, 205]),
+    ("slate_gray", [112, 128, 144]),
+    ("snow", [255, 250, 250]),
+    ("spring_green", [0, 255, 127]),
+    ("steel_blue", [70, 130, 180]),
+    ("tan", [210, 180, 140]),
+    ("teal", [0, 128, 128]),
+    ("thistle", [216, 191, 216]),
+    ("tomato", [255, 99, 71]),
+    ("turquoise", [64, 224, 208]),
+    ("violet", [238, 130, 238]),
+    ("wheat", [245, 222, 179]),
+    ("yellow_green", [154, 205, 50]),
+];
+
+/// Hue family used to narrow down the curated colors
+#[derive(Copy, Clone, Debug, PartialEq, ValueEnum)]
+pub enum HueFamily {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Cyan,
+    Blue,
+    Purple,
+    Pink,
+    /// Whites, grays and near-white tints
+    Neutral,
+}
+
+impl HueFamily {
+    /// Returns the hue family a color belongs to
+    pub(super) fn of(color: [u8; 3]) -> HueFamily {
+        let (h, s, l) = rgb_to_hsl(color);
+        if s < 0.2 || l > 0.93 {
+            return HueFamily::Neutral;
+        }
+        match h {
+            h if !(15.0..345.0).contains(&h) => HueFamily::Red,
+            h if h < 45.0 => HueFamily::Orange,
+            h if h < 70.0 => HueFamily::Yellow,
+            h if h < 160.0 => HueFamily::Green,
+            h if h < 200.0 => HueFamily::Cyan,
+            h if h < 250.0 => HueFamily::Blue,
+            h if h < 290.0 => HueFamily::Purple,
+            _ => HueFamily::Pink,
+        }
+    }
+}
+
+/// Picks a random color from the curated list, optionally limited to a hue family
+pub(super) fn pick_curated_color<R: Rng + ?Sized>(
+    hue: Option<HueFamily>,
+    rng: &mut R,
+) -> (&'static str, [u8; 3]) {
+    let candidates: Vec<&(&str, [u8; 3])> = CURATED_COLORS
+        .iter()
+        .filter(|(_, color)| hue.is_none_or(|hue| HueFamily::of(*color) == hue))
+        .collect();
+    **candidates
+        .choose(rng)
+        .expect("every hue family has at least one curated color")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn test_curated_selection_is_in_table() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..500 {
+            let picked = pick_curated_color(None, &mut rng);
+            assert!(CURATED_COLORS.contains(&picked));
+        }
+    }
+
+    #[test]
+    fn test_curated_selection_respects_hue_family() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for hue in HueFamily::value_variants() {
+            for _ in 0..50 {
+                let (name, color) = pick_curated_color(Some(*hue), &mut rng);
+                assert!(CURATED_COLORS.contains(&(name, color)));
+                assert_eq!(HueFamily::of(color), *hue, "{name} is not {hue:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_curated_color_names_are_unique() {
+        let mut names: Vec<&str> = CURATED_COLORS.iter().map(|(name, _)| *name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), CURATED_COLORS.len());
+    }
+}
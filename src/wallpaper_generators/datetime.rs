@@ -0,0 +1,133 @@
+use super::super::configuration::Config;
+use super::text::{TextAlignment, contrasting_color, draw_text, themed_background};
+use super::utils::{AstraImage, WallpaperGeneratorError};
+use chrono::{Local, NaiveDateTime};
+use std::fmt::Write;
+
+/// chrono format of the date, e.g. "Friday, October 16"
+const DEFAULT_DATE_FORMAT: &str = "%A, %B %-d";
+/// chrono format of the line added under the date by `datetime_gen.include_time`
+const TIME_FORMAT: &str = "%H:%M";
+/// Default line height as a fraction of the screen height, larger than the text generator's
+const DEFAULT_FONT_SIZE_RATIO: u32 = 8;
+
+pub fn generate_datetime(config: &Config) -> Result<AstraImage, WallpaperGeneratorError> {
+    render_datetime(config, Local::now().naive_local())
+}
+
+/// Draws the date (and time) of `now` centered over a themed gradient
+fn render_datetime(
+    config: &Config,
+    now: NaiveDateTime,
+) -> Result<AstraImage, WallpaperGeneratorError> {
+    config.print_if_verbose("Generating date...");
+    let (width, height) = config
+        .screen_resolution()
+        .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+    config.print_if_verbose(format!("Detected screen resolution: {}x{}", width, height).as_str());
+
+    if config.respect_user_config {
+        config.print_if_verbose("User config detected with datetime_gen options...");
+    }
+
+    let format = crate::respect_user_config_or_default!(config, datetime_gen, format, {
+        Ok(DEFAULT_DATE_FORMAT.to_string())
+    })?;
+    let include_time =
+        crate::respect_user_config_or_default!(config, datetime_gen, include_time, { Ok(false) })?;
+    let font_size = crate::respect_user_config_or_default!(config, datetime_gen, font_size, {
+        Ok(height / DEFAULT_FONT_SIZE_RATIO)
+    })?
+    .max(1);
+    let should_respect_color_themes =
+        crate::respect_user_config_or_default!(config, datetime_gen, respect_color_themes, {
+            Ok(false)
+        })?;
+
+    let text = datetime_text(now, &format, include_time)?;
+    let mut imgbuf = themed_background(config, should_respect_color_themes, width, height)?;
+    let color = contrasting_color(&imgbuf);
+    config.print_if_verbose(format!("Drawing {text:?} at {font_size}px").as_str());
+    draw_text(
+        &mut imgbuf,
+        &text,
+        font_size as f32,
+        TextAlignment::Center,
+        color,
+    )?;
+
+    config.print_if_verbose("Image generated!");
+    Ok(imgbuf)
+}
+
+/// `now` formatted with the chrono `format`, followed by a line with the time if `include_time`
+fn datetime_text(
+    now: NaiveDateTime,
+    format: &str,
+    include_time: bool,
+) -> Result<String, WallpaperGeneratorError> {
+    let mut text = String::new();
+    // an invalid specifier makes chrono fail the write instead of panicking
+    write!(text, "{}", now.format(format)).map_err(|_| {
+        WallpaperGeneratorError::Parse(format!("invalid datetime_gen.format {format:?}"))
+    })?;
+    if include_time {
+        write!(text, "\n{}", now.format(TIME_FORMAT))
+            .map_err(|e| WallpaperGeneratorError::Parse(e.to_string()))?;
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn fixed_now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 3, 9)
+            .unwrap()
+            .and_hms_opt(7, 5, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_format_string_is_applied_to_now() {
+        assert_eq!(
+            datetime_text(fixed_now(), DEFAULT_DATE_FORMAT, false).unwrap(),
+            "Saturday, March 9"
+        );
+        assert_eq!(
+            datetime_text(fixed_now(), "%Y-%m-%d", true).unwrap(),
+            "2024-03-09\n07:05"
+        );
+        assert!(datetime_text(fixed_now(), "%Q", false).is_err());
+    }
+
+    #[test]
+    fn test_date_is_drawn_over_background() {
+        let mut config = Config::from_json(
+            r#"{
+                "datetime_gen": { "format": "%d/%m", "font_size": 40 },
+                "themes": [{ "name": "Flat", "colors": [[30, 60, 90]] }]
+            }"#,
+        );
+        config.respect_user_config = true;
+        config.set_resolution_override(320, 200);
+        config.set_dark_mode_override(false);
+        config
+            .set_theme_from_flag_or_env(Some("Flat"), None)
+            .unwrap();
+
+        let image = render_datetime(&config, fixed_now()).unwrap();
+        assert_eq!(image.dimensions(), (320, 200));
+        // the text never reaches the left edge, which shows the background of each row
+        let drawn: Vec<(u32, u32)> = image
+            .enumerate_pixels()
+            .filter(|(_, y, pixel)| *pixel != image.get_pixel(0, *y))
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        assert!(!drawn.is_empty());
+        // "09/03" is a single line centered on the screen
+        assert!(drawn.iter().all(|(_, y)| (70..130).contains(y)));
+    }
+}
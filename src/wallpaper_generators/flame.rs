@@ -0,0 +1,291 @@
+use super::super::configuration::Config;
+use super::chaos_game::play_chaos_game;
+use super::utils::{AstraImage, Operator, WallpaperGeneratorError, create_color_map};
+use image::{ImageBuffer, Rgb};
+use rand::{Rng, RngExt, rngs::StdRng};
+use rayon::iter::ParallelIterator;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+const DEFAULT_ITERATIONS: u64 = 10_000_000;
+/// Share of the warm-up points (on each side) left out of the bounds, so that the few points
+/// flung far away by the non-linear variations do not shrink the flame
+const OUTLIER_RATIO: f64 = 0.01;
+/// Resolution of the color index summed per pixel
+const COLOR_SCALE: f64 = 1024.0;
+const GAMMA: f64 = 2.2;
+
+/// Non-linear functions applied after the affine part of a flame transform
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlameVariation {
+    Linear,
+    Sinusoidal,
+    Spherical,
+    Swirl,
+}
+
+impl FlameVariation {
+    const ALL: [FlameVariation; 4] = [
+        FlameVariation::Linear,
+        FlameVariation::Sinusoidal,
+        FlameVariation::Spherical,
+        FlameVariation::Swirl,
+    ];
+
+    fn apply(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let r2 = x * x + y * y;
+        match self {
+            FlameVariation::Linear => (x, y),
+            FlameVariation::Sinusoidal => (x.sin(), y.sin()),
+            FlameVariation::Spherical => {
+                let r2 = r2.max(f64::EPSILON);
+                (x / r2, y / r2)
+            }
+            FlameVariation::Swirl => {
+                let (sin, cos) = r2.sin_cos();
+                (x * sin - y * cos, x * cos + y * sin)
+            }
+        }
+    }
+}
+
+/// Affine transform followed by a weighted blend of variations. Points moved by the transform are
+/// pulled toward its `color` (an index in the color map, `0..=1`).
+#[derive(Clone, Debug, PartialEq)]
+struct FlameTransform {
+    affine: [f64; 6],
+    variations: Vec<(FlameVariation, f64)>,
+    color: f64,
+}
+
+impl FlameTransform {
+    fn apply(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let [a, b, c, d, e, f] = self.affine;
+        let point = (a * x + b * y + e, c * x + d * y + f);
+        self.variations
+            .iter()
+            .fold((0.0, 0.0), |(sum_x, sum_y), (variation, weight)| {
+                let (vx, vy) = variation.apply(point);
+                (sum_x + weight * vx, sum_y + weight * vy)
+            })
+    }
+}
+
+pub fn generate_flame(config: &Config) -> Result<AstraImage, WallpaperGeneratorError> {
+    config.print_if_verbose("Generating fractal flame...");
+    let (width, height) = config
+        .screen_resolution()
+        .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+    config.print_if_verbose(format!("Detected screen resolution: {}x{}", width, height).as_str());
+
+    if config.respect_user_config {
+        config.print_if_verbose("User config detected with flame_gen options...");
+    }
+
+    let iterations = crate::respect_user_config_or_default!(config, flame_gen, iterations, {
+        Ok(DEFAULT_ITERATIONS)
+    })?;
+    let variations = match crate::respect_user_config_or_default!(config, flame_gen, variations, {
+        Ok(vec![])
+    })? {
+        variations if !variations.is_empty() => variations,
+        _ => FlameVariation::ALL.to_vec(),
+    };
+    let dark_mode = config
+        .is_dark_mode_active()
+        .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+
+    let seed = config
+        .respect_user_config
+        .then(|| config.flame_gen())
+        .flatten()
+        .and_then(|flame_gen| flame_gen.seed());
+    let imgbuf = render(
+        config,
+        (width, height),
+        iterations,
+        &variations,
+        dark_mode,
+        &mut config.generator_rng(seed),
+    )?;
+
+    config.print_if_verbose("Image generated!");
+    Ok(imgbuf)
+}
+
+fn render<R: Rng + ?Sized>(
+    config: &Config,
+    (width, height): (u32, u32),
+    iterations: u64,
+    variations: &[FlameVariation],
+    dark_mode: bool,
+    rng: &mut R,
+) -> Result<AstraImage, WallpaperGeneratorError> {
+    let should_respect_color_themes =
+        crate::respect_user_config_or_default!(config, flame_gen, respect_color_themes, {
+            Ok(false)
+        })?;
+    let theme = config.select_theme(should_respect_color_themes, rng);
+    let selected_theme = theme.selected();
+    config.print_if_verbose(format!("Selected theme: {selected_theme}").as_str());
+    let color_map = create_color_map(
+        Operator::Gradient,
+        config.color_steps(),
+        selected_theme.get_colors(dark_mode),
+    )?;
+
+    let transforms = random_transforms(variations, rng);
+    config.print_if_verbose(
+        format!(
+            "Plotting {iterations} points with {} transforms using {variations:?}...",
+            transforms.len()
+        )
+        .as_str(),
+    );
+    let histogram = accumulate_histogram(&transforms, width, height, iterations, rng.random())?;
+    Ok(tone_map(&histogram, width, height, &color_map))
+}
+
+/// Picks 3 to 5 transforms, each blending the given variations with random weights
+fn random_transforms<R: Rng + ?Sized>(
+    variations: &[FlameVariation],
+    rng: &mut R,
+) -> Vec<FlameTransform> {
+    let count = rng.random_range(3..=5);
+    (0..count)
+        .map(|i| {
+            let affine = std::array::from_fn(|_| rng.random_range(-1.0..=1.0));
+            let weights: Vec<f64> = variations.iter().map(|_| rng.random::<f64>()).collect();
+            let total = weights.iter().sum::<f64>().max(f64::EPSILON);
+            FlameTransform {
+                affine,
+                variations: variations
+                    .iter()
+                    .zip(weights)
+                    .map(|(variation, weight)| (*variation, weight / total))
+                    .collect(),
+                color: i as f64 / (count - 1) as f64,
+            }
+        })
+        .collect()
+}
+
+/// Hits and summed color index (scaled by `COLOR_SCALE`) of one pixel
+type Bucket = (u32, u64);
+
+/// Runs the chaos game and returns the histogram of the points that landed on each pixel (row
+/// major). The flame is scaled to fill the image while keeping its aspect ratio.
+fn accumulate_histogram(
+    transforms: &[FlameTransform],
+    width: u32,
+    height: u32,
+    iterations: u64,
+    seed: u64,
+) -> Result<Vec<Bucket>, WallpaperGeneratorError> {
+    if transforms.is_empty() {
+        return Err(WallpaperGeneratorError::ImageGeneration(
+            "flame needs at least one transform".to_string(),
+        ));
+    }
+    // Moves the point (and its color) with a random transform, restarting from a random point
+    // if it escaped to infinity
+    let step = |rng: &mut StdRng, (point, color): ((f64, f64), f64)| {
+        let transform = &transforms[rng.random_range(0..transforms.len())];
+        let next = transform.apply(point);
+        let next = if next.0.is_finite() && next.1.is_finite() {
+            next
+        } else {
+            (rng.random_range(-1.0..=1.0), rng.random_range(-1.0..=1.0))
+        };
+        (next, (color + transform.color) / 2.0)
+    };
+
+    let hits: Vec<AtomicU32> = (0..width as usize * height as usize)
+        .map(|_| AtomicU32::new(0))
+        .collect();
+    let colors: Vec<AtomicU64> = (0..width as usize * height as usize)
+        .map(|_| AtomicU64::new(0))
+        .collect();
+    play_chaos_game(
+        (width, height),
+        iterations,
+        seed,
+        OUTLIER_RATIO,
+        ((0.0, 0.0), 0.5),
+        step,
+        |index, (_, color)| {
+            hits[index].fetch_add(1, Ordering::Relaxed);
+            colors[index].fetch_add((color * COLOR_SCALE) as u64, Ordering::Relaxed);
+        },
+    )?;
+    Ok(hits
+        .into_iter()
+        .zip(colors)
+        .map(|(hits, color)| (hits.into_inner(), color.into_inner()))
+        .collect())
+}
+
+/// Colors each pixel with its average color index, faded by its log density (gamma corrected) so
+/// that sparse regions still glow
+fn tone_map(histogram: &[Bucket], width: u32, height: u32, color_map: &[[u8; 3]]) -> AstraImage {
+    let max = histogram
+        .iter()
+        .map(|(hits, _)| *hits)
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+    let mut imgbuf = ImageBuffer::new(width, height);
+    imgbuf.par_enumerate_pixels_mut().for_each(|(x, y, pixel)| {
+        let (hits, color) = histogram[y as usize * width as usize + x as usize];
+        if hits == 0 {
+            *pixel = Rgb([0, 0, 0]);
+            return;
+        }
+        let alpha = ((1.0 + hits as f64).ln() / (1.0 + max).ln()).powf(1.0 / GAMMA);
+        let index = (color as f64 / COLOR_SCALE / hits as f64).clamp(0.0, 1.0);
+        let [r, g, b] = color_map[(index * (color_map.len() - 1) as f64).round() as usize];
+        *pixel = Rgb([
+            (r as f64 * alpha) as u8,
+            (g as f64 * alpha) as u8,
+            (b as f64 * alpha) as u8,
+        ]);
+    });
+    imgbuf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded_config() -> Config {
+        let mut config = Config::from_json(
+            r#"{ "flame_gen": { "seed": 5, "iterations": 200000, "variations": ["sinusoidal", "swirl"] } }"#,
+        );
+        config.respect_user_config = true;
+        config.set_resolution_override(80, 60);
+        config.set_dark_mode_override(true);
+        config
+    }
+
+    #[test]
+    fn test_flame_is_reproducible_with_seed() {
+        let first = generate_flame(&seeded_config()).unwrap();
+        let second = generate_flame(&seeded_config()).unwrap();
+        assert_eq!(first.dimensions(), (80, 60));
+        assert_eq!(first, second);
+        // Something was drawn, and not on every pixel
+        assert!(first.pixels().any(|pixel| pixel.0 != [0, 0, 0]));
+        assert!(first.pixels().any(|pixel| pixel.0 == [0, 0, 0]));
+    }
+
+    #[test]
+    fn test_variations() {
+        let point = (2.0, 0.0);
+        assert_eq!(FlameVariation::Linear.apply(point), (2.0, 0.0));
+        assert_eq!(FlameVariation::Spherical.apply(point), (0.5, 0.0));
+        assert_eq!(FlameVariation::Sinusoidal.apply(point), (2f64.sin(), 0.0));
+        let (x, y) = FlameVariation::Swirl.apply(point);
+        assert!((x.hypot(y) - 2.0).abs() < 1e-9, "swirl keeps the radius");
+    }
+}
@@ -0,0 +1,263 @@
+use super::super::configuration::Config;
+use super::chaos_game::play_chaos_game;
+use super::utils::{AstraImage, Operator, WallpaperGeneratorError, create_color_map};
+use image::{ImageBuffer, Rgb};
+use rand::{Rng, RngExt, rngs::StdRng};
+use rayon::iter::ParallelIterator;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+const DEFAULT_ITERATIONS: u64 = 5_000_000;
+
+/// Affine transform `(x, y) -> (a*x + b*y + e, c*x + d*y + f)`, picked with a probability
+/// proportional to `weight`
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AffineTransform {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl AffineTransform {
+    const fn new(a: f64, b: f64, c: f64, d: f64, e: f64, f: f64, weight: f64) -> Self {
+        AffineTransform {
+            a,
+            b,
+            c,
+            d,
+            e,
+            f,
+            weight,
+        }
+    }
+
+    fn apply(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (
+            self.a * x + self.b * y + self.e,
+            self.c * x + self.d * y + self.f,
+        )
+    }
+}
+
+/// Built-in iterated function systems
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IfsPreset {
+    Fern,
+    Sierpinski,
+    Dragon,
+}
+
+impl IfsPreset {
+    const ALL: [IfsPreset; 3] = [IfsPreset::Fern, IfsPreset::Sierpinski, IfsPreset::Dragon];
+
+    fn transforms(&self) -> Vec<AffineTransform> {
+        match self {
+            // Barnsley fern
+            IfsPreset::Fern => vec![
+                AffineTransform::new(0.0, 0.0, 0.0, 0.16, 0.0, 0.0, 0.01),
+                AffineTransform::new(0.85, 0.04, -0.04, 0.85, 0.0, 1.6, 0.85),
+                AffineTransform::new(0.2, -0.26, 0.23, 0.22, 0.0, 1.6, 0.07),
+                AffineTransform::new(-0.15, 0.28, 0.26, 0.24, 0.0, 0.44, 0.07),
+            ],
+            IfsPreset::Sierpinski => vec![
+                AffineTransform::new(0.5, 0.0, 0.0, 0.5, 0.0, 0.0, 1.0),
+                AffineTransform::new(0.5, 0.0, 0.0, 0.5, 0.5, 0.0, 1.0),
+                AffineTransform::new(0.5, 0.0, 0.0, 0.5, 0.25, 0.5, 1.0),
+            ],
+            // Heighway dragon
+            IfsPreset::Dragon => vec![
+                AffineTransform::new(0.5, -0.5, 0.5, 0.5, 0.0, 0.0, 1.0),
+                AffineTransform::new(-0.5, -0.5, 0.5, -0.5, 1.0, 0.0, 1.0),
+            ],
+        }
+    }
+}
+
+pub fn generate_ifs(config: &Config) -> Result<AstraImage, WallpaperGeneratorError> {
+    config.print_if_verbose("Generating IFS fractal...");
+    let (width, height) = config
+        .screen_resolution()
+        .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+    config.print_if_verbose(format!("Detected screen resolution: {}x{}", width, height).as_str());
+
+    if config.respect_user_config {
+        config.print_if_verbose("User config detected with ifs_gen options...");
+    }
+
+    let iterations = crate::respect_user_config_or_default!(config, ifs_gen, iterations, {
+        Ok(DEFAULT_ITERATIONS)
+    })?;
+    let dark_mode = config
+        .is_dark_mode_active()
+        .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+
+    let seed = config
+        .respect_user_config
+        .then(|| config.ifs_gen())
+        .flatten()
+        .and_then(|ifs_gen| ifs_gen.seed());
+    let imgbuf = render(
+        config,
+        width,
+        height,
+        iterations,
+        dark_mode,
+        &mut config.generator_rng(seed),
+    )?;
+
+    config.print_if_verbose("Image generated!");
+    Ok(imgbuf)
+}
+
+fn render<R: Rng + ?Sized>(
+    config: &Config,
+    width: u32,
+    height: u32,
+    iterations: u64,
+    dark_mode: bool,
+    rng: &mut R,
+) -> Result<AstraImage, WallpaperGeneratorError> {
+    let transforms = match crate::respect_user_config_or_default!(config, ifs_gen, transforms, {
+        Ok(vec![])
+    })? {
+        transforms if !transforms.is_empty() => {
+            config.print_if_verbose("Using custom IFS transforms");
+            transforms
+        }
+        _ => {
+            let preset = crate::respect_user_config_or_default!(config, ifs_gen, preset, {
+                Ok(IfsPreset::ALL[rng.random_range(0..IfsPreset::ALL.len())])
+            })?;
+            config.print_if_verbose(format!("Selected preset: {preset:?}").as_str());
+            preset.transforms()
+        }
+    };
+
+    let should_respect_color_themes =
+        crate::respect_user_config_or_default!(config, ifs_gen, respect_color_themes, {
+            Ok(false)
+        })?;
+    let theme = config.select_theme(should_respect_color_themes, rng);
+    let selected_theme = theme.selected();
+    config.print_if_verbose(format!("Selected theme: {selected_theme}").as_str());
+    let color_map = create_color_map(
+        Operator::Gradient,
+        config.color_steps(),
+        selected_theme.get_colors(dark_mode),
+    )?;
+
+    config.print_if_verbose(format!("Plotting {iterations} points...").as_str());
+    let density = accumulate_density(&transforms, width, height, iterations, rng.random())?;
+    Ok(colorize(&density, width, height, &color_map))
+}
+
+/// Runs the chaos game and returns how many points landed on each pixel (row major).
+///
+/// The attractor is scaled to fill the image while keeping its aspect ratio, with the y axis
+/// pointing up.
+fn accumulate_density(
+    transforms: &[AffineTransform],
+    width: u32,
+    height: u32,
+    iterations: u64,
+    seed: u64,
+) -> Result<Vec<u32>, WallpaperGeneratorError> {
+    let total_weight: f64 = transforms.iter().map(|t| t.weight.max(0.0)).sum();
+    if total_weight <= 0.0 {
+        return Err(WallpaperGeneratorError::ImageGeneration(
+            "IFS transforms need at least one positive weight".to_string(),
+        ));
+    }
+    let step = |rng: &mut StdRng, point: (f64, f64)| {
+        let mut target = rng.random::<f64>() * total_weight;
+        transforms
+            .iter()
+            .find(|t| {
+                target -= t.weight.max(0.0);
+                target < 0.0
+            })
+            .unwrap_or(&transforms[transforms.len() - 1])
+            .apply(point)
+    };
+
+    let density: Vec<AtomicU32> = (0..width as usize * height as usize)
+        .map(|_| AtomicU32::new(0))
+        .collect();
+    play_chaos_game(
+        (width, height),
+        iterations,
+        seed,
+        0.0,
+        (0.0, 0.0),
+        step,
+        |index, _| {
+            density[index].fetch_add(1, Ordering::Relaxed);
+        },
+    )?;
+    Ok(density.into_iter().map(AtomicU32::into_inner).collect())
+}
+
+/// Maps hit counts to colors on a log scale so that sparse regions still glow
+fn colorize(density: &[u32], width: u32, height: u32, color_map: &[[u8; 3]]) -> AstraImage {
+    let max = density.iter().copied().max().unwrap_or(0).max(1) as f64;
+    let mut imgbuf = ImageBuffer::new(width, height);
+    imgbuf.par_enumerate_pixels_mut().for_each(|(x, y, pixel)| {
+        let count = density[y as usize * width as usize + x as usize] as f64;
+        let t = (1.0 + count).ln() / (1.0 + max).ln();
+        *pixel = Rgb(color_map[(t * (color_map.len() - 1) as f64).round() as usize]);
+    });
+    imgbuf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fern_density_is_concentrated_in_the_fern() {
+        let (width, height) = (120, 120);
+        let density =
+            accumulate_density(&IfsPreset::Fern.transforms(), width, height, 400_000, 1).unwrap();
+        let hits = |xs: std::ops::Range<u32>, ys: std::ops::Range<u32>| -> u64 {
+            ys.flat_map(|y| xs.clone().map(move |x| (x, y)))
+                .map(|(x, y)| density[(y * width + x) as usize] as u64)
+                .sum()
+        };
+        let total = hits(0..width, 0..height);
+        assert!(total > 0);
+        // The fern is taller than wide, so it is centered horizontally and leaves the sides empty
+        assert_eq!(hits(0..width / 4, 0..height), 0);
+        assert_eq!(hits(width * 3 / 4..width, 0..height), 0);
+        // The fern stands upright: points pile up toward its tip at the top and its stem
+        // reaches the bottom of the image
+        let lower = hits(0..width, height / 2..height);
+        let upper = hits(0..width, 0..height / 2);
+        assert!(upper > lower, "lower {lower} upper {upper}");
+        assert!(hits(width / 4..width * 3 / 4, height * 9 / 10..height) > 0);
+    }
+
+    #[test]
+    fn test_ifs_is_reproducible_with_seed() {
+        let transforms = IfsPreset::Dragon.transforms();
+        assert_eq!(
+            accumulate_density(&transforms, 64, 48, 50_000, 3).unwrap(),
+            accumulate_density(&transforms, 64, 48, 50_000, 3).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_ifs_rejects_zero_weights() {
+        let transforms = vec![AffineTransform::new(0.5, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0)];
+        assert!(accumulate_density(&transforms, 8, 8, 100, 0).is_err());
+    }
+}
@@ -1,14 +1,11 @@
-use super::super::{
-    configuration::Config,
-    os_implementations::{get_screen_resolution, is_dark_mode_active},
-    themes::ThemeSelector,
-};
+use super::super::{configuration::Config, logger::LogLevel};
 use super::utils::{AstraImage, Operator, WallpaperGeneratorError, create_color_map, scale_image};
-use crate::configuration::generators::julia::Appearance;
+use crate::configuration::generators::julia::{Appearance, JuliaConstant};
 use image::{ImageBuffer, Rgb};
 use num_complex::Complex;
-use rand::random_range;
+use rand::RngExt;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::f64::consts::TAU;
 
 const COMPLEX_NUMS: [(f64, f64); 13] = [
     (-0.79, 0.15),
@@ -26,10 +23,64 @@ const COMPLEX_NUMS: [(f64, f64); 13] = [
     (0.0, 0.8),
 ];
 
+/// Size of the region of the complex plane the hotspots are sampled in and zoomed from
+const DEFAULT_PLANE_WIDTH: f64 = 3.0;
+const DEFAULT_PLANE_HEIGHT: f64 = 3.5;
+/// Iterations before a point is considered inside the set, raised to match a longer color map
+/// (`color_steps`)
+const ESCAPE_ITERATIONS: usize = 255;
+
 pub fn generate_julia_set(config: &Config) -> Result<AstraImage, WallpaperGeneratorError> {
     config.print_if_verbose("Generating julia set...");
-    let (width, height) =
-        get_screen_resolution().map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+    let setup = setup_julia_set(config)?;
+    config.print_if_verbose("Generating image...");
+    let imgbuf = setup.render();
+    config.print_if_verbose("Image generated!");
+
+    Ok(imgbuf)
+}
+
+/// Radius of the circle the julia constant travels along in `astra animate julia`
+const ANIMATION_DRIFT_RADIUS: f64 = 0.02;
+
+/// Renders `frames` images of a julia set whose constant drifts along a small circle, so the
+/// last frame leads back into the first one
+pub fn animate_julia_set(
+    config: &Config,
+    frames: u32,
+) -> Result<Vec<AstraImage>, WallpaperGeneratorError> {
+    config.print_if_verbose("Generating julia set animation...");
+    let mut setup = setup_julia_set(config)?;
+    let (c, blend_to) = (setup.view.c, setup.view.blend_to);
+    Ok((0..frames)
+        .map(|frame| {
+            let angle = TAU * frame as f64 / frames as f64;
+            let drift = Complex::new(angle.cos() - 1.0, angle.sin()) * ANIMATION_DRIFT_RADIUS;
+            setup.view.c = c + drift;
+            setup.view.blend_to = blend_to.map(|blend_to| blend_to + drift);
+            setup.render()
+        })
+        .collect())
+}
+
+/// Everything picked from the configuration (and the rng) to render a julia set
+struct JuliaSetup {
+    view: JuliaView,
+    width: u32,
+    height: u32,
+    color_map: Vec<[u8; 3]>,
+}
+
+impl JuliaSetup {
+    fn render(&self) -> AstraImage {
+        render_julia(&self.view, self.width, self.height, &self.color_map)
+    }
+}
+
+fn setup_julia_set(config: &Config) -> Result<JuliaSetup, WallpaperGeneratorError> {
+    let (width, height) = config
+        .screen_resolution()
+        .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
     config.print_if_verbose(format!("Detected screen resolution: {}x{}", width, height).as_str());
 
     if config.respect_user_config {
@@ -41,9 +92,9 @@ pub fn generate_julia_set(config: &Config) -> Result<AstraImage, WallpaperGenera
             Ok(Appearance::Auto)
         })?;
     let dark_mode: bool = match appearance {
-        Appearance::Auto => {
-            is_dark_mode_active().map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?
-        }
+        Appearance::Auto => config
+            .is_dark_mode_active()
+            .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?,
         Appearance::Light => false,
         Appearance::Dark => true,
     };
@@ -54,60 +105,201 @@ pub fn generate_julia_set(config: &Config) -> Result<AstraImage, WallpaperGenera
         crate::respect_user_config_or_default!(config, julia_gen, respect_color_themes, {
             Ok(false)
         })?;
-    let theme = match (should_respect_color_themes, config.themes()) {
-        (true, Some(themes)) => themes.random().to_theme_selector(),
-        (true, None) | (false, _) => ThemeSelector::random(),
-    };
+    let theme = config.select_theme(should_respect_color_themes, &mut *config.rng());
     let selected_theme = theme.selected();
     config.print_if_verbose(format!("Selected theme: {selected_theme}",).as_str());
 
     let color_map = create_color_map(
         Operator::Gradient,
-        256,
+        config.color_steps(),
         selected_theme.get_colors(dark_mode),
-    );
+    )?;
 
     // Setup
+    let blend_constants = config
+        .respect_user_config
+        .then(|| config.julia_gen())
+        .flatten()
+        .and_then(|julia_gen| julia_gen.blend_constants());
+    let (selected_julia_set, blend_to) = match blend_constants {
+        Some([(left_re, left_im), (right_re, right_im)]) => {
+            let (left, right) = (
+                Complex::new(left_re, left_im),
+                Complex::new(right_re, right_im),
+            );
+            config.print_if_verbose(format!("Blending julia sets: {left} to {right}").as_str());
+            (left, Some(right))
+        }
+        None => (select_julia_constant(config)?, None),
+    };
+
+    let (scale_x, scale_y, start_x, start_y) = match configured_plane(config)? {
+        Some((plane_width, plane_height, center)) => {
+            config.print_if_verbose(
+                format!("Showing a {plane_width}x{plane_height} region centered on {center:?}")
+                    .as_str(),
+            );
+            scale_image(plane_width, plane_height, center, 1.0)
+        }
+        None => {
+            // Find hotspots (around the middle of a blend) and randomly select one
+            let sampled_julia_set = match blend_to {
+                Some(blend_to) => (selected_julia_set + blend_to) / 2.0,
+                None => selected_julia_set,
+            };
+            let points_weights = sample_julia_set(config, sampled_julia_set, width, height)?;
+            let complex_hotspot =
+                points_weights[config.rng().random_range(0..points_weights.len())].0;
+            config.print_if_verbose(format!("Selected hotspot: {}", complex_hotspot).as_str());
+
+            let focus_pt = (complex_hotspot.re, complex_hotspot.im);
+            scale_image(
+                DEFAULT_PLANE_WIDTH,
+                DEFAULT_PLANE_HEIGHT,
+                focus_pt,
+                config.rng().random_range(1.0..10.0),
+            )
+        }
+    };
+    let view = JuliaView {
+        c: selected_julia_set,
+        blend_to,
+        scale_x,
+        scale_y,
+        start_x,
+        start_y,
+    };
+    Ok(JuliaSetup {
+        view,
+        width,
+        height,
+        color_map,
+    })
+}
+
+/// Width, height and center of a region of the complex plane
+type PlaneRegion = (f64, f64, (f64, f64));
+
+/// Region set with `julia_gen.plane_width`, `plane_height` and `plane_center` (the unset ones
+/// default to the sampled region centered on the origin). `None` when none of them is set, the
+/// region is then a random zoom on a hotspot.
+fn configured_plane(config: &Config) -> Result<Option<PlaneRegion>, WallpaperGeneratorError> {
+    let Some(julia_gen) = config
+        .respect_user_config
+        .then(|| config.julia_gen())
+        .flatten()
+    else {
+        return Ok(None);
+    };
+    let (plane_width, plane_height, center) = (
+        julia_gen.plane_width(),
+        julia_gen.plane_height(),
+        julia_gen.plane_center(),
+    );
+    if plane_width.is_none() && plane_height.is_none() && center.is_none() {
+        return Ok(None);
+    }
+    let size = |value: Option<f64>, default: f64, key: &str| match value.unwrap_or(default) {
+        size if size.is_finite() && size > 0.0 => Ok(size),
+        _ => Err(WallpaperGeneratorError::ImageGeneration(format!(
+            "julia_gen.{key} must be greater than 0"
+        ))),
+    };
+    Ok(Some((
+        size(plane_width, DEFAULT_PLANE_WIDTH, "plane_width")?,
+        size(plane_height, DEFAULT_PLANE_HEIGHT, "plane_height")?,
+        center.unwrap_or((0.0, 0.0)),
+    )))
+}
+
+/// Picks one of `julia_gen.complex_numbers` (or of the built-in constants)
+fn select_julia_constant(config: &Config) -> Result<Complex<f64>, WallpaperGeneratorError> {
     let complex_numbers =
         crate::respect_user_config_or_default!(config, julia_gen, complex_numbers, {
-            Ok(COMPLEX_NUMS.to_vec())
+            Ok(COMPLEX_NUMS.into_iter().map(JuliaConstant::from).collect())
         })?;
-    let (re, im) = complex_numbers[random_range(0..complex_numbers.len())];
-    let selected_julia_set = Complex::new(re, im);
-    config.print_if_verbose(format!("Selected julia set: {}", selected_julia_set).as_str());
-
-    // Find hotspots and randomly select one
-    let points_weights = sample_julia_set(config, selected_julia_set, width, height)?;
-    let complex_hotspot = points_weights[random_range(0..points_weights.len())].0;
-    config.print_if_verbose(format!("Selected hotspot: {}", complex_hotspot).as_str());
-
-    let focus_pt = (complex_hotspot.re, complex_hotspot.im);
-    let (scale_x, scale_y, start_x, start_y) =
-        scale_image(3.0, 3.5, focus_pt, random_range(1.0..10.0));
-    let mut imgbuf = ImageBuffer::new(width, height);
-    config.print_if_verbose("Generating image...");
+    if complex_numbers.is_empty() {
+        return Err(WallpaperGeneratorError::ImageGeneration(
+            "julia_gen.complex_numbers is empty".to_string(),
+        ));
+    }
+    let constant = &complex_numbers[config.rng().random_range(0..complex_numbers.len())];
+    let selected_julia_set = Complex::new(constant.re, constant.im);
+    match &constant.name {
+        Some(name) => {
+            config.print_if_verbose(
+                format!("Selected julia set: {name} ({selected_julia_set})").as_str(),
+            );
+            config.log(
+                LogLevel::Info,
+                format!("Julia constant: {constant}").as_str(),
+            );
+        }
+        None => {
+            config.print_if_verbose(format!("Selected julia set: {}", selected_julia_set).as_str())
+        }
+    }
+    Ok(selected_julia_set)
+}
 
-    // Generate full julia set
-    imgbuf.par_enumerate_pixels_mut().for_each(|(x, y, pixel)| {
-        let cx = x as f64 * (scale_x / width as f64) + start_x;
-        let cy = y as f64 * (scale_y / height as f64) + start_y;
+/// Region of the complex plane mapped onto the image
+struct JuliaView {
+    c: Complex<f64>,
+    // When set, the constant goes from `c` on the left edge to this one on the right edge
+    blend_to: Option<Complex<f64>>,
+    scale_x: f64,
+    scale_y: f64,
+    start_x: f64,
+    start_y: f64,
+}
+
+impl JuliaView {
+    /// Constant used for the pixels of column `x`
+    fn constant_at(&self, x: u32, width: u32) -> Complex<f64> {
+        match self.blend_to {
+            Some(blend_to) => {
+                let t = x as f64 / (width.max(2) - 1) as f64;
+                self.c * (1.0 - t) + blend_to * t
+            }
+            None => self.c,
+        }
+    }
 
-        let c = selected_julia_set;
-        let mut z = Complex::new(cx, cy);
+    /// Point of the complex plane at the top left corner of pixel (`x`, `y`)
+    fn point_at(&self, x: u32, y: u32, width: u32, height: u32) -> Complex<f64> {
+        Complex::new(
+            x as f64 * (self.scale_x / width as f64) + self.start_x,
+            y as f64 * (self.scale_y / height as f64) + self.start_y,
+        )
+    }
 
+    fn color_at(&self, x: u32, y: u32, width: u32, height: u32, color_map: &[[u8; 3]]) -> Rgb<u8> {
+        let c = self.constant_at(x, width);
+        let mut z = self.point_at(x, y, width, height);
+        let last_color = color_map.len() - 1;
+        let max_iterations = last_color.max(ESCAPE_ITERATIONS);
         let mut i = 0;
-        while i < 255 && z.norm() <= 2.0 {
+        while i < max_iterations && z.norm() <= 2.0 {
             z = z * z + c;
             i += 1;
         }
-        *pixel = Rgb(color_map[i]);
-    });
-
-    config.print_if_verbose("Image generated!");
+        Rgb(color_map[i * last_color / max_iterations])
+    }
+}
 
-    Ok(imgbuf)
+fn render_julia(view: &JuliaView, width: u32, height: u32, color_map: &[[u8; 3]]) -> AstraImage {
+    let mut imgbuf = ImageBuffer::new(width, height);
+    imgbuf.par_enumerate_pixels_mut().for_each(|(x, y, pixel)| {
+        *pixel = view.color_at(x, y, width, height, color_map);
+    });
+    imgbuf
 }
 
+/// Segments the screen height is split into on the first sampling pass (`julia_gen.segments`)
+const DEFAULT_SAMPLE_SEGMENTS: u32 = 10;
+/// Sampling passes (each with more segments and a lower threshold) before giving up
+const SAMPLE_BACKOFF_MAX: u8 = 15;
+
 fn sample_julia_set(
     config: &Config,
     c: Complex<f64>,
@@ -120,29 +312,59 @@ fn sample_julia_set(
             Ok(200u8)
         })?;
 
+    // More segments than pixel rows would only sample the same points again
+    let segments = crate::respect_user_config_or_default!(config, julia_gen, segments, {
+        Ok(DEFAULT_SAMPLE_SEGMENTS)
+    })?
+    .clamp(1, height.max(1));
+
     let mut points_weights = vec![];
     let mut backoff_count: u32 = 0;
-    let backoff_max: u8 = 15;
-    let threshold_decrease = dynamic_threshold_for_point_to_be_selected / backoff_max;
-    let segments: u32 = 10;
-    let aspect_ratio = (width as f64 / height as f64).round() as u32;
+    let threshold_decrease = dynamic_threshold_for_point_to_be_selected / SAMPLE_BACKOFF_MAX;
+    // max(1) keeps portrait screens (ratio rounding to 0) from producing no columns at all
+    let aspect_ratio = ((width as f64 / height as f64).round() as u32).max(1);
 
-    while points_weights.is_empty() && backoff_count < backoff_max as u32 {
+    while points_weights.is_empty() && backoff_count < SAMPLE_BACKOFF_MAX as u32 {
         // Algorithm
-        let num_height_segments: u32 = segments * (backoff_count + 1);
-        let num_width_segments = aspect_ratio * num_height_segments;
-        let x_interval = width / num_width_segments;
-        let y_interval = height / num_height_segments;
-        let scaled_x = 3.0 / width as f64;
-        let scaled_y = 3.5 / height as f64;
-
-        let points: Vec<(Complex<f64>, u32)> = (0..(num_width_segments * num_height_segments))
+        let num_height_segments = segments
+            .saturating_mul(backoff_count + 1)
+            .min(height.max(1));
+        let num_width_segments = aspect_ratio
+            .saturating_mul(num_height_segments)
+            .min(width.max(1));
+        let num_samples = num_width_segments
+            .checked_mul(num_height_segments)
+            .ok_or_else(|| {
+                WallpaperGeneratorError::ImageGeneration(format!(
+                    "too many sampling segments for a {width}x{height} image"
+                ))
+            })?;
+        // Intervals are at least a pixel, even when there are more segments than pixels
+        let x_interval = (width / num_width_segments).max(1);
+        let y_interval = (height / num_height_segments).max(1);
+        let scaled_x = DEFAULT_PLANE_WIDTH / width as f64;
+        let scaled_y = DEFAULT_PLANE_HEIGHT / height as f64;
+        // Jitter is generated up front so the sampled points only depend on the config's rng
+        // (and not on the order rayon evaluates the points in)
+        let jitter: Vec<(u32, u32)> = {
+            let mut rng = config.rng();
+            (0..num_samples)
+                .map(|_| {
+                    (
+                        // max(1) avoids an empty range once segments shrink to a pixel
+                        rng.random_range(0..(x_interval / 2).max(1)),
+                        rng.random_range(0..(y_interval / 2).max(1)),
+                    )
+                })
+                .collect()
+        };
+
+        let points: Vec<(Complex<f64>, u32)> = (0..num_samples)
             .into_par_iter()
             .map(|iteration| {
-                let x = x_interval * (iteration % num_width_segments)
-                    + random_range(0..(x_interval / 2));
-                let y = y_interval * (iteration / num_width_segments)
-                    + random_range(0..(y_interval / 2));
+                let (jitter_x, jitter_y) = jitter[iteration as usize];
+                let x = x_interval * (iteration % num_width_segments) + jitter_x;
+                let y = y_interval * (iteration / num_width_segments) + jitter_y;
                 let cx = x as f64 * scaled_x;
                 let cy = y as f64 * scaled_y;
                 // debug!("ITERATION: {} - x: {}, y: {}, cx: {}, cy: {}", iteration, x, y, cx, cy);
@@ -176,11 +398,138 @@ fn sample_julia_set(
 mod tests {
     use crate::configuration::Config;
 
+    #[test]
+    fn test_theme_seed_keeps_theme_while_seed_changes_view() {
+        let setups: Vec<_> = (0..4)
+            .map(|seed| {
+                let mut config = Config::from_json("{}");
+                config.set_resolution_override(80, 60);
+                config.set_dark_mode_override(false);
+                config.seed_rng(seed);
+                config.seed_theme_rng(7);
+                let setup = super::setup_julia_set(&config).unwrap();
+                (config.image_theme_name().unwrap(), setup.view)
+            })
+            .collect();
+        assert!(setups.iter().all(|(theme, _)| *theme == setups[0].0));
+        let views: std::collections::HashSet<String> = setups
+            .iter()
+            .map(|(_, view)| format!("{} {} {}", view.c, view.start_x, view.scale_x))
+            .collect();
+        assert!(views.len() > 1);
+    }
+
     #[test]
     fn test_sample_julia_set() {
+        let points = super::sample_julia_set(
+            &Config::new(false, None),
+            super::Complex::new(0.4, 0.4),
+            800,
+            600,
+        )
+        .unwrap();
+        assert!(!points.is_empty());
+    }
+
+    #[test]
+    fn test_sample_julia_set_at_tiny_resolution() {
+        // more segments than pixels
+        let mut config = Config::from_json(r#"{ "julia_gen": { "segments": 40 } }"#);
+        config.respect_user_config = true;
+        for (width, height) in [(10, 10), (3, 10), (1, 1)] {
+            let points =
+                super::sample_julia_set(&config, super::Complex::new(-0.8, 0.156), width, height)
+                    .unwrap();
+            assert!(!points.is_empty(), "{width}x{height}");
+        }
+    }
+
+    #[test]
+    fn test_huge_segments_are_clamped_to_the_image() {
+        let mut config = Config::from_json(&format!(
+            r#"{{ "julia_gen": {{ "segments": {} }} }}"#,
+            u32::MAX
+        ));
+        config.respect_user_config = true;
         let points =
-            super::sample_julia_set(&Config::new(false), super::Complex::new(0.4, 0.4), 800, 600)
-                .unwrap();
+            super::sample_julia_set(&config, super::Complex::new(-0.8, 0.156), 80, 60).unwrap();
         assert!(!points.is_empty());
+        assert!(points.len() <= 80 * 60);
+    }
+
+    #[test]
+    fn test_sample_julia_set_is_reproducible_with_seed() {
+        let mut config_a = Config::new(false, None);
+        config_a.seed_rng(42);
+        let mut config_b = Config::new(false, None);
+        config_b.seed_rng(42);
+        let c = super::Complex::new(0.4, 0.4);
+        assert_eq!(
+            super::sample_julia_set(&config_a, c, 800, 600).unwrap(),
+            super::sample_julia_set(&config_b, c, 800, 600).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_plane_bounds_map_corners_to_complex_coordinates() {
+        let mut config = Config::from_json(
+            r#"{ "julia_gen": { "plane_center": [-0.5, 0.25], "plane_width": 2.0, "plane_height": 1.0 } }"#,
+        );
+        config.respect_user_config = true;
+        config.set_resolution_override(200, 100);
+        config.set_dark_mode_override(false);
+        let view = super::setup_julia_set(&config).unwrap().view;
+
+        let close = |a: super::Complex<f64>, (re, im): (f64, f64)| {
+            (a.re - re).abs() < 1e-9 && (a.im - im).abs() < 1e-9
+        };
+        assert!(close(view.point_at(0, 0, 200, 100), (-1.5, -0.25)));
+        assert!(close(view.point_at(199, 0, 200, 100), (0.49, -0.25)));
+        assert!(close(view.point_at(0, 99, 200, 100), (-1.5, 0.74)));
+        assert!(close(view.point_at(199, 99, 200, 100), (0.49, 0.74)));
+        // the far edges of the image are the far edges of the region
+        assert!(close(view.point_at(200, 100, 200, 100), (0.5, 0.75)));
+    }
+
+    #[test]
+    fn test_invalid_plane_size_is_an_error() {
+        let mut config = Config::from_json(r#"{ "julia_gen": { "plane_width": 0.0 } }"#);
+        config.respect_user_config = true;
+        config.set_resolution_override(20, 10);
+        config.set_dark_mode_override(false);
+        assert!(super::setup_julia_set(&config).is_err());
+    }
+
+    #[test]
+    fn test_blend_constants_reach_both_endpoints() {
+        let (left, right) = (
+            super::Complex::new(-0.79, 0.15),
+            super::Complex::new(0.285, 0.01),
+        );
+        let view_with = |c, blend_to| super::JuliaView {
+            c,
+            blend_to,
+            scale_x: 3.0,
+            scale_y: 3.5,
+            start_x: -1.5,
+            start_y: -1.75,
+        };
+        let blend = view_with(left, Some(right));
+        let (width, height) = (64, 48);
+        assert_eq!(blend.constant_at(0, width), left);
+        assert_eq!(blend.constant_at(width - 1, width), right);
+
+        let color_map: Vec<[u8; 3]> = (0..=255).map(|i| [i as u8, 255 - i as u8, 0]).collect();
+        let blended = super::render_julia(&blend, width, height, &color_map);
+        let only_left = super::render_julia(&view_with(left, None), width, height, &color_map);
+        let only_right = super::render_julia(&view_with(right, None), width, height, &color_map);
+        for y in 0..height {
+            assert_eq!(blended.get_pixel(0, y), only_left.get_pixel(0, y));
+            assert_eq!(
+                blended.get_pixel(width - 1, y),
+                only_right.get_pixel(width - 1, y)
+            );
+        }
+        assert_ne!(blended, only_left);
     }
 }
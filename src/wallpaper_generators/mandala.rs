@@ -0,0 +1,219 @@
+use super::super::configuration::Config;
+use super::utils::{AstraImage, Operator, WallpaperGeneratorError, create_color_map};
+use image::{ImageBuffer, Rgb};
+use rand::{Rng, RngExt};
+use rayon::iter::ParallelIterator;
+use std::f64::consts::{PI, TAU};
+
+const DEFAULT_SYMMETRY: u8 = 8;
+const NOISE_OCTAVES: u32 = 5;
+/// Number of noise features across the shortest side of the screen
+const NOISE_FEATURES: f64 = 6.0;
+
+pub fn generate_mandala(config: &Config) -> Result<AstraImage, WallpaperGeneratorError> {
+    config.print_if_verbose("Generating mandala...");
+    let (width, height) = config
+        .screen_resolution()
+        .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+    config.print_if_verbose(format!("Detected screen resolution: {}x{}", width, height).as_str());
+
+    if config.respect_user_config {
+        config.print_if_verbose("User config detected with mandala_gen options...");
+    }
+
+    let symmetry = crate::respect_user_config_or_default!(config, mandala_gen, symmetry, {
+        Ok(DEFAULT_SYMMETRY)
+    })?
+    .max(1);
+    let mirror = crate::respect_user_config_or_default!(config, mandala_gen, mirror, { Ok(true) })?;
+    let dark_mode = config
+        .is_dark_mode_active()
+        .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+
+    let seed = config
+        .respect_user_config
+        .then(|| config.mandala_gen())
+        .flatten()
+        .and_then(|mandala_gen| mandala_gen.seed());
+    let imgbuf = render(
+        config,
+        width,
+        height,
+        symmetry,
+        mirror,
+        dark_mode,
+        &mut config.generator_rng(seed),
+    )?;
+
+    config.print_if_verbose("Image generated!");
+    Ok(imgbuf)
+}
+
+fn render<R: Rng + ?Sized>(
+    config: &Config,
+    width: u32,
+    height: u32,
+    symmetry: u8,
+    mirror: bool,
+    dark_mode: bool,
+    rng: &mut R,
+) -> Result<AstraImage, WallpaperGeneratorError> {
+    let should_respect_color_themes =
+        crate::respect_user_config_or_default!(config, mandala_gen, respect_color_themes, {
+            Ok(false)
+        })?;
+    let theme = config.select_theme(should_respect_color_themes, rng);
+    let selected_theme = theme.selected();
+    config.print_if_verbose(format!("Selected theme: {selected_theme}").as_str());
+    let color_map = create_color_map(
+        Operator::Gradient,
+        config.color_steps(),
+        selected_theme.get_colors(dark_mode),
+    )?;
+    config.print_if_verbose(format!("Symmetry: {symmetry} wedges, mirrored: {mirror}").as_str());
+
+    let noise_seed = rng.random::<u64>();
+    let feature_size = width.min(height) as f64 / NOISE_FEATURES;
+    let (center_x, center_y) = (width as f64 / 2.0, height as f64 / 2.0);
+
+    let mut imgbuf = ImageBuffer::new(width, height);
+    imgbuf.par_enumerate_pixels_mut().for_each(|(x, y, pixel)| {
+        let (wedge_x, wedge_y) = to_wedge(
+            x as f64 + 0.5 - center_x,
+            y as f64 + 0.5 - center_y,
+            symmetry,
+            mirror,
+        );
+        let value = fractal_noise(noise_seed, wedge_x / feature_size, wedge_y / feature_size);
+        *pixel = Rgb(color_map[(value * (color_map.len() - 1) as f64).round() as usize]);
+    });
+    Ok(imgbuf)
+}
+
+/// Maps a point (relative to the center) to the matching point in the first wedge, so that every
+/// wedge shows the same base pattern. With `mirror`, each wedge is also reflected around its
+/// middle (like a kaleidoscope) so that neighboring wedges join seamlessly.
+fn to_wedge(dx: f64, dy: f64, symmetry: u8, mirror: bool) -> (f64, f64) {
+    let wedge_angle = TAU / symmetry as f64;
+    let radius = dx.hypot(dy);
+    let angle = dy.atan2(dx) + PI;
+    let mut local_angle = angle.rem_euclid(wedge_angle);
+    if mirror && local_angle > wedge_angle / 2.0 {
+        local_angle = wedge_angle - local_angle;
+    }
+    (radius * local_angle.cos(), radius * local_angle.sin())
+}
+
+/// Smooth value noise summed over several octaves, in `0..=1`
+fn fractal_noise(seed: u64, x: f64, y: f64) -> f64 {
+    let (mut total, mut amplitude, mut frequency, mut max) = (0.0, 1.0, 1.0, 0.0);
+    for octave in 0..NOISE_OCTAVES {
+        total += value_noise(
+            seed.wrapping_add(octave as u64),
+            x * frequency,
+            y * frequency,
+        ) * amplitude;
+        max += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    (total / max).clamp(0.0, 1.0)
+}
+
+fn value_noise(seed: u64, x: f64, y: f64) -> f64 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let smooth = |t: f64| t * t * (3.0 - 2.0 * t);
+    let (tx, ty) = (smooth(x - x0), smooth(y - y0));
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+    lerp(
+        lerp(lattice(seed, x0, y0), lattice(seed, x0 + 1, y0), tx),
+        lerp(lattice(seed, x0, y0 + 1), lattice(seed, x0 + 1, y0 + 1), tx),
+        ty,
+    )
+}
+
+/// Pseudo random value in `0..=1` for a lattice point
+fn lattice(seed: u64, x: i64, y: i64) -> f64 {
+    let mut h = seed
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    h ^= h >> 33;
+    (h >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::themes::ColorTheme;
+    use image::imageops::rotate90;
+
+    fn seeded_config(symmetry: u8, mirror: bool) -> Config {
+        let mut config = Config::from_json(
+            format!(
+                r#"{{ "mandala_gen": {{ "seed": 11, "symmetry": {symmetry}, "mirror": {mirror} }} }}"#
+            )
+            .as_str(),
+        );
+        config.respect_user_config = true;
+        config.set_resolution_override(64, 64);
+        config.set_dark_mode_override(true);
+        config
+    }
+
+    fn max_channel_difference(a: &AstraImage, b: &AstraImage) -> u8 {
+        a.pixels()
+            .zip(b.pixels())
+            .flat_map(|(p, q)| (0..3).map(move |c| p.0[c].abs_diff(q.0[c])))
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn test_4_fold_mandala_is_invariant_under_90_degree_rotation() {
+        for mirror in [false, true] {
+            let image = generate_mandala(&seeded_config(4, mirror)).unwrap();
+            let rotated = rotate90(&image);
+            assert!(
+                max_channel_difference(&image, &rotated) <= 2,
+                "mirror: {mirror}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_3_fold_mandala_is_not_invariant_under_90_degree_rotation() {
+        let image = generate_mandala(&seeded_config(3, false)).unwrap();
+        assert!(max_channel_difference(&image, &rotate90(&image)) > 2);
+    }
+
+    #[test]
+    fn test_inline_palette_is_used_as_theme() {
+        let mut config = seeded_config(4, true);
+        let palette = ColorTheme::from_palette("#001f3f, #0074D9,#7FDBFF").unwrap();
+        config.set_theme_from_palette(palette);
+        let theme = config.select_theme(false, &mut *config.rng());
+        let color_map = create_color_map(
+            Operator::Gradient,
+            config.color_steps(),
+            theme.selected().get_colors(true),
+        )
+        .unwrap();
+        assert_eq!(color_map.first(), Some(&[0x00, 0x1f, 0x3f]));
+        assert_eq!(color_map.last(), Some(&[0x7f, 0xdb, 0xff]));
+
+        let image = generate_mandala(&config).unwrap();
+        assert!(image.pixels().all(|pixel| color_map.contains(&pixel.0)));
+        assert!(ColorTheme::from_palette("#001f3f,not-a-color").is_err());
+    }
+
+    #[test]
+    fn test_fractal_noise_range() {
+        for i in 0..100 {
+            let value = fractal_noise(3, i as f64 * 0.37, i as f64 * 0.11);
+            assert!((0.0..=1.0).contains(&value));
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use super::utils::{AstraImage, WallpaperGeneratorError};
+use crate::cli::Generator;
+use crate::configuration::Config;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Key of the text chunk holding the program that wrote the image (a standard PNG keyword)
+const SOFTWARE_KEY: &str = "Software";
+const GENERATOR_KEY: &str = "astra:generator";
+const SEED_KEY: &str = "astra:seed";
+const THEME_SEED_KEY: &str = "astra:theme_seed";
+const THEME_KEY: &str = "astra:theme";
+const CONFIG_KEY: &str = "astra:config";
+
+/// Key/value pairs describing how `generator` made the current image: the generator and its
+/// arguments, the seeds, the theme and the generator's settings from the configuration file
+pub(super) fn generation_metadata(config: &Config, generator: &Generator) -> Vec<(String, String)> {
+    let mut metadata = vec![
+        (
+            SOFTWARE_KEY.to_string(),
+            format!("astra {}", env!("CARGO_PKG_VERSION")),
+        ),
+        (GENERATOR_KEY.to_string(), format!("{generator:?}")),
+        (SEED_KEY.to_string(), config.seed().to_string()),
+    ];
+    if let Some(theme_seed) = config.theme_seed() {
+        metadata.push((THEME_SEED_KEY.to_string(), theme_seed.to_string()));
+    }
+    if let Some(theme) = config.image_theme_name() {
+        metadata.push((THEME_KEY.to_string(), theme));
+    }
+    if let Some(section) = config.generator_section(generator) {
+        metadata.push((CONFIG_KEY.to_string(), section.to_string()));
+    }
+    metadata
+}
+
+/// Writes `image` as a PNG to `path` with `metadata` as text chunks (tEXt, or iTXt for values
+/// that are not Latin-1)
+pub(super) fn save_png_with_metadata(
+    image: &AstraImage,
+    path: &Path,
+    metadata: &[(String, String)],
+) -> Result<(), WallpaperGeneratorError> {
+    let file = File::create(path).map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+    write_png_with_metadata(image, BufWriter::new(file), metadata)
+}
+
+/// Writes `image` as a PNG to `writer` along with how `generator` made it (`astra generate
+/// --stdout`)
+pub fn write_png(
+    config: &Config,
+    generator: &Generator,
+    image: &AstraImage,
+    writer: impl Write,
+) -> Result<(), WallpaperGeneratorError> {
+    write_png_with_metadata(image, writer, &generation_metadata(config, generator))
+}
+
+fn write_png_with_metadata(
+    image: &AstraImage,
+    writer: impl Write,
+    metadata: &[(String, String)],
+) -> Result<(), WallpaperGeneratorError> {
+    let mut encoder = png::Encoder::new(writer, image.width(), image.height());
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    for (key, value) in metadata {
+        let added = if value.chars().all(|c| (c as u32) < 256) {
+            encoder.add_text_chunk(key.clone(), value.clone())
+        } else {
+            encoder.add_itxt_chunk(key.clone(), value.clone())
+        };
+        added.map_err(|_| WallpaperGeneratorError::ImageSave)?;
+    }
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(image.as_raw()))
+        .map_err(|_| WallpaperGeneratorError::ImageSave)
+}
+
+/// Text chunks of the PNG at `path`, in the order they are stored (printed by `astra inspect`)
+pub fn read_png_metadata(path: &Path) -> Result<Vec<(String, String)>, WallpaperGeneratorError> {
+    let file = File::open(path).map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+    let reader = png::Decoder::new(BufReader::new(file))
+        .read_info()
+        .map_err(|e| WallpaperGeneratorError::Parse(format!("{} - {e}", path.display())))?;
+    let info = reader.info();
+    let mut metadata: Vec<(String, String)> = info
+        .uncompressed_latin1_text
+        .iter()
+        .map(|chunk| (chunk.keyword.clone(), chunk.text.clone()))
+        .collect();
+    for chunk in &info.compressed_latin1_text {
+        let text = chunk
+            .get_text()
+            .map_err(|e| WallpaperGeneratorError::Parse(e.to_string()))?;
+        metadata.push((chunk.keyword.clone(), text));
+    }
+    for chunk in &info.utf8_text {
+        let text = chunk
+            .get_text()
+            .map_err(|e| WallpaperGeneratorError::Parse(e.to_string()))?;
+        metadata.push((chunk.keyword.clone(), text));
+    }
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::SolidMode;
+    use image::{ImageBuffer, Rgb};
+
+    #[test]
+    fn test_generation_metadata_round_trips_through_png() {
+        let mut config = Config::from_json(r#"{ "solid_gen": { "respect_color_themes": true } }"#);
+        config.respect_user_config = true;
+        config.seed_rng(42);
+        let generator = Generator::Solid {
+            mode: SolidMode::Random,
+        };
+        let metadata = generation_metadata(&config, &generator);
+        let image: AstraImage = ImageBuffer::from_pixel(4, 3, Rgb([10, 20, 30]));
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("solid.png");
+        save_png_with_metadata(&image, &path, &metadata).unwrap();
+
+        let read = read_png_metadata(&path).unwrap();
+        assert_eq!(read, metadata);
+        assert!(read.contains(&(SEED_KEY.to_string(), "42".to_string())));
+        assert!(read.contains(&(
+            CONFIG_KEY.to_string(),
+            r#"{"respect_color_themes":true}"#.to_string()
+        )));
+        assert_eq!(image::open(&path).unwrap().to_rgb8(), image);
+    }
+
+    #[test]
+    fn test_written_png_decodes_to_image() {
+        let config = Config::from_json("{}");
+        let image: AstraImage = ImageBuffer::from_fn(7, 5, |x, y| Rgb([x as u8 * 30, y as u8, 9]));
+        let mut bytes = vec![];
+        write_png(&config, &Generator::Julia, &image, &mut bytes).unwrap();
+
+        let decoded = image::load_from_memory(&bytes).unwrap().to_rgb8();
+        assert_eq!(decoded.dimensions(), (7, 5));
+        assert_eq!(decoded, image);
+    }
+}
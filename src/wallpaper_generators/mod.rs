@@ -1,13 +1,51 @@
+mod animation;
+mod bench;
 mod bing_spotlight;
+mod chaos_game;
+mod color_harmony;
+mod compare;
+mod contact_sheet;
+mod curated_colors;
+mod datetime;
+mod flame;
+mod ifs;
 mod julia;
+mod mandala;
+mod metadata;
 mod nasa_apod;
+mod post_process;
+mod recent;
 mod solid_color;
+mod text;
+mod transaction;
+mod transition;
+mod truchet;
 mod utils;
 
-pub use bing_spotlight::generate_bing_spotlight;
+pub use animation::{MAX_ANIMATION_FRAMES, animation_resolution, encode_gif, generate_animation};
+pub use bench::benchmark_generators;
+pub use bing_spotlight::{SpotlightFit, generate_bing_spotlight};
+pub use color_harmony::{HarmonyScheme, parse_color};
+pub use compare::compare_images;
+pub use contact_sheet::create_contact_sheet;
+pub use curated_colors::HueFamily;
+pub use datetime::generate_datetime;
+pub use flame::{FlameVariation, generate_flame};
+pub use ifs::{AffineTransform, IfsPreset, generate_ifs};
 pub use julia::generate_julia_set;
+pub use mandala::generate_mandala;
+pub use metadata::{read_png_metadata, write_png};
 pub use nasa_apod::{ApodDate, generate_nasa_apod, parse_yymmdd};
-pub use solid_color::{Color, generate_solid_color};
+pub use post_process::{RetroPalette, WatermarkPosition, apply_post_processing, dominant_colors};
+pub use recent::{PREVIEW_COLUMNS, apply_recent, recent_wallpapers, terminal_preview};
+pub use solid_color::{Color, GradientDirection, SolidThemeMode, generate_solid_color};
+pub use text::{TextAlignment, TextOrder, generate_text};
+pub use transaction::Transaction;
+pub use truchet::{TruchetStyle, generate_truchet};
+pub(crate) use utils::write_atomically;
 pub use utils::{
-    AstraImage, WallpaperGeneratorError, average_color, delete_wallpapers, handle_generate_options,
+    AstraImage, FilenameScheme, OriginalImage, UpscaleFilter, WallpaperFormat,
+    WallpaperGeneratorError, apply_wallpaper_file, average_color, check_apply_to,
+    delete_wallpapers, generate_or_fallback, generate_with_fallback, generate_with_timeout,
+    handle_generate_options, reset_data, wallpaper_folder_path,
 };
@@ -1,7 +1,7 @@
 use super::super::configuration::Config;
-use super::utils::{AstraImage, WallpaperGeneratorError, download_image_to_memory};
+use super::utils::{AstraImage, WallpaperGeneratorError, download_image_to_memory, load_image};
 use chrono::{Local, NaiveDate};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 /// Generates a wallpaper from the NASA Astronomy Picutre of the Day website. The website
 /// provides a photo of the day, which is used as the wallpaper.
@@ -86,9 +86,7 @@ pub fn generate_nasa_apod(
     let html = download_page_html(&url)?;
     let img_download_link = format!("{base}/{}", retrieve_image_download_url(html)?);
     let downloaded_img = download_image_to_memory(config, &img_download_link)?;
-    Ok(image::load_from_memory(downloaded_img.as_slice())
-        .map_err(|e| WallpaperGeneratorError::ImageGeneration(e.to_string()))?
-        .to_rgb8())
+    load_image(config, &downloaded_img)
 }
 
 /// Helper to download the html content of a webpage
@@ -212,6 +210,15 @@ impl<'de> Deserialize<'de> for ApodDate {
     }
 }
 
+impl Serialize for ApodDate {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
 impl std::fmt::Display for ApodDate {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:02}{:02}{:02}", self.year, self.month, self.day)
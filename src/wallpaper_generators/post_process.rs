@@ -0,0 +1,599 @@
+use super::super::configuration::Config;
+use super::utils::{AstraImage, mix_color, upscale_to_output_resolution};
+use chrono::{Local, Timelike};
+use image::{Rgb, RgbaImage, imageops};
+use rayon::iter::ParallelIterator;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::TAU;
+
+/// Share of the red and blue channels added/removed at a color temperature of ±100
+const MAX_TEMPERATURE_SHIFT: f64 = 0.3;
+/// Strongest shift used by `temperature_schedule` (coolest at midday, warmest at midnight)
+const SCHEDULED_TEMPERATURE_AMPLITUDE: f64 = 50.0;
+/// Gap between the watermark and the edges, as a share of the shortest side of the wallpaper
+const WATERMARK_MARGIN: f64 = 0.02;
+
+/// Applies every post-processing effect defined in the user's `post_process` configuration to
+/// the generated image, then stamps the `watermark` if one is configured. Effects are applied in a
+/// fixed order so that results are predictable.
+///
+/// Post-processing is applied to every generated wallpaper (both `astra` and `astra generate`).
+pub fn apply_post_processing(config: &Config, image: AstraImage) -> AstraImage {
+    // effects apply to the final resolution, e.g. quantizing before upscaling would blur the palette
+    let mut image = upscale_to_output_resolution(config, image);
+    if let Some(post_process) = config.post_process() {
+        let temperature = if post_process.temperature_schedule().unwrap_or(false) {
+            let now = Local::now();
+            Some(scheduled_temperature(
+                now.hour() as f64 + now.minute() as f64 / 60.0,
+            ))
+        } else {
+            post_process.color_temperature()
+        };
+        if let Some(temperature) = temperature.filter(|t| *t != 0) {
+            config.print_if_verbose(
+                format!("Shifting color temperature by {temperature}...").as_str(),
+            );
+            shift_color_temperature(&mut image, temperature);
+        }
+
+        if let Some(quantize) = post_process.quantize() {
+            let palette = match (quantize.palette(), quantize.colors()) {
+                (Some(retro_palette), _) => {
+                    config.print_if_verbose(
+                        format!("Quantizing image to {retro_palette:?} palette...").as_str(),
+                    );
+                    retro_palette.colors().to_vec()
+                }
+                (None, Some(colors)) => {
+                    config.print_if_verbose(
+                        format!("Quantizing image to {colors} colors...").as_str(),
+                    );
+                    median_cut_palette(&image, colors as usize)
+                }
+                (None, None) => {
+                    config.print_if_verbose(
+                        "WARN: quantize requires either a palette or colors, skipping quantize",
+                    );
+                    vec![]
+                }
+            };
+            if !palette.is_empty() {
+                quantize_image(&mut image, &palette, quantize.dither().unwrap_or(false));
+            }
+        }
+    }
+
+    // stamped last so that the effects leave the logo's colors untouched
+    stamp_watermark(config, &mut image);
+    image
+}
+
+/// Corner of the wallpaper the `watermark` is stamped in
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+/// Loads the configured `watermark` and stamps it on the image. A watermark that cannot be loaded
+/// is skipped (with a verbose warning) so that the wallpaper is still set.
+fn stamp_watermark(config: &Config, image: &mut AstraImage) {
+    let Some(watermark) = config.watermark() else {
+        return;
+    };
+    let path = watermark.path();
+    let logo = match image::open(path) {
+        Ok(logo) => logo.to_rgba8(),
+        Err(e) => {
+            config.print_if_verbose(
+                format!(
+                    "WARN: unable to load watermark {} - {e}, skipping it",
+                    path.display()
+                )
+                .as_str(),
+            );
+            return;
+        }
+    };
+    let scale = watermark.scale().unwrap_or(1.0).max(0.0);
+    let logo = if scale == 1.0 {
+        logo
+    } else {
+        imageops::resize(
+            &logo,
+            ((logo.width() as f64 * scale).round() as u32).max(1),
+            ((logo.height() as f64 * scale).round() as u32).max(1),
+            imageops::FilterType::Lanczos3,
+        )
+    };
+    let position = watermark.position().unwrap_or_default();
+    config.print_if_verbose(format!("Stamping watermark in the {position:?} corner...").as_str());
+    apply_watermark(
+        image,
+        &logo,
+        position,
+        watermark.opacity().unwrap_or(1.0).clamp(0.0, 1.0),
+    );
+}
+
+/// Blends `logo` into the `position` corner of the image, leaving a margin of
+/// `WATERMARK_MARGIN`. The transparency of each pixel of the logo is multiplied by `opacity`.
+fn apply_watermark(
+    image: &mut AstraImage,
+    logo: &RgbaImage,
+    position: WatermarkPosition,
+    opacity: f64,
+) {
+    let (width, height) = (image.width() as i64, image.height() as i64);
+    let margin = (width.min(height) as f64 * WATERMARK_MARGIN).round() as i64;
+    let left = match position {
+        WatermarkPosition::TopLeft | WatermarkPosition::BottomLeft => margin,
+        WatermarkPosition::TopRight | WatermarkPosition::BottomRight => {
+            width - logo.width() as i64 - margin
+        }
+    };
+    let top = match position {
+        WatermarkPosition::TopLeft | WatermarkPosition::TopRight => margin,
+        WatermarkPosition::BottomLeft | WatermarkPosition::BottomRight => {
+            height - logo.height() as i64 - margin
+        }
+    };
+    for (x, y, pixel) in logo.enumerate_pixels() {
+        let (x, y) = (left + x as i64, top + y as i64);
+        let alpha = pixel[3] as f64 / 255.0 * opacity;
+        if alpha <= 0.0 || !(0..width).contains(&x) || !(0..height).contains(&y) {
+            continue;
+        }
+        let target = image.get_pixel_mut(x as u32, y as u32);
+        target.0 = mix_color(target.0, [pixel[0], pixel[1], pixel[2]], alpha);
+    }
+}
+
+/// Shifts the white balance of the image: positive `temperature` (up to `100`) warms the image by
+/// scaling red up and blue down, negative (down to `-100`) cools it
+pub(super) fn shift_color_temperature(image: &mut AstraImage, temperature: i8) {
+    let shift = temperature.clamp(-100, 100) as f64 / 100.0 * MAX_TEMPERATURE_SHIFT;
+    image.par_pixels_mut().for_each(|pixel| {
+        let [r, g, b] = pixel.0;
+        let scale = |channel: u8, factor: f64| (channel as f64 * factor).round().clamp(0.0, 255.0);
+        *pixel = Rgb([scale(r, 1.0 + shift) as u8, g, scale(b, 1.0 - shift) as u8]);
+    });
+}
+
+/// Color temperature for the local `hour` (`0.0..24.0`): cool around midday, warm at night
+fn scheduled_temperature(hour: f64) -> i8 {
+    let daylight = ((hour - 12.0) / 24.0 * TAU).cos();
+    (-daylight * SCHEDULED_TEMPERATURE_AMPLITUDE).round() as i8
+}
+
+/// Named retro palettes that can be used to quantize an image
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetroPalette {
+    /// The 4 shades of green used by the original Game Boy
+    Gameboy,
+    /// The full 16 color CGA palette
+    Cga,
+    /// The NES (2C02) palette
+    Nes,
+}
+
+impl RetroPalette {
+    pub fn colors(&self) -> &'static [[u8; 3]] {
+        match self {
+            RetroPalette::Gameboy => &[[15, 56, 15], [48, 98, 48], [139, 172, 15], [155, 188, 15]],
+            RetroPalette::Cga => &[
+                [0, 0, 0],
+                [0, 0, 170],
+                [0, 170, 0],
+                [0, 170, 170],
+                [170, 0, 0],
+                [170, 0, 170],
+                [170, 85, 0],
+                [170, 170, 170],
+                [85, 85, 85],
+                [85, 85, 255],
+                [85, 255, 85],
+                [85, 255, 255],
+                [255, 85, 85],
+                [255, 85, 255],
+                [255, 255, 85],
+                [255, 255, 255],
+            ],
+            RetroPalette::Nes => &[
+                [124, 124, 124],
+                [0, 0, 252],
+                [0, 0, 188],
+                [68, 40, 188],
+                [148, 0, 132],
+                [168, 0, 32],
+                [168, 16, 0],
+                [136, 20, 0],
+                [80, 48, 0],
+                [0, 120, 0],
+                [0, 104, 0],
+                [0, 88, 0],
+                [0, 64, 88],
+                [0, 0, 0],
+                [188, 188, 188],
+                [0, 120, 248],
+                [0, 88, 248],
+                [104, 68, 252],
+                [216, 0, 204],
+                [228, 0, 88],
+                [248, 56, 0],
+                [228, 92, 16],
+                [172, 124, 0],
+                [0, 184, 0],
+                [0, 168, 0],
+                [0, 168, 68],
+                [0, 136, 136],
+                [248, 248, 248],
+                [60, 188, 252],
+                [104, 136, 252],
+                [152, 120, 248],
+                [248, 120, 248],
+                [248, 88, 152],
+                [248, 120, 88],
+                [252, 160, 68],
+                [248, 184, 0],
+                [184, 248, 24],
+                [88, 216, 84],
+                [88, 248, 152],
+                [0, 232, 216],
+                [120, 120, 120],
+                [252, 252, 252],
+                [164, 228, 252],
+                [184, 184, 248],
+                [216, 184, 248],
+                [248, 184, 248],
+                [248, 164, 192],
+                [240, 208, 176],
+                [252, 224, 168],
+                [248, 216, 120],
+                [216, 248, 120],
+                [184, 248, 184],
+                [184, 248, 216],
+                [0, 252, 252],
+                [248, 216, 248],
+            ],
+        }
+    }
+}
+
+/// Maps every pixel of the image to the nearest color of the palette.
+///
+/// When `dither` is true, Floyd-Steinberg error diffusion is used which gives the classic retro
+/// look. Error diffusion is inherently sequential, so the dithered path does not run in parallel.
+pub(super) fn quantize_image(image: &mut AstraImage, palette: &[[u8; 3]], dither: bool) {
+    if dither {
+        floyd_steinberg_dither(image, palette);
+    } else {
+        image.par_pixels_mut().for_each(|pixel| {
+            *pixel = Rgb(nearest_color(pixel.0, palette));
+        });
+    }
+}
+
+/// Returns the color of the palette closest to `color` (squared euclidean distance in RGB space)
+///
+/// # Panics
+///
+/// Panics if `palette` is empty
+pub(super) fn nearest_color(color: [u8; 3], palette: &[[u8; 3]]) -> [u8; 3] {
+    *palette
+        .iter()
+        .min_by_key(|candidate| color_distance(color, **candidate))
+        .expect("palette must contain at least one color")
+}
+
+fn color_distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+    let dr = a[0] as i32 - b[0] as i32;
+    let dg = a[1] as i32 - b[1] as i32;
+    let db = a[2] as i32 - b[2] as i32;
+    (dr * dr + dg * dg + db * db) as u32
+}
+
+fn floyd_steinberg_dither(image: &mut AstraImage, palette: &[[u8; 3]]) {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    // Working copy in f32 so accumulated error can go out of the 0-255 range
+    let mut buffer: Vec<[f32; 3]> = image
+        .pixels()
+        .map(|p| [p[0] as f32, p[1] as f32, p[2] as f32])
+        .collect();
+
+    for y in 0..height {
+        for x in 0..width {
+            let idx = y * width + x;
+            let old = buffer[idx];
+            let clamped = [
+                old[0].clamp(0.0, 255.0) as u8,
+                old[1].clamp(0.0, 255.0) as u8,
+                old[2].clamp(0.0, 255.0) as u8,
+            ];
+            let new = nearest_color(clamped, palette);
+            image.put_pixel(x as u32, y as u32, Rgb(new));
+            let error = [
+                old[0] - new[0] as f32,
+                old[1] - new[1] as f32,
+                old[2] - new[2] as f32,
+            ];
+
+            let mut diffuse = |dx: isize, dy: usize, weight: f32| {
+                let nx = x as isize + dx;
+                let ny = y + dy;
+                if nx >= 0 && (nx as usize) < width && ny < height {
+                    let n = &mut buffer[ny * width + nx as usize];
+                    for c in 0..3 {
+                        n[c] += error[c] * weight;
+                    }
+                }
+            };
+            diffuse(1, 0, 7.0 / 16.0);
+            diffuse(-1, 1, 3.0 / 16.0);
+            diffuse(0, 1, 5.0 / 16.0);
+            diffuse(1, 1, 1.0 / 16.0);
+        }
+    }
+}
+
+/// Builds a palette of at most `size` colors representative of the image using the median cut
+/// algorithm. The image's colors are repeatedly split along the channel with the widest range
+/// and each resulting bucket is averaged into one palette color.
+pub(super) fn median_cut_palette(image: &AstraImage, size: usize) -> Vec<[u8; 3]> {
+    let size = size.max(1);
+    let mut buckets: Vec<Vec<[u8; 3]>> = vec![image.pixels().map(|p| p.0).collect()];
+
+    while buckets.len() < size {
+        // Split the bucket with the largest channel range
+        let Some((bucket_idx, channel, range)) = buckets
+            .iter()
+            .enumerate()
+            .filter(|(_, bucket)| bucket.len() > 1)
+            .map(|(i, bucket)| {
+                let (channel, range) = widest_channel(bucket);
+                (i, channel, range)
+            })
+            .max_by_key(|(_, _, range)| *range)
+        else {
+            break;
+        };
+        if range == 0 {
+            break;
+        }
+        let mut bucket = buckets.swap_remove(bucket_idx);
+        bucket.sort_unstable_by_key(|color| color[channel]);
+        let upper = bucket.split_off(bucket.len() / 2);
+        buckets.push(bucket);
+        buckets.push(upper);
+    }
+
+    buckets
+        .iter()
+        .filter(|bucket| !bucket.is_empty())
+        .map(|bucket| {
+            let mut sum = [0u64; 3];
+            for color in bucket {
+                for c in 0..3 {
+                    sum[c] += color[c] as u64;
+                }
+            }
+            let n = bucket.len() as u64;
+            [(sum[0] / n) as u8, (sum[1] / n) as u8, (sum[2] / n) as u8]
+        })
+        .collect()
+}
+
+/// Clusters the image's colors into at most `count` groups with k-means (starting from the median
+/// cut palette) and returns each group's color with the share of the image it covers (`0..=1`),
+/// largest share first. Large images are sampled to keep the analysis fast.
+pub fn dominant_colors(image: &AstraImage, count: usize) -> Vec<([u8; 3], f64)> {
+    const MAX_SAMPLES: usize = 100_000;
+    const ROUNDS: usize = 10;
+
+    let step = (image.len() / 3).div_ceil(MAX_SAMPLES).max(1);
+    let samples: Vec<[u8; 3]> = image.pixels().step_by(step).map(|p| p.0).collect();
+    if samples.is_empty() {
+        return vec![];
+    }
+    let mut centroids = median_cut_palette(image, count);
+    let mut assignments = vec![0; samples.len()];
+    for _ in 0..ROUNDS {
+        let mut changed = false;
+        for (sample, assignment) in samples.iter().zip(assignments.iter_mut()) {
+            let nearest = (0..centroids.len())
+                .min_by_key(|i| color_distance(*sample, centroids[*i]))
+                .unwrap_or(0);
+            changed |= nearest != *assignment;
+            *assignment = nearest;
+        }
+        let mut sums = vec![[0u64; 4]; centroids.len()];
+        for (sample, assignment) in samples.iter().zip(&assignments) {
+            let sum = &mut sums[*assignment];
+            (0..3).for_each(|c| sum[c] += sample[c] as u64);
+            sum[3] += 1;
+        }
+        for (centroid, sum) in centroids.iter_mut().zip(&sums) {
+            if sum[3] > 0 {
+                *centroid = [0, 1, 2].map(|c| (sum[c] / sum[3]) as u8);
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut sizes = vec![0usize; centroids.len()];
+    assignments
+        .iter()
+        .for_each(|assignment| sizes[*assignment] += 1);
+    let mut colors: Vec<([u8; 3], f64)> = centroids
+        .into_iter()
+        .zip(sizes)
+        .filter(|(_, size)| *size > 0)
+        .map(|(color, size)| (color, size as f64 / samples.len() as f64))
+        .collect();
+    colors.sort_by(|a, b| b.1.total_cmp(&a.1));
+    colors
+}
+
+/// Returns the channel index with the widest range of values and the size of that range
+fn widest_channel(colors: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|c| {
+            let min = colors.iter().map(|color| color[c]).min().unwrap_or(0);
+            let max = colors.iter().map(|color| color[c]).max().unwrap_or(0);
+            (c, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or((0, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgba};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_watermark_blends_logo_into_target_corner_only() {
+        let background = [20, 40, 60];
+        let mut image: AstraImage = ImageBuffer::from_pixel(50, 50, Rgb(background));
+        let logo = RgbaImage::from_pixel(4, 3, Rgba([220, 200, 180, 255]));
+        apply_watermark(&mut image, &logo, WatermarkPosition::BottomRight, 0.5);
+
+        // margin of 2% of 50 pixels, from the bottom right corner
+        let (xs, ys) = (45..49, 46..49);
+        for (x, y, pixel) in image.enumerate_pixels() {
+            if xs.contains(&x) && ys.contains(&y) {
+                assert_eq!(
+                    pixel.0,
+                    [120, 120, 120],
+                    "half way between logo and background"
+                );
+            } else {
+                assert_eq!(
+                    pixel.0, background,
+                    "pixel ({x}, {y}) outside the corner changed"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_missing_watermark_is_skipped() {
+        let config = Config::from_json(r#"{ "watermark": { "path": "/nonexistent/logo.png" } }"#);
+        let image = gradient(16, 16);
+        assert_eq!(apply_post_processing(&config, image.clone()), image);
+    }
+
+    fn gradient(width: u32, height: u32) -> AstraImage {
+        ImageBuffer::from_fn(width, height, |x, _| {
+            let v = (x * 255 / (width - 1)) as u8;
+            Rgb([v, v / 2, 255 - v])
+        })
+    }
+
+    fn distinct_colors(image: &AstraImage) -> HashSet<[u8; 3]> {
+        image.pixels().map(|p| p.0).collect()
+    }
+
+    #[test]
+    fn test_quantize_gradient_to_4_color_palette() {
+        let mut image = gradient(256, 8);
+        quantize_image(&mut image, RetroPalette::Gameboy.colors(), false);
+        let colors = distinct_colors(&image);
+        assert!(colors.len() <= 4);
+        assert!(
+            colors
+                .iter()
+                .all(|c| RetroPalette::Gameboy.colors().contains(c))
+        );
+    }
+
+    #[test]
+    fn test_quantize_gradient_to_4_color_palette_with_dither() {
+        let mut image = gradient(256, 8);
+        quantize_image(&mut image, RetroPalette::Gameboy.colors(), true);
+        assert!(distinct_colors(&image).len() <= 4);
+    }
+
+    #[test]
+    fn test_dominant_colors_of_half_red_half_blue_image() {
+        let image: AstraImage = ImageBuffer::from_fn(40, 10, |x, y| {
+            // a little noise so that the clusters are not single colors
+            let noise = ((x + y) % 3) as u8;
+            if x < 20 {
+                Rgb([250 - noise, noise, noise])
+            } else {
+                Rgb([noise, noise, 250 - noise])
+            }
+        });
+        let colors = dominant_colors(&image, 2);
+        assert_eq!(colors.len(), 2);
+        let near = |color: [u8; 3], target: [u8; 3]| color_distance(color, target) < 30;
+        assert!(colors.iter().any(|(color, _)| near(*color, [250, 0, 0])));
+        assert!(colors.iter().any(|(color, _)| near(*color, [0, 0, 250])));
+        assert!(colors.iter().all(|(_, share)| (share - 0.5).abs() < 0.01));
+    }
+
+    #[test]
+    fn test_median_cut_palette_respects_size() {
+        let image = gradient(256, 8);
+        let palette = median_cut_palette(&image, 4);
+        assert_eq!(palette.len(), 4);
+        let mut quantized = image.clone();
+        quantize_image(&mut quantized, &palette, false);
+        assert!(distinct_colors(&quantized).len() <= 4);
+    }
+
+    #[test]
+    fn test_median_cut_palette_single_color_image() {
+        let image: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb([10, 20, 30]));
+        assert_eq!(median_cut_palette(&image, 16), vec![[10, 20, 30]]);
+    }
+
+    fn average_channels(image: &AstraImage) -> [f64; 3] {
+        let count = image.pixels().len() as f64;
+        let mut sum = [0.0; 3];
+        image
+            .pixels()
+            .for_each(|p| (0..3).for_each(|c| sum[c] += p.0[c] as f64));
+        sum.map(|s| s / count)
+    }
+
+    #[test]
+    fn test_warm_shift_increases_red_and_decreases_blue() {
+        let mut image = gradient(64, 4);
+        let before = average_channels(&image);
+        shift_color_temperature(&mut image, 60);
+        let after = average_channels(&image);
+        assert!(after[0] > before[0]);
+        assert_eq!(after[1], before[1]);
+        assert!(after[2] < before[2]);
+
+        let mut cooled = gradient(64, 4);
+        shift_color_temperature(&mut cooled, -60);
+        let cooled = average_channels(&cooled);
+        assert!(cooled[0] < before[0] && cooled[2] > before[2]);
+    }
+
+    #[test]
+    fn test_scheduled_temperature_is_cool_at_midday_and_warm_at_night() {
+        assert!(scheduled_temperature(12.0) < 0);
+        assert!(scheduled_temperature(0.0) > 0);
+        assert!(scheduled_temperature(21.0) > scheduled_temperature(15.0));
+    }
+
+    #[test]
+    fn test_nearest_color() {
+        let palette = [[0, 0, 0], [255, 255, 255]];
+        assert_eq!(nearest_color([30, 30, 30], &palette), [0, 0, 0]);
+        assert_eq!(nearest_color([200, 220, 210], &palette), [255, 255, 255]);
+    }
+}
@@ -0,0 +1,137 @@
+use super::super::configuration::{AppliedWallpaper, Config, State};
+use super::utils::{AstraImage, WallpaperGeneratorError};
+use image::imageops;
+use std::{error::Error, fmt::Write, path::PathBuf};
+
+/// Width in terminal columns of the previews printed by `astra recent`
+pub const PREVIEW_COLUMNS: u32 = 32;
+
+/// Wallpapers listed by `astra recent`: the `count` most recently applied ones, the most recent
+/// first
+pub fn recent_wallpapers(state: &State, count: usize) -> &[AppliedWallpaper] {
+    let history = state.history();
+    &history[..count.min(history.len())]
+}
+
+/// Sets the wallpaper listed at `index` (from 1, as printed by `astra recent`) again with
+/// `set_wallpaper`. Fails if there is no such entry or if its file was deleted (e.g. by
+/// `auto_clean`).
+///
+/// Returns the wallpaper that was applied.
+pub fn apply_recent(
+    config: &Config,
+    history: &[AppliedWallpaper],
+    index: usize,
+    set_wallpaper: impl FnOnce(&Config, PathBuf) -> Result<(), Box<dyn Error>>,
+) -> Result<AppliedWallpaper, Box<dyn Error>> {
+    let wallpaper = index
+        .checked_sub(1)
+        .and_then(|index| history.get(index))
+        .ok_or_else(|| {
+            WallpaperGeneratorError::Parse(format!(
+                "no recent wallpaper at index {index}, {} recorded (the first one is 1)",
+                history.len()
+            ))
+        })?;
+    if !wallpaper.path.is_file() {
+        return Err(WallpaperGeneratorError::OS(format!(
+            "{} no longer exists",
+            wallpaper.path.display()
+        ))
+        .into());
+    }
+    config.print_if_verbose(format!("Applying {}...", wallpaper.path.display()).as_str());
+    set_wallpaper(config, wallpaper.path.clone())?;
+    Ok(wallpaper.clone())
+}
+
+/// Renders `image` for a terminal with 24-bit colors, `columns` wide. Each character shows two
+/// pixels stacked vertically (upper half block with a foreground and a background color).
+pub fn terminal_preview(image: &AstraImage, columns: u32) -> String {
+    let columns = columns.max(1);
+    let rows = ((columns as f64 * image.height() as f64 / image.width().max(1) as f64).round()
+        as u32)
+        .max(2)
+        .div_ceil(2)
+        * 2;
+    let thumbnail = imageops::resize(image, columns, rows, imageops::FilterType::Triangle);
+    let mut preview = String::new();
+    for y in (0..rows).step_by(2) {
+        for x in 0..columns {
+            let [r, g, b] = thumbnail.get_pixel(x, y).0;
+            let [br, bg, bb] = thumbnail.get_pixel(x, y + 1).0;
+            let _ = write!(
+                preview,
+                "\x1b[38;2;{r};{g};{b}m\x1b[48;2;{br};{bg};{bb}m\u{2580}"
+            );
+        }
+        preview.push_str("\x1b[0m\n");
+    }
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+    use std::cell::RefCell;
+
+    fn history_in(dir: &std::path::Path) -> State {
+        let mut state = State::default();
+        for generator in ["julia", "solid", "flame"] {
+            let path = dir.join(format!("{generator}.png"));
+            std::fs::write(&path, b"image").unwrap();
+            state.record_applied(AppliedWallpaper::now(path, generator));
+        }
+        state
+    }
+
+    #[test]
+    fn test_recent_wallpapers_lists_most_recent_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = history_in(dir.path());
+        let generators = |list: &[AppliedWallpaper]| {
+            list.iter()
+                .map(|applied| applied.generator.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            generators(recent_wallpapers(&state, 10)),
+            ["flame", "solid", "julia"]
+        );
+        assert_eq!(generators(recent_wallpapers(&state, 2)), ["flame", "solid"]);
+        assert!(recent_wallpapers(&State::default(), 5).is_empty());
+    }
+
+    #[test]
+    fn test_apply_recent_sets_wallpaper_at_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = history_in(dir.path());
+        let config = Config::from_json("{}");
+        let applied = RefCell::new(vec![]);
+        let set_wallpaper = |_: &Config, path: PathBuf| {
+            applied.borrow_mut().push(path);
+            Ok(())
+        };
+
+        let wallpaper = apply_recent(&config, state.history(), 2, set_wallpaper).unwrap();
+        assert_eq!(wallpaper.generator, "solid");
+        assert_eq!(*applied.borrow(), vec![dir.path().join("solid.png")]);
+
+        for index in [0, 4] {
+            assert!(apply_recent(&config, state.history(), index, set_wallpaper).is_err());
+        }
+        std::fs::remove_file(dir.path().join("julia.png")).unwrap();
+        assert!(apply_recent(&config, state.history(), 3, set_wallpaper).is_err());
+        assert_eq!(applied.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_terminal_preview_has_one_line_per_two_rows() {
+        let image: AstraImage = ImageBuffer::from_pixel(64, 36, Rgb([10, 20, 30]));
+        let preview = terminal_preview(&image, 16);
+        assert_eq!(preview.lines().count(), 5);
+        assert!(preview.starts_with("\x1b[38;2;10;20;30m\x1b[48;2;10;20;30m\u{2580}"));
+        assert!(preview.lines().all(|line| line.ends_with("\x1b[0m")));
+    }
+}
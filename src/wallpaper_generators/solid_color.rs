@@ -1,13 +1,14 @@
 use super::super::{
     cli::SolidMode, configuration::Config, configuration::generators::julia::Appearance,
-    os_implementations::get_screen_resolution, os_implementations::is_dark_mode_active,
-    themes::ThemeSelector,
+    themes::ColorTheme,
 };
-use super::utils::{AstraImage, WallpaperGeneratorError};
+use super::color_harmony::{harmony_color, parse_color, random_pleasant_color, rotate_hue};
+use super::curated_colors::pick_curated_color;
+use super::utils::{AstraImage, Operator, WallpaperGeneratorError, create_color_map, mix_color};
 use clap::ValueEnum;
 use image::{ImageBuffer, Rgb};
-use rand::{RngExt, rng};
-use serde::Deserialize;
+use rand::{Rng, RngExt};
+use serde::{Deserialize, Serialize};
 
 pub fn generate_solid_color(
     config: &Config,
@@ -15,11 +16,13 @@ pub fn generate_solid_color(
 ) -> Result<AstraImage, WallpaperGeneratorError> {
     config.print_if_verbose("Generating solid color image...");
 
-    let (width, height) =
-        get_screen_resolution().map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+    let (width, height) = config
+        .screen_resolution()
+        .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
     config.print_if_verbose(format!("Detected screen resolution: {}x{}", width, height).as_str());
 
-    if config.respect_user_config {
+    // A theme forced with `--theme-from-file` takes precedence over the solid mode
+    if config.respect_user_config || config.has_theme_override() {
         config.print_if_verbose("User config detected with solid_gen options...");
 
         // Current setup will always prefer user_theme to config setup, need to decide if this is desired behavior
@@ -27,10 +30,7 @@ pub fn generate_solid_color(
             crate::respect_user_config_or_default!(config, solid_gen, respect_color_themes, {
                 Ok(false)
             })?;
-        let theme = match (should_respect_color_themes, config.themes()) {
-            (true, Some(themes)) => themes.random().to_theme_selector(),
-            (true, None) | (false, _) => ThemeSelector::random(),
-        };
+        let theme = config.select_theme(should_respect_color_themes, &mut *config.rng());
         let selected_theme = theme.selected();
         // TODO: this feels weird, why is solid using julia_gen config? Fix this
         let appearance: Appearance =
@@ -38,17 +38,32 @@ pub fn generate_solid_color(
                 Ok(Appearance::Auto)
             })?;
         let dark_mode: bool = match appearance {
-            Appearance::Auto => {
-                is_dark_mode_active().map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?
-            }
+            Appearance::Auto => config
+                .is_dark_mode_active()
+                .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?,
             Appearance::Light => false,
             Appearance::Dark => true,
         };
         config.print_if_verbose(format!("Selected theme: {selected_theme}",).as_str());
-        let [r, g, b] = selected_theme
-            .average_color(dark_mode)
-            .map_err(|e| WallpaperGeneratorError::ImageGeneration(e.to_string()))?;
-        let imgbuf = generate_image(&SolidMode::Rgb { r, g, b }, width, height);
+        let theme_mode = crate::respect_user_config_or_default!(config, solid_gen, theme_mode, {
+            Ok(SolidThemeMode::Average)
+        })?;
+        let theme_color = theme_color(selected_theme, dark_mode, theme_mode, &mut *config.rng())?;
+        let [r, g, b] = match config.solid_gen().and_then(|solid_gen| solid_gen.harmony()) {
+            Some(scheme) => {
+                config.print_if_verbose(
+                    format!("Using {scheme:?} harmony of the theme's color").as_str(),
+                );
+                harmony_color(theme_color, scheme, &mut *config.rng())
+            }
+            None => theme_color,
+        };
+        let imgbuf = generate_image(
+            &SolidMode::Rgb { r, g, b },
+            width,
+            height,
+            &mut *config.rng(),
+        )?;
         config.print_if_verbose("Image generated!");
         return Ok(imgbuf);
     }
@@ -79,43 +94,169 @@ pub fn generate_solid_color(
             "read preferred_default_colors & preferred_rgb_colors config, but none were found",
         );
         // Use mode passed in instead since no config setup
-        generate_image(mode, width, height)
+        generate_image(mode, width, height, &mut *config.rng())?
     } else {
         config.print_if_verbose(
             "selecting random mode based on preferred_default_colors & preferred_rgb_colors config",
         );
-        let mut rng = rng();
+        let mut rng = config.rng();
         let n = rng.random_range(..mode_options.len());
         let rand_mode = mode_options
             .get(n)
             .expect("random selected solid mode from user config should be defined");
-        generate_image(rand_mode, width, height)
+        generate_image(rand_mode, width, height, &mut *rng)?
     };
 
     config.print_if_verbose("Image generated!");
     Ok(imgbuf)
 }
 
-fn generate_image(mode: &SolidMode, width: u32, height: u32) -> AstraImage {
-    match mode {
-        SolidMode::Random => ImageBuffer::from_pixel(
-            width,
-            height,
-            Rgb([
-                rand::random::<u8>(),
-                rand::random::<u8>(),
-                rand::random::<u8>(),
-            ]),
-        ),
-        SolidMode::Rgb { r, g, b } => ImageBuffer::from_pixel(width, height, Rgb([*r, *g, *b])),
+/// Renders `frames` images rotating the hue of the solid color (of both colors of a gradient) by
+/// 360° over the animation, so the last frame leads back into the first one
+pub(super) fn animate_solid_color(
+    config: &Config,
+    mode: &SolidMode,
+    frames: u32,
+) -> Result<Vec<AstraImage>, WallpaperGeneratorError> {
+    config.print_if_verbose("Generating hue rotation animation...");
+    let hue_step = 360.0 / frames as f64;
+    if let SolidMode::Gradient {
+        from,
+        to,
+        direction,
+    } = mode
+    {
+        let (width, height) = config
+            .screen_resolution()
+            .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+        let from = parse_color(from).map_err(WallpaperGeneratorError::Parse)?;
+        let to = parse_color(to).map_err(WallpaperGeneratorError::Parse)?;
+        return Ok((0..frames)
+            .map(|frame| {
+                let degrees = hue_step * frame as f64;
+                generate_gradient(
+                    rotate_hue(from, degrees),
+                    rotate_hue(to, degrees),
+                    *direction,
+                    width,
+                    height,
+                )
+            })
+            .collect());
+    }
+    let image = generate_solid_color(config, mode)?;
+    let color = image.get_pixel(0, 0).0;
+    Ok((0..frames)
+        .map(|frame| {
+            let color = rotate_hue(color, hue_step * frame as f64);
+            ImageBuffer::from_pixel(image.width(), image.height(), Rgb(color))
+        })
+        .collect())
+}
+
+fn generate_image<R: Rng + ?Sized>(
+    mode: &SolidMode,
+    width: u32,
+    height: u32,
+    rng: &mut R,
+) -> Result<AstraImage, WallpaperGeneratorError> {
+    let color = match mode {
+        SolidMode::Random => [rng.random::<u8>(), rng.random::<u8>(), rng.random::<u8>()],
+        SolidMode::Rgb { r, g, b } => [*r, *g, *b],
         SolidMode::Color { name } => {
             let (r, g, b) = name.rgb();
-            ImageBuffer::from_pixel(width, height, Rgb([r, g, b]))
+            [r, g, b]
+        }
+        SolidMode::Curated { hue } => pick_curated_color(*hue, rng).1,
+        SolidMode::Gradient {
+            from,
+            to,
+            direction,
+        } => {
+            let from = parse_color(from).map_err(WallpaperGeneratorError::Parse)?;
+            let to = parse_color(to).map_err(WallpaperGeneratorError::Parse)?;
+            return Ok(generate_gradient(from, to, *direction, width, height));
+        }
+        SolidMode::Harmony { scheme, base } => {
+            let base = match base {
+                Some(base) => parse_color(base).map_err(WallpaperGeneratorError::Parse)?,
+                None => random_pleasant_color(rng),
+            };
+            harmony_color(base, *scheme, rng)
+        }
+    };
+    Ok(ImageBuffer::from_pixel(width, height, Rgb(color)))
+}
+
+/// How a solid wallpaper's color is derived from the selected theme (`solid_gen.theme_mode`)
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SolidThemeMode {
+    /// Average color of the theme, the same color on every run
+    #[default]
+    Average,
+    /// One of the theme's colors picked at random
+    RandomSwatch,
+    /// Random mix of two neighboring colors of the theme
+    RandomBlend,
+}
+
+/// Color of a themed solid wallpaper, see `SolidThemeMode`
+fn theme_color<R: Rng + ?Sized>(
+    theme: &ColorTheme,
+    dark_mode: bool,
+    mode: SolidThemeMode,
+    rng: &mut R,
+) -> Result<[u8; 3], WallpaperGeneratorError> {
+    let colors = theme.get_colors(dark_mode);
+    match mode {
+        SolidThemeMode::RandomBlend if colors.len() >= 2 => {
+            let i = rng.random_range(..colors.len() - 1);
+            Ok(mix_color(colors[i], colors[i + 1], rng.random::<f64>()))
         }
+        SolidThemeMode::RandomSwatch | SolidThemeMode::RandomBlend if !colors.is_empty() => {
+            Ok(colors[rng.random_range(..colors.len())])
+        }
+        _ => theme
+            .average_color(dark_mode)
+            .map_err(|e| WallpaperGeneratorError::ImageGeneration(e.to_string())),
     }
 }
 
-#[derive(Copy, Clone, Debug, Deserialize, PartialEq, ValueEnum)]
+/// Direction of `astra generate solid gradient`, from the first color to the second one
+#[derive(Copy, Clone, Debug, PartialEq, ValueEnum)]
+pub enum GradientDirection {
+    /// Left to right
+    Horizontal,
+    /// Top to bottom
+    Vertical,
+    /// Top left to bottom right
+    Diagonal,
+}
+
+/// Paints a linear gradient from `from` to `to`
+pub(super) fn generate_gradient(
+    from: [u8; 3],
+    to: [u8; 3],
+    direction: GradientDirection,
+    width: u32,
+    height: u32,
+) -> AstraImage {
+    let color_map = create_color_map(Operator::Gradient, 256, &[from, to])
+        .expect("a gradient always has two colors");
+    let progress = |x: u32, y: u32| match direction {
+        GradientDirection::Horizontal => x as f64 / width.saturating_sub(1).max(1) as f64,
+        GradientDirection::Vertical => y as f64 / height.saturating_sub(1).max(1) as f64,
+        GradientDirection::Diagonal => {
+            (x + y) as f64 / (width + height).saturating_sub(2).max(1) as f64
+        }
+    };
+    ImageBuffer::from_fn(width, height, |x, y| {
+        Rgb(color_map[(progress(x, y) * 255.0).round() as usize])
+    })
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize, ValueEnum)]
 pub enum Color {
     White,
     Black,
@@ -140,7 +281,7 @@ pub enum Color {
 }
 
 impl Color {
-    fn rgb(&self) -> (u8, u8, u8) {
+    pub(super) fn rgb(&self) -> (u8, u8, u8) {
         match self {
             Color::White => (255, 255, 255),
             Color::Black => (0, 0, 0),
@@ -165,3 +306,79 @@ impl Color {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::average_color;
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn test_theme_from_file_is_used_for_generation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("themes.json");
+        std::fs::write(
+            &path,
+            r#"[{ "name": "ocean", "colors": [[0, 40, 200], [0, 60, 220]] }]"#,
+        )
+        .unwrap();
+        let mut config = Config::from_json("{}");
+        config.set_resolution_override(8, 8);
+        config.set_dark_mode_override(false);
+        assert!(config.set_theme_from_file(&path, "forest").is_err());
+        config.set_theme_from_file(&path, "ocean").unwrap();
+
+        let image = generate_solid_color(&config, &SolidMode::Random).unwrap();
+        let [r, g, b] = average_color(&image).0;
+        let distance = r.abs_diff(0) as u32 + g.abs_diff(50) as u32 + b.abs_diff(210) as u32;
+        assert!(distance < 10, "average color was {:?}", [r, g, b]);
+    }
+
+    #[test]
+    fn test_theme_modes_pick_colors_from_the_theme() {
+        let theme = ColorTheme::new(
+            "test".to_string(),
+            false,
+            vec![[10, 20, 30], [200, 100, 0], [0, 0, 255]],
+            None,
+        );
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(
+            theme_color(&theme, false, SolidThemeMode::Average, &mut rng).unwrap(),
+            theme.average_color(false).unwrap()
+        );
+        for _ in 0..50 {
+            let color = theme_color(&theme, false, SolidThemeMode::RandomSwatch, &mut rng).unwrap();
+            assert!(theme.get_colors(false).contains(&color), "{color:?}");
+
+            // a blend stays between two neighboring colors
+            let [r, g, b] =
+                theme_color(&theme, false, SolidThemeMode::RandomBlend, &mut rng).unwrap();
+            let between = |c: u8, a: u8, b: u8| a.min(b) <= c && c <= a.max(b);
+            let colors = theme.get_colors(false);
+            assert!(colors.windows(2).any(|pair| {
+                between(r, pair[0][0], pair[1][0])
+                    && between(g, pair[0][1], pair[1][1])
+                    && between(b, pair[0][2], pair[1][2])
+            }));
+        }
+    }
+
+    #[test]
+    fn test_gradient_from_black_to_white_ramps_left_to_right() {
+        let mode = SolidMode::Gradient {
+            from: "black".to_string(),
+            to: "#ffffff".to_string(),
+            direction: GradientDirection::Horizontal,
+        };
+        let image = generate_image(&mode, 64, 8, &mut StdRng::seed_from_u64(0)).unwrap();
+        for y in 0..8 {
+            assert_eq!(image.get_pixel(0, y), &Rgb([0, 0, 0]));
+            assert_eq!(image.get_pixel(63, y), &Rgb([255, 255, 255]));
+            for x in 1..64 {
+                let (left, right) = (image.get_pixel(x - 1, y), image.get_pixel(x, y));
+                assert!(left[0] < right[0] && left == image.get_pixel(x - 1, 0));
+            }
+        }
+    }
+}
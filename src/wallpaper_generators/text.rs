@@ -0,0 +1,357 @@
+use super::super::{
+    configuration::{Config, State},
+    themes::contrast_ratio,
+};
+use super::solid_color::{GradientDirection, generate_gradient};
+use super::utils::{AstraImage, WallpaperGeneratorError, average_color, mix_color};
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont, point};
+use rand::RngExt;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+/// DejaVu Sans, see assets/fonts/LICENSE
+const FONT: &[u8] = include_bytes!("../../assets/fonts/DejaVuSans.ttf");
+const DEFAULT_CONTENT: &str = "Make today count";
+/// Default line height as a fraction of the screen height
+const DEFAULT_FONT_SIZE_RATIO: u32 = 12;
+/// Widest a line of text may be, as a fraction of the screen width
+const MAX_TEXT_WIDTH_RATIO: f32 = 0.8;
+const WHITE: [u8; 3] = [255, 255, 255];
+const BLACK: [u8; 3] = [0, 0, 0];
+
+/// Alignment of the lines of `text_gen.content` within the (centered) block of text
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextAlignment {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+/// How the text is picked from `text_gen.content` or the quotes of `text_gen.source_file`
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextOrder {
+    #[default]
+    Random,
+    /// One after the other, the position is kept in the state file
+    Sequential,
+}
+
+pub fn generate_text(config: &Config) -> Result<AstraImage, WallpaperGeneratorError> {
+    config.print_if_verbose("Generating text...");
+    let (width, height) = config
+        .screen_resolution()
+        .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+    config.print_if_verbose(format!("Detected screen resolution: {}x{}", width, height).as_str());
+
+    if config.respect_user_config {
+        config.print_if_verbose("User config detected with text_gen options...");
+    }
+
+    let source_file = config
+        .respect_user_config
+        .then(|| config.text_gen())
+        .flatten()
+        .and_then(|text_gen| text_gen.source_file());
+    let content = match source_file {
+        Some(path) => {
+            config.print_if_verbose(format!("Reading quotes from {}", path.display()).as_str());
+            load_quotes(path)?
+        }
+        None => crate::respect_user_config_or_default!(config, text_gen, content, {
+            Ok(vec![DEFAULT_CONTENT.to_string()])
+        })?,
+    };
+    let order = crate::respect_user_config_or_default!(config, text_gen, order, {
+        Ok(TextOrder::default())
+    })?;
+    let index = match order {
+        TextOrder::Random => config.rng().random_range(..content.len()),
+        // the next index is only stored once the wallpaper is applied
+        TextOrder::Sequential => {
+            let mut index = 0;
+            config.stage_state(|state| index = next_sequential_index(content.len(), state));
+            index
+        }
+    };
+    let text = &content[index];
+    let font_size = crate::respect_user_config_or_default!(config, text_gen, font_size, {
+        Ok(height / DEFAULT_FONT_SIZE_RATIO)
+    })?
+    .max(1);
+    let alignment = crate::respect_user_config_or_default!(config, text_gen, alignment, {
+        Ok(TextAlignment::default())
+    })?;
+    let should_respect_color_themes =
+        crate::respect_user_config_or_default!(config, text_gen, respect_color_themes, {
+            Ok(false)
+        })?;
+    let mut imgbuf = themed_background(config, should_respect_color_themes, width, height)?;
+
+    let color = contrasting_color(&imgbuf);
+    config.print_if_verbose(format!("Drawing {text:?} at {font_size}px").as_str());
+    draw_text(&mut imgbuf, text, font_size as f32, alignment, color)?;
+
+    config.print_if_verbose("Image generated!");
+    Ok(imgbuf)
+}
+
+/// Vertical gradient between the first and last colors of the theme selected for the run, in the
+/// current appearance
+pub(super) fn themed_background(
+    config: &Config,
+    respect_color_themes: bool,
+    width: u32,
+    height: u32,
+) -> Result<AstraImage, WallpaperGeneratorError> {
+    let dark_mode = config
+        .is_dark_mode_active()
+        .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+    let theme = config.select_theme(respect_color_themes, &mut *config.rng());
+    let selected_theme = theme.selected();
+    config.print_if_verbose(format!("Selected theme: {selected_theme}").as_str());
+    let colors = selected_theme.get_colors(dark_mode);
+    let (Some(from), Some(to)) = (colors.first(), colors.last()) else {
+        return Err(WallpaperGeneratorError::ImageGeneration(format!(
+            "theme {selected_theme} has no colors"
+        )));
+    };
+    Ok(generate_gradient(
+        *from,
+        *to,
+        GradientDirection::Vertical,
+        width,
+        height,
+    ))
+}
+
+/// Reads the quotes of `text_gen.source_file`: a JSON array of strings, or one quote per line
+fn load_quotes(path: &Path) -> Result<Vec<String>, WallpaperGeneratorError> {
+    let data = fs::read_to_string(path).map_err(|e| {
+        WallpaperGeneratorError::OS(format!(
+            "unable to read quotes from {}: {e}",
+            path.display()
+        ))
+    })?;
+    let quotes: Vec<String> = if data.trim_start().starts_with('[') {
+        serde_json::from_str(&data).map_err(|e| {
+            WallpaperGeneratorError::Parse(format!("invalid quotes in {}: {e}", path.display()))
+        })?
+    } else {
+        data.lines().map(str::to_string).collect()
+    };
+    let quotes: Vec<String> = quotes
+        .into_iter()
+        .map(|quote| quote.trim().to_string())
+        .filter(|quote| !quote.is_empty())
+        .collect();
+    if quotes.is_empty() {
+        return Err(WallpaperGeneratorError::Parse(format!(
+            "no quotes found in {}",
+            path.display()
+        )));
+    }
+    Ok(quotes)
+}
+
+/// Index of the text to draw among `len` texts in sequential order: the index stored in `state`
+/// is used and the next one is stored, wrapping around at the end of the list
+fn next_sequential_index(len: usize, state: &mut State) -> usize {
+    // the list may have shrunk since the index was stored
+    let index = state.quote_index().unwrap_or(0) % len;
+    state.set_quote_index((index + 1) % len);
+    index
+}
+
+/// White or black, whichever is easier to read over the average color of `background`
+pub(super) fn contrasting_color(background: &AstraImage) -> [u8; 3] {
+    let average = average_color(background).0;
+    if contrast_ratio(average, WHITE) >= contrast_ratio(average, BLACK) {
+        WHITE
+    } else {
+        BLACK
+    }
+}
+
+/// Draws `text` centered on `image`, wrapping lines wider than `MAX_TEXT_WIDTH_RATIO` of the
+/// image. Line breaks in `text` are kept.
+pub(super) fn draw_text(
+    image: &mut AstraImage,
+    text: &str,
+    font_size: f32,
+    alignment: TextAlignment,
+    color: [u8; 3],
+) -> Result<(), WallpaperGeneratorError> {
+    let font = FontRef::try_from_slice(FONT)
+        .map_err(|e| WallpaperGeneratorError::ImageGeneration(e.to_string()))?;
+    let scale = PxScale::from(font_size);
+    let scaled = font.as_scaled(scale);
+    let (width, height) = image.dimensions();
+
+    let lines = wrap_lines(&scaled, text, width as f32 * MAX_TEXT_WIDTH_RATIO);
+    let line_height = scaled.height() + scaled.line_gap();
+    let block_width = lines.iter().map(|(_, w)| *w).fold(0.0, f32::max);
+    let left = (width as f32 - block_width) / 2.0;
+    let top = (height as f32 - line_height * lines.len() as f32) / 2.0;
+
+    for (index, (line, line_width)) in lines.iter().enumerate() {
+        let mut caret = left
+            + match alignment {
+                TextAlignment::Left => 0.0,
+                TextAlignment::Center => (block_width - line_width) / 2.0,
+                TextAlignment::Right => block_width - line_width,
+            };
+        let baseline = top + line_height * index as f32 + scaled.ascent();
+        let mut previous = None;
+        for c in line.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                caret += scaled.kern(previous, id);
+            }
+            previous = Some(id);
+            let glyph = id.with_scale_and_position(scale, point(caret, baseline));
+            caret += scaled.h_advance(id);
+            let Some(outline) = font.outline_glyph(glyph) else {
+                continue; // e.g. spaces
+            };
+            let bounds = outline.px_bounds();
+            outline.draw(|x, y, coverage| {
+                let x = bounds.min.x as i64 + x as i64;
+                let y = bounds.min.y as i64 + y as i64;
+                if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+                    let pixel = image.get_pixel_mut(x as u32, y as u32);
+                    pixel.0 = mix_color(pixel.0, color, coverage.clamp(0.0, 1.0) as f64);
+                }
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Splits `text` into lines no wider than `max_width` (a word wider than that gets a line of its
+/// own), returned with their width
+fn wrap_lines<F: Font, S: ScaleFont<F>>(
+    font: &S,
+    text: &str,
+    max_width: f32,
+) -> Vec<(String, f32)> {
+    let mut lines = vec![];
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{line} {word}")
+            };
+            if line.is_empty() || line_width(font, &candidate) <= max_width {
+                line = candidate;
+            } else {
+                let width = line_width(font, &line);
+                lines.push((std::mem::replace(&mut line, word.to_string()), width));
+            }
+        }
+        let width = line_width(font, &line);
+        lines.push((line, width));
+    }
+    lines
+}
+
+fn line_width<F: Font, S: ScaleFont<F>>(font: &S, line: &str) -> f32 {
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in line.chars() {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            width += font.kern(previous, id);
+        }
+        width += font.h_advance(id);
+        previous = Some(id);
+    }
+    width
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+
+    #[test]
+    fn test_text_is_drawn_only_within_centered_text_block() {
+        let (width, height) = (400, 300);
+        let background = [10, 20, 30];
+        let mut image: AstraImage = ImageBuffer::from_pixel(width, height, Rgb(background));
+        let text = "the quick brown fox jumps over the lazy dog";
+        draw_text(&mut image, text, 24.0, TextAlignment::Center, WHITE).unwrap();
+
+        let font = FontRef::try_from_slice(FONT).unwrap();
+        let scaled = font.as_scaled(PxScale::from(24.0));
+        let max_width = width as f32 * MAX_TEXT_WIDTH_RATIO;
+        let lines = wrap_lines(&scaled, text, max_width);
+        assert!(lines.len() > 1, "text should wrap at this width");
+        let block_height = (scaled.height() + scaled.line_gap()) * lines.len() as f32;
+        // glyphs may overhang their advance box by a pixel or two
+        let margin = 2.0;
+        let left = (width as f32 - max_width) / 2.0 - margin;
+        let right = (width as f32 + max_width) / 2.0 + margin;
+        let top = (height as f32 - block_height) / 2.0 - margin;
+        let bottom = (height as f32 + block_height) / 2.0 + margin;
+
+        let mut drawn = 0;
+        for (x, y, pixel) in image.enumerate_pixels() {
+            if pixel.0 != background {
+                drawn += 1;
+                assert!(
+                    (left..=right).contains(&(x as f32)) && (top..=bottom).contains(&(y as f32)),
+                    "pixel ({x}, {y}) drawn outside the text block"
+                );
+            }
+        }
+        assert!(drawn > 0);
+    }
+
+    #[test]
+    fn test_sequential_order_advances_stored_index_and_wraps_around() {
+        let mut state = State::default();
+        let picks: Vec<usize> = (0..4)
+            .map(|_| next_sequential_index(3, &mut state))
+            .collect();
+        assert_eq!(picks, vec![0, 1, 2, 0]);
+        assert_eq!(state.quote_index(), Some(1));
+
+        // an index past the end of a shorter list starts over
+        state.set_quote_index(5);
+        assert_eq!(next_sequential_index(2, &mut state), 1);
+        assert_eq!(state.quote_index(), Some(0));
+    }
+
+    #[test]
+    fn test_quotes_are_read_from_lines_or_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let lines = dir.path().join("quotes.txt");
+        fs::write(&lines, "First quote\n\n  Second quote  \n").unwrap();
+        assert_eq!(
+            load_quotes(&lines).unwrap(),
+            vec!["First quote", "Second quote"]
+        );
+
+        let json = dir.path().join("quotes.json");
+        fs::write(&json, r#"["One\ntwo", "Three"]"#).unwrap();
+        assert_eq!(load_quotes(&json).unwrap(), vec!["One\ntwo", "Three"]);
+
+        let empty = dir.path().join("empty.txt");
+        fs::write(&empty, "\n  \n").unwrap();
+        assert!(load_quotes(&empty).is_err());
+        assert!(load_quotes(&dir.path().join("missing.txt")).is_err());
+    }
+
+    #[test]
+    fn test_contrasting_color_is_readable() {
+        let light: AstraImage = ImageBuffer::from_pixel(2, 2, Rgb([240, 240, 200]));
+        let dark: AstraImage = ImageBuffer::from_pixel(2, 2, Rgb([20, 10, 40]));
+        assert_eq!(contrasting_color(&light), BLACK);
+        assert_eq!(contrasting_color(&dark), WHITE);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::configuration::{Config, State};
+use crate::logger::LogLevel;
+use std::{error::Error, fs::remove_file, path::PathBuf};
+
+/// Side effects of generating and applying a wallpaper, staged until the wallpaper is applied.
+/// `commit` writes the pending state, removes the files the run replaces and records the run,
+/// `rollback` removes the files created by the run and writes nothing, so a failure in between
+/// leaves astra's files as they were.
+#[derive(Debug, Default)]
+pub struct Transaction {
+    state_path: PathBuf,
+    created_files: Vec<PathBuf>,
+    replaced_files: Vec<PathBuf>,
+    pending_state: Option<State>,
+}
+
+impl Transaction {
+    pub fn begin() -> Self {
+        Self::with_state_path(State::path())
+    }
+
+    /// Transaction writing its pending state to `state_path` instead of the state file
+    pub fn with_state_path(state_path: PathBuf) -> Self {
+        Self {
+            state_path,
+            ..Self::default()
+        }
+    }
+
+    /// Registers a file the run created (it did not exist before), removed on rollback
+    pub fn stage_file(&mut self, path: PathBuf) {
+        self.created_files.push(path);
+    }
+
+    /// Registers a file the run replaces (e.g. the previous wallpaper of `single_file_mode`),
+    /// removed on commit and kept on rollback
+    pub fn stage_removal(&mut self, path: PathBuf) {
+        self.replaced_files.push(path);
+    }
+
+    /// State written on commit, replacing the state staged before
+    pub fn stage_state(&mut self, state: State) {
+        self.pending_state = Some(state);
+    }
+
+    /// Keeps the created files, writes the pending state, removes the replaced files then records
+    /// the run with `record_run`.
+    /// The wallpaper is already applied by then, so a state that cannot be written is only a
+    /// warning (it disables `skip_if_unchanged` and the wallpaper's entry in `astra recent`).
+    pub fn commit(
+        self,
+        config: &Config,
+        record_run: impl FnOnce() -> Result<(), Box<dyn Error>>,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some(state) = &self.pending_state
+            && let Err(e) = state.save_to(&self.state_path)
+        {
+            config.print_if_verbose(format!("WARN: {e}").as_str());
+            config.log(LogLevel::Warn, e.to_string().as_str());
+        }
+        for path in &self.replaced_files {
+            if let Err(e) = remove_file(path) {
+                config.print_if_verbose(
+                    format!("WARN: unable to remove {}: {e}", path.display()).as_str(),
+                );
+            }
+        }
+        record_run()
+    }
+
+    /// Removes the files created by the run and drops the pending state
+    pub fn rollback(self, config: &Config) {
+        for path in &self.created_files {
+            match remove_file(path) {
+                Ok(()) => config.print_if_verbose(
+                    format!(
+                        "Rolled back {}, the wallpaper was not applied",
+                        path.display()
+                    )
+                    .as_str(),
+                ),
+                Err(e) => config.print_if_verbose(
+                    format!("WARN: unable to remove {}: {e}", path.display()).as_str(),
+                ),
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use super::super::{
+    configuration::Config,
+    os_implementations::{get_current_wallpaper, update_wallpaper},
+};
+use super::utils::{AstraImage, WallpaperGeneratorError, create_wallpaper_folder};
+use image::{ImageBuffer, Rgb, imageops::FilterType};
+use std::{fs::remove_file, thread, time::Duration};
+
+/// Time each intermediate frame stays on screen
+const FRAME_DELAY: Duration = Duration::from_millis(60);
+
+/// Crossfades from the current wallpaper to `target` by setting `transition_frames` blended
+/// images in quick succession. The caller is responsible for setting `target` afterwards.
+///
+/// If the current wallpaper cannot be determined, no transition is played.
+pub(super) fn play_transition(
+    config: &Config,
+    target: &AstraImage,
+) -> Result<(), WallpaperGeneratorError> {
+    let Some(current_path) =
+        get_current_wallpaper().map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?
+    else {
+        config.print_if_verbose("No current wallpaper found, skipping transition");
+        return Ok(());
+    };
+    config.print_if_verbose(format!("Transitioning from {}...", current_path.display()).as_str());
+    let current = image::open(&current_path)
+        .map_err(|e| {
+            WallpaperGeneratorError::ImageGeneration(format!(
+                "failed to read current wallpaper: {e}"
+            ))
+        })?
+        .to_rgb8();
+
+    let folder = create_wallpaper_folder()?;
+    let mut frame_paths = vec![];
+    let result = blend_frames(&current, target, config.transition_frames())
+        .iter()
+        .enumerate()
+        .try_for_each(|(i, frame)| {
+            let path = folder.join(format!("transition_{i}.png"));
+            frame
+                .save(&path)
+                .map_err(|_| WallpaperGeneratorError::ImageSave)?;
+            frame_paths.push(path.clone());
+            update_wallpaper(path).map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+            thread::sleep(FRAME_DELAY);
+            Ok(())
+        });
+
+    for path in frame_paths {
+        let _ = remove_file(path);
+    }
+    result
+}
+
+/// Returns `frames` images blending linearly from `from` to `to` (both excluded).
+///
+/// `from` is resized to the dimensions of `to` when they differ.
+pub(super) fn blend_frames(from: &AstraImage, to: &AstraImage, frames: u8) -> Vec<AstraImage> {
+    let (width, height) = to.dimensions();
+    let resized;
+    let from = if from.dimensions() == to.dimensions() {
+        from
+    } else {
+        resized = image::imageops::resize(from, width, height, FilterType::Triangle);
+        &resized
+    };
+
+    (1..=frames as u32)
+        .map(|i| {
+            let t = i as f32 / (frames as f32 + 1.0);
+            ImageBuffer::from_fn(width, height, |x, y| {
+                let a = from.get_pixel(x, y).0;
+                let b = to.get_pixel(x, y).0;
+                Rgb(std::array::from_fn(|c| {
+                    (a[c] as f32 * (1.0 - t) + b[c] as f32 * t).round() as u8
+                }))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blend_frames_count() {
+        let from: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb([0, 0, 0]));
+        let to: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb([255, 255, 255]));
+        assert_eq!(blend_frames(&from, &to, 5).len(), 5);
+        assert!(blend_frames(&from, &to, 0).is_empty());
+    }
+
+    #[test]
+    fn test_blend_frames_are_intermediate() {
+        let from: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb([0, 0, 0]));
+        let to: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb([200, 100, 40]));
+        let frames = blend_frames(&from, &to, 3);
+        assert_eq!(frames[0].get_pixel(0, 0).0, [50, 25, 10]);
+        assert_eq!(frames[1].get_pixel(0, 0).0, [100, 50, 20]);
+        assert_eq!(frames[2].get_pixel(0, 0).0, [150, 75, 30]);
+    }
+
+    #[test]
+    fn test_blend_frames_resizes_current_wallpaper() {
+        let from: AstraImage = ImageBuffer::from_pixel(2, 2, Rgb([0, 0, 0]));
+        let to: AstraImage = ImageBuffer::from_pixel(8, 4, Rgb([255, 255, 255]));
+        let frames = blend_frames(&from, &to, 2);
+        assert!(frames.iter().all(|frame| frame.dimensions() == (8, 4)));
+    }
+}
@@ -0,0 +1,170 @@
+use super::super::configuration::Config;
+use super::utils::{AstraImage, WallpaperGeneratorError, mix_color};
+use image::{ImageBuffer, Rgb};
+use rand::{Rng, RngExt};
+use rayon::iter::ParallelIterator;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_TILE_SIZE: u32 = 80;
+
+/// Shape drawn inside each Truchet tile
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TruchetStyle {
+    /// Two quarter-circle arcs joining the midpoints of adjacent edges
+    Arcs,
+    /// A single diagonal line from corner to corner
+    Diagonals,
+}
+
+pub fn generate_truchet(config: &Config) -> Result<AstraImage, WallpaperGeneratorError> {
+    config.print_if_verbose("Generating truchet tiles...");
+    let (width, height) = config
+        .screen_resolution()
+        .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+    config.print_if_verbose(format!("Detected screen resolution: {}x{}", width, height).as_str());
+
+    if config.respect_user_config {
+        config.print_if_verbose("User config detected with truchet_gen options...");
+    }
+
+    let tile_size = crate::respect_user_config_or_default!(config, truchet_gen, tile_size, {
+        Ok(DEFAULT_TILE_SIZE)
+    })?
+    .max(1);
+    let style = crate::respect_user_config_or_default!(config, truchet_gen, style, {
+        Ok(TruchetStyle::Arcs)
+    })?;
+    let dark_mode = config
+        .is_dark_mode_active()
+        .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+
+    let seed = config
+        .respect_user_config
+        .then(|| config.truchet_gen())
+        .flatten()
+        .and_then(|truchet_gen| truchet_gen.seed());
+    let imgbuf = render(
+        config,
+        width,
+        height,
+        tile_size,
+        style,
+        dark_mode,
+        &mut config.generator_rng(seed),
+    )?;
+
+    config.print_if_verbose("Image generated!");
+    Ok(imgbuf)
+}
+
+fn render<R: Rng + ?Sized>(
+    config: &Config,
+    width: u32,
+    height: u32,
+    tile_size: u32,
+    style: TruchetStyle,
+    dark_mode: bool,
+    rng: &mut R,
+) -> Result<AstraImage, WallpaperGeneratorError> {
+    let should_respect_color_themes =
+        crate::respect_user_config_or_default!(config, truchet_gen, respect_color_themes, {
+            Ok(false)
+        })?;
+    let theme = config.select_theme(should_respect_color_themes, rng);
+    let selected_theme = theme.selected();
+    config.print_if_verbose(format!("Selected theme: {selected_theme}").as_str());
+    let colors = selected_theme.get_colors(dark_mode);
+    let (background, foreground) = match (colors.first(), colors.last()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => {
+            return Err(WallpaperGeneratorError::ImageGeneration(
+                "selected theme has no colors".to_string(),
+            ));
+        }
+    };
+
+    let columns = width.div_ceil(tile_size);
+    let rows = height.div_ceil(tile_size);
+    // Orientations are picked up front so the output only depends on the rng
+    let flipped: Vec<bool> = (0..columns * rows).map(|_| rng.random()).collect();
+
+    let mut imgbuf = ImageBuffer::new(width, height);
+    imgbuf.par_enumerate_pixels_mut().for_each(|(x, y, pixel)| {
+        let tile = (y / tile_size) * columns + x / tile_size;
+        let coverage = tile_coverage(
+            style,
+            flipped[tile as usize],
+            tile_size as f64,
+            (x % tile_size) as f64 + 0.5,
+            (y % tile_size) as f64 + 0.5,
+        );
+        *pixel = Rgb(mix_color(background, foreground, coverage));
+    });
+    Ok(imgbuf)
+}
+
+/// Returns how much of the pixel centered at (`u`, `v`) inside a tile is covered by the tile's
+/// line (`0.0` = background, `1.0` = line). The one pixel falloff at the line's edges
+/// anti-aliases the curves.
+fn tile_coverage(style: TruchetStyle, flipped: bool, size: f64, u: f64, v: f64) -> f64 {
+    let half_width = (size / 12.0).max(1.0);
+    let distance = match (style, flipped) {
+        (TruchetStyle::Arcs, false) => {
+            let radius = size / 2.0;
+            let top_left = (u.hypot(v) - radius).abs();
+            let bottom_right = ((size - u).hypot(size - v) - radius).abs();
+            top_left.min(bottom_right)
+        }
+        (TruchetStyle::Arcs, true) => {
+            let radius = size / 2.0;
+            let top_right = ((size - u).hypot(v) - radius).abs();
+            let bottom_left = (u.hypot(size - v) - radius).abs();
+            top_right.min(bottom_left)
+        }
+        (TruchetStyle::Diagonals, false) => (u - v).abs() / 2f64.sqrt(),
+        (TruchetStyle::Diagonals, true) => (u + v - size).abs() / 2f64.sqrt(),
+    };
+    (half_width + 0.5 - distance).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded_config(style: &str) -> Config {
+        let mut config = Config::from_json(
+            format!(r#"{{ "truchet_gen": {{ "seed": 7, "tile_size": 16, "style": "{style}" }} }}"#)
+                .as_str(),
+        );
+        config.respect_user_config = true;
+        config.set_resolution_override(96, 64);
+        config.set_dark_mode_override(false);
+        config
+    }
+
+    #[test]
+    fn test_truchet_is_reproducible_with_seed() {
+        for style in ["arcs", "diagonals"] {
+            let first = generate_truchet(&seeded_config(style)).unwrap();
+            let second = generate_truchet(&seeded_config(style)).unwrap();
+            assert_eq!(first.dimensions(), (96, 64));
+            assert_eq!(first, second);
+        }
+    }
+
+    #[test]
+    fn test_tile_coverage_is_antialiased() {
+        // Midpoint of the top edge is on the arc, the tile center is far away from both arcs
+        assert_eq!(
+            tile_coverage(TruchetStyle::Arcs, false, 40.0, 20.0, 0.0),
+            1.0
+        );
+        assert_eq!(
+            tile_coverage(TruchetStyle::Arcs, false, 40.0, 20.0, 20.0),
+            0.0
+        );
+        let edge = tile_coverage(TruchetStyle::Diagonals, false, 40.0, 25.0, 20.0);
+        assert!(edge > 0.0 && edge < 1.0, "coverage was {edge}");
+    }
+}
@@ -1,21 +1,186 @@
 use super::super::{
-    configuration::{Config, Frequency},
-    constants::{APPLICATION, ORGANIZATION, QUALIFIER},
+    configuration::{AppliedWallpaper, Config, Decision, Frequency, HookCommand, State},
+    constants::WALLPAPER_ENV_VAR,
 };
+use super::color_harmony::parse_color;
+use super::metadata::{generation_metadata, save_png_with_metadata};
+#[cfg(target_os = "windows")]
+use super::post_process::apply_post_processing;
+use super::transaction::Transaction;
+use super::transition::play_transition;
 use crate::cli::Generator;
+use crate::logger::LogLevel;
 use crate::os_implementations::update_wallpaper;
-use directories::ProjectDirs;
-use image::{ImageBuffer, Rgb};
+#[cfg(target_os = "windows")]
+use crate::os_implementations::{
+    get_wallpaper_monitors, update_wallpaper_on_monitor, update_wallpaper_per_monitor,
+};
+#[cfg(target_os = "macos")]
+use crate::os_implementations::{
+    screen_count, update_wallpaper_all_spaces, update_wallpaper_on_screen,
+};
+use image::{DynamicImage, ImageBuffer, Rgb, imageops};
+use rand::{Rng, RngExt};
+use serde::{Deserialize, Serialize};
 use std::{
+    collections::{BTreeMap, HashSet},
     error::Error,
     fmt,
-    fs::{create_dir_all, read_dir, remove_dir_all, remove_file},
-    path::PathBuf,
-    time::{SystemTime, UNIX_EPOCH},
+    fs::{DirEntry, create_dir_all, read_dir, remove_dir_all, remove_file},
+    path::{Path, PathBuf},
+    process::ExitStatus,
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 pub type AstraImage = ImageBuffer<Rgb<u8>, Vec<u8>>;
 
+/// File names (without extension) saved wallpapers alternate between when `single_file_mode` is
+/// enabled. The new wallpaper never overwrites the displayed one, so a failed run can be rolled
+/// back and desktops that only redraw when the path changes (e.g. GNOME) pick it up.
+const SINGLE_FILE_MODE_FILE_STEMS: [&str; 2] = ["current_1", "current_2"];
+/// Number of distinct colors from which an image is considered a photo by the `auto` format
+const AUTO_FORMAT_PHOTO_COLORS: usize = 10_000;
+
+/// Image format wallpapers are saved in (`format` key, globally or per generator)
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WallpaperFormat {
+    /// Lossless, best for the generated images with large flat areas and sharp edges
+    #[default]
+    Png,
+    /// Lossy but much smaller for photos (e.g. spotlight or nasa_apod)
+    #[serde(alias = "jpg")]
+    Jpeg,
+    /// JPEG for images with many distinct colors (photos), PNG otherwise
+    Auto,
+}
+
+impl WallpaperFormat {
+    /// Extension of the saved file, the encoder is picked from it (`auto` is resolved with
+    /// `resolve` first)
+    pub fn extension(&self) -> &'static str {
+        match self {
+            WallpaperFormat::Png | WallpaperFormat::Auto => "png",
+            WallpaperFormat::Jpeg => "jpg",
+        }
+    }
+
+    /// Format `image` is saved in: `auto` becomes JPEG if the image has at least
+    /// `AUTO_FORMAT_PHOTO_COLORS` distinct colors and PNG otherwise, other formats are kept
+    pub fn resolve(self, image: &AstraImage) -> WallpaperFormat {
+        if self != WallpaperFormat::Auto {
+            return self;
+        }
+        let mut colors = HashSet::new();
+        for pixel in image.pixels() {
+            colors.insert(pixel.0);
+            if colors.len() >= AUTO_FORMAT_PHOTO_COLORS {
+                return WallpaperFormat::Jpeg;
+            }
+        }
+        WallpaperFormat::Png
+    }
+}
+
+/// Filter used to scale images rendered below the screen resolution (`render_scale`) back up
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpscaleFilter {
+    /// Keeps hard pixel edges (pixel-art look)
+    Nearest,
+    /// Smooth, the sharpest of the smooth filters
+    #[default]
+    Lanczos,
+}
+
+impl fmt::Display for UpscaleFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpscaleFilter::Nearest => write!(f, "nearest"),
+            UpscaleFilter::Lanczos => write!(f, "lanczos"),
+        }
+    }
+}
+
+/// Scales an image rendered at a fraction of the output resolution (`render_scale`) up to the
+/// output resolution. Images already at that size (e.g. downloads) are returned as is.
+pub fn upscale_to_output_resolution(config: &Config, image: AstraImage) -> AstraImage {
+    if config.render_scale() >= 1.0 {
+        return image;
+    }
+    let (width, height) = match config.output_resolution() {
+        Ok(resolution) => resolution,
+        Err(e) => {
+            config.print_if_verbose(format!("WARN: skipping upscale - {e}").as_str());
+            return image;
+        }
+    };
+    if image.dimensions() == (width, height) {
+        return image;
+    }
+    let filter = config.upscale_filter();
+    config.print_if_verbose(
+        format!(
+            "Upscaling from {}x{} to {width}x{height} ({filter})",
+            image.width(),
+            image.height()
+        )
+        .as_str(),
+    );
+    let filter = match filter {
+        UpscaleFilter::Nearest => imageops::FilterType::Nearest,
+        UpscaleFilter::Lanczos => imageops::FilterType::Lanczos3,
+    };
+    imageops::resize(&image, width, height, filter)
+}
+
+/// How saved wallpapers are named (`filename_scheme` key)
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilenameScheme {
+    /// `<prefix>_<unix_timestamp>.<ext>`
+    #[default]
+    Timestamp,
+    /// `<prefix>_<image_hash>.<ext>`, an identical image is only saved once
+    Hash,
+    /// `<prefix>_<unix_timestamp>_<image_hash>.<ext>`, unique even within the same second
+    TimestampHash,
+}
+
+impl fmt::Display for FilenameScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilenameScheme::Timestamp => write!(f, "timestamp"),
+            FilenameScheme::Hash => write!(f, "hash"),
+            FilenameScheme::TimestampHash => write!(f, "timestamp_hash"),
+        }
+    }
+}
+
+impl fmt::Display for WallpaperFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WallpaperFormat::Png => write!(f, "png"),
+            WallpaperFormat::Jpeg => write!(f, "jpeg"),
+            WallpaperFormat::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+/// Whether the OS keeps reading the wallpaper file after it is set. Windows copies the image when
+/// it is applied and macOS displays a copy in the wallpaper folder (see `update_wallpaper`), while
+/// GNOME and feh re-read the file (e.g. on next login), so temporary wallpaper files are only kept
+/// on Linux.
+const WALLPAPER_FILE_MUST_PERSIST: bool = !cfg!(any(target_os = "windows", target_os = "macos"));
+
+/// Names of the temporary files wallpapers are applied from with `--no-save`. The two names are
+/// alternated so the new file never has the name of the displayed one (which GNOME would not
+/// reload), and the previous one is deleted so at most one is left behind.
+const TEMP_WALLPAPER_FILES: [&str; 2] =
+    ["astra_temp_wallpaper_1.png", "astra_temp_wallpaper_2.png"];
+
 /// Creates a folder named "wallpapers" under the data_dir folder of Astra.
 /// For each path, see: https://lib.rs/crates/directories
 ///
@@ -24,13 +189,16 @@ pub type AstraImage = ImageBuffer<Rgb<u8>, Vec<u8>>;
 /// A `Result` containing the path to the created folder on success, or a
 /// `WallpaperGeneratorError` on failure.
 pub(super) fn create_wallpaper_folder() -> Result<PathBuf, WallpaperGeneratorError> {
-    let proj_dirs = ProjectDirs::from(QUALIFIER, ORGANIZATION, APPLICATION)
-        .ok_or_else(|| WallpaperGeneratorError::OS("could not derive data_dir".to_string()))?;
-    let path = proj_dirs.data_dir().join("Wallpapers");
+    let path = wallpaper_folder_path()?;
     create_dir_all(&path).map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
     Ok(path)
 }
 
+/// Path of the "Wallpapers" folder under the data_dir folder of Astra (may not exist yet)
+pub fn wallpaper_folder_path() -> Result<PathBuf, WallpaperGeneratorError> {
+    Ok(Config::data_dir().join("Wallpapers"))
+}
+
 /// Deletes wallpapers from the "wallpapers" folder.
 /// For each path, see: https://lib.rs/crates/directories
 ///
@@ -49,12 +217,22 @@ pub fn delete_wallpapers(
     delete_dir: bool,
     older_than: Option<&Frequency>,
 ) -> Result<(), WallpaperGeneratorError> {
-    let path = ProjectDirs::from(QUALIFIER, ORGANIZATION, APPLICATION)
-        .map(|dirs| dirs.data_dir().join("Wallpapers"))
-        .ok_or_else(|| {
-            WallpaperGeneratorError::OS("could not derive wallpapers path".to_string())
-        })?;
+    delete_wallpapers_in(
+        config,
+        &wallpaper_folder_path()?,
+        delete_all,
+        delete_dir,
+        older_than,
+    )
+}
 
+fn delete_wallpapers_in(
+    config: &Config,
+    path: &Path,
+    delete_all: bool,
+    delete_dir: bool,
+    older_than: Option<&Frequency>,
+) -> Result<(), WallpaperGeneratorError> {
     // NOTE: if user has never run astra before, the directory might not exist
     if !path.is_dir() {
         config.print_if_verbose(
@@ -65,7 +243,7 @@ pub fn delete_wallpapers(
 
     config.print_if_verbose(format!("Deleting wallpapers from {}", path.display()).as_str());
     if delete_dir {
-        remove_dir_all(&path).map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+        remove_dir_all(path).map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
         config.print_if_verbose(
             format!(
                 "Deleted all images and directory {} successfully",
@@ -74,8 +252,8 @@ pub fn delete_wallpapers(
             .as_str(),
         );
     } else if delete_all {
-        remove_dir_all(&path).map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
-        create_wallpaper_folder().map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+        remove_dir_all(path).map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+        create_dir_all(path).map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
         config.print_if_verbose(
             format!(
                 "Deleted all images from directory {} successfully",
@@ -90,15 +268,11 @@ pub fn delete_wallpapers(
             .as_secs();
         let older_than_sec = frequency.to_seconds();
         config.print_if_verbose(format!("Deleting images older than {}", &frequency).as_str());
-        let oldest_timestamp_to_keep = now - older_than_sec;
-        for entry in read_dir(&path).map_err(|e| WallpaperGeneratorError::OS(e.to_string()))? {
+        let oldest_timestamp_to_keep = now.saturating_sub(older_than_sec);
+        for entry in read_dir(path).map_err(|e| WallpaperGeneratorError::OS(e.to_string()))? {
             let entry = entry.map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
-            let file_name = entry.file_name().to_string_lossy().to_string();
-            // string like spotlight_1640000000.png
-            let timestamp_str =
-                &file_name[file_name.rfind('_').map(|i| i + 1).unwrap_or(0)..file_name.len() - 4];
-            match timestamp_str.parse::<u64>() {
-                Ok(timestamp) => {
+            match wallpaper_timestamp(&entry) {
+                Some(timestamp) => {
                     if timestamp < oldest_timestamp_to_keep {
                         remove_file(entry.path())
                             .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
@@ -108,11 +282,14 @@ pub fn delete_wallpapers(
                         );
                     }
                 }
-                Err(_) => {
-                    config.print_if_verbose(format!(
-                        "ERROR: Encountered file that is not an astra formatted image, skipping file... {}",
-                        entry.path().display()
-                    ).as_str());
+                None => {
+                    config.print_if_verbose(
+                        format!(
+                            "ERROR: Unable to determine the age of file, skipping file... {}",
+                            entry.path().display()
+                        )
+                        .as_str(),
+                    );
                     continue;
                 }
             };
@@ -122,32 +299,718 @@ pub fn delete_wallpapers(
     Ok(())
 }
 
+/// Unix timestamp of when a wallpaper was saved: the file's modification time, or the timestamp
+/// in its name if the filesystem does not report one
+fn wallpaper_timestamp(entry: &DirEntry) -> Option<u64> {
+    entry
+        .metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|age| age.as_secs())
+        .or_else(|| parse_wallpaper_timestamp(&entry.file_name().to_string_lossy()))
+}
+
+/// Timestamp in the name of a saved wallpaper, e.g. `spotlight_1640000000.png` or
+/// `nasa_apod_1640000000_<hash>.jpg`. Names using the `hash` scheme have none.
+fn parse_wallpaper_timestamp(file_name: &str) -> Option<u64> {
+    let (stem, _extension) = file_name.rsplit_once('.')?;
+    let (_prefix, rest) = stem.split_once('_')?;
+    rest.split('_').find_map(|part| part.parse::<u64>().ok())
+}
+
+/// Files astra keeps next to the "Wallpapers" folder in its data directory
+const STATE_FILES: [&str; 3] = ["state.json", "last_exec.txt", "slideshow.xml"];
+
+/// Removes what astra stored under `data_dir` (the "Wallpapers" folder and the state files) and,
+/// unless `keep_config` is set, the configuration file at `config_path`. Missing files are skipped.
+/// The folders of other instances (`instances` in the default data directory) are left as is.
+pub fn reset_data(
+    config: &Config,
+    data_dir: &Path,
+    config_path: &Path,
+    keep_config: bool,
+) -> Result<(), WallpaperGeneratorError> {
+    delete_wallpapers_in(config, &data_dir.join("Wallpapers"), true, true, None)?;
+    let config_file = (!keep_config).then_some(config_path);
+    for path in STATE_FILES
+        .iter()
+        .map(|file| data_dir.join(file))
+        .chain(config_file.map(Path::to_path_buf))
+        .filter(|path| path.is_file())
+    {
+        remove_file(&path).map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+        config.print_if_verbose(format!("Deleted {}", path.display()).as_str());
+    }
+    Ok(())
+}
+
+/// What `handle_generate_options` did with a generated image
+#[derive(Debug, PartialEq)]
+pub struct GenerationOutcome {
+    pub generator: Generator,
+    /// File the image was saved to in the wallpaper folder (`None` with `--no-save`)
+    pub saved_path: Option<PathBuf>,
+    /// File set as the wallpaper (`None` when it was not applied)
+    pub applied_path: Option<PathBuf>,
+    /// Name of the color theme picked while generating the image, if any
+    pub theme: Option<String>,
+    /// Temporary files the wallpaper was applied from, deleted by `finish`
+    temp_files: Vec<PathBuf>,
+}
+
+impl GenerationOutcome {
+    /// Runs the `post_update_hook` for the applied wallpaper, then deletes the temporary files it
+    /// was applied from when the OS does not keep reading them. Called once the wallpaper is
+    /// committed, so a slow hook does not hold back an interruption.
+    pub fn finish(&self, config: &Config) {
+        if let Some(path) = &self.applied_path {
+            run_post_update_hook(config, path);
+        }
+        for path in &self.temp_files {
+            if let Err(e) = remove_file(path) {
+                config.print_if_verbose(
+                    format!("WARN: unable to delete temporary file: {e}").as_str(),
+                );
+            }
+        }
+    }
+}
+
+impl fmt::Display for GenerationOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.generator.prefix())?;
+        if let Some(theme) = &self.theme {
+            write!(f, " (theme {theme})")?;
+        }
+        if let Some(path) = &self.saved_path {
+            write!(f, ", saved to {}", path.display())?;
+        }
+        write!(
+            f,
+            "{}",
+            if self.applied_path.is_some() {
+                ", applied"
+            } else {
+                ", not applied"
+            }
+        )
+    }
+}
+
+/// Saves and/or sets the generated image as the wallpaper, following the `--no-save`,
+/// `--no-update` and `--apply-to` flags and the `skip_if_unchanged` and `transition` settings.
+/// The saved file and the updated state are staged in `transaction`, which the caller commits
+/// once this returns, before calling `GenerationOutcome::finish`.
 pub fn handle_generate_options(
     config: &Config,
     image_buf: &AstraImage,
     image: &Generator,
     no_save: bool,
     no_update: bool,
-) -> Result<(), Box<dyn Error>> {
-    // Handle options
-    if !no_update {
-        config.print_if_verbose(
-            "NOTE: to update wallpaper, astra must save the image to astra_wallpapers folder.",
-        );
-        // Updating requires a saved image
-        let saved_image_path = save_image(config, image, image_buf)?;
-        // TODO: move verbose logs into OS implementations of update_wallpaper
-        config.print_if_verbose("Updating wallpaper...");
-        update_wallpaper(saved_image_path)?;
+    apply_to: Option<usize>,
+    transaction: &mut Transaction,
+) -> Result<GenerationOutcome, Box<dyn Error>> {
+    let mut state = config.take_staged_state().unwrap_or_else(State::load);
+    let new_image_hash = (!no_update).then(|| image_hash(image_buf));
+    if let Some(new_image_hash) = &new_image_hash {
+        if config.skip_if_unchanged() && is_unchanged(state.last_image_hash(), new_image_hash) {
+            config.print_if_verbose(
+                "Image is identical to the current wallpaper, skipping update (skip_if_unchanged)",
+            );
+            return Ok(GenerationOutcome {
+                generator: image.clone(),
+                saved_path: None,
+                applied_path: None,
+                theme: config.image_theme_name(),
+                temp_files: vec![],
+            });
+        }
+        // Transition frames are set on every monitor
+        if config.transition() && apply_to.is_none() {
+            config.print_if_verbose("Playing transition...");
+            if let Err(e) = play_transition(config, image_buf) {
+                config.print_if_verbose(format!("WARN: skipping transition - {e}").as_str());
+            }
+        }
+    }
+    let outcome = save_and_apply(
+        config,
+        image_buf,
+        image,
+        transaction,
+        (!no_save).then_some(save_image),
+        (!no_update).then_some(|config: &Config, path, transaction: &mut Transaction| {
+            set_wallpaper(config, path, Some((image, transaction)), apply_to)
+        }),
+    )?;
+    if let (Some(link), Some(saved_path)) = (config.latest_symlink(), &outcome.saved_path) {
+        match update_latest_link(link, saved_path) {
+            Ok(()) => config.print_if_verbose(
+                format!("Pointed {} at the new wallpaper", link.display()).as_str(),
+            ),
+            Err(e) => {
+                // the wallpaper is saved, only external tools reading the link are affected
+                let message = format!("failed to update {}: {e}", link.display());
+                config.print_if_verbose(format!("WARN: {message}").as_str());
+                config.log(LogLevel::Warn, message.as_str());
+            }
+        }
+    }
+    if let Some(new_image_hash) = new_image_hash {
+        state.set_last_image_hash(new_image_hash);
+        if let Some(saved_path) = &outcome.saved_path {
+            state.record_applied(AppliedWallpaper::now(saved_path.clone(), image.prefix()));
+        }
+        transaction.stage_state(state);
+    }
+    Ok(outcome)
+}
+
+/// Runs the `post_update_hook` for the wallpaper at `path`. A hook that fails is reported as a
+/// warning, the wallpaper is already updated.
+fn run_post_update_hook(config: &Config, path: &Path) {
+    let Some(hook) = config.post_update_hook() else {
+        return;
+    };
+    config.print_if_verbose(format!("Running post_update_hook: {hook}").as_str());
+    let message = match run_hook(hook, path) {
+        Ok(status) if status.success() => {
+            config.print_if_verbose(format!("post_update_hook exited with {status}").as_str());
+            return;
+        }
+        Ok(status) => format!("post_update_hook exited with {status}"),
+        Err(e) => format!("failed to run post_update_hook - {e}"),
+    };
+    config.print_if_verbose(format!("WARN: {message}").as_str());
+    config.log(LogLevel::Warn, message.as_str());
+}
+
+/// Runs `hook` until it exits, with the wallpaper `path` as its argument and in
+/// `WALLPAPER_ENV_VAR`
+fn run_hook(hook: &HookCommand, path: &Path) -> std::io::Result<ExitStatus> {
+    let mut command = hook.command().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "the command is empty")
+    })?;
+    command.arg(path).env(WALLPAPER_ENV_VAR, path).status()
+}
+
+/// Points `link` at `target` (`latest_symlink`). The link is created next to `link` then renamed
+/// over it, so readers never see a missing file. Windows requires privileges for symbolic links,
+/// so the file is copied there instead.
+fn update_latest_link(link: &Path, target: &Path) -> std::io::Result<()> {
+    let file_name = link.file_name().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "path has no file name")
+    })?;
+    let temp_link = link.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+    if temp_link.symlink_metadata().is_ok() {
+        remove_file(&temp_link)?;
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(target, &temp_link)?;
+    #[cfg(not(unix))]
+    std::fs::copy(target, &temp_link)?;
+    std::fs::rename(&temp_link, link)
+}
+
+/// Saves the image with `save` unless it is `None` (`--no-save`), and sets it as the wallpaper
+/// with `set_wallpaper` unless it is `None` (`--no-update`), from a temporary file when the image
+/// is not saved. Both stage the files they create in `transaction`, `set_wallpaper` returns the
+/// ones it created next to the wallpaper (the images of the other monitors with `per_monitor`).
+fn save_and_apply(
+    config: &Config,
+    image_buf: &AstraImage,
+    image: &Generator,
+    transaction: &mut Transaction,
+    save: Option<
+        impl FnOnce(
+            &Config,
+            &Generator,
+            &AstraImage,
+            &mut Transaction,
+        ) -> Result<PathBuf, WallpaperGeneratorError>,
+    >,
+    set_wallpaper: Option<
+        impl FnOnce(&Config, PathBuf, &mut Transaction) -> Result<Vec<PathBuf>, Box<dyn Error>>,
+    >,
+) -> Result<GenerationOutcome, Box<dyn Error>> {
+    // Read before applying, `per_monitor` generates more images with their own theme
+    let theme = config.image_theme_name();
+    let saved_path = match save {
+        Some(save) => Some(save(config, image, image_buf, transaction)?),
+        None => None,
+    };
+    let (mut applied_path, mut temp_files) = (None, vec![]);
+    if let Some(set_wallpaper) = set_wallpaper {
+        match &saved_path {
+            Some(path) => {
+                set_wallpaper(config, path.clone(), transaction)?;
+                applied_path = Some(path.clone());
+            }
+            // Updating requires an image on disk, use a temporary file instead of astra_wallpapers
+            None => {
+                let mut monitor_files = vec![];
+                let temp_path = apply_from_temp_file(
+                    config,
+                    &std::env::temp_dir(),
+                    image,
+                    image_buf,
+                    |config, path| {
+                        monitor_files = set_wallpaper(config, path, transaction)?;
+                        Ok(())
+                    },
+                )?;
+                if WALLPAPER_FILE_MUST_PERSIST {
+                    config.print_if_verbose(
+                        "NOTE: keeping the temporary file as this OS reads the wallpaper from it",
+                    );
+                } else {
+                    temp_files.push(temp_path.clone());
+                    temp_files.append(&mut monitor_files);
+                }
+                applied_path = Some(temp_path);
+            }
+        }
         config.print_if_verbose("Updated wallpaper");
     }
-    // If no_update == false, we already saved the image as its required to update wallpaper
-    if no_update && !no_save {
-        let _ = save_image(config, image, image_buf)?;
+    Ok(GenerationOutcome {
+        generator: image.clone(),
+        saved_path,
+        applied_path,
+        theme,
+        temp_files,
+    })
+}
+
+/// Generates an image with `generator`. If it fails and a `fallback_generator` is configured, the
+/// fallback generates the image instead (its own failure is returned as is).
+///
+/// Returns the generator that succeeded along with its image.
+pub fn generate_or_fallback(
+    config: &Config,
+    generator: &Generator,
+    mut generate: impl FnMut(&Generator) -> Result<AstraImage, WallpaperGeneratorError>,
+) -> Result<(Generator, AstraImage), WallpaperGeneratorError> {
+    config.reset_image_theme();
+    config.set_staged_state(None);
+    let error = match generate(generator) {
+        Ok(image) => return Ok((generator.clone(), image)),
+        Err(e) => e,
+    };
+    match config.fallback_generator() {
+        Some(fallback) if fallback.prefix() != generator.prefix() => {
+            config.explain(Decision::Failed {
+                generator: generator.prefix().to_string(),
+                error: error.to_string(),
+            });
+            config.explain(Decision::Fallback(fallback.prefix().to_string()));
+            let message = format!("{} generator failed: {error}", generator.prefix());
+            config.print_if_verbose(
+                format!("WARN: {message}, using fallback {}", fallback.prefix()).as_str(),
+            );
+            config.log(LogLevel::Warn, message.as_str());
+            config.reset_image_theme();
+            config.set_staged_state(None);
+            generate(fallback).map(|image| (fallback.clone(), image))
+        }
+        _ => Err(error),
+    }
+}
+
+/// Picks one of `candidates` at random, each one as likely as its weight in `weights` (keyed by
+/// generator prefix). Generators without a weight count as 1 and a weight of 0 excludes a
+/// generator. Returns `None` when no candidate can be picked.
+fn select_generator<'a, R: Rng + ?Sized>(
+    candidates: &'a [Generator],
+    weights: Option<&BTreeMap<String, u32>>,
+    rng: &mut R,
+) -> Option<&'a Generator> {
+    let weight = |generator: &Generator| {
+        weights
+            .and_then(|weights| weights.get(generator.prefix()))
+            .copied()
+            .unwrap_or(1) as u64
+    };
+    let total: u64 = candidates.iter().map(weight).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut roll = rng.random_range(0..total);
+    candidates.iter().find(|generator| {
+        let weight = weight(generator);
+        if roll < weight {
+            true
+        } else {
+            roll -= weight;
+            false
+        }
+    })
+}
+
+/// Generates an image with a randomly picked generator from `candidates` (see `select_generator`).
+/// If it fails, the configured `fallback_generator` is used, otherwise the failure is logged and
+/// another candidate is picked, until one succeeds or all of them failed.
+///
+/// Returns the generator that succeeded along with its image.
+pub fn generate_with_fallback(
+    config: &Config,
+    candidates: &[Generator],
+    mut generate: impl FnMut(&Generator) -> Result<AstraImage, WallpaperGeneratorError>,
+) -> Result<(Generator, AstraImage), WallpaperGeneratorError> {
+    let mut remaining = candidates.to_vec();
+    let mut failures = vec![];
+    loop {
+        let weights = config.generator_weights();
+        let picked = select_generator(&remaining, weights, &mut *config.rng()).cloned();
+        let Some(generator) = picked else {
+            break;
+        };
+        config.explain(Decision::Roll {
+            candidates: remaining.len(),
+            weighted: weights.is_some(),
+        });
+        remaining.retain(|candidate| candidate != &generator);
+        let generator = &generator;
+        config.explain(Decision::Chosen(generator.prefix().to_string()));
+        config.log(
+            LogLevel::Info,
+            format!("Selected generator: {}", generator.prefix()).as_str(),
+        );
+        if config.fallback_generator().is_some() {
+            return generate_or_fallback(config, generator, generate);
+        }
+        config.reset_image_theme();
+        config.set_staged_state(None);
+        match generate(generator) {
+            Ok(image) => return Ok((generator.clone(), image)),
+            Err(e) => {
+                config.explain(Decision::Failed {
+                    generator: generator.prefix().to_string(),
+                    error: e.to_string(),
+                });
+                let message = format!("{} generator failed: {e}", generator.prefix());
+                config.print_if_verbose(
+                    format!("WARN: {message}, trying another generator").as_str(),
+                );
+                config.log(LogLevel::Warn, message.as_str());
+                failures.push(message);
+            }
+        }
+    }
+    Err(WallpaperGeneratorError::ImageGeneration(
+        if failures.is_empty() {
+            "no generators to choose from (or all of them have a weight of 0)".to_string()
+        } else {
+            format!("all generators failed ({})", failures.join("; "))
+        },
+    ))
+}
+
+/// Runs `generate` on a worker thread and gives up once `timeout` elapses, so that a generator
+/// that hangs (e.g. a network request that never completes) cannot block a scheduled run forever.
+///
+/// The worker gets a copy of the configuration from `Config::fork`, and the state it stages is only
+/// staged in `config` once its image is received. Threads cannot be stopped safely, so a worker
+/// that timed out is left running in the background until `astra` exits, without changing the
+/// state. Without a timeout, `generate` runs on the current thread.
+pub fn generate_with_timeout(
+    config: &Config,
+    timeout: Option<Duration>,
+    generate: impl FnOnce(&Config) -> Result<AstraImage, WallpaperGeneratorError> + Send + 'static,
+) -> Result<AstraImage, WallpaperGeneratorError> {
+    let Some(timeout) = timeout else {
+        return generate(config);
+    };
+    let worker_config = config.fork();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        // The receiver is gone if the generation timed out, nobody is waiting for the result
+        let result = generate(&worker_config);
+        let _ = sender.send(result.map(|image| (image, worker_config.take_staged_state())));
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result.map(|(image, state)| {
+            config.set_staged_state(state);
+            image
+        }),
+        Err(RecvTimeoutError::Timeout) => {
+            let message = format!("generation timed out after {}s", timeout.as_secs_f64());
+            config.log(LogLevel::Warn, message.as_str());
+            Err(WallpaperGeneratorError::ImageGeneration(message))
+        }
+        Err(RecvTimeoutError::Disconnected) => Err(WallpaperGeneratorError::ImageGeneration(
+            "generation stopped unexpectedly".to_string(),
+        )),
+    }
+}
+
+/// Sets `path` as the wallpaper. With `per_monitor`, `render` (the generator of the image and the
+/// transaction the files it creates are staged in) renders an image at the resolution of each
+/// other monitor, `None` sets `path` on every monitor.
+///
+/// Returns the images rendered for the other monitors.
+fn set_wallpaper(
+    config: &Config,
+    path: PathBuf,
+    render: Option<(&Generator, &mut Transaction)>,
+    apply_to: Option<usize>,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    // TODO: move verbose logs into OS implementations of update_wallpaper
+    config.print_if_verbose("Updating wallpaper...");
+    #[cfg(not(target_os = "macos"))]
+    if config.all_spaces() {
+        config.print_if_verbose("WARN: all_spaces is only supported on macOS, ignoring it");
     }
+    #[cfg(target_os = "windows")]
+    if let Some(index) = apply_to {
+        config.print_if_verbose(format!("Updating wallpaper on monitor {index}...").as_str());
+        let monitors = get_wallpaper_monitors()?;
+        set_wallpaper_on_monitor(path, index, monitors.len(), |path, index| {
+            Ok(update_wallpaper_on_monitor(path, &monitors[index])?)
+        })?;
+    } else if config.per_monitor() {
+        config.print_if_verbose("Updating wallpaper on each monitor...");
+        let monitors = get_wallpaper_monitors()?;
+        let paths = match render {
+            Some((generator, transaction)) => {
+                let resolutions: Vec<(u32, u32)> =
+                    monitors.iter().map(|monitor| monitor.resolution).collect();
+                wallpapers_per_monitor(
+                    config,
+                    generator,
+                    &path,
+                    &resolutions,
+                    transaction,
+                    |(width, height)| {
+                        let mut monitor_config = config.fork();
+                        monitor_config.set_resolution_override(width, height);
+                        let image = generator.with_default_mode(&monitor_config)?;
+                        Ok(apply_post_processing(&monitor_config, image))
+                    },
+                )?
+            }
+            None => vec![path.clone(); monitors.len()],
+        };
+        update_wallpaper_per_monitor(&monitors, &paths)?;
+        return Ok(paths
+            .into_iter()
+            .filter(|monitor_path| monitor_path != &path)
+            .collect());
+    } else {
+        update_wallpaper(path)?;
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = render;
+        #[cfg(target_os = "macos")]
+        if let Some(index) = apply_to {
+            config.print_if_verbose(format!("Updating wallpaper on display {index}...").as_str());
+            set_wallpaper_on_monitor(path, index, screen_count()?, |path, index| {
+                Ok(update_wallpaper_on_screen(path, index)?)
+            })?;
+            return Ok(vec![]);
+        }
+        #[cfg(not(target_os = "macos"))]
+        if apply_to.is_some() {
+            return Err(apply_to_unsupported().into());
+        }
+        if config.per_monitor() {
+            config.print_if_verbose(
+                "WARN: per_monitor is only supported on Windows, updating all displays",
+            );
+        }
+        #[cfg(target_os = "macos")]
+        if config.all_spaces() {
+            config.print_if_verbose("Updating wallpaper on every space...");
+            update_wallpaper_all_spaces(path)?;
+            return Ok(vec![]);
+        }
+        update_wallpaper(path)?;
+    }
+    Ok(vec![])
+}
+
+/// Sets an image already on disk as the wallpaper (on every monitor) and runs the
+/// `post_update_hook`, e.g. to apply a wallpaper listed by `astra recent` again
+pub fn apply_wallpaper_file(config: &Config, path: PathBuf) -> Result<(), Box<dyn Error>> {
+    set_wallpaper(config, path.clone(), None, None)?;
+    run_post_update_hook(config, &path);
     Ok(())
 }
 
+/// Fails early (before an image is generated) when `--apply-to <index>` cannot be honored
+pub fn check_apply_to(index: usize) -> Result<(), WallpaperGeneratorError> {
+    #[cfg(target_os = "windows")]
+    {
+        // the same enumeration as `set_wallpaper`, so the index designates the same monitor
+        let monitor_count = get_wallpaper_monitors()
+            .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?
+            .len();
+        validate_monitor_index(index, monitor_count)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let screen_count =
+            screen_count().map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+        validate_monitor_index(index, screen_count)
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        let _ = index;
+        Err(apply_to_unsupported())
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn apply_to_unsupported() -> WallpaperGeneratorError {
+    WallpaperGeneratorError::OS(
+        "--apply-to is only supported on Windows and macOS, where each monitor can have its own \
+         wallpaper"
+            .to_string(),
+    )
+}
+
+/// Checks that `index` is one of the `monitor_count` connected monitors
+#[cfg(any(target_os = "windows", target_os = "macos", test))]
+fn validate_monitor_index(
+    index: usize,
+    monitor_count: usize,
+) -> Result<(), WallpaperGeneratorError> {
+    if index < monitor_count {
+        Ok(())
+    } else {
+        Err(WallpaperGeneratorError::OS(format!(
+            "no monitor at index {index}, {monitor_count} monitor(s) detected (the first one is 0)"
+        )))
+    }
+}
+
+/// Sets `path` as the wallpaper of the monitor at `index` only, using `set_on_monitor`
+#[cfg(any(target_os = "windows", target_os = "macos", test))]
+fn set_wallpaper_on_monitor(
+    path: PathBuf,
+    index: usize,
+    monitor_count: usize,
+    set_on_monitor: impl FnOnce(PathBuf, usize) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    validate_monitor_index(index, monitor_count)?;
+    set_on_monitor(path, index)
+}
+
+/// Wallpapers of each monitor with `per_monitor`, in the order of `resolutions`. The first
+/// monitor whose resolution (scaled down to fit `max_resolution`) matches the image at `path`
+/// keeps it, every other monitor gets an image rendered by `generate` at its own resolution,
+/// saved next to `path` as `<name>_monitor<index>.png`. The name follows the wallpaper's, so with
+/// `single_file_mode` the images of the monitors are replaced on every run too. The images
+/// that did not exist before are staged in `transaction`.
+#[cfg(any(target_os = "windows", test))]
+fn wallpapers_per_monitor(
+    config: &Config,
+    generator: &Generator,
+    path: &Path,
+    resolutions: &[(u32, u32)],
+    transaction: &mut Transaction,
+    mut generate: impl FnMut((u32, u32)) -> Result<AstraImage, WallpaperGeneratorError>,
+) -> Result<Vec<PathBuf>, WallpaperGeneratorError> {
+    let dimensions =
+        image::image_dimensions(path).map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+    let resolutions: Vec<(u32, u32)> = resolutions
+        .iter()
+        .map(|&resolution| config.capped_resolution(resolution))
+        .collect();
+    let reused = resolutions
+        .iter()
+        .position(|&resolution| resolution == dimensions);
+    let stem = path.file_stem().map_or_else(
+        || generator.prefix().to_string(),
+        |stem| stem.to_string_lossy().to_string(),
+    );
+    resolutions
+        .iter()
+        .enumerate()
+        .map(|(index, &(width, height))| {
+            if reused == Some(index) {
+                return Ok(path.to_path_buf());
+            }
+            config.print_if_verbose(
+                format!("Generating a {width}x{height} image for monitor {index}...").as_str(),
+            );
+            let image = generate((width, height))?;
+            let monitor_path = path.with_file_name(format!("{stem}_monitor{index}.png"));
+            let created = !monitor_path.exists();
+            save_atomically(
+                &image,
+                &monitor_path,
+                image::ImageFormat::Png,
+                &generation_metadata(config, generator),
+            )?;
+            if created {
+                transaction.stage_file(monitor_path.clone());
+            }
+            Ok(monitor_path)
+        })
+        .collect()
+}
+
+/// Writes the image to one of the `TEMP_WALLPAPER_FILES` in `temp_dir` and sets it as the
+/// wallpaper with `set_wallpaper`. The file is deleted if it could not be set, otherwise the other
+/// one (the previous wallpaper) is deleted and the caller decides whether this one is kept (see
+/// `WALLPAPER_FILE_MUST_PERSIST`).
+///
+/// Returns the path of the temporary file.
+fn apply_from_temp_file(
+    config: &Config,
+    temp_dir: &Path,
+    generator: &Generator,
+    image: &AstraImage,
+    set_wallpaper: impl FnOnce(&Config, PathBuf) -> Result<(), Box<dyn Error>>,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let [first, second] = TEMP_WALLPAPER_FILES.map(|name| temp_dir.join(name));
+    let (temp_path, previous_path) = if first.exists() {
+        (second, first)
+    } else {
+        (first, second)
+    };
+    save_atomically(
+        image,
+        &temp_path,
+        image::ImageFormat::Png,
+        &generation_metadata(config, generator),
+    )?;
+    config.print_if_verbose(
+        format!("Image saved to temporary file: {}", temp_path.display()).as_str(),
+    );
+
+    if let Err(e) = set_wallpaper(config, temp_path.clone()) {
+        if let Err(e) = remove_file(&temp_path) {
+            config.print_if_verbose(format!("WARN: unable to delete temporary file: {e}").as_str());
+        }
+        return Err(e);
+    }
+    if previous_path.exists()
+        && let Err(e) = remove_file(&previous_path)
+    {
+        config.print_if_verbose(
+            format!("WARN: unable to delete previous temporary file: {e}").as_str(),
+        );
+    }
+    Ok(temp_path)
+}
+
+/// Returns a hex encoded hash of the image's dimensions and pixels
+pub(super) fn image_hash(image: &AstraImage) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&image.width().to_le_bytes());
+    hasher.update(&image.height().to_le_bytes());
+    hasher.update(image.as_raw());
+    hasher.finalize().to_hex().to_string()
+}
+
+/// Returns true if the hash of the new image matches the hash of the last applied image
+fn is_unchanged(last_image_hash: Option<&str>, new_image_hash: &str) -> bool {
+    last_image_hash == Some(new_image_hash)
+}
+
 /// Enum that specifies the color map generation algorithm
 pub(super) enum Operator {
     Gradient,
@@ -163,19 +1026,36 @@ pub(super) enum Operator {
 ///
 /// # Returns
 ///
-/// A vector of color map entries.
-pub(super) fn create_color_map(op: Operator, steps: usize, colors: &[[u8; 3]]) -> Vec<[u8; 3]> {
+/// A vector of color map entries, or an error if `colors` is empty.
+pub(super) fn create_color_map(
+    op: Operator,
+    steps: usize,
+    colors: &[[u8; 3]],
+) -> Result<Vec<[u8; 3]>, WallpaperGeneratorError> {
+    if colors.is_empty() {
+        return Err(WallpaperGeneratorError::ImageGeneration(
+            "cannot create a color map from a theme without colors".to_string(),
+        ));
+    }
     let mut color_map = Vec::with_capacity(steps);
     match op {
         Operator::Gradient => {
-            if colors.len() == 1 {
+            // a single step has no room for a gradient (and would divide by zero below)
+            if colors.len() == 1 || steps < 2 {
                 for _ in 0..steps {
                     color_map.push(colors[0]);
                 }
+            } else if steps < colors.len() {
+                // too few steps to blend between every color, pick evenly spaced colors instead
+                for i in 0..steps {
+                    color_map.push(colors[i * (colors.len() - 1) / (steps - 1)]);
+                }
             } else {
                 let color_steps = (steps - 1) / (colors.len() - 1);
                 for i in 0..steps {
-                    let color_idx = (i as f64 / color_steps as f64).floor() as usize;
+                    // `color_steps` is rounded down, the last entries can run past the last color
+                    let color_idx =
+                        ((i as f64 / color_steps as f64).floor() as usize).min(colors.len() - 1);
                     if color_idx == (colors.len() - 1) {
                         color_map.push(colors[color_idx]);
                     } else {
@@ -190,7 +1070,7 @@ pub(super) fn create_color_map(op: Operator, steps: usize, colors: &[[u8; 3]]) -
             }
         }
     }
-    color_map
+    Ok(color_map)
 }
 
 /// Interpolates between two colors to create a new color.
@@ -204,23 +1084,52 @@ pub(super) fn create_color_map(op: Operator, steps: usize, colors: &[[u8; 3]]) -
 /// # Returns
 ///
 /// A new color that is a mix of `color1` and `color2`.
-fn mix_color(color1: [u8; 3], color2: [u8; 3], weight_color_2: f64) -> [u8; 3] {
+pub(super) fn mix_color(color1: [u8; 3], color2: [u8; 3], weight_color_2: f64) -> [u8; 3] {
     let r = color1[0] as f64 * (1.0 - weight_color_2) + color2[0] as f64 * weight_color_2;
     let g = color1[1] as f64 * (1.0 - weight_color_2) + color2[1] as f64 * weight_color_2;
     let b = color1[2] as f64 * (1.0 - weight_color_2) + color2[2] as f64 * weight_color_2;
     [r as u8, g as u8, b as u8]
 }
 
+/// File an image was downloaded as (e.g. a JPEG photo), saved as is instead of re-encoding the
+/// image decoded from it (`spotlight_gen.keep_original`)
+#[derive(Clone, Debug)]
+pub struct OriginalImage {
+    bytes: Vec<u8>,
+    extension: &'static str,
+    // `image_hash` of the decoded image, the file is only kept while the image is unchanged
+    decoded_hash: String,
+}
+
+impl OriginalImage {
+    /// `None` if the format of `bytes` is unknown
+    pub(super) fn new(bytes: Vec<u8>, decoded: &AstraImage) -> Option<Self> {
+        let format = image::guess_format(&bytes).ok()?;
+        Some(Self {
+            bytes,
+            extension: format.extensions_str().first()?,
+            decoded_hash: image_hash(decoded),
+        })
+    }
+
+    /// Whether `image` is still the image decoded from the file (not cropped, padded or
+    /// post-processed since)
+    fn matches(&self, image: &AstraImage) -> bool {
+        image_hash(image) == self.decoded_hash
+    }
+}
+
 /// Saves the given image to a file in the desktop wallpaper folder.
 ///
 /// The file is named using the current UNIX timestamp to ensure uniqueness.
-/// The image is saved in PNG format.
+/// The image is saved in the format configured for `generator` (PNG by default).
 ///
 /// # Arguments
 ///
 /// * `config` - A reference to the `Config` struct.
 /// * `prefix` - A string to prepend to the file name.
 /// * `image` - A reference to the `ImageBuffer` containing the image to save.
+/// * `transaction` - The run's transaction, the new file is removed on rollback.
 ///
 /// # Returns
 ///
@@ -230,18 +1139,130 @@ pub fn save_image(
     config: &Config,
     generator: &Generator,
     image: &AstraImage,
+    transaction: &mut Transaction,
 ) -> Result<PathBuf, WallpaperGeneratorError> {
     config.print_if_verbose("Saving image to astra_wallpapers folder...");
-    let mut save_path = create_wallpaper_folder()?;
+    let folder = create_wallpaper_folder()?;
+    save_image_to_folder(config, &folder, generator, image, transaction)
+}
 
-    let time = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))?;
+/// Writes `image` to `path` with `write_atomically`. PNGs get `metadata` as text chunks.
+fn save_atomically(
+    image: &AstraImage,
+    path: &Path,
+    format: image::ImageFormat,
+    metadata: &[(String, String)],
+) -> Result<(), WallpaperGeneratorError> {
+    write_atomically(path, |partial_path| {
+        if format == image::ImageFormat::Png {
+            save_png_with_metadata(image, partial_path, metadata)
+        } else {
+            image
+                .save_with_format(partial_path, format)
+                .map_err(|_| WallpaperGeneratorError::ImageSave)
+        }
+    })
+}
 
-    save_path = save_path.join(format!("{}_{}.png", generator.prefix(), time.as_secs()));
-    image
-        .save(&save_path)
-        .map_err(|_| WallpaperGeneratorError::ImageSave)?;
+/// Writes a file with `write` next to `path` under a temporary name then renames it to `path`, so
+/// a wallpaper (or state) file is either complete or absent even if astra is stopped mid write.
+/// The temporary file is removed if any step fails.
+pub(crate) fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&Path) -> Result<(), WallpaperGeneratorError>,
+) -> Result<(), WallpaperGeneratorError> {
+    let file_name = path
+        .file_name()
+        .ok_or(WallpaperGeneratorError::ImageSave)?
+        .to_string_lossy();
+    let partial_path = path.with_file_name(format!(".{file_name}.partial"));
+    let result = write(&partial_path).and_then(|_| {
+        std::fs::rename(&partial_path, path).map_err(|e| WallpaperGeneratorError::OS(e.to_string()))
+    });
+    if result.is_err() {
+        let _ = remove_file(&partial_path);
+    }
+    result
+}
+
+/// Saves the given image to a file in `folder`.
+///
+/// When `single_file_mode` is enabled in the user config, the image is always written to
+/// `current.<ext>` (overwriting the previous wallpaper). Otherwise, the file is named after the
+/// `filename_scheme` (`<prefix>_<unix_timestamp>.<ext>` by default), where `<ext>` depends on the
+/// generator's `format` (and on the image itself with `auto`). With the `hash` scheme, an image
+/// that was already saved is not written again. The downloaded file of the image is written as is
+/// when the generator kept it (`Config::set_original_image`) and the image was not changed since.
+/// A file that did not exist before is staged in `transaction`, to be removed if the wallpaper
+/// cannot be applied.
+fn save_image_to_folder(
+    config: &Config,
+    folder: &Path,
+    generator: &Generator,
+    image: &AstraImage,
+    transaction: &mut Transaction,
+) -> Result<PathBuf, WallpaperGeneratorError> {
+    let original = config.original_image().filter(|original| {
+        let unchanged = original.matches(image);
+        if !unchanged {
+            config.print_if_verbose(
+                "Image was changed after it was downloaded (fit or post processing), re-encoding it",
+            );
+        }
+        unchanged
+    });
+    let extension = match &original {
+        Some(original) => original.extension,
+        None => config
+            .wallpaper_format(generator)
+            .resolve(image)
+            .extension(),
+    };
+    let file_name = if config.single_file_mode() {
+        config.print_if_verbose("Single file mode enabled, replacing the current wallpaper file");
+        let [first, second] = SINGLE_FILE_MODE_FILE_STEMS;
+        let (stem, previous_stem) = if single_file_mode_files(folder, first).is_empty() {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        // removed once the new wallpaper is applied
+        for previous in single_file_mode_files(folder, previous_stem) {
+            transaction.stage_removal(previous);
+        }
+        format!("{stem}.{extension}")
+    } else {
+        wallpaper_file_name(config.filename_scheme(), generator, image, extension)?
+    };
+
+    let save_path = folder.join(&file_name);
+    if config.filename_scheme() == FilenameScheme::Hash && save_path.is_file() {
+        config.print_if_verbose(
+            format!(
+                "Identical image already saved to {}, skipping save",
+                save_path.display()
+            )
+            .as_str(),
+        );
+        return Ok(save_path);
+    }
+    let created = !save_path.exists();
+    match original {
+        Some(original) => write_atomically(&save_path, |partial_path| {
+            std::fs::write(partial_path, &original.bytes)
+                .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))
+        })?,
+        None => {
+            let format = image::ImageFormat::from_extension(extension)
+                .ok_or(WallpaperGeneratorError::ImageSave)?;
+            save_atomically(
+                image,
+                &save_path,
+                format,
+                &generation_metadata(config, generator),
+            )?
+        }
+    }
     config.print_if_verbose(
         format!(
             "Image saved to astra_wallpapers folder: {}",
@@ -249,9 +1270,87 @@ pub fn save_image(
         )
         .as_str(),
     );
+    if created {
+        transaction.stage_file(save_path.clone());
+    }
     Ok(save_path)
 }
 
+/// Files of `folder` saved under `stem` by `single_file_mode`, in any format and including the
+/// images of the other monitors (`<stem>_monitor<index>.png`)
+fn single_file_mode_files(folder: &Path, stem: &str) -> Vec<PathBuf> {
+    let Ok(entries) = read_dir(folder) else {
+        return vec![];
+    };
+    let monitor_prefix = format!("{stem}_monitor");
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path.file_stem().is_some_and(|file_stem| {
+                    let file_stem = file_stem.to_string_lossy();
+                    file_stem == stem || file_stem.starts_with(&monitor_prefix)
+                })
+        })
+        .collect()
+}
+
+/// Name of a wallpaper saved by `generator` following `scheme`
+fn wallpaper_file_name(
+    scheme: FilenameScheme,
+    generator: &Generator,
+    image: &AstraImage,
+    extension: &str,
+) -> Result<String, WallpaperGeneratorError> {
+    let timestamp = || {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .map_err(|e| WallpaperGeneratorError::OS(e.to_string()))
+    };
+    let prefix = generator.prefix();
+    Ok(match scheme {
+        FilenameScheme::Timestamp => format!("{prefix}_{}.{extension}", timestamp()?),
+        FilenameScheme::Hash => format!("{prefix}_{}.{extension}", image_hash(image)),
+        FilenameScheme::TimestampHash => {
+            format!(
+                "{prefix}_{}_{}.{extension}",
+                timestamp()?,
+                image_hash(image)
+            )
+        }
+    })
+}
+
+/// Decodes an image downloaded or read by a generator. Transparent pixels are composited over
+/// the configured `background` color (black by default) instead of revealing whatever color is
+/// stored under the alpha channel.
+pub(super) fn load_image(
+    config: &Config,
+    bytes: &[u8],
+) -> Result<AstraImage, WallpaperGeneratorError> {
+    let image = image::load_from_memory(bytes)
+        .map_err(|e| WallpaperGeneratorError::ImageGeneration(e.to_string()))?;
+    let background = config
+        .background()
+        .map(parse_color)
+        .transpose()
+        .map_err(WallpaperGeneratorError::Parse)?
+        .unwrap_or([0, 0, 0]);
+    Ok(composite_over(&image, background))
+}
+
+fn composite_over(image: &DynamicImage, background: [u8; 3]) -> AstraImage {
+    if !image.color().has_alpha() {
+        return image.to_rgb8();
+    }
+    let rgba = image.to_rgba8();
+    ImageBuffer::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        Rgb(mix_color(background, [r, g, b], a as f64 / 255.0))
+    })
+}
+
 /// Download an image from the URL into memory
 ///
 /// # Retuns
@@ -336,10 +1435,12 @@ pub fn average_color(image: &AstraImage) -> Rgb<u8> {
         rgb_avg.1 += g.powi(2);
         rgb_avg.2 += b.powi(2);
     });
+    // `len` counts subpixels, the average is over pixels
+    let pixel_count = (image.width() as f64 * image.height() as f64).max(1.0);
     Rgb::from([
-        (rgb_avg.0 / image.len() as f64).sqrt() as u8,
-        (rgb_avg.1 / image.len() as f64).sqrt() as u8,
-        (rgb_avg.2 / image.len() as f64).sqrt() as u8,
+        (rgb_avg.0 / pixel_count).sqrt() as u8,
+        (rgb_avg.1 / pixel_count).sqrt() as u8,
+        (rgb_avg.2 / pixel_count).sqrt() as u8,
     ])
 }
 
@@ -384,6 +1485,925 @@ impl Error for WallpaperGeneratorError {}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn test_save_image_single_file_mode_leaves_one_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::from_json(r#"{ "single_file_mode": true }"#);
+        let image: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb([1, 2, 3]));
+
+        for stem in ["current_1", "current_2", "current_1"] {
+            let mut transaction = Transaction::with_state_path(dir.path().join("state.json"));
+            let path = save_image_to_folder(
+                &config,
+                dir.path(),
+                &Generator::Julia,
+                &image,
+                &mut transaction,
+            )
+            .unwrap();
+            // the new wallpaper has another name than the displayed one
+            assert_eq!(path, dir.path().join(format!("{stem}.png")));
+            transaction.commit(&config, || Ok(())).unwrap();
+            assert_eq!(read_dir(dir.path()).unwrap().count(), 1);
+        }
+
+        // a run that fails leaves the displayed wallpaper as it was
+        let mut transaction = Transaction::with_state_path(dir.path().join("state.json"));
+        save_image_to_folder(
+            &config,
+            dir.path(),
+            &Generator::Julia,
+            &image,
+            &mut transaction,
+        )
+        .unwrap();
+        transaction.rollback(&config);
+        let entries: Vec<_> = read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(entries, vec![std::ffi::OsString::from("current_1.png")]);
+    }
+
+    #[test]
+    fn test_save_image_default_mode_uses_timestamped_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::from_json("{}");
+        let image: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb([1, 2, 3]));
+
+        let path = save_image_to_folder(
+            &config,
+            dir.path(),
+            &Generator::Julia,
+            &image,
+            &mut Transaction::default(),
+        )
+        .unwrap();
+        let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+        assert!(file_name.starts_with("julia_"));
+        assert!(file_name.ends_with(".png"));
+    }
+
+    #[test]
+    fn test_failed_save_leaves_no_partial_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::from_json(r#"{ "single_file_mode": true }"#);
+        let image: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb([1, 2, 3]));
+        // a non-empty directory in place of the wallpaper makes the final rename fail
+        let blocker = dir.path().join("current_1.png");
+        std::fs::create_dir(&blocker).unwrap();
+        std::fs::write(blocker.join("keep"), b"").unwrap();
+
+        assert!(
+            save_image_to_folder(
+                &config,
+                dir.path(),
+                &Generator::Julia,
+                &image,
+                &mut Transaction::default()
+            )
+            .is_err()
+        );
+        let entries: Vec<_> = read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(entries, vec![std::ffi::OsString::from("current_1.png")]);
+        assert!(blocker.is_dir());
+    }
+
+    #[test]
+    fn test_keep_original_writes_downloaded_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::from_json("{}");
+        let photo: AstraImage =
+            ImageBuffer::from_fn(16, 9, |x, y| Rgb([(x * 15) as u8, (y * 28) as u8, 200]));
+        let mut bytes = std::io::Cursor::new(vec![]);
+        photo
+            .write_to(&mut bytes, image::ImageFormat::Jpeg)
+            .unwrap();
+        let bytes = bytes.into_inner();
+        let decoded = load_image(&config, &bytes).unwrap();
+        config.set_original_image(OriginalImage::new(bytes.clone(), &decoded));
+
+        let path = save_image_to_folder(
+            &config,
+            dir.path(),
+            &Generator::Spotlight,
+            &decoded,
+            &mut Transaction::default(),
+        )
+        .unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+        let file_name = path.file_name().unwrap().to_string_lossy();
+        assert!(file_name.ends_with(".jpg"));
+        assert!(parse_wallpaper_timestamp(&file_name).is_some());
+
+        // a cropped image no longer matches the downloaded file
+        let cropped = imageops::crop_imm(&decoded, 0, 0, 9, 9).to_image();
+        let path = save_image_to_folder(
+            &config,
+            dir.path(),
+            &Generator::Spotlight,
+            &cropped,
+            &mut Transaction::default(),
+        )
+        .unwrap();
+        assert!(path.to_string_lossy().ends_with(".png"));
+    }
+
+    #[test]
+    fn test_save_image_uses_format_of_generator() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::from_json(r#"{ "spotlight_gen": { "format": "jpeg" } }"#);
+        let image: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb([1, 2, 3]));
+
+        let spotlight = save_image_to_folder(
+            &config,
+            dir.path(),
+            &Generator::Spotlight,
+            &image,
+            &mut Transaction::default(),
+        )
+        .unwrap();
+        let julia = save_image_to_folder(
+            &config,
+            dir.path(),
+            &Generator::Julia,
+            &image,
+            &mut Transaction::default(),
+        )
+        .unwrap();
+        assert!(spotlight.to_string_lossy().ends_with(".jpg"));
+        assert_eq!(
+            image::guess_format(&std::fs::read(&spotlight).unwrap()).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+        assert_eq!(
+            image::guess_format(&std::fs::read(&julia).unwrap()).unwrap(),
+            image::ImageFormat::Png
+        );
+
+        let config = Config::from_json(r#"{ "format": "jpg", "julia_gen": { "format": "png" } }"#);
+        assert_eq!(
+            config.wallpaper_format(&Generator::Truchet),
+            WallpaperFormat::Jpeg
+        );
+        assert_eq!(
+            config.wallpaper_format(&Generator::Julia),
+            WallpaperFormat::Png
+        );
+    }
+
+    #[test]
+    fn test_auto_format_picks_png_for_flat_images_and_jpeg_for_photos() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::from_json(r#"{ "format": "auto" }"#);
+        let solid: AstraImage = ImageBuffer::from_pixel(128, 128, Rgb([40, 90, 200]));
+        let photo: AstraImage = ImageBuffer::from_fn(128, 128, |x, y| {
+            Rgb([(x * 2) as u8, (y * 2) as u8, (x * y) as u8])
+        });
+
+        let solid_path = save_image_to_folder(
+            &config,
+            dir.path(),
+            &Generator::Julia,
+            &solid,
+            &mut Transaction::default(),
+        )
+        .unwrap();
+        let photo_path = save_image_to_folder(
+            &config,
+            dir.path(),
+            &Generator::Julia,
+            &photo,
+            &mut Transaction::default(),
+        )
+        .unwrap();
+        assert!(solid_path.to_string_lossy().ends_with(".png"));
+        assert_eq!(
+            image::guess_format(&std::fs::read(&solid_path).unwrap()).unwrap(),
+            image::ImageFormat::Png
+        );
+        assert!(photo_path.to_string_lossy().ends_with(".jpg"));
+        assert_eq!(
+            image::guess_format(&std::fs::read(&photo_path).unwrap()).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+
+        // An explicit format overrides auto
+        let config = Config::from_json(r#"{ "format": "auto", "julia_gen": { "format": "png" } }"#);
+        assert_eq!(
+            config.wallpaper_format(&Generator::Julia).resolve(&photo),
+            WallpaperFormat::Png
+        );
+    }
+
+    #[test]
+    fn test_rollback_removes_only_files_created_by_the_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::from_json(r#"{ "filename_scheme": "hash" }"#);
+        let image: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb([1, 2, 3]));
+        let saved = save_image_to_folder(
+            &config,
+            dir.path(),
+            &Generator::Julia,
+            &image,
+            &mut Transaction::default(),
+        )
+        .unwrap();
+
+        // the identical image is already saved, rolling back must keep it
+        let mut transaction = Transaction::default();
+        let again = save_image_to_folder(
+            &config,
+            dir.path(),
+            &Generator::Julia,
+            &image,
+            &mut transaction,
+        )
+        .unwrap();
+        assert_eq!(again, saved);
+        transaction.rollback(&config);
+        assert!(saved.is_file());
+
+        let mut transaction = Transaction::default();
+        let other: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb([4, 5, 6]));
+        let new = save_image_to_folder(
+            &config,
+            dir.path(),
+            &Generator::Julia,
+            &other,
+            &mut transaction,
+        )
+        .unwrap();
+        transaction.rollback(&config);
+        assert!(!new.exists());
+        assert!(saved.is_file());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hook_receives_wallpaper_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let wallpaper = dir.path().join("astra julia.png");
+        let argument = dir.path().join("argument.txt");
+        let env = dir.path().join("env.txt");
+
+        let hook: HookCommand = serde_json::from_value(serde_json::json!(format!(
+            "printf %s \"$1\" > '{}'",
+            argument.display()
+        )))
+        .unwrap();
+        assert!(run_hook(&hook, &wallpaper).unwrap().success());
+        assert_eq!(
+            std::fs::read_to_string(&argument).unwrap(),
+            wallpaper.to_string_lossy()
+        );
+
+        let hook: HookCommand = serde_json::from_value(serde_json::json!([
+            "sh",
+            "-c",
+            format!("printf %s \"${WALLPAPER_ENV_VAR}\" > '{}'", env.display())
+        ]))
+        .unwrap();
+        assert!(run_hook(&hook, &wallpaper).unwrap().success());
+        assert_eq!(
+            std::fs::read_to_string(&env).unwrap(),
+            wallpaper.to_string_lossy()
+        );
+
+        let hook = HookCommand::Shell("exit 3".to_string());
+        assert!(!run_hook(&hook, &wallpaper).unwrap().success());
+        assert!(run_hook(&HookCommand::Args(vec![]), &wallpaper).is_err());
+    }
+
+    #[test]
+    fn test_hash_filename_scheme_names_file_after_image() {
+        let image: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb([1, 2, 3]));
+        let hash = image_hash(&image);
+
+        let name =
+            wallpaper_file_name(FilenameScheme::Hash, &Generator::Julia, &image, "png").unwrap();
+        assert_eq!(name, format!("julia_{hash}.png"));
+        assert_eq!(parse_wallpaper_timestamp(&name), None);
+
+        let name = wallpaper_file_name(
+            FilenameScheme::TimestampHash,
+            &Generator::NasaAPOD { date: None },
+            &image,
+            "jpg",
+        )
+        .unwrap();
+        assert!(name.starts_with("nasa_apod_") && name.ends_with(&format!("_{hash}.jpg")));
+        assert!(parse_wallpaper_timestamp(&name).is_some());
+        assert_eq!(
+            parse_wallpaper_timestamp("spotlight_1640000000.png"),
+            Some(1640000000)
+        );
+    }
+
+    #[test]
+    fn test_hash_filename_scheme_skips_saving_identical_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::from_json(r#"{ "filename_scheme": "hash" }"#);
+        let image: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb([1, 2, 3]));
+
+        let first = save_image_to_folder(
+            &config,
+            dir.path(),
+            &Generator::Julia,
+            &image,
+            &mut Transaction::default(),
+        )
+        .unwrap();
+        std::fs::write(&first, b"already saved").unwrap();
+        let second = save_image_to_folder(
+            &config,
+            dir.path(),
+            &Generator::Julia,
+            &image,
+            &mut Transaction::default(),
+        )
+        .unwrap();
+        assert_eq!(first, second);
+        // the existing file was not overwritten
+        assert_eq!(std::fs::read(&second).unwrap(), b"already saved");
+        assert_eq!(read_dir(dir.path()).unwrap().count(), 1);
+
+        let other: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb([4, 5, 6]));
+        save_image_to_folder(
+            &config,
+            dir.path(),
+            &Generator::Julia,
+            &other,
+            &mut Transaction::default(),
+        )
+        .unwrap();
+        assert_eq!(read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_older_than_cleanup_uses_modification_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::from_json("{}");
+        let now = SystemTime::now();
+        let two_days_ago = now - Duration::from_secs(2 * 24 * 60 * 60);
+        // names do not match the age, only the modification time counts
+        for (name, modified) in [
+            ("julia_1.png", now),
+            ("julia_9999999999.png", two_days_ago),
+            ("downloaded.jpg", two_days_ago),
+            ("notes.txt", now),
+        ] {
+            let file = std::fs::File::create(dir.path().join(name)).unwrap();
+            file.set_modified(modified).unwrap();
+        }
+
+        let frequency = Frequency::new("1d").unwrap();
+        delete_wallpapers_in(&config, dir.path(), false, false, Some(&frequency)).unwrap();
+        let mut remaining: Vec<String> = read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, vec!["julia_1.png", "notes.txt"]);
+    }
+
+    #[test]
+    fn test_explain_mentions_chosen_generator_and_excluded_ones() {
+        let mut config = Config::from_json(r#"{ "generators": ["julia"] }"#);
+        config.enable_explain();
+        let candidates = config.candidate_generators();
+        let (generator, _) = generate_with_fallback(&config, &candidates, |_| {
+            Ok(ImageBuffer::from_pixel(1, 1, Rgb([0, 0, 0])))
+        })
+        .unwrap();
+        assert_eq!(generator, Generator::Julia);
+
+        let explanation = config.explanation();
+        assert!(explanation.contains(&Decision::Chosen("julia".to_string())));
+        assert!(explanation.contains(&Decision::Excluded {
+            generator: "spotlight".to_string(),
+            reason: "not in generators",
+        }));
+        let lines: Vec<String> = explanation.iter().map(|d| d.to_string()).collect();
+        assert!(lines.contains(&"excluded spotlight: not in generators".to_string()));
+        assert!(lines.contains(&"chosen generator: julia".to_string()));
+    }
+
+    #[test]
+    fn test_wallpaper_folder_path_is_in_data_dir() {
+        let path = wallpaper_folder_path().unwrap();
+        assert!(path.ends_with("Wallpapers"));
+        assert_eq!(path.parent(), Some(Config::data_dir().as_path()));
+    }
+
+    #[test]
+    fn test_apply_to_sets_only_the_given_monitor() {
+        let monitor_count = 2;
+        let mut updated = vec![];
+        set_wallpaper_on_monitor(PathBuf::from("a.png"), 1, monitor_count, |path, index| {
+            updated.push((path, index));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(updated, vec![(PathBuf::from("a.png"), 1)]);
+
+        let result = set_wallpaper_on_monitor(PathBuf::from("a.png"), 2, monitor_count, |_, _| {
+            panic!("no monitor should be updated for an invalid index")
+        });
+        assert!(result.is_err());
+        assert!(validate_monitor_index(0, monitor_count).is_ok());
+    }
+
+    #[test]
+    fn test_per_monitor_renders_an_image_for_each_other_monitor() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::from_json("{}");
+        let path = dir.path().join("julia_1.png");
+        let image: AstraImage = ImageBuffer::from_pixel(16, 9, Rgb([1, 2, 3]));
+        image.save(&path).unwrap();
+        // left by a previous run with `single_file_mode`
+        image.save(dir.path().join("julia_1_monitor2.png")).unwrap();
+
+        let mut rendered = vec![];
+        let mut transaction = Transaction::default();
+        let paths = wallpapers_per_monitor(
+            &config,
+            &Generator::Julia,
+            &path,
+            &[(8, 8), (16, 9), (16, 9)],
+            &mut transaction,
+            |(width, height)| {
+                rendered.push((width, height));
+                Ok(ImageBuffer::from_pixel(width, height, Rgb([4, 5, 6])))
+            },
+        )
+        .unwrap();
+        assert_eq!(rendered, vec![(8, 8), (16, 9)]);
+        assert_eq!(
+            paths,
+            vec![
+                dir.path().join("julia_1_monitor0.png"),
+                path,
+                dir.path().join("julia_1_monitor2.png"),
+            ]
+        );
+        assert_eq!(image::image_dimensions(&paths[0]).unwrap(), (8, 8));
+        assert_eq!(image::image_dimensions(&paths[2]).unwrap(), (16, 9));
+
+        // only the image that did not exist before is removed when the wallpaper is not applied
+        transaction.rollback(&config);
+        assert!(!paths[0].exists());
+        assert!(paths[1].exists() && paths[2].exists());
+    }
+
+    #[test]
+    fn test_reset_data_removes_wallpapers_and_state() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let config_dir = tempfile::tempdir().unwrap();
+        let config = Config::from_json("{}");
+        let wallpapers = data_dir.path().join("Wallpapers");
+        create_dir_all(&wallpapers).unwrap();
+        std::fs::write(wallpapers.join("julia_1.png"), b"").unwrap();
+        for file in STATE_FILES {
+            std::fs::write(data_dir.path().join(file), b"{}").unwrap();
+        }
+        let config_path = config_dir.path().join("config.json");
+        std::fs::write(&config_path, b"{}").unwrap();
+
+        reset_data(&config, data_dir.path(), &config_path, true).unwrap();
+        assert!(!wallpapers.exists());
+        assert_eq!(read_dir(data_dir.path()).unwrap().count(), 0);
+        assert!(config_path.exists(), "config is kept with keep_config");
+
+        reset_data(&config, data_dir.path(), &config_path, false).unwrap();
+        assert!(!config_path.exists());
+    }
+
+    #[test]
+    fn test_reset_data_only_deletes_the_files_of_its_instance() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let config = Config::from_json("{}");
+        let work = crate::configuration::Instance::new("work").unwrap();
+        let work_dir = crate::configuration::Instance::data_dir(data_dir.path(), Some(&work));
+        for dir in [data_dir.path(), work_dir.as_path()] {
+            create_dir_all(dir.join("Wallpapers")).unwrap();
+            std::fs::write(dir.join("Wallpapers").join("julia_1.png"), b"").unwrap();
+            std::fs::write(dir.join("state.json"), b"{}").unwrap();
+        }
+
+        reset_data(&config, &work_dir, Path::new("config.json"), true).unwrap();
+        assert!(!work_dir.join("state.json").exists());
+        assert!(!work_dir.join("Wallpapers").join("julia_1.png").exists());
+        assert!(data_dir.path().join("state.json").exists());
+        assert!(
+            data_dir
+                .path()
+                .join("Wallpapers")
+                .join("julia_1.png")
+                .exists()
+        );
+
+        std::fs::write(work_dir.join("state.json"), b"{}").unwrap();
+        reset_data(&config, data_dir.path(), Path::new("config.json"), true).unwrap();
+        assert!(!data_dir.path().join("state.json").exists());
+        assert!(work_dir.join("state.json").exists());
+    }
+
+    #[test]
+    fn test_generation_outcome_reports_saved_path_and_applied() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::from_json("{}");
+        let image: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb([1, 2, 3]));
+        let save = |config: &Config,
+                    generator: &Generator,
+                    image: &AstraImage,
+                    transaction: &mut Transaction| {
+            save_image_to_folder(config, dir.path(), generator, image, transaction)
+        };
+
+        for (no_save, no_update) in [(false, false), (true, false), (false, true), (true, true)] {
+            let mut applied_path = None;
+            let outcome = save_and_apply(
+                &config,
+                &image,
+                &Generator::Julia,
+                &mut Transaction::default(),
+                (!no_save).then_some(save),
+                (!no_update).then_some(|_: &Config, path, _: &mut Transaction| {
+                    applied_path = Some(path);
+                    Ok(vec![])
+                }),
+            )
+            .unwrap();
+            assert_eq!(outcome.generator, Generator::Julia);
+            assert_eq!(outcome.applied_path, applied_path);
+            assert_eq!(applied_path.is_some(), !no_update);
+            assert_eq!(outcome.saved_path.is_some(), !no_save);
+            if let Some(path) = &outcome.saved_path {
+                assert!(path.starts_with(dir.path()) && path.is_file());
+                if !no_update {
+                    assert_eq!(applied_path.as_ref(), Some(path));
+                }
+            }
+        }
+
+        let result = save_and_apply(
+            &config,
+            &image,
+            &Generator::Julia,
+            &mut Transaction::default(),
+            None::<fn(&Config, &Generator, &AstraImage, &mut Transaction) -> _>,
+            Some(|_: &Config, _, _: &mut Transaction| Err("no desktop".into())),
+        );
+        assert!(result.is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_latest_link_points_at_most_recent_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::from_json(r#"{ "filename_scheme": "hash" }"#);
+        let link = dir.path().join("latest.png");
+        let mut saved = vec![];
+        for color in [1, 2] {
+            let image: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb([color, 0, 0]));
+            let path = save_image_to_folder(
+                &config,
+                dir.path(),
+                &Generator::Julia,
+                &image,
+                &mut Transaction::default(),
+            )
+            .unwrap();
+            update_latest_link(&link, &path).unwrap();
+            saved.push(path);
+        }
+        assert_eq!(std::fs::read_link(&link).unwrap(), saved[1]);
+        assert_eq!(
+            image::open(&link).unwrap().to_rgb8().get_pixel(0, 0),
+            &Rgb([2, 0, 0])
+        );
+        // only the link and the two images, no temporary link left behind
+        assert_eq!(read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn test_render_scale_renders_smaller_then_upscales() {
+        let mut config = Config::from_json(r#"{ "render_scale": 0.5 }"#);
+        config.set_resolution_override(64, 32);
+        let rendered = Generator::Truchet.with_default_mode(&config).unwrap();
+        assert_eq!(rendered.dimensions(), (32, 16));
+        let image = super::super::apply_post_processing(&config, rendered);
+        assert_eq!(image.dimensions(), (64, 32));
+
+        let mut config = Config::from_json(r#"{ "render_scale": 0.01 }"#);
+        config.set_resolution_override(64, 32);
+        assert_eq!(config.screen_resolution().unwrap(), (16, 8));
+    }
+
+    #[test]
+    fn test_apply_from_temp_file_sets_temp_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::from_json("{}");
+        let image: AstraImage = ImageBuffer::from_pixel(4, 4, Rgb([1, 2, 3]));
+        let mut set_path = None;
+
+        let path =
+            apply_from_temp_file(&config, dir.path(), &Generator::Julia, &image, |_, path| {
+                assert!(path.exists(), "file should exist while it is applied");
+                set_path = Some(path);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(set_path, Some(path.clone()));
+        assert!(path.starts_with(dir.path()));
+        assert!(path.exists());
+
+        // The next wallpaper gets the other name and the previous file is deleted
+        let next =
+            apply_from_temp_file(
+                &config,
+                dir.path(),
+                &Generator::Julia,
+                &image,
+                |_, _| Ok(()),
+            )
+            .unwrap();
+        assert_ne!(next, path);
+        assert!(next.exists() && !path.exists());
+
+        // A failing setter cleans up and reports the error
+        let result = apply_from_temp_file(
+            &config,
+            dir.path(),
+            &Generator::Solid {
+                mode: crate::cli::SolidMode::Random,
+            },
+            &image,
+            |_, _| Err("no desktop".into()),
+        );
+        assert!(result.is_err());
+        assert_eq!(read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_finish_runs_the_hook_before_deleting_the_temp_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let temp_file = dir.path().join("astra_julia_0.png");
+        let copy = dir.path().join("copy.png");
+        std::fs::write(&temp_file, "png").unwrap();
+        let config = Config::from_json(
+            &serde_json::json!({
+                "post_update_hook": format!("cp \"$1\" '{}'", copy.display())
+            })
+            .to_string(),
+        );
+        let outcome = GenerationOutcome {
+            generator: Generator::Julia,
+            saved_path: None,
+            applied_path: Some(temp_file.clone()),
+            theme: None,
+            temp_files: vec![temp_file.clone()],
+        };
+
+        outcome.finish(&config);
+        assert!(copy.is_file());
+        assert!(!temp_file.exists());
+    }
+
+    #[test]
+    fn test_generate_with_fallback_tries_another_generator() {
+        let mut config = Config::from_json("{}");
+        config.seed_rng(0);
+        let candidates = [
+            Generator::Spotlight,
+            Generator::Solid {
+                mode: crate::cli::SolidMode::Random,
+            },
+        ];
+        let mut attempts = vec![];
+        let (generator, image) = generate_with_fallback(&config, &candidates, |generator| {
+            attempts.push(generator.prefix().to_string());
+            match generator {
+                Generator::Spotlight => Err(WallpaperGeneratorError::Network("offline".into())),
+                _ => Ok(ImageBuffer::from_pixel(4, 4, Rgb([1, 2, 3]))),
+            }
+        })
+        .unwrap();
+        assert_eq!(generator.prefix(), "solid");
+        assert_eq!(image.dimensions(), (4, 4));
+        assert!(attempts.len() <= 2 && attempts.last().unwrap() == "solid");
+    }
+
+    #[test]
+    fn test_failing_spotlight_falls_back_to_configured_solid() {
+        let mut config = Config::from_json(r#"{ "fallback_generator": "solid" }"#);
+        config.set_resolution_override(8, 8);
+        config.enable_explain();
+        let candidates = [Generator::Spotlight];
+        let (generator, image) =
+            generate_with_fallback(&config, &candidates, |generator| match generator {
+                Generator::Spotlight => Err(WallpaperGeneratorError::Network("offline".into())),
+                _ => generator.with_default_mode(&config),
+            })
+            .unwrap();
+        assert_eq!(generator.prefix(), "solid");
+        assert_eq!(image.dimensions(), (8, 8));
+        assert!(
+            config
+                .explanation()
+                .contains(&Decision::Fallback("solid".to_string()))
+        );
+
+        // no re-roll when the fallback fails too
+        let mut attempts = vec![];
+        let result = generate_with_fallback(&config, &candidates, |generator| {
+            attempts.push(generator.prefix().to_string());
+            Err(WallpaperGeneratorError::Network("offline".into()))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, vec!["spotlight", "solid"]);
+    }
+
+    #[test]
+    fn test_theme_of_a_failed_attempt_is_not_reported_for_the_fallback_image() {
+        let config = Config::from_json(r#"{ "fallback_generator": "spotlight" }"#);
+        let (generator, _) =
+            generate_or_fallback(&config, &Generator::Julia, |generator| match generator {
+                Generator::Julia => {
+                    config.select_theme(false, &mut *config.rng());
+                    Err(WallpaperGeneratorError::ImageGeneration("failed".into()))
+                }
+                _ => Ok(ImageBuffer::from_pixel(4, 4, Rgb([1, 2, 3]))),
+            })
+            .unwrap();
+        assert_eq!(generator.prefix(), "spotlight");
+        assert_eq!(config.image_theme_name(), None);
+
+        // the next image (e.g. with `--count`) does not inherit the theme of the previous one
+        generate_or_fallback(&config, &Generator::Julia, |_| {
+            config.select_theme(false, &mut *config.rng());
+            Ok(ImageBuffer::from_pixel(4, 4, Rgb([1, 2, 3])))
+        })
+        .unwrap();
+        assert!(config.image_theme_name().is_some());
+        generate_or_fallback(&config, &Generator::Spotlight, |_| {
+            Ok(ImageBuffer::from_pixel(4, 4, Rgb([1, 2, 3])))
+        })
+        .unwrap();
+        assert_eq!(config.image_theme_name(), None);
+    }
+
+    fn pick_counts(weights: Option<&BTreeMap<String, u32>>, runs: u64) -> BTreeMap<String, u64> {
+        let candidates = [Generator::Julia, Generator::Mandala, Generator::Truchet];
+        let mut counts = BTreeMap::new();
+        for seed in 0..runs {
+            let mut rng = StdRng::seed_from_u64(seed);
+            if let Some(generator) = select_generator(&candidates, weights, &mut rng) {
+                *counts.entry(generator.prefix().to_string()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    #[test]
+    fn test_select_generator_is_uniform_without_weights() {
+        let counts = pick_counts(None, 3000);
+        assert_eq!(counts.len(), 3);
+        for count in counts.values() {
+            assert!((900..=1100).contains(count), "{counts:?}");
+        }
+        // generators missing from the weights count as 1
+        let weights = BTreeMap::from([("spotlight".to_string(), 5)]);
+        assert_eq!(pick_counts(Some(&weights), 3000), counts);
+    }
+
+    #[test]
+    fn test_select_generator_excludes_zero_weight() {
+        let weights = BTreeMap::from([("julia".to_string(), 0), ("mandala".to_string(), 0)]);
+        assert_eq!(
+            pick_counts(Some(&weights), 200),
+            BTreeMap::from([("truchet".to_string(), 200)])
+        );
+
+        let weights = BTreeMap::from([
+            ("julia".to_string(), 0),
+            ("mandala".to_string(), 0),
+            ("truchet".to_string(), 0),
+        ]);
+        assert!(pick_counts(Some(&weights), 20).is_empty());
+    }
+
+    #[test]
+    fn test_select_generator_follows_weights() {
+        let weights = BTreeMap::from([("julia".to_string(), 6), ("mandala".to_string(), 3)]);
+        let counts = pick_counts(Some(&weights), 10000);
+        // expected shares: 6/10, 3/10 and 1/10
+        for (prefix, expected) in [("julia", 6000), ("mandala", 3000), ("truchet", 1000)] {
+            let count = counts[prefix] as i64;
+            assert!((count - expected).abs() < 300, "{counts:?}");
+        }
+    }
+
+    #[test]
+    fn test_generate_with_fallback_fails_when_all_generators_fail() {
+        let config = Config::from_json("{}");
+        let candidates = [Generator::Spotlight, Generator::Julia];
+        let mut attempts = 0;
+        let result = generate_with_fallback(&config, &candidates, |_| {
+            attempts += 1;
+            Err(WallpaperGeneratorError::Network("offline".into()))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 2);
+        assert!(generate_with_fallback(&config, &[], |_| unreachable!()).is_err());
+    }
+
+    #[test]
+    fn test_generate_with_timeout_gives_up_on_slow_generator() {
+        let config = Config::from_json("{}");
+        let slow = |_: &Config| {
+            thread::sleep(Duration::from_secs(5));
+            Ok(ImageBuffer::from_pixel(4, 4, Rgb([1, 2, 3])))
+        };
+        let started = SystemTime::now();
+        let result = generate_with_timeout(&config, Some(Duration::from_millis(50)), slow);
+        assert!(
+            matches!(result, Err(WallpaperGeneratorError::ImageGeneration(ref e)) if e.contains("timed out"))
+        );
+        assert!(started.elapsed().unwrap() < Duration::from_secs(5));
+
+        let fast = |_: &Config| Ok(ImageBuffer::from_pixel(4, 4, Rgb([1, 2, 3])));
+        assert!(generate_with_timeout(&config, Some(Duration::from_secs(5)), fast).is_ok());
+    }
+
+    #[test]
+    fn test_state_staged_by_a_worker_is_kept_only_when_its_image_is_received() {
+        let config = Config::from_json("{}");
+        let generate = |delay: Duration| {
+            move |config: &Config| {
+                config.stage_state(|state| state.set_quote_index(7));
+                thread::sleep(delay);
+                Ok(ImageBuffer::from_pixel(4, 4, Rgb([1, 2, 3])))
+            }
+        };
+
+        let timeout = Some(Duration::from_millis(50));
+        assert!(generate_with_timeout(&config, timeout, generate(Duration::from_secs(5))).is_err());
+        assert_eq!(config.take_staged_state(), None);
+
+        let timeout = Some(Duration::from_secs(5));
+        assert!(generate_with_timeout(&config, timeout, generate(Duration::ZERO)).is_ok());
+        let staged = config.take_staged_state().unwrap();
+        assert_eq!(staged.quote_index(), Some(7));
+    }
+
+    #[test]
+    fn test_transparent_pixels_take_background_color() {
+        let image = image::RgbaImage::from_fn(4, 2, |x, _| {
+            if x < 2 {
+                image::Rgba([255, 0, 0, 0])
+            } else {
+                image::Rgba([0, 0, 255, 255])
+            }
+        });
+        let mut png = std::io::Cursor::new(vec![]);
+        image.write_to(&mut png, image::ImageFormat::Png).unwrap();
+
+        let config = Config::from_json(r##"{ "background": "#102030" }"##);
+        let loaded = load_image(&config, png.get_ref()).unwrap();
+        assert_eq!(loaded.get_pixel(0, 0).0, [16, 32, 48]);
+        assert_eq!(loaded.get_pixel(3, 1).0, [0, 0, 255]);
+
+        let config = Config::from_json(r#"{ "background": "not-a-color" }"#);
+        assert!(load_image(&config, png.get_ref()).is_err());
+    }
+
+    #[test]
+    fn test_identical_images_are_unchanged() {
+        let a: AstraImage = ImageBuffer::from_pixel(8, 8, Rgb([10, 20, 30]));
+        let b = a.clone();
+        assert!(is_unchanged(Some(&image_hash(&a)), &image_hash(&b)));
+    }
+
+    #[test]
+    fn test_different_images_are_changed() {
+        let a: AstraImage = ImageBuffer::from_pixel(8, 8, Rgb([10, 20, 30]));
+        let mut b = a.clone();
+        b.put_pixel(3, 3, Rgb([10, 20, 31]));
+        assert!(!is_unchanged(Some(&image_hash(&a)), &image_hash(&b)));
+        // same pixels, different dimensions
+        let c: AstraImage = ImageBuffer::from_pixel(4, 16, Rgb([10, 20, 30]));
+        assert!(!is_unchanged(Some(&image_hash(&a)), &image_hash(&c)));
+        // nothing applied yet
+        assert!(!is_unchanged(None, &image_hash(&a)));
+    }
 
     #[test]
     fn test_scale_image() {
@@ -396,7 +2416,7 @@ mod tests {
 
     #[test]
     fn test_create_color_map_all_red() {
-        let color_map = create_color_map(Operator::Gradient, 256, &[[255, 0, 0]]);
+        let color_map = create_color_map(Operator::Gradient, 256, &[[255, 0, 0]]).unwrap();
         assert_eq!(color_map.len(), 256);
         for color in color_map {
             assert_eq!(color, [255, 0, 0]);
@@ -405,10 +2425,41 @@ mod tests {
 
     #[test]
     fn test_create_color_map_red_green() {
-        let color_map = create_color_map(Operator::Gradient, 256, &[[255, 0, 0], [0, 255, 0]]);
+        let color_map =
+            create_color_map(Operator::Gradient, 256, &[[255, 0, 0], [0, 255, 0]]).unwrap();
         assert_eq!(color_map.len(), 256);
         assert_eq!(color_map[0], [255, 0, 0]);
         assert_eq!(color_map[255], [0, 255, 0]);
     }
+
+    #[test]
+    fn test_create_color_map_with_two_steps_keeps_endpoints() {
+        let colors = [[255, 0, 0], [0, 0, 255]];
+        assert_eq!(
+            create_color_map(Operator::Gradient, 2, &colors).unwrap(),
+            colors
+        );
+        assert_eq!(
+            create_color_map(Operator::Gradient, 1, &colors).unwrap(),
+            vec![[255, 0, 0]]
+        );
+    }
+
+    #[test]
+    fn test_create_color_map_with_fewer_steps_than_colors_samples_colors() {
+        let colors = [[0, 0, 0], [1, 1, 1], [2, 2, 2], [3, 3, 3], [4, 4, 4]];
+        assert_eq!(
+            create_color_map(Operator::Gradient, 3, &colors).unwrap(),
+            vec![[0, 0, 0], [2, 2, 2], [4, 4, 4]]
+        );
+        let color_map = create_color_map(Operator::Gradient, 8, &colors).unwrap();
+        assert_eq!(color_map.len(), 8);
+        assert_eq!(color_map[7], [4, 4, 4]);
+    }
+
+    #[test]
+    fn test_create_color_map_without_colors_is_an_error() {
+        assert!(create_color_map(Operator::Gradient, 256, &[]).is_err());
+    }
 }
 // --- Tests ---